@@ -0,0 +1,108 @@
+//! Measures the throughput of the deserializers and serializer on a corpus of class files.
+//!
+//! Run with `cargo bench --bench corpus -- <directory>`, where the directory contains class
+//! files (searched recursively), e.g. the classes extracted from the JDK's `jmods`.
+
+use phoron_core::{
+    deserializer::Deserializer,
+    lazy::LazyDeserializer,
+    rw::{reader::Reader, writer::Writer},
+    serializer::Serializer,
+};
+use std::{
+    env, fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// The number of passes over the corpus, of which the fastest is reported.
+const PASSES: usize = 5;
+
+fn class_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).expect("cannot read directory") {
+        let path = entry.expect("cannot read directory entry").path();
+        if path.is_dir() {
+            class_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "class") {
+            files.push(path);
+        }
+    }
+}
+
+/// Run `f` over the corpus `PASSES` times and return the duration of the fastest pass.
+fn fastest(mut f: impl FnMut()) -> Duration {
+    (0..PASSES)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn report(name: &str, duration: Duration, bytes: usize) {
+    let mib_per_sec = bytes as f64 / duration.as_secs_f64() / (1024.0 * 1024.0);
+    println!(
+        "{:<12} {:>10.2?} {:>10.1} MiB/s",
+        name, duration, mib_per_sec
+    );
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to the benchmark
+    let Some(dir) = env::args().skip(1).find(|arg| !arg.starts_with("--")) else {
+        eprintln!("usage: cargo bench --bench corpus -- <directory of class files>");
+        return;
+    };
+
+    let mut files = Vec::new();
+    class_files(Path::new(&dir), &mut files);
+    let corpus: Vec<Vec<u8>> = files
+        .iter()
+        .map(|path| fs::read(path).expect("cannot read class file"))
+        .collect();
+    let bytes = corpus.iter().map(Vec::len).sum();
+    println!("{} class files, {} bytes", corpus.len(), bytes);
+
+    let deserialize = |bytes: &[u8]| {
+        Deserializer::new(Reader::new(Cursor::new(bytes)))
+            .deserialize()
+            .expect("cannot deserialize class file")
+    };
+
+    let duration = fastest(|| {
+        for class_bytes in &corpus {
+            deserialize(class_bytes);
+        }
+    });
+    report("deserialize", duration, bytes);
+
+    // a header and member scan, as done by tools that index class files
+    let duration = fastest(|| {
+        for class_bytes in &corpus {
+            let classfile = LazyDeserializer::new(class_bytes)
+                .deserialize()
+                .expect("cannot deserialize class file");
+            for member in classfile.fields.iter().chain(&classfile.methods) {
+                classfile
+                    .constant_pool
+                    .get_utf8(member.name_index)
+                    .expect("invalid member name");
+            }
+        }
+    });
+    report("lazy scan", duration, bytes);
+
+    let classfiles: Vec<_> = corpus.iter().map(|bytes| deserialize(bytes)).collect();
+    let duration = fastest(|| {
+        for classfile in &classfiles {
+            let mut out = Vec::new();
+            Serializer::new(Writer::new(&mut out))
+                .serialize(classfile)
+                .expect("cannot serialize class file");
+        }
+    });
+    report("serialize", duration, bytes);
+}
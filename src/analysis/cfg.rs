@@ -0,0 +1,546 @@
+//! Control-flow graphs of the code in `Code` attributes.
+//!
+//! The code is split into basic blocks, maximal sequences of instructions that are only entered
+//! at the first instruction and only left after the last. Blocks start at the first instruction,
+//! at the targets of branches and switches, after instructions that branch, return or throw, and
+//! at exception handlers and the bounds of the ranges they cover. Every block in the range of a
+//! handler has an exceptional edge to it.
+//!
+//! Subroutines are not inlined: a `jsr` has an edge to the subroutine and one to the following
+//! instruction (where the matching `ret` returns to), and a `ret` has no successors.
+
+use super::{stack_map::successors, AnalysisError, AnalysisResult};
+use crate::{
+    bytecode::{self, Instruction},
+    model::{
+        attributes::{AttributeInfo, ExceptionHandler},
+        MethodInfo,
+    },
+};
+use std::collections::BTreeMap;
+
+/// The index of a block in a `ControlFlowGraph`.
+pub type BlockId = usize;
+
+/// The kinds of edges between basic blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Execution continues with the instruction following the block.
+    FallThrough,
+    /// The last instruction of the block branches, switches or jumps to a subroutine.
+    Jump,
+    /// An exception thrown in the block is caught by the handler at `index` in the exception
+    /// table.
+    Exception { index: usize },
+}
+
+/// An edge to the block `target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub target: BlockId,
+    pub kind: EdgeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The offset of the first instruction of the block.
+    pub start: usize,
+    /// The offset following the last instruction of the block.
+    pub end: usize,
+    /// The instructions of the block, with their offsets.
+    pub instructions: Vec<(usize, Instruction)>,
+    /// The edges leaving the block: normal edges first, then exceptional edges in the order of
+    /// the exception table.
+    pub successors: Vec<Edge>,
+    /// The blocks with an edge to this block, in ascending order.
+    pub predecessors: Vec<BlockId>,
+}
+
+/// The control-flow graph of the code of a method. Blocks are ordered by offset, so the entry
+/// block, if the code is not empty, is block 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// Build the control-flow graph of the `Code` attribute of `method`, or `None` if the
+    /// method has no code.
+    pub fn from_method(method: &MethodInfo) -> AnalysisResult<Option<Self>> {
+        method
+            .attributes
+            .iter()
+            .find_map(|attr| match attr {
+                AttributeInfo::Code {
+                    code,
+                    exception_table,
+                    ..
+                } => Some(ControlFlowGraph::build(code, exception_table)),
+                _ => None,
+            })
+            .transpose()
+    }
+
+    /// Build the control-flow graph of `code`, with the exception handlers of `exception_table`.
+    pub fn build(code: &[u8], exception_table: &[ExceptionHandler]) -> AnalysisResult<Self> {
+        let instructions = bytecode::decode(code)?;
+        let len = instructions.len();
+
+        // map each offset, and the end of the code, to the position of the instruction there
+        let mut positions = vec![None; code.len() + 1];
+        for (pos, (offset, _)) in instructions.iter().enumerate() {
+            positions[*offset] = Some(pos);
+        }
+        positions[code.len()] = Some(len);
+        let position = |offset: usize| positions.get(offset).copied().flatten();
+
+        let mut leaders = vec![false; len + 1];
+        leaders[0] = true;
+
+        // the start, end and handler positions of each exception handler
+        let mut handlers = Vec::with_capacity(exception_table.len());
+        for (index, entry) in exception_table.iter().enumerate() {
+            let start = position(entry.start_pc as usize);
+            let end = position(entry.end_pc as usize);
+            let handler = position(entry.handler_pc as usize).filter(|&pos| pos < len);
+            match (start, end, handler) {
+                (Some(start), Some(end), Some(handler)) if start < end => {
+                    leaders[start] = true;
+                    leaders[end] = true;
+                    leaders[handler] = true;
+                    handlers.push((start, end, handler));
+                }
+                _ => return Err(AnalysisError::InvalidExceptionHandler { index }),
+            }
+        }
+
+        // the positions of the branch targets of each instruction, and whether it falls through
+        let mut jumps = Vec::with_capacity(len);
+        for (pos, (offset, instruction)) in instructions.iter().enumerate() {
+            let (targets, falls_through) = successors(*offset, instruction);
+            let targets = targets
+                .into_iter()
+                .map(|target| {
+                    usize::try_from(target)
+                        .ok()
+                        .and_then(position)
+                        .filter(|&pos| pos < len)
+                        .ok_or(AnalysisError::InvalidBranchTarget {
+                            offset: *offset,
+                            target,
+                        })
+                })
+                .collect::<AnalysisResult<Vec<_>>>()?;
+
+            if falls_through && pos + 1 == len {
+                return Err(AnalysisError::FallsOffEnd { offset: *offset });
+            }
+            for &target in &targets {
+                leaders[target] = true;
+            }
+            if !targets.is_empty() || !falls_through {
+                leaders[pos + 1] = true;
+            }
+            jumps.push((targets, falls_through));
+        }
+
+        // split the instructions into blocks
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+        let mut block_of = Vec::with_capacity(len);
+        let mut first = Vec::new();
+        for (pos, (offset, instruction)) in instructions.into_iter().enumerate() {
+            if leaders[pos] {
+                blocks.push(BasicBlock {
+                    start: offset,
+                    end: offset,
+                    instructions: Vec::new(),
+                    successors: Vec::new(),
+                    predecessors: Vec::new(),
+                });
+                first.push(pos);
+            }
+            let block = blocks
+                .last_mut()
+                .expect("the first instruction starts a block");
+            block.end = offset + instruction.size(offset);
+            block.instructions.push((offset, instruction));
+            block_of.push(blocks.len() - 1);
+        }
+
+        for (id, block) in blocks.iter_mut().enumerate() {
+            let last = first[id] + block.instructions.len() - 1;
+            let (targets, falls_through) = &jumps[last];
+
+            let mut add = |target, kind| {
+                let edge = Edge { target, kind };
+                if !block.successors.contains(&edge) {
+                    block.successors.push(edge);
+                }
+            };
+            if *falls_through {
+                add(block_of[last + 1], EdgeKind::FallThrough);
+            }
+            for &target in targets {
+                add(block_of[target], EdgeKind::Jump);
+            }
+            for (index, &(start, end, handler)) in handlers.iter().enumerate() {
+                if (start..end).contains(&first[id]) {
+                    add(block_of[handler], EdgeKind::Exception { index });
+                }
+            }
+        }
+
+        for id in 0..blocks.len() {
+            for edge in blocks[id].successors.clone() {
+                let predecessors = &mut blocks[edge.target].predecessors;
+                if predecessors.last() != Some(&id) {
+                    predecessors.push(id);
+                }
+            }
+        }
+
+        Ok(ControlFlowGraph { blocks })
+    }
+
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id]
+    }
+
+    /// Return the block containing the instruction at `offset`.
+    pub fn block_at(&self, offset: usize) -> Option<BlockId> {
+        self.blocks
+            .partition_point(|block| block.start <= offset)
+            .checked_sub(1)
+            .filter(|&id| offset < self.blocks[id].end)
+    }
+
+    /// Return the blocks reachable from the entry block in reverse postorder, in which every
+    /// block comes before its successors, except along back edges.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut order = Vec::with_capacity(self.blocks.len());
+        if self.blocks.is_empty() {
+            return order;
+        }
+
+        let mut visited = vec![false; self.blocks.len()];
+        // blocks being visited, with the number of their successors visited so far
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((id, next)) = stack.last_mut() {
+            match self.blocks[*id].successors.get(*next) {
+                Some(edge) => {
+                    *next += 1;
+                    if !visited[edge.target] {
+                        visited[edge.target] = true;
+                        stack.push((edge.target, 0));
+                    }
+                }
+                None => {
+                    order.push(*id);
+                    stack.pop();
+                }
+            }
+        }
+
+        order.reverse();
+        order
+    }
+
+    /// Compute the dominator tree of the graph, following both normal and exceptional edges.
+    pub fn dominators(&self) -> Dominators {
+        // "A Simple, Fast Dominance Algorithm" (Cooper, Harvey and Kennedy)
+        let order = self.reverse_postorder();
+        let mut rank = vec![usize::MAX; self.blocks.len()];
+        for (idx, &id) in order.iter().enumerate() {
+            rank[id] = idx;
+        }
+
+        let mut idom = vec![None; self.blocks.len()];
+        if let Some(&entry) = order.first() {
+            idom[entry] = Some(entry);
+        }
+
+        let intersect = |idom: &[Option<BlockId>], mut a: BlockId, mut b: BlockId| {
+            while a != b {
+                while rank[a] > rank[b] {
+                    a = idom[a].expect("processed blocks have a dominator");
+                }
+                while rank[b] > rank[a] {
+                    b = idom[b].expect("processed blocks have a dominator");
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &id in order.iter().skip(1) {
+                let mut new_idom = None;
+                for &pred in &self.blocks[id].predecessors {
+                    if idom[pred].is_some() {
+                        new_idom = Some(match new_idom {
+                            None => pred,
+                            Some(other) => intersect(&idom, pred, other),
+                        });
+                    }
+                }
+                if new_idom != idom[id] {
+                    idom[id] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        Dominators { idom }
+    }
+
+    /// Find the natural loops of the graph: for each block that is the target of a back edge
+    /// (an edge to a block that dominates its source), the blocks that can reach a source of
+    /// such an edge without passing through it. Irreducible loops, which have no such header,
+    /// are not found.
+    pub fn loops(&self, dominators: &Dominators) -> Vec<Loop> {
+        let mut latches: BTreeMap<BlockId, Vec<BlockId>> = BTreeMap::new();
+        for (id, block) in self.blocks.iter().enumerate() {
+            for edge in &block.successors {
+                if dominators.dominates(edge.target, id) {
+                    let sources = latches.entry(edge.target).or_default();
+                    if !sources.contains(&id) {
+                        sources.push(id);
+                    }
+                }
+            }
+        }
+
+        latches
+            .into_iter()
+            .map(|(header, latches)| {
+                let mut in_loop = vec![false; self.blocks.len()];
+                in_loop[header] = true;
+                let mut worklist = latches.clone();
+                while let Some(id) = worklist.pop() {
+                    if !in_loop[id] && dominators.is_reachable(id) {
+                        in_loop[id] = true;
+                        worklist.extend(&self.blocks[id].predecessors);
+                    }
+                }
+
+                Loop {
+                    header,
+                    latches,
+                    blocks: (0..self.blocks.len()).filter(|&id| in_loop[id]).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The dominator tree of a `ControlFlowGraph`. A block `a` dominates a block `b` if every path
+/// from the entry block to `b` passes through `a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dominators {
+    /// The immediate dominator of each block, the entry block for itself, and `None` for
+    /// unreachable blocks.
+    idom: Vec<Option<BlockId>>,
+}
+
+impl Dominators {
+    /// Return the immediate dominator of `id`, or `None` for the entry block and unreachable
+    /// blocks.
+    pub fn immediate_dominator(&self, id: BlockId) -> Option<BlockId> {
+        self.idom[id].filter(|&idom| idom != id)
+    }
+
+    /// Return whether `id` is reachable from the entry block.
+    pub fn is_reachable(&self, id: BlockId) -> bool {
+        self.idom[id].is_some()
+    }
+
+    /// Return whether `a` dominates `b`. Every reachable block dominates itself, and no block
+    /// dominates an unreachable block.
+    pub fn dominates(&self, a: BlockId, mut b: BlockId) -> bool {
+        if !self.is_reachable(b) {
+            return false;
+        }
+        loop {
+            if a == b {
+                return true;
+            }
+            match self.immediate_dominator(b) {
+                Some(idom) => b = idom,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// A natural loop of a `ControlFlowGraph`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loop {
+    /// The block that dominates all blocks of the loop, through which it is entered.
+    pub header: BlockId,
+    /// The blocks with a back edge to the header.
+    pub latches: Vec<BlockId>,
+    /// The blocks of the loop, including the header, in ascending order.
+    pub blocks: Vec<BlockId>,
+}
+
+impl Loop {
+    pub fn contains(&self, id: BlockId) -> bool {
+        self.blocks.binary_search(&id).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::{opcodes::*, CodeBuilder};
+
+    #[test]
+    fn test_blocks_and_edges() {
+        // for (int i = 0; i < 10; i++) { try { f(i); } catch (Throwable t) {} } return;
+        let mut builder = CodeBuilder::new();
+        let (header, start, end, handler, exit) = (
+            builder.label(),
+            builder.label(),
+            builder.label(),
+            builder.label(),
+            builder.label(),
+        );
+        builder
+            .emit(Instruction::Iconst0)
+            .emit(Instruction::Istore1)
+            .place(header)
+            .emit(Instruction::Iload1)
+            .emit(Instruction::Bipush(10))
+            .branch(IF_ICMPGE, exit)
+            .place(start)
+            .emit(Instruction::Iload1)
+            .emit(Instruction::Invokestatic(1))
+            .place(end)
+            .emit(Instruction::Iinc {
+                index: 1,
+                constant: 1,
+            })
+            .branch(GOTO, header)
+            .place(handler)
+            .emit(Instruction::Astore2)
+            .branch(GOTO, end)
+            .place(exit)
+            .emit(Instruction::Return)
+            .try_catch(start, end, handler, 0);
+        let assembled = builder.build().unwrap();
+
+        let cfg = ControlFlowGraph::build(&assembled.code, &assembled.exception_table).unwrap();
+        let ranges: Vec<_> = cfg
+            .blocks()
+            .iter()
+            .map(|block| (block.start, block.end))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(0, 2), (2, 8), (8, 12), (12, 18), (18, 22), (22, 23)]
+        );
+        assert_eq!(cfg.block_at(9), Some(2));
+        assert_eq!(cfg.block_at(23), None);
+
+        let edge = |target, kind| Edge { target, kind };
+        assert_eq!(
+            cfg.block(1).successors,
+            vec![edge(2, EdgeKind::FallThrough), edge(5, EdgeKind::Jump)]
+        );
+        assert_eq!(
+            cfg.block(2).successors,
+            vec![
+                edge(3, EdgeKind::FallThrough),
+                edge(4, EdgeKind::Exception { index: 0 })
+            ]
+        );
+        assert_eq!(cfg.block(3).successors, vec![edge(1, EdgeKind::Jump)]);
+        assert_eq!(cfg.block(4).successors, vec![edge(3, EdgeKind::Jump)]);
+        assert_eq!(cfg.block(3).predecessors, vec![2, 4]);
+
+        let dominators = cfg.dominators();
+        let idoms: Vec<_> = (0..6)
+            .map(|id| dominators.immediate_dominator(id))
+            .collect();
+        assert_eq!(
+            idoms,
+            vec![None, Some(0), Some(1), Some(2), Some(2), Some(1)]
+        );
+        assert!(dominators.dominates(1, 4));
+        assert!(!dominators.dominates(4, 3));
+
+        assert_eq!(
+            cfg.loops(&dominators),
+            vec![Loop {
+                header: 1,
+                latches: vec![3],
+                blocks: vec![1, 2, 3, 4],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_and_nested_loops() {
+        let code = bytecode::encode(&[
+            Instruction::Iload0,   // 0
+            Instruction::Ifeq(0),  // 1: inner loop
+            Instruction::Iload0,   // 4
+            Instruction::Ifne(-5), // 5: outer loop
+            Instruction::Return,   // 8
+            Instruction::Goto(-9), // 9: unreachable
+        ]);
+
+        let cfg = ControlFlowGraph::build(&code, &[]).unwrap();
+        assert_eq!(cfg.blocks().len(), 5);
+        assert_eq!(cfg.reverse_postorder(), vec![0, 1, 2, 3]);
+
+        let dominators = cfg.dominators();
+        assert!(!dominators.is_reachable(4));
+        assert!(!dominators.dominates(0, 4));
+        assert_eq!(
+            cfg.loops(&dominators),
+            vec![
+                Loop {
+                    header: 0,
+                    latches: vec![2],
+                    blocks: vec![0, 1, 2],
+                },
+                Loop {
+                    header: 1,
+                    latches: vec![1],
+                    blocks: vec![1],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let code = bytecode::encode(&[Instruction::Goto(2), Instruction::Return]);
+        assert_eq!(
+            ControlFlowGraph::build(&code, &[]),
+            Err(AnalysisError::InvalidBranchTarget {
+                offset: 0,
+                target: 2
+            })
+        );
+
+        let code = bytecode::encode(&[Instruction::Iconst0]);
+        assert_eq!(
+            ControlFlowGraph::build(&code, &[]),
+            Err(AnalysisError::FallsOffEnd { offset: 0 })
+        );
+
+        let code = bytecode::encode(&[Instruction::Nop, Instruction::Return]);
+        assert_eq!(
+            ControlFlowGraph::build(&code, &[ExceptionHandler::new(1, 1, 0, 0)]),
+            Err(AnalysisError::InvalidExceptionHandler { index: 0 })
+        );
+    }
+}
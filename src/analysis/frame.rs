@@ -0,0 +1,1297 @@
+//! Verification types, frames, and the effect of each instruction on a frame.
+
+use super::{hierarchy::ClassHierarchy, AnalysisError, AnalysisResult};
+use crate::{
+    bytecode::{opcodes::NEW, BytecodeError, Instruction, WideInstruction},
+    descriptor::{DescriptorError, DescriptorErrorKind, FieldType, MethodDescriptor},
+    model::{
+        attributes::VerificationTypeInfo,
+        constant_pool::{types::CpInfo, ConstantPool, ConstantPoolBuilder, ConstantPoolResult},
+    },
+};
+use std::fmt;
+
+/// The type of a local variable or operand stack slot, as used by the type checking verifier
+/// (JVMS 4.10.1.2). Class types are internal names (`java/lang/String`) and array types are
+/// descriptors (`[I`, `[Ljava/lang/String;`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VerificationType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    /// `this` in an instance initialization method, before the superclass constructor is called.
+    UninitializedThis,
+    /// The result of the `new` instruction at the offset, before its constructor is called.
+    Uninitialized(u16),
+    Object(String),
+}
+
+impl VerificationType {
+    /// Return the type of values of the field type `descriptor`, or `None` if it is malformed.
+    /// `boolean`, `byte`, `char` and `short` values are `Integer`s.
+    pub fn from_descriptor(descriptor: &str) -> Option<VerificationType> {
+        FieldType::parse(descriptor)
+            .ok()
+            .map(|ty| VerificationType::from(&ty))
+    }
+
+    /// Return the type of the class whose `CONSTANT_Class_info` entry names `name`, which is
+    /// either an internal class name or an array descriptor.
+    pub fn object(name: &str) -> VerificationType {
+        VerificationType::Object(name.to_string())
+    }
+
+    /// The number of slots taken by a value of this type.
+    pub fn size(&self) -> usize {
+        match self {
+            VerificationType::Long | VerificationType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            VerificationType::Null
+                | VerificationType::UninitializedThis
+                | VerificationType::Uninitialized(_)
+                | VerificationType::Object(_)
+        )
+    }
+
+    /// Return the most specific type that both `self` and `other` are assignable to, which is
+    /// `Top` if they have nothing in common.
+    pub fn merge(&self, other: &VerificationType, hierarchy: &dyn ClassHierarchy) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a.clone(),
+            (VerificationType::Null, VerificationType::Object(_)) => other.clone(),
+            (VerificationType::Object(_), VerificationType::Null) => self.clone(),
+            (VerificationType::Object(a), VerificationType::Object(b)) => {
+                VerificationType::Object(merge_reference(a, b, hierarchy))
+            }
+            _ => VerificationType::Top,
+        }
+    }
+
+    /// Return whether a value of this type can be used where a value of type `target` is
+    /// expected (JVMS 4.10.1.2). Every type is assignable to `Top`, and `null` to every class
+    /// and array type.
+    pub fn is_assignable_to(
+        &self,
+        target: &VerificationType,
+        hierarchy: &dyn ClassHierarchy,
+    ) -> bool {
+        match (self, target) {
+            (a, b) if a == b => true,
+            (_, VerificationType::Top) => true,
+            (VerificationType::Null, VerificationType::Object(_)) => true,
+            (VerificationType::Object(from), VerificationType::Object(to)) => {
+                is_assignable_reference(from, to, hierarchy)
+            }
+            _ => false,
+        }
+    }
+
+    /// Convert the type to its `StackMapTable` representation, adding the `CONSTANT_Class_info`
+    /// entries of class types to `constant_pool`.
+    pub fn to_info(
+        &self,
+        constant_pool: &mut ConstantPoolBuilder,
+    ) -> ConstantPoolResult<VerificationTypeInfo> {
+        let info = match self {
+            VerificationType::Top => VerificationTypeInfo::TopVariableInfo { tag: 0 },
+            VerificationType::Integer => VerificationTypeInfo::IntegerVariableInfo { tag: 1 },
+            VerificationType::Float => VerificationTypeInfo::FloatVariableInfo { tag: 2 },
+            VerificationType::Double => VerificationTypeInfo::DoubleVariableInfo { tag: 3 },
+            VerificationType::Long => VerificationTypeInfo::LongVariableInfo { tag: 4 },
+            VerificationType::Null => VerificationTypeInfo::NullVariableInfo { tag: 5 },
+            VerificationType::UninitializedThis => {
+                VerificationTypeInfo::UninitializedThisVariableInfo { tag: 6 }
+            }
+            VerificationType::Object(name) => VerificationTypeInfo::ObjectVariableInfo {
+                tag: 7,
+                cpool_index: constant_pool.class(name)?,
+            },
+            VerificationType::Uninitialized(offset) => {
+                VerificationTypeInfo::UninitializedVariableInfo {
+                    tag: 8,
+                    offset: *offset,
+                }
+            }
+        };
+        Ok(info)
+    }
+
+    /// Convert the `StackMapTable` representation of a type, looking up class types in
+    /// `constant_pool`.
+    pub fn from_info(
+        info: &VerificationTypeInfo,
+        constant_pool: &ConstantPool,
+    ) -> ConstantPoolResult<VerificationType> {
+        let ty = match info {
+            VerificationTypeInfo::TopVariableInfo { .. } => VerificationType::Top,
+            VerificationTypeInfo::IntegerVariableInfo { .. } => VerificationType::Integer,
+            VerificationTypeInfo::FloatVariableInfo { .. } => VerificationType::Float,
+            VerificationTypeInfo::DoubleVariableInfo { .. } => VerificationType::Double,
+            VerificationTypeInfo::LongVariableInfo { .. } => VerificationType::Long,
+            VerificationTypeInfo::NullVariableInfo { .. } => VerificationType::Null,
+            VerificationTypeInfo::UninitializedThisVariableInfo { .. } => {
+                VerificationType::UninitializedThis
+            }
+            VerificationTypeInfo::ObjectVariableInfo { cpool_index, .. } => {
+                VerificationType::object(&constant_pool.get_class_name(*cpool_index)?)
+            }
+            VerificationTypeInfo::UninitializedVariableInfo { offset, .. } => {
+                VerificationType::Uninitialized(*offset)
+            }
+        };
+        Ok(ty)
+    }
+}
+
+impl From<&FieldType> for VerificationType {
+    /// `boolean`, `byte`, `char` and `short` values are `Integer`s.
+    fn from(ty: &FieldType) -> Self {
+        match ty {
+            FieldType::Boolean
+            | FieldType::Byte
+            | FieldType::Char
+            | FieldType::Short
+            | FieldType::Int => VerificationType::Integer,
+            FieldType::Float => VerificationType::Float,
+            FieldType::Long => VerificationType::Long,
+            FieldType::Double => VerificationType::Double,
+            FieldType::Object(name) => VerificationType::Object(name.clone()),
+            FieldType::Array(_) => VerificationType::Object(ty.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for VerificationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationType::Top => write!(f, "top"),
+            VerificationType::Integer => write!(f, "int"),
+            VerificationType::Float => write!(f, "float"),
+            VerificationType::Long => write!(f, "long"),
+            VerificationType::Double => write!(f, "double"),
+            VerificationType::Null => write!(f, "null"),
+            VerificationType::UninitializedThis => write!(f, "uninitializedThis"),
+            VerificationType::Uninitialized(offset) => write!(f, "uninitialized({})", offset),
+            VerificationType::Object(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Return the element type of the array type `name`, or `None` if it is not an array type.
+fn element_type(name: &str) -> Option<VerificationType> {
+    name.strip_prefix('[')
+        .and_then(VerificationType::from_descriptor)
+}
+
+/// Return the array type with elements of the reference type `name`.
+fn array_of(name: &str) -> String {
+    if name.starts_with('[') {
+        format!("[{}", name)
+    } else {
+        format!("[L{};", name)
+    }
+}
+
+/// Return the most specific common supertype of the reference types `a` and `b`.
+fn merge_reference(a: &str, b: &str, hierarchy: &dyn ClassHierarchy) -> String {
+    if a == b {
+        return a.to_string();
+    }
+
+    match (element_type(a), element_type(b)) {
+        (Some(VerificationType::Object(a)), Some(VerificationType::Object(b))) => {
+            array_of(&merge_reference(&a, &b, hierarchy))
+        }
+        (None, None) => hierarchy.common_super_class(a, b),
+        // arrays of different primitive types, or an array and a class
+        _ => super::hierarchy::OBJECT.to_string(),
+    }
+}
+
+/// Return whether a value of the reference type `from` can be assigned to the reference type
+/// `to`. Arrays are assignable to `Object`, `Cloneable` and `Serializable`, and to arrays whose
+/// elements their own elements are assignable to.
+fn is_assignable_reference(from: &str, to: &str, hierarchy: &dyn ClassHierarchy) -> bool {
+    if from == to || to == super::hierarchy::OBJECT {
+        return true;
+    }
+
+    match (element_type(from), element_type(to)) {
+        (Some(VerificationType::Object(from)), Some(VerificationType::Object(to))) => {
+            is_assignable_reference(&from, &to, hierarchy)
+        }
+        (Some(_), None) => to == "java/lang/Cloneable" || to == "java/io/Serializable",
+        (None, None) => hierarchy.is_assignable(from, to),
+        // arrays of different primitive types, or a class and an array
+        _ => false,
+    }
+}
+
+/// The types of the local variables and the operand stack at an instruction.
+///
+/// Unlike in the `StackMapTable` representation, each entry is a single slot: `long` and
+/// `double` values take two entries, the second of which is `Top`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub locals: Vec<VerificationType>,
+    pub stack: Vec<VerificationType>,
+}
+
+impl Frame {
+    /// Return the frame on entry to a method of the class `this_class` with descriptor
+    /// `descriptor`. `this` is uninitialized in instance initialization methods, except in
+    /// those of `java/lang/Object`.
+    pub fn initial(
+        this_class: &str,
+        method_name: &str,
+        descriptor: &str,
+        is_static: bool,
+    ) -> AnalysisResult<Frame> {
+        let mut frame = Frame::default();
+
+        if !is_static {
+            if method_name == "<init>" && this_class != super::hierarchy::OBJECT {
+                frame.locals.push(VerificationType::UninitializedThis);
+            } else {
+                frame.locals.push(VerificationType::object(this_class));
+            }
+        }
+
+        for parameter in MethodDescriptor::parse(descriptor)?.parameters {
+            push_slots(&mut frame.locals, VerificationType::from(&parameter));
+        }
+
+        Ok(frame)
+    }
+
+    /// Return the frame whose locals and operand stack are given in the `StackMapTable`
+    /// representation, where `long` and `double` values take a single entry.
+    pub fn from_compact(locals: &[VerificationType], stack: &[VerificationType]) -> Frame {
+        let mut frame = Frame::default();
+        for ty in locals {
+            push_slots(&mut frame.locals, ty.clone());
+        }
+        for ty in stack {
+            push_slots(&mut frame.stack, ty.clone());
+        }
+        frame
+    }
+
+    /// Return the locals in the `StackMapTable` representation, where `long` and `double`
+    /// values take a single entry, and trailing `Top`s are omitted.
+    pub fn compact_locals(&self) -> Vec<VerificationType> {
+        let mut locals = compact(&self.locals);
+        while locals.last() == Some(&VerificationType::Top) {
+            locals.pop();
+        }
+        locals
+    }
+
+    /// Return the operand stack in the `StackMapTable` representation, where `long` and
+    /// `double` values take a single entry.
+    pub fn compact_stack(&self) -> Vec<VerificationType> {
+        compact(&self.stack)
+    }
+
+    /// Merge `other` into this frame, returning whether this frame changed. The operand stacks
+    /// must have the same height; locals that are only present in one of the frames become
+    /// `Top`.
+    pub fn merge(
+        &mut self,
+        other: &Frame,
+        offset: usize,
+        hierarchy: &dyn ClassHierarchy,
+    ) -> AnalysisResult<bool> {
+        if self.stack.len() != other.stack.len() {
+            return Err(AnalysisError::InconsistentStackHeight {
+                offset,
+                expected: self.stack.len() as u16,
+                found: other.stack.len() as u16,
+            });
+        }
+
+        let mut changed = false;
+        if self.locals.len() > other.locals.len() {
+            self.locals.truncate(other.locals.len());
+            changed = true;
+        }
+        let locals = self.locals.iter_mut().zip(&other.locals);
+        for (slot, ty) in locals.chain(self.stack.iter_mut().zip(&other.stack)) {
+            let merged = slot.merge(ty, hierarchy);
+            if merged != *slot {
+                *slot = merged;
+                changed = true;
+            }
+        }
+
+        // a merged `long` or `double` whose second half was lost is no longer usable
+        for idx in 0..self.locals.len() {
+            if self.locals[idx].size() == 2
+                && self.locals.get(idx + 1) != Some(&VerificationType::Top)
+            {
+                self.locals[idx] = VerificationType::Top;
+                changed = true;
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Return whether this frame can flow into an instruction whose frame is `target`: the
+    /// operand stacks must have the same height, and each slot must be assignable to the
+    /// corresponding slot of `target`. Locals missing from either frame are `Top`.
+    pub fn is_assignable_to(&self, target: &Frame, hierarchy: &dyn ClassHierarchy) -> bool {
+        let top = VerificationType::Top;
+        self.stack.len() == target.stack.len()
+            && self
+                .stack
+                .iter()
+                .zip(&target.stack)
+                .all(|(ty, target)| ty.is_assignable_to(target, hierarchy))
+            && target.locals.iter().enumerate().all(|(idx, target)| {
+                self.locals
+                    .get(idx)
+                    .unwrap_or(&top)
+                    .is_assignable_to(target, hierarchy)
+            })
+    }
+
+    fn push(&mut self, ty: VerificationType) {
+        push_slots(&mut self.stack, ty);
+    }
+
+    fn pop(&mut self, offset: usize) -> AnalysisResult<VerificationType> {
+        self.stack
+            .pop()
+            .ok_or(AnalysisError::StackUnderflow { offset })
+    }
+
+    /// Pop a value of type `expected`, which must be `Integer`, `Float`, `Long` or `Double`.
+    fn pop_primitive(&mut self, offset: usize, expected: VerificationType) -> AnalysisResult<()> {
+        if expected.size() == 2 {
+            let second = self.pop(offset)?;
+            if second != VerificationType::Top {
+                return Err(mismatch(offset, describe(&expected), second));
+            }
+            let first = self.pop(offset)?;
+            if first != expected {
+                return Err(mismatch(offset, describe(&expected), first));
+            }
+        } else {
+            let value = self.pop(offset)?;
+            if value != expected {
+                return Err(mismatch(offset, describe(&expected), value));
+            }
+        }
+        Ok(())
+    }
+
+    fn pop_reference(&mut self, offset: usize) -> AnalysisResult<VerificationType> {
+        let value = self.pop(offset)?;
+        if !value.is_reference() {
+            return Err(mismatch(offset, "reference", value));
+        }
+        Ok(value)
+    }
+
+    /// Pop an initialized reference, e.g. the receiver of a method other than `<init>`.
+    fn pop_initialized(&mut self, offset: usize) -> AnalysisResult<VerificationType> {
+        match self.pop(offset)? {
+            value @ (VerificationType::Null | VerificationType::Object(_)) => Ok(value),
+            value => Err(mismatch(offset, "initialized reference", value)),
+        }
+    }
+
+    /// Pop an array reference (or `null`).
+    fn pop_array(&mut self, offset: usize) -> AnalysisResult<VerificationType> {
+        match self.pop(offset)? {
+            VerificationType::Object(name) if name.starts_with('[') => {
+                Ok(VerificationType::Object(name))
+            }
+            VerificationType::Null => Ok(VerificationType::Null),
+            value => Err(mismatch(offset, "array", value)),
+        }
+    }
+
+    /// Pop an array (or `null`) whose elements have one of the primitive types in `elements`,
+    /// e.g. `"BZ"` for `baload`.
+    fn pop_primitive_array(&mut self, offset: usize, elements: &str) -> AnalysisResult<()> {
+        match self.pop_array(offset)? {
+            VerificationType::Object(name) if name.len() != 2 || !elements.contains(&name[1..]) => {
+                let expected = match elements {
+                    "I" => "int array",
+                    "J" => "long array",
+                    "F" => "float array",
+                    "D" => "double array",
+                    "C" => "char array",
+                    "S" => "short array",
+                    _ => "byte or boolean array",
+                };
+                Err(mismatch(offset, expected, VerificationType::Object(name)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Pop an array of references (or `null`), returning its element type.
+    fn pop_reference_array(&mut self, offset: usize) -> AnalysisResult<VerificationType> {
+        match self.pop_array(offset)? {
+            VerificationType::Object(name) => match element_type(&name) {
+                Some(element @ VerificationType::Object(_)) => Ok(element),
+                _ => Err(mismatch(
+                    offset,
+                    "array of references",
+                    VerificationType::Object(name),
+                )),
+            },
+            _ => Ok(VerificationType::Null),
+        }
+    }
+
+    /// Check that the value whose topmost slot is `depth` slots from the top of the operand
+    /// stack is not the second half of a `long` or `double`.
+    fn check_boundary(&self, offset: usize, depth: usize) -> AnalysisResult<()> {
+        let idx = self
+            .stack
+            .len()
+            .checked_sub(depth)
+            .ok_or(AnalysisError::StackUnderflow { offset })?;
+        match &self.stack[idx] {
+            VerificationType::Top => {
+                // a frame may record a lone `top` at the bottom of the stack
+                let found = match idx {
+                    0 => VerificationType::Top,
+                    _ => self.stack[idx - 1].clone(),
+                };
+                Err(mismatch(offset, "category 1 value", found))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Duplicate the top `count` slots of the operand stack, inserting the copies `depth` slots
+    /// below the top.
+    fn dup(&mut self, count: usize, depth: usize) {
+        let len = self.stack.len();
+        let copies = self.stack[len - count..].to_vec();
+        let at = len - depth;
+        self.stack.splice(at..at, copies);
+    }
+
+    /// Check that the local variable at `index` holds a value of type `expected`, which must be
+    /// `Integer`, `Float`, `Long` or `Double`.
+    fn local(&self, offset: usize, index: usize, expected: VerificationType) -> AnalysisResult<()> {
+        let value = self.locals.get(index).unwrap_or(&VerificationType::Top);
+        let second = self.locals.get(index + 1);
+        if *value != expected || (expected.size() == 2 && second != Some(&VerificationType::Top)) {
+            return Err(mismatch(offset, describe(&expected), value.clone()));
+        }
+        Ok(())
+    }
+
+    fn load(
+        &mut self,
+        offset: usize,
+        index: usize,
+        expected: VerificationType,
+    ) -> AnalysisResult<()> {
+        self.local(offset, index, expected.clone())?;
+        self.push(expected);
+        Ok(())
+    }
+
+    fn load_reference(&mut self, offset: usize, index: usize) -> AnalysisResult<()> {
+        match self.locals.get(index) {
+            Some(value) if value.is_reference() => {
+                let value = value.clone();
+                self.push(value);
+                Ok(())
+            }
+            value => Err(mismatch(
+                offset,
+                "reference",
+                value.cloned().unwrap_or(VerificationType::Top),
+            )),
+        }
+    }
+
+    /// Store a value of type `ty` in the local variable at `index`, invalidating any `long` or
+    /// `double` that it overwrites half of.
+    fn store(&mut self, index: usize, ty: VerificationType) {
+        let end = index + ty.size();
+        if self.locals.len() < end {
+            self.locals.resize(end, VerificationType::Top);
+        }
+        if index > 0 && self.locals[index - 1].size() == 2 {
+            self.locals[index - 1] = VerificationType::Top;
+        }
+        if ty.size() == 2 {
+            self.locals[index + 1] = VerificationType::Top;
+        }
+        self.locals[index] = ty;
+    }
+
+    /// Replace every occurrence of the uninitialized type `uninitialized` with `initialized`.
+    fn initialize(&mut self, uninitialized: &VerificationType, initialized: VerificationType) {
+        for slot in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if slot == uninitialized {
+                *slot = initialized.clone();
+            }
+        }
+    }
+}
+
+/// Push the slots of a value of type `ty` onto `slots`.
+fn push_slots(slots: &mut Vec<VerificationType>, ty: VerificationType) {
+    let size = ty.size();
+    slots.push(ty);
+    if size == 2 {
+        slots.push(VerificationType::Top);
+    }
+}
+
+/// Remove the second slot of each `long` and `double` in `slots`.
+fn compact(slots: &[VerificationType]) -> Vec<VerificationType> {
+    let mut types = Vec::with_capacity(slots.len());
+    let mut idx = 0;
+    while idx < slots.len() {
+        types.push(slots[idx].clone());
+        idx += slots[idx].size();
+    }
+    types
+}
+
+/// Describe the operand expected by an instruction that takes a value of type `ty`.
+fn describe(ty: &VerificationType) -> &'static str {
+    match ty {
+        VerificationType::Integer => "int",
+        VerificationType::Float => "float",
+        VerificationType::Long => "long",
+        VerificationType::Double => "double",
+        _ => "reference",
+    }
+}
+
+fn mismatch(offset: usize, expected: &'static str, found: VerificationType) -> AnalysisError {
+    AnalysisError::TypeMismatch {
+        offset,
+        expected,
+        found,
+    }
+}
+
+/// Computes the effect of each instruction of a method's code on a frame.
+pub(crate) struct Interpreter<'a> {
+    pub code: &'a [u8],
+    pub constant_pool: &'a ConstantPool,
+    pub this_class: &'a str,
+    /// When set, references passed to fields, methods and `athrow` must be assignable to the
+    /// types these expect, as in the verifier. Otherwise only their kind is checked.
+    pub hierarchy: Option<&'a dyn ClassHierarchy>,
+}
+
+impl Interpreter<'_> {
+    /// Update `frame` from the state before the instruction at `offset` to the state after it.
+    pub fn execute(
+        &self,
+        offset: usize,
+        instruction: &Instruction,
+        frame: &mut Frame,
+    ) -> AnalysisResult<()> {
+        use Instruction::*;
+        use VerificationType::{Double as D, Float as F, Integer as I, Long as J};
+
+        match instruction {
+            Nop | Goto(_) | GotoW(_) | Return => {}
+            Iinc { index, .. } => {
+                frame.local(offset, *index as usize, I)?;
+            }
+
+            AconstNull => frame.push(VerificationType::Null),
+            IconstM1 | Iconst0 | Iconst1 | Iconst2 | Iconst3 | Iconst4 | Iconst5 | Bipush(_)
+            | Sipush(_) => frame.push(I),
+            Lconst0 | Lconst1 => frame.push(J),
+            Fconst0 | Fconst1 | Fconst2 => frame.push(F),
+            Dconst0 | Dconst1 => frame.push(D),
+            Ldc(index) => frame.push(self.constant(offset, *index as u16, false)?),
+            LdcW(index) => frame.push(self.constant(offset, *index, false)?),
+            Ldc2W(index) => frame.push(self.constant(offset, *index, true)?),
+
+            Iload(index) => frame.load(offset, *index as usize, I)?,
+            Lload(index) => frame.load(offset, *index as usize, J)?,
+            Fload(index) => frame.load(offset, *index as usize, F)?,
+            Dload(index) => frame.load(offset, *index as usize, D)?,
+            Aload(index) => frame.load_reference(offset, *index as usize)?,
+            Iload0 => frame.load(offset, 0, I)?,
+            Iload1 => frame.load(offset, 1, I)?,
+            Iload2 => frame.load(offset, 2, I)?,
+            Iload3 => frame.load(offset, 3, I)?,
+            Lload0 => frame.load(offset, 0, J)?,
+            Lload1 => frame.load(offset, 1, J)?,
+            Lload2 => frame.load(offset, 2, J)?,
+            Lload3 => frame.load(offset, 3, J)?,
+            Fload0 => frame.load(offset, 0, F)?,
+            Fload1 => frame.load(offset, 1, F)?,
+            Fload2 => frame.load(offset, 2, F)?,
+            Fload3 => frame.load(offset, 3, F)?,
+            Dload0 => frame.load(offset, 0, D)?,
+            Dload1 => frame.load(offset, 1, D)?,
+            Dload2 => frame.load(offset, 2, D)?,
+            Dload3 => frame.load(offset, 3, D)?,
+            Aload0 => frame.load_reference(offset, 0)?,
+            Aload1 => frame.load_reference(offset, 1)?,
+            Aload2 => frame.load_reference(offset, 2)?,
+            Aload3 => frame.load_reference(offset, 3)?,
+
+            Istore(index) => self.store(offset, frame, *index as usize, I)?,
+            Lstore(index) => self.store(offset, frame, *index as usize, J)?,
+            Fstore(index) => self.store(offset, frame, *index as usize, F)?,
+            Dstore(index) => self.store(offset, frame, *index as usize, D)?,
+            Astore(index) => self.store_reference(offset, frame, *index as usize)?,
+            Istore0 => self.store(offset, frame, 0, I)?,
+            Istore1 => self.store(offset, frame, 1, I)?,
+            Istore2 => self.store(offset, frame, 2, I)?,
+            Istore3 => self.store(offset, frame, 3, I)?,
+            Lstore0 => self.store(offset, frame, 0, J)?,
+            Lstore1 => self.store(offset, frame, 1, J)?,
+            Lstore2 => self.store(offset, frame, 2, J)?,
+            Lstore3 => self.store(offset, frame, 3, J)?,
+            Fstore0 => self.store(offset, frame, 0, F)?,
+            Fstore1 => self.store(offset, frame, 1, F)?,
+            Fstore2 => self.store(offset, frame, 2, F)?,
+            Fstore3 => self.store(offset, frame, 3, F)?,
+            Dstore0 => self.store(offset, frame, 0, D)?,
+            Dstore1 => self.store(offset, frame, 1, D)?,
+            Dstore2 => self.store(offset, frame, 2, D)?,
+            Dstore3 => self.store(offset, frame, 3, D)?,
+            Astore0 => self.store_reference(offset, frame, 0)?,
+            Astore1 => self.store_reference(offset, frame, 1)?,
+            Astore2 => self.store_reference(offset, frame, 2)?,
+            Astore3 => self.store_reference(offset, frame, 3)?,
+
+            Wide(wide) => match wide {
+                WideInstruction::Iload(index) => frame.load(offset, *index as usize, I)?,
+                WideInstruction::Lload(index) => frame.load(offset, *index as usize, J)?,
+                WideInstruction::Fload(index) => frame.load(offset, *index as usize, F)?,
+                WideInstruction::Dload(index) => frame.load(offset, *index as usize, D)?,
+                WideInstruction::Aload(index) => frame.load_reference(offset, *index as usize)?,
+                WideInstruction::Istore(index) => self.store(offset, frame, *index as usize, I)?,
+                WideInstruction::Lstore(index) => self.store(offset, frame, *index as usize, J)?,
+                WideInstruction::Fstore(index) => self.store(offset, frame, *index as usize, F)?,
+                WideInstruction::Dstore(index) => self.store(offset, frame, *index as usize, D)?,
+                WideInstruction::Astore(index) => {
+                    self.store_reference(offset, frame, *index as usize)?
+                }
+                WideInstruction::Iinc { index, .. } => {
+                    frame.local(offset, *index as usize, I)?;
+                }
+                WideInstruction::Ret(_) => {
+                    return Err(AnalysisError::UnsupportedSubroutine { offset })
+                }
+            },
+
+            Iaload => self.array_load(offset, frame, "I", I)?,
+            Baload => self.array_load(offset, frame, "BZ", I)?,
+            Caload => self.array_load(offset, frame, "C", I)?,
+            Saload => self.array_load(offset, frame, "S", I)?,
+            Laload => self.array_load(offset, frame, "J", J)?,
+            Faload => self.array_load(offset, frame, "F", F)?,
+            Daload => self.array_load(offset, frame, "D", D)?,
+            Aaload => {
+                frame.pop_primitive(offset, I)?;
+                let element = frame.pop_reference_array(offset)?;
+                frame.push(element);
+            }
+
+            Iastore => self.array_store(offset, frame, "I", I)?,
+            Bastore => self.array_store(offset, frame, "BZ", I)?,
+            Castore => self.array_store(offset, frame, "C", I)?,
+            Sastore => self.array_store(offset, frame, "S", I)?,
+            Lastore => self.array_store(offset, frame, "J", J)?,
+            Fastore => self.array_store(offset, frame, "F", F)?,
+            Dastore => self.array_store(offset, frame, "D", D)?,
+            Aastore => {
+                // the element type is checked at run time
+                frame.pop_reference(offset)?;
+                frame.pop_primitive(offset, I)?;
+                frame.pop_reference_array(offset)?;
+            }
+
+            Pop => {
+                frame.check_boundary(offset, 1)?;
+                frame.pop(offset)?;
+            }
+            Pop2 => {
+                frame.check_boundary(offset, 2)?;
+                frame.pop(offset)?;
+                frame.pop(offset)?;
+            }
+            Dup => {
+                frame.check_boundary(offset, 1)?;
+                frame.dup(1, 1);
+            }
+            DupX1 => {
+                frame.check_boundary(offset, 1)?;
+                frame.check_boundary(offset, 2)?;
+                frame.dup(1, 2);
+            }
+            DupX2 => {
+                frame.check_boundary(offset, 1)?;
+                frame.check_boundary(offset, 3)?;
+                frame.dup(1, 3);
+            }
+            Dup2 => {
+                frame.check_boundary(offset, 2)?;
+                frame.dup(2, 2);
+            }
+            Dup2X1 => {
+                frame.check_boundary(offset, 2)?;
+                frame.check_boundary(offset, 3)?;
+                frame.dup(2, 3);
+            }
+            Dup2X2 => {
+                frame.check_boundary(offset, 2)?;
+                frame.check_boundary(offset, 4)?;
+                frame.dup(2, 4);
+            }
+            Swap => {
+                frame.check_boundary(offset, 1)?;
+                frame.check_boundary(offset, 2)?;
+                let len = frame.stack.len();
+                frame.stack.swap(len - 1, len - 2);
+            }
+
+            Iadd | Isub | Imul | Idiv | Irem | Iand | Ior | Ixor | Ishl | Ishr | Iushr => {
+                self.binary(offset, frame, I, I, I)?
+            }
+            Ladd | Lsub | Lmul | Ldiv | Lrem | Land | Lor | Lxor => {
+                self.binary(offset, frame, J, J, J)?
+            }
+            Lshl | Lshr | Lushr => self.binary(offset, frame, J, I, J)?,
+            Fadd | Fsub | Fmul | Fdiv | Frem => self.binary(offset, frame, F, F, F)?,
+            Dadd | Dsub | Dmul | Ddiv | Drem => self.binary(offset, frame, D, D, D)?,
+            Lcmp => self.binary(offset, frame, J, J, I)?,
+            Fcmpl | Fcmpg => self.binary(offset, frame, F, F, I)?,
+            Dcmpl | Dcmpg => self.binary(offset, frame, D, D, I)?,
+
+            Ineg | I2b | I2c | I2s => self.unary(offset, frame, I, I)?,
+            Lneg => self.unary(offset, frame, J, J)?,
+            Fneg => self.unary(offset, frame, F, F)?,
+            Dneg => self.unary(offset, frame, D, D)?,
+            I2l => self.unary(offset, frame, I, J)?,
+            I2f => self.unary(offset, frame, I, F)?,
+            I2d => self.unary(offset, frame, I, D)?,
+            L2i => self.unary(offset, frame, J, I)?,
+            L2f => self.unary(offset, frame, J, F)?,
+            L2d => self.unary(offset, frame, J, D)?,
+            F2i => self.unary(offset, frame, F, I)?,
+            F2l => self.unary(offset, frame, F, J)?,
+            F2d => self.unary(offset, frame, F, D)?,
+            D2i => self.unary(offset, frame, D, I)?,
+            D2l => self.unary(offset, frame, D, J)?,
+            D2f => self.unary(offset, frame, D, F)?,
+
+            Ifeq(_)
+            | Ifne(_)
+            | Iflt(_)
+            | Ifge(_)
+            | Ifgt(_)
+            | Ifle(_)
+            | Tableswitch { .. }
+            | Lookupswitch { .. }
+            | Ireturn => frame.pop_primitive(offset, I)?,
+            IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_) => {
+                frame.pop_primitive(offset, I)?;
+                frame.pop_primitive(offset, I)?;
+            }
+            IfAcmpeq(_) | IfAcmpne(_) => {
+                frame.pop_reference(offset)?;
+                frame.pop_reference(offset)?;
+            }
+            Ifnull(_) | Ifnonnull(_) => {
+                frame.pop_reference(offset)?;
+            }
+            Jsr(_) | JsrW(_) | Ret(_) => {
+                return Err(AnalysisError::UnsupportedSubroutine { offset })
+            }
+
+            Lreturn => frame.pop_primitive(offset, J)?,
+            Freturn => frame.pop_primitive(offset, F)?,
+            Dreturn => frame.pop_primitive(offset, D)?,
+            Areturn | Monitorenter | Monitorexit => {
+                frame.pop_initialized(offset)?;
+            }
+            Athrow => {
+                let value = frame.pop_initialized(offset)?;
+                self.check_assignable(
+                    offset,
+                    &value,
+                    &VerificationType::object("java/lang/Throwable"),
+                )?;
+            }
+
+            Getstatic(index) => {
+                let ty = self.field_type(*index)?;
+                frame.push(ty);
+            }
+            Putstatic(index) => {
+                let ty = self.field_type(*index)?;
+                self.pop_value(offset, frame, &ty)?;
+            }
+            Getfield(index) => {
+                let ty = self.field_type(*index)?;
+                let owner = self.constant_pool.get_member_ref(*index)?.owner;
+                let receiver = frame.pop_initialized(offset)?;
+                self.check_assignable(offset, &receiver, &VerificationType::object(&owner))?;
+                frame.push(ty);
+            }
+            Putfield(index) => {
+                let ty = self.field_type(*index)?;
+                let owner = self.constant_pool.get_member_ref(*index)?.owner;
+                self.pop_value(offset, frame, &ty)?;
+                // fields of `this` may be assigned before the superclass constructor is called
+                let receiver = match frame.pop_reference(offset)? {
+                    VerificationType::UninitializedThis if owner == self.this_class => {
+                        VerificationType::object(self.this_class)
+                    }
+                    receiver => receiver,
+                };
+                self.check_assignable(offset, &receiver, &VerificationType::object(&owner))?;
+                if let VerificationType::Uninitialized(_) | VerificationType::UninitializedThis =
+                    receiver
+                {
+                    return Err(mismatch(offset, "initialized reference", receiver));
+                }
+            }
+
+            Invokevirtual(index) => {
+                let method = self.constant_pool.get_member_ref(*index)?;
+                let receiver = VerificationType::object(&method.owner);
+                self.call(offset, frame, &method.descriptor, Some(&receiver))?
+            }
+            Invokeinterface { index, .. } => {
+                // as for assignments, the receiver's interfaces are checked at run time
+                let descriptor = self.constant_pool.get_member_ref(*index)?.descriptor;
+                let receiver = VerificationType::object(super::hierarchy::OBJECT);
+                self.call(offset, frame, &descriptor, Some(&receiver))?
+            }
+            Invokestatic(index) => {
+                let descriptor = self.constant_pool.get_member_ref(*index)?.descriptor;
+                self.call(offset, frame, &descriptor, None)?
+            }
+            Invokespecial(index) => self.invokespecial(offset, frame, *index)?,
+            Invokedynamic(index) => {
+                let descriptor = self.constant_pool.get_dynamic(*index)?.descriptor;
+                self.call(offset, frame, &descriptor, None)?;
+            }
+
+            New(index) => {
+                self.constant_pool.get_class_name(*index)?;
+                frame.push(VerificationType::Uninitialized(offset as u16));
+            }
+            Newarray(atype) => {
+                frame.pop_primitive(offset, I)?;
+                let name = match atype {
+                    4 => "[Z",
+                    5 => "[C",
+                    6 => "[F",
+                    7 => "[D",
+                    8 => "[B",
+                    9 => "[S",
+                    10 => "[I",
+                    11 => "[J",
+                    _ => {
+                        return Err(AnalysisError::Bytecode(BytecodeError::InvalidOperand {
+                            offset,
+                        }))
+                    }
+                };
+                frame.push(VerificationType::object(name));
+            }
+            Anewarray(index) => {
+                let name = self.constant_pool.get_class_name(*index)?;
+                frame.pop_primitive(offset, I)?;
+                frame.push(VerificationType::Object(array_of(&name)));
+            }
+            Multianewarray { index, dimensions } => {
+                let name = self.constant_pool.get_class_name(*index)?;
+                for _ in 0..*dimensions {
+                    frame.pop_primitive(offset, I)?;
+                }
+                frame.push(VerificationType::object(&name));
+            }
+            Arraylength => {
+                frame.pop_array(offset)?;
+                frame.push(I);
+            }
+            Checkcast(index) => {
+                let name = self.constant_pool.get_class_name(*index)?;
+                frame.pop_initialized(offset)?;
+                frame.push(VerificationType::object(&name));
+            }
+            Instanceof(index) => {
+                self.constant_pool.get_class_name(*index)?;
+                frame.pop_initialized(offset)?;
+                frame.push(I);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the type of the loadable constant at `index`.
+    fn constant(&self, offset: usize, index: u16, wide: bool) -> AnalysisResult<VerificationType> {
+        let ty = match self.constant_pool.entry(index)? {
+            CpInfo::ConstantIntegerInfo { .. } if !wide => VerificationType::Integer,
+            CpInfo::ConstantFloatInfo { .. } if !wide => VerificationType::Float,
+            CpInfo::ConstantLongInfo { .. } if wide => VerificationType::Long,
+            CpInfo::ConstantDoubleInfo { .. } if wide => VerificationType::Double,
+            CpInfo::ConstantStringInfo { .. } if !wide => {
+                VerificationType::object("java/lang/String")
+            }
+            CpInfo::ConstantClassInfo { .. } if !wide => {
+                VerificationType::object("java/lang/Class")
+            }
+            CpInfo::ConstantMethodTypeInfo { .. } if !wide => {
+                VerificationType::object("java/lang/invoke/MethodType")
+            }
+            CpInfo::ConstantMethodHandleInfo { .. } if !wide => {
+                VerificationType::object("java/lang/invoke/MethodHandle")
+            }
+            CpInfo::ConstantDynamicInfo { .. } => {
+                let descriptor = self.constant_pool.get_dynamic(index)?.descriptor;
+                let ty = VerificationType::from(&FieldType::parse(&descriptor)?);
+                if (ty.size() == 2) != wide {
+                    return Err(AnalysisError::Bytecode(BytecodeError::InvalidOperand {
+                        offset,
+                    }));
+                }
+                ty
+            }
+            _ => {
+                return Err(AnalysisError::Bytecode(BytecodeError::InvalidOperand {
+                    offset,
+                }))
+            }
+        };
+        Ok(ty)
+    }
+
+    /// Return the type of the field referenced by the constant pool entry at `index`.
+    fn field_type(&self, index: u16) -> AnalysisResult<VerificationType> {
+        let descriptor = self.constant_pool.get_member_ref(index)?.descriptor;
+        Ok(VerificationType::from(&FieldType::parse(&descriptor)?))
+    }
+
+    fn store(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        index: usize,
+        ty: VerificationType,
+    ) -> AnalysisResult<()> {
+        frame.pop_primitive(offset, ty.clone())?;
+        frame.store(index, ty);
+        Ok(())
+    }
+
+    fn store_reference(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        index: usize,
+    ) -> AnalysisResult<()> {
+        let value = frame.pop_reference(offset)?;
+        frame.store(index, value);
+        Ok(())
+    }
+
+    /// Load an element of type `element` from an array whose elements have one of the
+    /// primitive types `elements`.
+    fn array_load(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        elements: &str,
+        element: VerificationType,
+    ) -> AnalysisResult<()> {
+        frame.pop_primitive(offset, VerificationType::Integer)?;
+        frame.pop_primitive_array(offset, elements)?;
+        frame.push(element);
+        Ok(())
+    }
+
+    /// Store an element of type `element` into an array whose elements have one of the
+    /// primitive types `elements`.
+    fn array_store(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        elements: &str,
+        element: VerificationType,
+    ) -> AnalysisResult<()> {
+        frame.pop_primitive(offset, element)?;
+        frame.pop_primitive(offset, VerificationType::Integer)?;
+        frame.pop_primitive_array(offset, elements)?;
+        Ok(())
+    }
+
+    /// Check that `value` is assignable to `expected`, if assignability is checked.
+    fn check_assignable(
+        &self,
+        offset: usize,
+        value: &VerificationType,
+        expected: &VerificationType,
+    ) -> AnalysisResult<()> {
+        match self.hierarchy {
+            Some(hierarchy) if !value.is_assignable_to(expected, hierarchy) => {
+                Err(AnalysisError::NotAssignable {
+                    offset,
+                    expected: expected.clone(),
+                    found: value.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Pop a value of the type `ty`, which is taken from a descriptor.
+    fn pop_value(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        ty: &VerificationType,
+    ) -> AnalysisResult<()> {
+        match ty {
+            VerificationType::Object(_) => {
+                let value = frame.pop_initialized(offset)?;
+                self.check_assignable(offset, &value, ty)
+            }
+            _ => frame.pop_primitive(offset, ty.clone()),
+        }
+    }
+
+    fn unary(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        operand: VerificationType,
+        result: VerificationType,
+    ) -> AnalysisResult<()> {
+        frame.pop_primitive(offset, operand)?;
+        frame.push(result);
+        Ok(())
+    }
+
+    fn binary(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        left: VerificationType,
+        right: VerificationType,
+        result: VerificationType,
+    ) -> AnalysisResult<()> {
+        frame.pop_primitive(offset, right)?;
+        frame.pop_primitive(offset, left)?;
+        frame.push(result);
+        Ok(())
+    }
+
+    /// Pop the arguments (and the receiver of type `receiver`, for instance methods) of a call
+    /// to a method with descriptor `descriptor`, and push its result.
+    fn call(
+        &self,
+        offset: usize,
+        frame: &mut Frame,
+        descriptor: &str,
+        receiver: Option<&VerificationType>,
+    ) -> AnalysisResult<()> {
+        let descriptor = MethodDescriptor::parse(descriptor)?;
+        for parameter in descriptor.parameters.iter().rev() {
+            self.pop_value(offset, frame, &VerificationType::from(parameter))?;
+        }
+        if let Some(receiver) = receiver {
+            self.pop_value(offset, frame, receiver)?;
+        }
+        if let Some(return_type) = &descriptor.return_type {
+            frame.push(VerificationType::from(return_type));
+        }
+        Ok(())
+    }
+
+    /// `invokespecial` either calls an instance initialization method, which initializes its
+    /// receiver, or a private or superclass method.
+    fn invokespecial(&self, offset: usize, frame: &mut Frame, index: u16) -> AnalysisResult<()> {
+        let method = self.constant_pool.get_member_ref(index)?;
+        if method.name != "<init>" {
+            let receiver = VerificationType::object(self.this_class);
+            return self.call(offset, frame, &method.descriptor, Some(&receiver));
+        }
+
+        let descriptor = MethodDescriptor::parse(&method.descriptor)?;
+        if descriptor.return_type.is_some() {
+            return Err(DescriptorError::new(
+                &method.descriptor,
+                DescriptorErrorKind::NonVoidInitializer,
+            )
+            .into());
+        }
+        for parameter in descriptor.parameters.iter().rev() {
+            self.pop_value(offset, frame, &VerificationType::from(parameter))?;
+        }
+
+        let receiver = frame.pop(offset)?;
+        let owner = VerificationType::object(&method.owner);
+        let initialized = match &receiver {
+            VerificationType::UninitializedThis => {
+                // `this` is initialized by a constructor of its own class or its superclass
+                let this_class = VerificationType::object(self.this_class);
+                let is_super = self.hierarchy.is_none_or(|hierarchy| {
+                    hierarchy.super_class(self.this_class).as_deref() == Some(&method.owner)
+                });
+                if owner != this_class && !is_super {
+                    return Err(AnalysisError::NotAssignable {
+                        offset,
+                        expected: owner,
+                        found: this_class,
+                    });
+                }
+                this_class
+            }
+            VerificationType::Uninitialized(new_offset) => {
+                let class = VerificationType::object(&self.new_class(offset, *new_offset)?);
+                if self.hierarchy.is_some() && class != owner {
+                    return Err(AnalysisError::NotAssignable {
+                        offset,
+                        expected: owner,
+                        found: class,
+                    });
+                }
+                class
+            }
+            _ => return Err(mismatch(offset, "uninitialized reference", receiver)),
+        };
+        frame.initialize(&receiver, initialized);
+        Ok(())
+    }
+
+    /// Return the name of the class instantiated by the `new` instruction at `new_offset`.
+    fn new_class(&self, offset: usize, new_offset: u16) -> AnalysisResult<String> {
+        let new_offset = new_offset as usize;
+        match self.code.get(new_offset..new_offset + 3) {
+            Some([NEW, hi, lo]) => {
+                let index = u16::from_be_bytes([*hi, *lo]);
+                Ok(self.constant_pool.get_class_name(index)?.into_owned())
+            }
+            _ => Err(mismatch(
+                offset,
+                "uninitialized reference",
+                VerificationType::Uninitialized(new_offset as u16),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SimpleClassHierarchy;
+
+    #[test]
+    fn test_merge_types() {
+        use VerificationType::*;
+
+        let hierarchy = SimpleClassHierarchy::new();
+        let merge = |a: VerificationType, b: VerificationType| a.merge(&b, &hierarchy);
+
+        assert_eq!(merge(Integer, Integer), Integer);
+        assert_eq!(merge(Integer, Float), Top);
+        assert_eq!(
+            merge(Null, VerificationType::object("A")),
+            VerificationType::object("A")
+        );
+        assert_eq!(merge(Null, Uninitialized(3)), Top);
+        assert_eq!(
+            merge(VerificationType::object("A"), VerificationType::object("B")),
+            VerificationType::object("java/lang/Object")
+        );
+        assert_eq!(
+            merge(
+                VerificationType::object("[LA;"),
+                VerificationType::object("[LB;")
+            ),
+            VerificationType::object("[Ljava/lang/Object;")
+        );
+        assert_eq!(
+            merge(
+                VerificationType::object("[[I"),
+                VerificationType::object("[[J")
+            ),
+            VerificationType::object("[Ljava/lang/Object;")
+        );
+        assert_eq!(
+            merge(
+                VerificationType::object("[I"),
+                VerificationType::object("[J")
+            ),
+            VerificationType::object("java/lang/Object")
+        );
+    }
+
+    #[test]
+    fn test_frame_merge() {
+        use VerificationType::*;
+
+        let hierarchy = SimpleClassHierarchy::new();
+        let mut frame = Frame {
+            locals: vec![Integer, Long, Top, Float],
+            stack: vec![Integer],
+        };
+        let other = Frame {
+            locals: vec![Integer, Integer, Top],
+            stack: vec![Integer],
+        };
+
+        assert!(frame.merge(&other, 0, &hierarchy).unwrap());
+        assert_eq!(frame.locals, [Integer, Top, Top]);
+        assert_eq!(frame.compact_locals(), [Integer]);
+        assert!(!frame.merge(&other, 0, &hierarchy).unwrap());
+
+        let empty = Frame::default();
+        assert_eq!(
+            frame.merge(&empty, 7, &hierarchy),
+            Err(AnalysisError::InconsistentStackHeight {
+                offset: 7,
+                expected: 1,
+                found: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_initial_frame() {
+        use VerificationType::*;
+
+        let frame = Frame::initial("A", "<init>", "(J[I)V", false).unwrap();
+        assert_eq!(
+            frame.locals,
+            [UninitializedThis, Long, Top, VerificationType::object("[I")]
+        );
+        assert_eq!(
+            frame.compact_locals(),
+            [UninitializedThis, Long, VerificationType::object("[I")]
+        );
+
+        let frame = Frame::initial("java/lang/Object", "<init>", "()V", false).unwrap();
+        assert_eq!(frame.locals, [VerificationType::object("java/lang/Object")]);
+    }
+}
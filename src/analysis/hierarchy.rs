@@ -0,0 +1,207 @@
+//! The class hierarchy oracle used to merge reference types when computing stack map frames, and
+//! to check assignability when verifying them.
+
+use crate::model::{access_flags::ACC_INTERFACE, constant_pool::ConstantPoolError, ClassFile};
+use std::collections::{HashMap, HashSet};
+
+/// The internal name of `java.lang.Object`, the root of the class hierarchy.
+pub const OBJECT: &str = "java/lang/Object";
+
+/// Answers questions about classes that are not (necessarily) part of the code being analysed.
+/// Class names are internal names, e.g. `java/lang/String`.
+pub trait ClassHierarchy {
+    /// Return the internal name of the direct superclass of `class`, or `None` if `class` is
+    /// `java/lang/Object`, an interface, or unknown.
+    fn super_class(&self, class: &str) -> Option<String>;
+
+    /// Return whether `class` is an interface.
+    fn is_interface(&self, class: &str) -> bool;
+
+    /// Return the internal name of the most specific common superclass of the classes `a` and
+    /// `b`. Interfaces, and classes whose superclass chains do not meet, have
+    /// `java/lang/Object` in common.
+    fn common_super_class(&self, a: &str, b: &str) -> String {
+        if a == b {
+            return a.to_string();
+        }
+        if self.is_interface(a) || self.is_interface(b) {
+            return OBJECT.to_string();
+        }
+
+        let mut ancestors = HashSet::new();
+        let mut class = Some(a.to_string());
+        while let Some(name) = class {
+            class = self.super_class(&name);
+            if !ancestors.insert(name) {
+                break;
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut class = Some(b.to_string());
+        while let Some(name) = class {
+            if ancestors.contains(&name) {
+                return name;
+            }
+            class = self.super_class(&name);
+            if !visited.insert(name) {
+                break;
+            }
+        }
+
+        OBJECT.to_string()
+    }
+
+    /// Return whether a value of the class `from` can be assigned to a variable of the class
+    /// or interface `to`. As in the type checking verifier (JVMS 4.10.1.2), every class is
+    /// assignable to every interface; interface checks happen at run time.
+    fn is_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT || self.is_interface(to) {
+            return true;
+        }
+
+        let mut visited = HashSet::new();
+        let mut class = self.super_class(from);
+        while let Some(name) = class {
+            if name == to {
+                return true;
+            }
+            class = self.super_class(&name);
+            if !visited.insert(name) {
+                break;
+            }
+        }
+        false
+    }
+}
+
+/// A `ClassHierarchy` over a fixed set of classes, e.g. those of an application and the library
+/// classes it uses.
+#[derive(Debug, Clone, Default)]
+pub struct SimpleClassHierarchy {
+    /// The superclass and "is an interface" flag of each class, by name.
+    classes: HashMap<String, (Option<String>, bool)>,
+}
+
+impl SimpleClassHierarchy {
+    pub fn new() -> Self {
+        SimpleClassHierarchy::default()
+    }
+
+    /// Add the class `name` with the direct superclass `super_class`.
+    pub fn add_class(&mut self, name: &str, super_class: &str) -> &mut Self {
+        self.classes
+            .insert(name.to_string(), (Some(super_class.to_string()), false));
+        self
+    }
+
+    /// Add the interface `name`.
+    pub fn add_interface(&mut self, name: &str) -> &mut Self {
+        self.classes.insert(name.to_string(), (None, true));
+        self
+    }
+
+    /// Add the class or interface defined by `class_file`.
+    pub fn add_class_file(
+        &mut self,
+        class_file: &ClassFile,
+    ) -> Result<&mut Self, ConstantPoolError> {
+        let constant_pool = &class_file.constant_pool;
+        let name = constant_pool.get_class_name(class_file.this_class)?;
+
+        if class_file.access_flags & ACC_INTERFACE != 0 {
+            return Ok(self.add_interface(&name));
+        }
+        match class_file.super_class {
+            0 => {
+                self.classes.insert(name.into_owned(), (None, false));
+                Ok(self)
+            }
+            super_class => {
+                let super_class = constant_pool.get_class_name(super_class)?;
+                Ok(self.add_class(&name, &super_class))
+            }
+        }
+    }
+}
+
+impl ClassHierarchy for SimpleClassHierarchy {
+    fn super_class(&self, class: &str) -> Option<String> {
+        self.classes
+            .get(class)
+            .and_then(|(super_class, _)| super_class.clone())
+    }
+
+    fn is_interface(&self, class: &str) -> bool {
+        self.classes
+            .get(class)
+            .is_some_and(|(_, is_interface)| *is_interface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_super_class() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy
+            .add_class("java/lang/Exception", "java/lang/Throwable")
+            .add_class("java/lang/RuntimeException", "java/lang/Exception")
+            .add_class("java/io/IOException", "java/lang/Exception")
+            .add_class("java/lang/Throwable", OBJECT)
+            .add_interface("java/lang/Runnable");
+
+        assert_eq!(
+            hierarchy.common_super_class("java/lang/RuntimeException", "java/io/IOException"),
+            "java/lang/Exception"
+        );
+        assert_eq!(
+            hierarchy.common_super_class("java/lang/Exception", "java/lang/RuntimeException"),
+            "java/lang/Exception"
+        );
+        assert_eq!(
+            hierarchy.common_super_class("java/lang/Exception", "java/lang/Runnable"),
+            OBJECT
+        );
+        assert_eq!(
+            hierarchy.common_super_class("java/lang/Exception", "Unknown"),
+            OBJECT
+        );
+        assert_eq!(
+            hierarchy.common_super_class("Unknown", "Unknown"),
+            "Unknown"
+        );
+    }
+
+    #[test]
+    fn test_cyclic_hierarchy() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy
+            .add_class("A", "B")
+            .add_class("B", "A")
+            .add_class("C", "C");
+
+        assert_eq!(hierarchy.common_super_class("A", "C"), OBJECT);
+        assert_eq!(hierarchy.common_super_class("C", "B"), OBJECT);
+        assert!(!hierarchy.is_assignable("A", "C"));
+    }
+
+    #[test]
+    fn test_is_assignable() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy
+            .add_class("java/lang/Exception", "java/lang/Throwable")
+            .add_class("java/io/IOException", "java/lang/Exception")
+            .add_class("java/lang/Throwable", OBJECT)
+            .add_interface("java/lang/Runnable");
+
+        assert!(hierarchy.is_assignable("java/io/IOException", "java/lang/Throwable"));
+        assert!(hierarchy.is_assignable("java/io/IOException", "java/io/IOException"));
+        assert!(hierarchy.is_assignable("java/io/IOException", "java/lang/Runnable"));
+        assert!(hierarchy.is_assignable("Unknown", OBJECT));
+        assert!(!hierarchy.is_assignable("java/lang/Exception", "java/io/IOException"));
+        assert!(!hierarchy.is_assignable("Unknown", "java/lang/Exception"));
+    }
+}
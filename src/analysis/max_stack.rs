@@ -0,0 +1,478 @@
+//! Computation of the `max_stack` and `max_locals` items of a `Code` attribute.
+//!
+//! `max_stack` is found by following every path through the code from its first instruction
+//! and from each exception handler, tracking the height of the operand stack (in slots, with
+//! `long` and `double` values taking two). Unreachable code does not contribute to it.
+
+use super::{AnalysisError, AnalysisResult};
+use crate::{
+    bytecode::{self, Instruction, WideInstruction},
+    descriptor::{FieldType, MethodDescriptor},
+    model::{
+        access_flags::ACC_STATIC,
+        attributes::{AttributeInfo, ExceptionHandler},
+        constant_pool::ConstantPool,
+        MethodInfo,
+    },
+};
+
+/// The `max_stack` and `max_locals` items of a `Code` attribute.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Maxs {
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+/// Compute the `max_stack` and `max_locals` of the `Code` attribute of `method`, or `None` if
+/// the method has no code (i.e. it is `abstract` or `native`).
+pub fn compute_maxs(
+    method: &MethodInfo,
+    constant_pool: &ConstantPool,
+) -> AnalysisResult<Option<Maxs>> {
+    let (code, exception_table) = match method.attributes.iter().find_map(|attr| match attr {
+        AttributeInfo::Code {
+            code,
+            exception_table,
+            ..
+        } => Some((code, exception_table)),
+        _ => None,
+    }) {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+
+    let descriptor = constant_pool.get_utf8(method.descriptor_index)?;
+    let is_static = method.access_flags & ACC_STATIC != 0;
+
+    Ok(Some(Maxs {
+        max_stack: compute_max_stack(code, exception_table, constant_pool)?,
+        max_locals: compute_max_locals(code, &descriptor, is_static)?,
+    }))
+}
+
+/// Compute the maximum depth of the operand stack during the execution of `code`.
+pub fn compute_max_stack(
+    code: &[u8],
+    exception_table: &[ExceptionHandler],
+    constant_pool: &ConstantPool,
+) -> AnalysisResult<u16> {
+    let instructions = bytecode::decode(code)?;
+
+    // map each offset to the position of the instruction starting there
+    let mut positions = vec![None; code.len()];
+    for (pos, (offset, _)) in instructions.iter().enumerate() {
+        positions[*offset] = Some(pos);
+    }
+
+    let mut flow = Flow {
+        positions,
+        heights: vec![None; instructions.len()],
+        worklist: Vec::new(),
+        max_stack: 0,
+    };
+
+    if !instructions.is_empty() {
+        flow.reach(0, 0, 0)?;
+    }
+    for handler in exception_table {
+        let handler_pc = handler.handler_pc as usize;
+        flow.reach(handler_pc, handler_pc as i64, 1)?;
+    }
+
+    while let Some(pos) = flow.worklist.pop() {
+        let (offset, instruction) = &instructions[pos];
+        let offset = *offset;
+        let height = flow.heights[pos].unwrap_or_default();
+
+        let (pop, push) = stack_effect(instruction, constant_pool)?;
+        if pop > height {
+            return Err(AnalysisError::StackUnderflow { offset });
+        }
+        let after = u16::try_from(u32::from(height - pop) + u32::from(push))
+            .map_err(|_| AnalysisError::StackOverflow { offset })?;
+
+        let next = offset + instruction.size(offset);
+        let branch = |delta: i32| offset as i64 + delta as i64;
+
+        match instruction {
+            Instruction::Goto(delta) => flow.reach(offset, branch(*delta as i32), after)?,
+            Instruction::GotoW(delta) => flow.reach(offset, branch(*delta), after)?,
+
+            // the return address is consumed by the subroutine, which returns to the next
+            // instruction with the operand stack as it was before the `jsr`
+            Instruction::Jsr(delta) => {
+                flow.reach(offset, branch(*delta as i32), after)?;
+                flow.fall_through(offset, next, height)?;
+            }
+            Instruction::JsrW(delta) => {
+                flow.reach(offset, branch(*delta), after)?;
+                flow.fall_through(offset, next, height)?;
+            }
+
+            Instruction::Ifeq(delta)
+            | Instruction::Ifne(delta)
+            | Instruction::Iflt(delta)
+            | Instruction::Ifge(delta)
+            | Instruction::Ifgt(delta)
+            | Instruction::Ifle(delta)
+            | Instruction::IfIcmpeq(delta)
+            | Instruction::IfIcmpne(delta)
+            | Instruction::IfIcmplt(delta)
+            | Instruction::IfIcmpge(delta)
+            | Instruction::IfIcmpgt(delta)
+            | Instruction::IfIcmple(delta)
+            | Instruction::IfAcmpeq(delta)
+            | Instruction::IfAcmpne(delta)
+            | Instruction::Ifnull(delta)
+            | Instruction::Ifnonnull(delta) => {
+                flow.reach(offset, branch(*delta as i32), after)?;
+                flow.fall_through(offset, next, after)?;
+            }
+
+            Instruction::Tableswitch {
+                default, offsets, ..
+            } => {
+                flow.reach(offset, branch(*default), after)?;
+                for delta in offsets {
+                    flow.reach(offset, branch(*delta), after)?;
+                }
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                flow.reach(offset, branch(*default), after)?;
+                for (_, delta) in pairs {
+                    flow.reach(offset, branch(*delta), after)?;
+                }
+            }
+
+            Instruction::Ret(_)
+            | Instruction::Wide(WideInstruction::Ret(_))
+            | Instruction::Ireturn
+            | Instruction::Lreturn
+            | Instruction::Freturn
+            | Instruction::Dreturn
+            | Instruction::Areturn
+            | Instruction::Return
+            | Instruction::Athrow => {}
+
+            _ => flow.fall_through(offset, next, after)?,
+        }
+    }
+
+    Ok(flow.max_stack)
+}
+
+/// The state of the traversal in `compute_max_stack`.
+struct Flow {
+    positions: Vec<Option<usize>>,
+    heights: Vec<Option<u16>>,
+    worklist: Vec<usize>,
+    max_stack: u16,
+}
+
+impl Flow {
+    /// Record that the instruction at `target` is reached from the instruction at `offset` with
+    /// `height` slots on the operand stack.
+    fn reach(&mut self, offset: usize, target: i64, height: u16) -> AnalysisResult<()> {
+        let pos = usize::try_from(target)
+            .ok()
+            .and_then(|target| self.positions.get(target).copied().flatten())
+            .ok_or(AnalysisError::InvalidBranchTarget { offset, target })?;
+
+        match self.heights[pos] {
+            None => {
+                self.heights[pos] = Some(height);
+                self.max_stack = self.max_stack.max(height);
+                self.worklist.push(pos);
+                Ok(())
+            }
+            Some(expected) if expected != height => Err(AnalysisError::InconsistentStackHeight {
+                offset: target as usize,
+                expected,
+                found: height,
+            }),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Continue from the instruction at `offset` to the one following it at `next`.
+    fn fall_through(&mut self, offset: usize, next: usize, height: u16) -> AnalysisResult<()> {
+        if next >= self.positions.len() {
+            return Err(AnalysisError::FallsOffEnd { offset });
+        }
+        self.reach(offset, next as i64, height)
+    }
+}
+
+/// Return the number of slots popped from and pushed onto the operand stack by `instruction`.
+fn stack_effect(
+    instruction: &Instruction,
+    constant_pool: &ConstantPool,
+) -> AnalysisResult<(u16, u16)> {
+    use Instruction::*;
+
+    let effect =
+        match instruction {
+            Nop | Iinc { .. } | Goto(_) | GotoW(_) | Ret(_) | Return => (0, 0),
+
+            AconstNull | IconstM1 | Iconst0 | Iconst1 | Iconst2 | Iconst3 | Iconst4 | Iconst5
+            | Fconst0 | Fconst1 | Fconst2 | Bipush(_) | Sipush(_) | Ldc(_) | LdcW(_) | New(_)
+            | Jsr(_) | JsrW(_) => (0, 1),
+            Lconst0 | Lconst1 | Dconst0 | Dconst1 | Ldc2W(_) => (0, 2),
+
+            Iload(_) | Fload(_) | Aload(_) | Iload0 | Iload1 | Iload2 | Iload3 | Fload0
+            | Fload1 | Fload2 | Fload3 | Aload0 | Aload1 | Aload2 | Aload3 => (0, 1),
+            Lload(_) | Dload(_) | Lload0 | Lload1 | Lload2 | Lload3 | Dload0 | Dload1 | Dload2
+            | Dload3 => (0, 2),
+
+            Istore(_) | Fstore(_) | Astore(_) | Istore0 | Istore1 | Istore2 | Istore3 | Fstore0
+            | Fstore1 | Fstore2 | Fstore3 | Astore0 | Astore1 | Astore2 | Astore3 => (1, 0),
+            Lstore(_) | Dstore(_) | Lstore0 | Lstore1 | Lstore2 | Lstore3 | Dstore0 | Dstore1
+            | Dstore2 | Dstore3 => (2, 0),
+
+            Wide(wide) => match wide {
+                WideInstruction::Iload(_)
+                | WideInstruction::Fload(_)
+                | WideInstruction::Aload(_) => (0, 1),
+                WideInstruction::Lload(_) | WideInstruction::Dload(_) => (0, 2),
+                WideInstruction::Istore(_)
+                | WideInstruction::Fstore(_)
+                | WideInstruction::Astore(_) => (1, 0),
+                WideInstruction::Lstore(_) | WideInstruction::Dstore(_) => (2, 0),
+                WideInstruction::Ret(_) | WideInstruction::Iinc { .. } => (0, 0),
+            },
+
+            Iaload | Faload | Aaload | Baload | Caload | Saload => (2, 1),
+            Laload | Daload => (2, 2),
+            Iastore | Fastore | Aastore | Bastore | Castore | Sastore => (3, 0),
+            Lastore | Dastore => (4, 0),
+
+            Pop => (1, 0),
+            Pop2 => (2, 0),
+            Dup => (1, 2),
+            DupX1 => (2, 3),
+            DupX2 => (3, 4),
+            Dup2 => (2, 4),
+            Dup2X1 => (3, 5),
+            Dup2X2 => (4, 6),
+            Swap => (2, 2),
+
+            Iadd | Isub | Imul | Idiv | Irem | Ishl | Ishr | Iushr | Iand | Ior | Ixor | Fadd
+            | Fsub | Fmul | Fdiv | Frem => (2, 1),
+            Ladd | Lsub | Lmul | Ldiv | Lrem | Land | Lor | Lxor | Dadd | Dsub | Dmul | Ddiv
+            | Drem => (4, 2),
+            Lshl | Lshr | Lushr => (3, 2),
+            Ineg | Fneg => (1, 1),
+            Lneg | Dneg => (2, 2),
+
+            I2f | F2i | I2b | I2c | I2s => (1, 1),
+            I2l | I2d | F2l | F2d => (1, 2),
+            L2i | L2f | D2i | D2f => (2, 1),
+            L2d | D2l => (2, 2),
+
+            Lcmp | Dcmpl | Dcmpg => (4, 1),
+            Fcmpl | Fcmpg => (2, 1),
+
+            Ifeq(_) | Ifne(_) | Iflt(_) | Ifge(_) | Ifgt(_) | Ifle(_) | Ifnull(_)
+            | Ifnonnull(_) => (1, 0),
+            IfIcmpeq(_) | IfIcmpne(_) | IfIcmplt(_) | IfIcmpge(_) | IfIcmpgt(_) | IfIcmple(_)
+            | IfAcmpeq(_) | IfAcmpne(_) => (2, 0),
+            Tableswitch { .. } | Lookupswitch { .. } => (1, 0),
+
+            Ireturn | Freturn | Areturn | Athrow | Monitorenter | Monitorexit => (1, 0),
+            Lreturn | Dreturn => (2, 0),
+
+            Getstatic(index) => (0, field_slots(constant_pool, *index)?),
+            Putstatic(index) => (field_slots(constant_pool, *index)?, 0),
+            Getfield(index) => (1, field_slots(constant_pool, *index)?),
+            Putfield(index) => (1 + field_slots(constant_pool, *index)?, 0),
+
+            Invokevirtual(index) | Invokespecial(index) | Invokeinterface { index, .. } => {
+                let (args, ret) = method_slots(&member_descriptor(constant_pool, *index)?)?;
+                (args + 1, ret)
+            }
+            Invokestatic(index) => method_slots(&member_descriptor(constant_pool, *index)?)?,
+            Invokedynamic(index) => method_slots(&constant_pool.get_dynamic(*index)?.descriptor)?,
+
+            Newarray(_) | Anewarray(_) | Arraylength | Checkcast(_) | Instanceof(_) => (1, 1),
+            Multianewarray { dimensions, .. } => (*dimensions as u16, 1),
+        };
+
+    Ok(effect)
+}
+
+/// Return the descriptor of the field or method referenced by the constant pool entry at
+/// `index`.
+fn member_descriptor(constant_pool: &ConstantPool, index: u16) -> AnalysisResult<String> {
+    Ok(constant_pool.get_member_ref(index)?.descriptor.into_owned())
+}
+
+/// Return the number of slots taken by a value of the field referenced by the constant pool
+/// entry at `index`.
+fn field_slots(constant_pool: &ConstantPool, index: u16) -> AnalysisResult<u16> {
+    Ok(FieldType::parse(&member_descriptor(constant_pool, index)?)?.slots())
+}
+
+/// Return the number of slots taken by the arguments and by the return value of the method
+/// descriptor `descriptor`.
+fn method_slots(descriptor: &str) -> AnalysisResult<(u16, u16)> {
+    let descriptor = MethodDescriptor::parse(descriptor)?;
+    Ok((descriptor.parameter_slots(), descriptor.return_slots()))
+}
+
+/// Compute the number of local variable slots used by `code`, including those holding the
+/// arguments of a method with descriptor `descriptor` (and `this`, unless `is_static`).
+pub fn compute_max_locals(code: &[u8], descriptor: &str, is_static: bool) -> AnalysisResult<u16> {
+    let (args, _) = method_slots(descriptor)?;
+    let mut max_locals = u32::from(args) + u32::from(!is_static);
+
+    for instruction in bytecode::Instructions::new(code) {
+        use Instruction::*;
+
+        let (index, size) = match instruction?.1 {
+            Iload(index)
+            | Fload(index)
+            | Aload(index)
+            | Istore(index)
+            | Fstore(index)
+            | Astore(index)
+            | Ret(index)
+            | Iinc { index, .. } => (index as u32, 1),
+            Lload(index) | Dload(index) | Lstore(index) | Dstore(index) => (index as u32, 2),
+
+            Iload0 | Fload0 | Aload0 | Istore0 | Fstore0 | Astore0 => (0, 1),
+            Iload1 | Fload1 | Aload1 | Istore1 | Fstore1 | Astore1 => (1, 1),
+            Iload2 | Fload2 | Aload2 | Istore2 | Fstore2 | Astore2 => (2, 1),
+            Iload3 | Fload3 | Aload3 | Istore3 | Fstore3 | Astore3 => (3, 1),
+            Lload0 | Dload0 | Lstore0 | Dstore0 => (0, 2),
+            Lload1 | Dload1 | Lstore1 | Dstore1 => (1, 2),
+            Lload2 | Dload2 | Lstore2 | Dstore2 => (2, 2),
+            Lload3 | Dload3 | Lstore3 | Dstore3 => (3, 2),
+
+            Wide(wide) => match wide {
+                WideInstruction::Lload(_)
+                | WideInstruction::Dload(_)
+                | WideInstruction::Lstore(_)
+                | WideInstruction::Dstore(_) => (wide.index() as u32, 2),
+                _ => (wide.index() as u32, 1),
+            },
+
+            _ => continue,
+        };
+        max_locals = max_locals.max(index + size);
+    }
+
+    u16::try_from(max_locals).map_err(|_| AnalysisError::TooManyLocals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bytecode::{opcodes::*, CodeBuilder},
+        model::constant_pool::ConstantPoolBuilder,
+    };
+
+    #[test]
+    fn test_straight_line_code() {
+        // static long f(int a, long b) { return a + b * 2L; }
+        let code = bytecode::encode(&[
+            Instruction::Iload0,
+            Instruction::I2l,
+            Instruction::Lload1,
+            Instruction::Ldc2W(2),
+            Instruction::Lmul,
+            Instruction::Ladd,
+            Instruction::Lreturn,
+        ]);
+
+        let mut cp = ConstantPoolBuilder::new();
+        cp.long(2).unwrap();
+        let cp = cp.build();
+
+        assert_eq!(compute_max_stack(&code, &[], &cp).unwrap(), 6);
+        assert_eq!(compute_max_locals(&code, "(IJ)J", true).unwrap(), 3);
+        assert_eq!(compute_max_locals(&code, "(IJ)J", false).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_branches_and_handlers() {
+        let mut builder = CodeBuilder::new();
+        let (start, end, handler, exit) = (
+            builder.label(),
+            builder.label(),
+            builder.label(),
+            builder.label(),
+        );
+        builder
+            .place(start)
+            .emit(Instruction::Iload1)
+            .branch(IFEQ, exit)
+            .emit(Instruction::Iconst1)
+            .emit(Instruction::Iconst2)
+            .emit(Instruction::Iadd)
+            .emit(Instruction::Wide(WideInstruction::Istore(300)))
+            .place(end)
+            .place(exit)
+            .emit(Instruction::Return)
+            .place(handler)
+            .emit(Instruction::Astore2)
+            .emit(Instruction::Return)
+            .try_catch(start, end, handler, 0);
+        let assembled = builder.build().unwrap();
+
+        let cp = ConstantPool::new();
+        assert_eq!(
+            compute_max_stack(&assembled.code, &assembled.exception_table, &cp).unwrap(),
+            2
+        );
+        assert_eq!(
+            compute_max_locals(&assembled.code, "(Z)V", false).unwrap(),
+            301
+        );
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let cp = ConstantPool::new();
+
+        let code = bytecode::encode(&[Instruction::Pop, Instruction::Return]);
+        assert_eq!(
+            compute_max_stack(&code, &[], &cp),
+            Err(AnalysisError::StackUnderflow { offset: 0 })
+        );
+
+        let code = bytecode::encode(&[Instruction::Iconst0]);
+        assert_eq!(
+            compute_max_stack(&code, &[], &cp),
+            Err(AnalysisError::FallsOffEnd { offset: 0 })
+        );
+
+        let code = bytecode::encode(&[Instruction::Goto(2), Instruction::Return]);
+        assert_eq!(
+            compute_max_stack(&code, &[], &cp),
+            Err(AnalysisError::InvalidBranchTarget {
+                offset: 0,
+                target: 2
+            })
+        );
+
+        // a loop that pushes a value on every iteration
+        let code = bytecode::encode(&[Instruction::Iconst0, Instruction::Goto(-1)]);
+        assert_eq!(
+            compute_max_stack(&code, &[], &cp),
+            Err(AnalysisError::InconsistentStackHeight {
+                offset: 0,
+                expected: 0,
+                found: 1
+            })
+        );
+
+        let code = bytecode::encode(&[Instruction::Getstatic(1), Instruction::Return]);
+        assert_eq!(
+            compute_max_stack(&code, &[], &cp),
+            Err(AnalysisError::ConstantPool(
+                crate::model::constant_pool::ConstantPoolError::InvalidIndex(1)
+            ))
+        );
+    }
+}
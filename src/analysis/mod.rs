@@ -0,0 +1,241 @@
+//! Analyses of the bytecode in `Code` attributes.
+
+pub mod cfg;
+pub mod frame;
+pub mod hierarchy;
+pub mod max_stack;
+pub mod stack_map;
+pub mod verifier;
+
+pub use cfg::{BasicBlock, BlockId, ControlFlowGraph, Dominators, Edge, EdgeKind, Loop};
+pub use frame::{Frame, VerificationType};
+pub use hierarchy::{ClassHierarchy, SimpleClassHierarchy};
+pub use max_stack::{compute_max_locals, compute_max_stack, compute_maxs, Maxs};
+pub use stack_map::{compute_frames, compute_stack_map, StackMap};
+pub use verifier::{verify_class, verify_method};
+
+use crate::{
+    bytecode::BytecodeError, descriptor::DescriptorError, model::constant_pool::ConstantPoolError,
+};
+use std::{error::Error, fmt};
+
+pub type AnalysisResult<T> = Result<T, AnalysisError>;
+
+/// Error type for code that cannot be analysed, e.g. when computing its `max_stack`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AnalysisError {
+    /// The code could not be decoded.
+    Bytecode(BytecodeError),
+    /// A constant pool lookup failed.
+    ConstantPool(ConstantPoolError),
+    /// A field or method descriptor is malformed.
+    InvalidDescriptor(DescriptorError),
+    /// The instruction at `offset` pops more values than there are on the operand stack.
+    StackUnderflow { offset: usize },
+    /// The operand stack would grow beyond its maximum size (`max_stack` when verifying, 65535
+    /// slots otherwise) at `offset`.
+    StackOverflow { offset: usize },
+    /// The instruction at `offset` is reached with different operand stack heights.
+    InconsistentStackHeight {
+        offset: usize,
+        expected: u16,
+        found: u16,
+    },
+    /// A branch, switch or exception handler at `offset` targets a position that is not the
+    /// start of an instruction.
+    InvalidBranchTarget { offset: usize, target: i64 },
+    /// Execution can continue past the last instruction, which ends at `offset`.
+    FallsOffEnd { offset: usize },
+    /// The local variables would need more than 65535 slots.
+    TooManyLocals,
+    /// The instruction at `offset` expects an operand (on the operand stack or in a local
+    /// variable) of a different type.
+    TypeMismatch {
+        offset: usize,
+        expected: &'static str,
+        found: VerificationType,
+    },
+    /// The instruction at `offset` is a `jsr`, `jsr_w` or `ret`, which cannot be used in code
+    /// with stack map frames.
+    UnsupportedSubroutine { offset: usize },
+    /// The instruction at `offset` expects a reference of type `expected`, but `found` is not
+    /// assignable to it.
+    NotAssignable {
+        offset: usize,
+        expected: VerificationType,
+        found: VerificationType,
+    },
+    /// The instruction at `offset` is a branch target, an exception handler or follows an
+    /// unconditional branch, but has no stack map frame.
+    MissingFrame { offset: usize },
+    /// The frame flowing into the instruction at `offset` is not assignable to its stack map
+    /// frame.
+    IncompatibleFrame { offset: usize },
+    /// The `StackMapTable` entry at `index` is malformed, e.g. it does not start at an
+    /// instruction or has more locals than `max_locals`.
+    InvalidStackMapFrame { index: usize },
+    /// The exception table entry at `index` does not cover a valid range of instructions.
+    InvalidExceptionHandler { index: usize },
+    /// The instruction at `offset` uses a local variable beyond `max_locals`.
+    LocalsOverflow { offset: usize },
+    /// The return instruction at `offset` does not match the method's return type.
+    InvalidReturn { offset: usize },
+    /// The count operand of the `invokeinterface` at `offset` is not one more than the number
+    /// of argument slots of the method's descriptor.
+    InconsistentArgsCount {
+        offset: usize,
+        expected: u16,
+        found: u16,
+    },
+    /// The match keys of the `lookupswitch` at `offset` are not in increasing order.
+    UnsortedLookupswitch { offset: usize },
+}
+
+impl AnalysisError {
+    /// The offset of the instruction at which the error was found, if any.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            AnalysisError::Bytecode(
+                BytecodeError::InvalidOpcode { offset, .. }
+                | BytecodeError::InvalidWideOpcode { offset, .. }
+                | BytecodeError::Truncated { offset }
+                | BytecodeError::InvalidOperand { offset },
+            )
+            | AnalysisError::StackUnderflow { offset }
+            | AnalysisError::StackOverflow { offset }
+            | AnalysisError::InconsistentStackHeight { offset, .. }
+            | AnalysisError::InvalidBranchTarget { offset, .. }
+            | AnalysisError::FallsOffEnd { offset }
+            | AnalysisError::TypeMismatch { offset, .. }
+            | AnalysisError::UnsupportedSubroutine { offset }
+            | AnalysisError::NotAssignable { offset, .. }
+            | AnalysisError::MissingFrame { offset }
+            | AnalysisError::IncompatibleFrame { offset }
+            | AnalysisError::LocalsOverflow { offset }
+            | AnalysisError::InvalidReturn { offset }
+            | AnalysisError::InconsistentArgsCount { offset, .. }
+            | AnalysisError::UnsortedLookupswitch { offset } => Some(*offset),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AnalysisError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalysisError::Bytecode(err) => write!(f, "{}", err),
+            AnalysisError::ConstantPool(err) => write!(f, "{}", err),
+            AnalysisError::InvalidDescriptor(err) => write!(f, "{}", err),
+            AnalysisError::StackUnderflow { offset } => {
+                write!(f, "operand stack underflow at offset {}", offset)
+            }
+            AnalysisError::StackOverflow { offset } => {
+                write!(f, "operand stack overflow at offset {}", offset)
+            }
+            AnalysisError::InconsistentStackHeight {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "inconsistent operand stack height at offset {}: expected {}, found {}",
+                offset, expected, found
+            ),
+            AnalysisError::InvalidBranchTarget { offset, target } => write!(
+                f,
+                "invalid branch target {} for instruction at offset {}",
+                target, offset
+            ),
+            AnalysisError::FallsOffEnd { offset } => {
+                write!(
+                    f,
+                    "execution falls off the end of the code at offset {}",
+                    offset
+                )
+            }
+            AnalysisError::TooManyLocals => {
+                write!(f, "local variables exceed the maximum of 65535 slots")
+            }
+            AnalysisError::TypeMismatch {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "type mismatch at offset {}: expected {}, found {}",
+                offset, expected, found
+            ),
+            AnalysisError::UnsupportedSubroutine { offset } => write!(
+                f,
+                "subroutine instruction at offset {} is not supported with stack map frames",
+                offset
+            ),
+            AnalysisError::NotAssignable {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "type mismatch at offset {}: {} is not assignable to {}",
+                offset, found, expected
+            ),
+            AnalysisError::MissingFrame { offset } => {
+                write!(f, "missing stack map frame at offset {}", offset)
+            }
+            AnalysisError::IncompatibleFrame { offset } => write!(
+                f,
+                "frame at offset {} is not assignable to its stack map frame",
+                offset
+            ),
+            AnalysisError::InvalidStackMapFrame { index } => {
+                write!(f, "invalid stack map frame #{}", index)
+            }
+            AnalysisError::InvalidExceptionHandler { index } => {
+                write!(f, "invalid exception handler #{}", index)
+            }
+            AnalysisError::LocalsOverflow { offset } => write!(
+                f,
+                "local variable beyond max_locals used at offset {}",
+                offset
+            ),
+            AnalysisError::InvalidReturn { offset } => write!(
+                f,
+                "return instruction at offset {} does not match the return type",
+                offset
+            ),
+            AnalysisError::InconsistentArgsCount {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "inconsistent args count operand at offset {}: expected {}, found {}",
+                offset, expected, found
+            ),
+            AnalysisError::UnsortedLookupswitch { offset } => {
+                write!(f, "lookupswitch keys at offset {} are not sorted", offset)
+            }
+        }
+    }
+}
+
+impl Error for AnalysisError {}
+
+impl From<BytecodeError> for AnalysisError {
+    fn from(err: BytecodeError) -> Self {
+        AnalysisError::Bytecode(err)
+    }
+}
+
+impl From<DescriptorError> for AnalysisError {
+    fn from(err: DescriptorError) -> Self {
+        AnalysisError::InvalidDescriptor(err)
+    }
+}
+
+impl From<ConstantPoolError> for AnalysisError {
+    fn from(err: ConstantPoolError) -> Self {
+        AnalysisError::ConstantPool(err)
+    }
+}
@@ -0,0 +1,792 @@
+//! Computation of `StackMapTable` frames by type inference over the code of a method.
+//!
+//! The frame at each instruction is found by following every path through the code from its
+//! first instruction and from each exception handler, merging the frames where paths meet
+//! (using a `ClassHierarchy` to find the common supertype of two classes), until nothing
+//! changes. Frames are then emitted at the instructions that require one: branch targets,
+//! exception handlers, and instructions following an unconditional branch.
+//!
+//! Code that no path reaches is legal, but the JVM still checks it against a frame. As ASM does,
+//! each run of unreachable code gets a frame with no locals and a `java/lang/Throwable` on the
+//! stack, which holds once `StackMap::replace_unreachable_code` has replaced the code with `nop`s
+//! followed by an `athrow`.
+
+use super::{
+    frame::{Frame, Interpreter, VerificationType},
+    hierarchy::ClassHierarchy,
+    AnalysisError, AnalysisResult,
+};
+use crate::{
+    bytecode::{
+        self,
+        opcodes::{ATHROW, NOP},
+        Instruction,
+    },
+    model::{
+        access_flags::ACC_STATIC,
+        attributes::{predefined_attributes, AttributeInfo, ExceptionHandler, StackMapFrame},
+        constant_pool::{ConstantPool, ConstantPoolBuilder, ConstantPoolResult},
+        ClassFile, MethodInfo,
+    },
+};
+use std::ops::Range;
+
+/// The frames of a method's code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackMap {
+    /// The frame on entry to the method, which is implicit in the `StackMapTable`.
+    pub initial: Frame,
+    /// The frames at the instructions that require one, by offset, in increasing order.
+    pub frames: Vec<(u16, Frame)>,
+    /// The ranges of offsets of the code that no path reaches, in increasing order.
+    pub unreachable: Vec<Range<u16>>,
+}
+
+impl StackMap {
+    /// Replace each range of unreachable code in `code` with `nop`s followed by an `athrow`, and
+    /// remove the ranges from `exception_table`, dropping handlers that cover nothing else.
+    ///
+    /// The frames at unreachable code only hold once this is done, and they have one stack item,
+    /// so `max_stack` must be at least 1 if there is any.
+    pub fn replace_unreachable_code(
+        &self,
+        code: &mut [u8],
+        exception_table: &mut Vec<ExceptionHandler>,
+    ) {
+        for range in &self.unreachable {
+            let (start, end) = (range.start as usize, range.end as usize);
+            code[start..end - 1].fill(NOP);
+            code[end - 1] = ATHROW;
+        }
+
+        *exception_table = exception_table
+            .iter()
+            .flat_map(|handler| self.reachable_ranges(handler))
+            .collect();
+    }
+
+    /// Split the range of `handler` into the parts that do not overlap unreachable code.
+    fn reachable_ranges(&self, handler: &ExceptionHandler) -> Vec<ExceptionHandler> {
+        let mut handlers = Vec::new();
+        let mut start = handler.start_pc;
+        for range in &self.unreachable {
+            if range.end <= start || range.start >= handler.end_pc {
+                continue;
+            }
+            if range.start > start {
+                handlers.push(ExceptionHandler {
+                    start_pc: start,
+                    end_pc: range.start,
+                    ..handler.clone()
+                });
+            }
+            start = range.end;
+        }
+        if start < handler.end_pc {
+            handlers.push(ExceptionHandler {
+                start_pc: start,
+                ..handler.clone()
+            });
+        }
+        handlers
+    }
+
+    /// Encode the frames as `StackMapTable` entries, using the most compact frame type for each
+    /// one, and adding the `CONSTANT_Class_info` entries of class types to `constant_pool`.
+    pub fn to_entries(
+        &self,
+        constant_pool: &mut ConstantPoolBuilder,
+    ) -> ConstantPoolResult<Vec<StackMapFrame>> {
+        let mut entries = Vec::with_capacity(self.frames.len());
+        let mut previous_locals = self.initial.compact_locals();
+        let mut previous_offset = None;
+
+        for (offset, frame) in &self.frames {
+            let offset_delta = match previous_offset {
+                Some(previous) => offset - previous - 1,
+                None => *offset,
+            };
+            previous_offset = Some(*offset);
+
+            let locals = frame.compact_locals();
+            let stack = frame.compact_stack();
+            let entry = encode_frame(
+                offset_delta,
+                &previous_locals,
+                &locals,
+                &stack,
+                constant_pool,
+            )?;
+            entries.push(entry);
+            previous_locals = locals;
+        }
+
+        Ok(entries)
+    }
+
+    /// Return a `StackMapTable` attribute with the frames, adding its name and the
+    /// `CONSTANT_Class_info` entries of class types to `constant_pool`.
+    pub fn to_attribute(
+        &self,
+        constant_pool: &mut ConstantPoolBuilder,
+    ) -> ConstantPoolResult<AttributeInfo> {
+        let attribute_name_index = constant_pool.utf8(predefined_attributes::STACK_MAP_TABLE)?;
+        let entries = self.to_entries(constant_pool)?;
+        let attribute_length = 2 + entries.iter().map(frame_length).sum::<u32>();
+
+        Ok(AttributeInfo::StackMapTable {
+            attribute_name_index,
+            attribute_length,
+            number_of_entries: entries.len() as u16,
+            entries,
+        })
+    }
+}
+
+/// Encode a frame with the given `locals` and `stack`, relative to the locals of the previous
+/// frame.
+fn encode_frame(
+    offset_delta: u16,
+    previous_locals: &[VerificationType],
+    locals: &[VerificationType],
+    stack: &[VerificationType],
+    constant_pool: &mut ConstantPoolBuilder,
+) -> ConstantPoolResult<StackMapFrame> {
+    let to_infos = |types: &[VerificationType], constant_pool: &mut ConstantPoolBuilder| {
+        types
+            .iter()
+            .map(|ty| ty.to_info(constant_pool))
+            .collect::<ConstantPoolResult<Vec<_>>>()
+    };
+
+    let frame = match stack.len() {
+        0 if locals == previous_locals => {
+            if offset_delta < 64 {
+                StackMapFrame::SameFrame {
+                    frame_type: offset_delta as u8,
+                }
+            } else {
+                StackMapFrame::SameFrameExtended {
+                    frame_type: 251,
+                    offset_delta,
+                }
+            }
+        }
+
+        1 if locals == previous_locals => {
+            let stack = to_infos(stack, constant_pool)?;
+            if offset_delta < 64 {
+                StackMapFrame::SameLocals1StackItemFrame {
+                    frame_type: 64 + offset_delta as u8,
+                    stack,
+                }
+            } else {
+                StackMapFrame::SameLocals1StackItemFrameExtended {
+                    frame_type: 247,
+                    offset_delta,
+                    stack,
+                }
+            }
+        }
+
+        0 if locals.len() < previous_locals.len()
+            && previous_locals.len() - locals.len() <= 3
+            && previous_locals.starts_with(locals) =>
+        {
+            StackMapFrame::ChopFrame {
+                frame_type: (251 - (previous_locals.len() - locals.len())) as u8,
+                offset_delta,
+            }
+        }
+
+        0 if locals.len() > previous_locals.len()
+            && locals.len() - previous_locals.len() <= 3
+            && locals.starts_with(previous_locals) =>
+        {
+            StackMapFrame::AppendFrame {
+                frame_type: (251 + (locals.len() - previous_locals.len())) as u8,
+                offset_delta,
+                locals: to_infos(&locals[previous_locals.len()..], constant_pool)?,
+            }
+        }
+
+        _ => StackMapFrame::FullFrame {
+            frame_type: 255,
+            offset_delta,
+            number_of_locals: locals.len() as u16,
+            locals: to_infos(locals, constant_pool)?,
+            number_of_stack_items: stack.len() as u16,
+            stack: to_infos(stack, constant_pool)?,
+        },
+    };
+
+    Ok(frame)
+}
+
+/// The number of bytes taken by `frame` in a `StackMapTable` attribute.
+fn frame_length(frame: &StackMapFrame) -> u32 {
+    use crate::model::attributes::VerificationTypeInfo;
+
+    let types_length = |types: &[VerificationTypeInfo]| -> u32 {
+        types
+            .iter()
+            .map(|ty| match ty {
+                VerificationTypeInfo::ObjectVariableInfo { .. }
+                | VerificationTypeInfo::UninitializedVariableInfo { .. } => 3,
+                _ => 1,
+            })
+            .sum()
+    };
+
+    match frame {
+        StackMapFrame::SameFrame { .. } => 1,
+        StackMapFrame::SameLocals1StackItemFrame { stack, .. } => 1 + types_length(stack),
+        StackMapFrame::SameLocals1StackItemFrameExtended { stack, .. } => 3 + types_length(stack),
+        StackMapFrame::ChopFrame { .. } | StackMapFrame::SameFrameExtended { .. } => 3,
+        StackMapFrame::AppendFrame { locals, .. } => 3 + types_length(locals),
+        StackMapFrame::FullFrame { locals, stack, .. } => {
+            7 + types_length(locals) + types_length(stack)
+        }
+    }
+}
+
+/// Compute the frames of the `Code` attribute of `method`, a method of `class_file`, or `None`
+/// if the method has no code. If the code has unreachable parts, it must be passed to
+/// `StackMap::replace_unreachable_code` for the frames to hold.
+pub fn compute_frames(
+    class_file: &ClassFile,
+    method: &MethodInfo,
+    hierarchy: &dyn ClassHierarchy,
+) -> AnalysisResult<Option<StackMap>> {
+    let (code, exception_table) = match method.attributes.iter().find_map(|attr| match attr {
+        AttributeInfo::Code {
+            code,
+            exception_table,
+            ..
+        } => Some((code, exception_table)),
+        _ => None,
+    }) {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+
+    let constant_pool = &class_file.constant_pool;
+    let this_class = constant_pool.get_class_name(class_file.this_class)?;
+    let initial = Frame::initial(
+        &this_class,
+        &constant_pool.get_utf8(method.name_index)?,
+        &constant_pool.get_utf8(method.descriptor_index)?,
+        method.access_flags & ACC_STATIC != 0,
+    )?;
+
+    compute_stack_map(
+        code,
+        exception_table,
+        initial,
+        &this_class,
+        constant_pool,
+        hierarchy,
+    )
+    .map(Some)
+}
+
+/// Compute the frames of `code`, a method of the class `this_class` whose frame on entry is
+/// `initial`.
+pub fn compute_stack_map(
+    code: &[u8],
+    exception_table: &[ExceptionHandler],
+    initial: Frame,
+    this_class: &str,
+    constant_pool: &ConstantPool,
+    hierarchy: &dyn ClassHierarchy,
+) -> AnalysisResult<StackMap> {
+    let interpreter = Interpreter {
+        code,
+        constant_pool,
+        this_class,
+        hierarchy: None,
+    };
+    let inference = infer_frames(&interpreter, exception_table, &initial, hierarchy)?;
+    let count = inference.instructions.len();
+    let is_reachable = |pos: usize| inference.frames[pos].is_some();
+    let end_offset = |pos: usize| match inference.instructions.get(pos) {
+        Some((offset, _)) => *offset as u16,
+        None => code.len() as u16,
+    };
+
+    let mut required = vec![false; count];
+    for handler in &inference.handlers {
+        required[handler.handler] |= is_reachable(handler.handler);
+    }
+    for (pos, (offset, instruction)) in inference.instructions.iter().enumerate() {
+        if !is_reachable(pos) {
+            continue;
+        }
+        let (targets, falls_through) = successors(*offset, instruction);
+        for target in targets {
+            required[inference.position(*offset, target)?] = true;
+        }
+        if !falls_through && pos + 1 < count {
+            required[pos + 1] = true;
+        }
+    }
+
+    // each run of unreachable code ends with an athrow once replaced, so the instructions on
+    // both sides of it need a frame
+    let mut unreachable = Vec::new();
+    let mut pos = 0;
+    while pos < count {
+        if is_reachable(pos) {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        while pos < count && !is_reachable(pos) {
+            pos += 1;
+        }
+        required[start] = true;
+        if pos < count {
+            required[pos] = true;
+        }
+        unreachable.push(end_offset(start)..end_offset(pos));
+    }
+
+    let throwable = Frame {
+        locals: Vec::new(),
+        stack: vec![VerificationType::object("java/lang/Throwable")],
+    };
+    let frames = inference
+        .instructions
+        .iter()
+        .zip(inference.frames)
+        .zip(required)
+        .filter(|(_, required)| *required)
+        .map(|(((offset, _), frame), _)| {
+            (*offset as u16, frame.unwrap_or_else(|| throwable.clone()))
+        })
+        .collect();
+
+    Ok(StackMap {
+        initial,
+        frames,
+        unreachable,
+    })
+}
+
+/// An exception handler, with offsets resolved to instruction positions.
+pub(crate) struct Handler {
+    pub start: usize,
+    pub end: usize,
+    pub handler: usize,
+    pub catch_type: VerificationType,
+}
+
+/// The result of `infer_frames`.
+pub(crate) struct Inference {
+    /// The instructions of the code, with their offsets.
+    pub instructions: Vec<(usize, Instruction)>,
+    /// The frame before each instruction, or `None` if no path reaches it.
+    pub frames: Vec<Option<Frame>>,
+    pub handlers: Vec<Handler>,
+    /// The position of the instruction starting at each offset.
+    positions: Vec<Option<usize>>,
+}
+
+impl Inference {
+    /// Return the position of the instruction at `target`, which is referenced by the
+    /// instruction at `offset`.
+    fn position(&self, offset: usize, target: i64) -> AnalysisResult<usize> {
+        usize::try_from(target)
+            .ok()
+            .and_then(|target| self.positions.get(target).copied().flatten())
+            .ok_or(AnalysisError::InvalidBranchTarget { offset, target })
+    }
+}
+
+/// Return the branch targets of `instruction` at `offset`, and whether execution can continue
+/// with the next instruction.
+pub(crate) fn successors(offset: usize, instruction: &Instruction) -> (Vec<i64>, bool) {
+    let branch = |delta: i32| offset as i64 + delta as i64;
+
+    match instruction {
+        Instruction::Goto(delta) => (vec![branch(*delta as i32)], false),
+        Instruction::GotoW(delta) => (vec![branch(*delta)], false),
+        Instruction::Ifeq(delta)
+        | Instruction::Ifne(delta)
+        | Instruction::Iflt(delta)
+        | Instruction::Ifge(delta)
+        | Instruction::Ifgt(delta)
+        | Instruction::Ifle(delta)
+        | Instruction::IfIcmpeq(delta)
+        | Instruction::IfIcmpne(delta)
+        | Instruction::IfIcmplt(delta)
+        | Instruction::IfIcmpge(delta)
+        | Instruction::IfIcmpgt(delta)
+        | Instruction::IfIcmple(delta)
+        | Instruction::IfAcmpeq(delta)
+        | Instruction::IfAcmpne(delta)
+        | Instruction::Ifnull(delta)
+        | Instruction::Ifnonnull(delta)
+        | Instruction::Jsr(delta) => (vec![branch(*delta as i32)], true),
+        Instruction::JsrW(delta) => (vec![branch(*delta)], true),
+        Instruction::Tableswitch {
+            default, offsets, ..
+        } => {
+            let mut targets = vec![branch(*default)];
+            targets.extend(offsets.iter().map(|delta| branch(*delta)));
+            (targets, false)
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            let mut targets = vec![branch(*default)];
+            targets.extend(pairs.iter().map(|(_, delta)| branch(*delta)));
+            (targets, false)
+        }
+        Instruction::Ret(_)
+        | Instruction::Wide(bytecode::WideInstruction::Ret(_))
+        | Instruction::Ireturn
+        | Instruction::Lreturn
+        | Instruction::Freturn
+        | Instruction::Dreturn
+        | Instruction::Areturn
+        | Instruction::Return
+        | Instruction::Athrow => (Vec::new(), false),
+        _ => (Vec::new(), true),
+    }
+}
+
+/// Compute the frame before each instruction of the code that can be reached, starting from
+/// `initial`.
+pub(crate) fn infer_frames(
+    interpreter: &Interpreter<'_>,
+    exception_table: &[ExceptionHandler],
+    initial: &Frame,
+    hierarchy: &dyn ClassHierarchy,
+) -> AnalysisResult<Inference> {
+    let code = interpreter.code;
+    let instructions = bytecode::decode(code)?;
+    let mut positions = vec![None; code.len()];
+    for (pos, (offset, _)) in instructions.iter().enumerate() {
+        positions[*offset] = Some(pos);
+    }
+
+    let mut inference = Inference {
+        instructions,
+        frames: Vec::new(),
+        handlers: Vec::new(),
+        positions,
+    };
+
+    for handler in exception_table {
+        let position = |pc: u16| inference.position(handler.handler_pc as usize, pc as i64);
+        let end = match handler.end_pc as usize {
+            end if end == code.len() => inference.instructions.len(),
+            _ => position(handler.end_pc)?,
+        };
+        let catch_type = match handler.catch_type {
+            0 => VerificationType::object("java/lang/Throwable"),
+            index => VerificationType::object(&interpreter.constant_pool.get_class_name(index)?),
+        };
+        inference.handlers.push(Handler {
+            start: position(handler.start_pc)?,
+            end,
+            handler: position(handler.handler_pc)?,
+            catch_type,
+        });
+    }
+
+    if inference.instructions.is_empty() {
+        return Ok(inference);
+    }
+
+    let mut frames: Vec<Option<Frame>> = vec![None; inference.instructions.len()];
+    frames[0] = Some(initial.clone());
+    let mut worklist = vec![0];
+
+    let merge_into = |frames: &mut Vec<Option<Frame>>,
+                      worklist: &mut Vec<usize>,
+                      pos: usize,
+                      frame: Frame|
+     -> AnalysisResult<()> {
+        let changed = match &mut frames[pos] {
+            Some(existing) => existing.merge(&frame, inference.instructions[pos].0, hierarchy)?,
+            slot @ None => {
+                *slot = Some(frame);
+                true
+            }
+        };
+        if changed && !worklist.contains(&pos) {
+            worklist.push(pos);
+        }
+        Ok(())
+    };
+
+    while let Some(pos) = worklist.pop() {
+        let (offset, instruction) = &inference.instructions[pos];
+        let mut frame = frames[pos].clone().unwrap_or_default();
+
+        for handler in &inference.handlers {
+            if (handler.start..handler.end).contains(&pos) {
+                let handler_frame = Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![handler.catch_type.clone()],
+                };
+                merge_into(&mut frames, &mut worklist, handler.handler, handler_frame)?;
+            }
+        }
+
+        interpreter.execute(*offset, instruction, &mut frame)?;
+
+        let (targets, falls_through) = successors(*offset, instruction);
+        for target in targets {
+            let target = inference.position(*offset, target)?;
+            merge_into(&mut frames, &mut worklist, target, frame.clone())?;
+        }
+        if falls_through {
+            if pos + 1 == inference.instructions.len() {
+                return Err(AnalysisError::FallsOffEnd { offset: *offset });
+            }
+            merge_into(&mut frames, &mut worklist, pos + 1, frame)?;
+        }
+    }
+
+    inference.frames = frames;
+    Ok(inference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::SimpleClassHierarchy,
+        bytecode::{opcodes::*, CodeBuilder},
+        model::attributes::VerificationTypeInfo,
+    };
+
+    fn frame(locals: &[VerificationType], stack: &[VerificationType]) -> Frame {
+        Frame {
+            locals: locals.to_vec(),
+            stack: stack.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_compact_encoding() {
+        use VerificationType::*;
+
+        let string = VerificationType::object("java/lang/String");
+        let stack_map = StackMap {
+            initial: frame(&[Integer], &[]),
+            frames: vec![
+                (10, frame(&[Integer], &[])),
+                (100, frame(&[Integer], &[string])),
+                (101, frame(&[Integer, Long, Top, Float], &[])),
+                (102, frame(&[Integer, Top, Top, Top, Top], &[])),
+                (103, frame(&[Double, Top], &[Integer])),
+                (104, frame(&[Integer, Top, Integer], &[])),
+            ],
+            unreachable: Vec::new(),
+        };
+
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let entries = stack_map.to_entries(&mut constant_pool).unwrap();
+        let string_index = constant_pool.class("java/lang/String").unwrap();
+
+        assert!(matches!(
+            entries[0],
+            StackMapFrame::SameFrame { frame_type: 10 }
+        ));
+        assert!(matches!(
+            &entries[1],
+            StackMapFrame::SameLocals1StackItemFrameExtended {
+                frame_type: 247,
+                offset_delta: 89,
+                stack,
+            } if matches!(stack[..], [VerificationTypeInfo::ObjectVariableInfo { cpool_index, .. }] if cpool_index == string_index)
+        ));
+        assert!(matches!(
+            &entries[2],
+            StackMapFrame::AppendFrame {
+                frame_type: 253,
+                offset_delta: 0,
+                locals,
+            } if matches!(locals[..], [VerificationTypeInfo::LongVariableInfo { .. }, VerificationTypeInfo::FloatVariableInfo { .. }])
+        ));
+        assert!(matches!(
+            entries[3],
+            StackMapFrame::ChopFrame {
+                frame_type: 249,
+                offset_delta: 0
+            }
+        ));
+        assert!(matches!(
+            &entries[4],
+            StackMapFrame::FullFrame {
+                number_of_locals: 1,
+                number_of_stack_items: 1,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &entries[5],
+            StackMapFrame::FullFrame {
+                number_of_locals: 3,
+                number_of_stack_items: 0,
+                ..
+            }
+        ));
+
+        match stack_map.to_attribute(&mut constant_pool).unwrap() {
+            AttributeInfo::StackMapTable {
+                attribute_length,
+                number_of_entries,
+                ..
+            } => {
+                assert_eq!(number_of_entries, 6);
+                assert_eq!(attribute_length, 2 + 1 + 6 + 5 + 3 + 9 + 10);
+            }
+            attr => panic!("expected a StackMapTable attribute, found {:?}", attr),
+        }
+    }
+
+    #[test]
+    fn test_merge_at_branch_target() {
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let list = constant_pool.class("java/util/ArrayList").unwrap();
+        let list_init = constant_pool
+            .method_ref("java/util/ArrayList", "<init>", "()V")
+            .unwrap();
+        let set = constant_pool.class("java/util/HashSet").unwrap();
+        let set_init = constant_pool
+            .method_ref("java/util/HashSet", "<init>", "()V")
+            .unwrap();
+        let constant_pool = constant_pool.build();
+
+        // static Object f(boolean b) { return b ? new ArrayList() : new HashSet(); }
+        let mut builder = CodeBuilder::new();
+        let (other, end) = (builder.label(), builder.label());
+        builder
+            .emit(Instruction::Iload0)
+            .branch(IFEQ, other)
+            .emit(Instruction::New(list))
+            .emit(Instruction::Dup)
+            .emit(Instruction::Invokespecial(list_init))
+            .branch(GOTO, end)
+            .place(other)
+            .emit(Instruction::New(set))
+            .emit(Instruction::Dup)
+            .emit(Instruction::Invokespecial(set_init))
+            .place(end)
+            .emit(Instruction::Areturn);
+        let code = builder.build().unwrap().code;
+
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy
+            .add_class("java/util/ArrayList", "java/util/AbstractList")
+            .add_class("java/util/AbstractList", "java/util/AbstractCollection")
+            .add_class("java/util/HashSet", "java/util/AbstractSet")
+            .add_class("java/util/AbstractSet", "java/util/AbstractCollection");
+
+        let initial = Frame::initial("Test", "f", "(Z)Ljava/lang/Object;", true).unwrap();
+        let stack_map =
+            compute_stack_map(&code, &[], initial, "Test", &constant_pool, &hierarchy).unwrap();
+
+        assert_eq!(
+            stack_map.frames,
+            [
+                (14, frame(&[VerificationType::Integer], &[])),
+                (
+                    21,
+                    frame(
+                        &[VerificationType::Integer],
+                        &[VerificationType::object("java/util/AbstractCollection")]
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_code() {
+        use VerificationType::*;
+
+        let constant_pool = ConstantPool::new();
+        let hierarchy = SimpleClassHierarchy::new();
+        let mut code = bytecode::encode(&[
+            Instruction::Iload0,
+            Instruction::Ifeq(8),
+            Instruction::Goto(5),
+            Instruction::Iload3, // 7: unreachable
+            Instruction::Pop,    // 8: unreachable
+            Instruction::Return,
+            Instruction::Athrow, // 10: handler
+        ]);
+        let mut exception_table = vec![ExceptionHandler::new(0, 10, 10, 0)];
+        let initial = Frame::initial("Test", "f", "(I)V", true).unwrap();
+        let stack_map = compute_stack_map(
+            &code,
+            &exception_table,
+            initial,
+            "Test",
+            &constant_pool,
+            &hierarchy,
+        )
+        .unwrap();
+
+        let throwable = VerificationType::object("java/lang/Throwable");
+        assert_eq!(stack_map.unreachable, vec![7..9]);
+        assert_eq!(
+            stack_map.frames,
+            [
+                (7, frame(&[], std::slice::from_ref(&throwable))),
+                (9, frame(&[Integer], &[])),
+                (10, frame(&[Integer], &[throwable])),
+            ]
+        );
+
+        stack_map.replace_unreachable_code(&mut code, &mut exception_table);
+        assert_eq!(code[7..9], [NOP, ATHROW]);
+        assert_eq!(
+            exception_table,
+            [
+                ExceptionHandler::new(0, 7, 10, 0),
+                ExceptionHandler::new(9, 10, 10, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let constant_pool = ConstantPool::new();
+        let hierarchy = SimpleClassHierarchy::new();
+        let compute = |instructions: &[Instruction]| {
+            let code = bytecode::encode(instructions);
+            let initial = Frame::initial("Test", "f", "(I)V", true).unwrap();
+            compute_stack_map(&code, &[], initial, "Test", &constant_pool, &hierarchy)
+        };
+
+        assert_eq!(
+            compute(&[Instruction::Fload0, Instruction::Return]),
+            Err(AnalysisError::TypeMismatch {
+                offset: 0,
+                expected: "float",
+                found: VerificationType::Integer,
+            })
+        );
+        assert_eq!(
+            compute(&[
+                Instruction::Iconst1,
+                Instruction::Lstore1,
+                Instruction::Return
+            ]),
+            Err(AnalysisError::TypeMismatch {
+                offset: 1,
+                expected: "long",
+                found: VerificationType::Integer,
+            })
+        );
+        assert_eq!(
+            compute(&[Instruction::Jsr(3), Instruction::Return]),
+            Err(AnalysisError::UnsupportedSubroutine { offset: 0 })
+        );
+    }
+}
@@ -0,0 +1,902 @@
+//! Verification of the code of methods by type checking (JVMS 4.10.1).
+//!
+//! The instructions of a method are checked once, in order. The frame before each instruction
+//! is the frame after the previous one or, where the `StackMapTable` records a frame, the
+//! recorded frame, which the incoming frame must be assignable to. The frames flowing into
+//! branch targets and exception handlers must be assignable to the frames recorded there.
+//!
+//! Class files older than version 50 have no `StackMapTable`; the frames of their methods are
+//! inferred as in `compute_stack_map` and then checked in the same way, except that code no path
+//! reaches is not checked.
+
+use super::{
+    frame::{Frame, Interpreter, VerificationType},
+    hierarchy::ClassHierarchy,
+    stack_map::{infer_frames, successors, Handler},
+    AnalysisError, AnalysisResult,
+};
+use crate::{
+    bytecode::{self, Instruction},
+    descriptor::{FieldType, MethodDescriptor},
+    model::{
+        access_flags::ACC_STATIC,
+        attributes::{AttributeInfo, ExceptionHandler, StackMapFrame, VerificationTypeInfo},
+        constant_pool::ConstantPool,
+        ClassFile, MethodInfo,
+    },
+};
+use std::{error::Error, fmt};
+
+/// The first class file version whose methods must have `StackMapTable`s.
+pub(crate) const TYPE_CHECKING_VERSION: u16 = 50;
+
+/// Error type for methods that fail bytecode verification, identifying the method and, where
+/// known, the offending instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    method: String,
+    instruction: Option<(usize, Box<Instruction>)>,
+    kind: AnalysisError,
+}
+
+impl VerifyError {
+    /// Create an error for the method `method`, its name followed by its descriptor.
+    pub fn new(method: String, kind: AnalysisError) -> Self {
+        VerifyError {
+            method,
+            instruction: None,
+            kind,
+        }
+    }
+
+    /// Record the instruction at which the error was found, with its offset.
+    pub(crate) fn with_instruction(mut self, offset: usize, instruction: Instruction) -> Self {
+        self.instruction = Some((offset, Box::new(instruction)));
+        self
+    }
+
+    pub fn kind(&self) -> &AnalysisError {
+        &self.kind
+    }
+
+    /// The name and descriptor of the method, e.g. `main([Ljava/lang/String;)V`.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// The bytecode offset at which the error was found, if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.instruction
+            .as_ref()
+            .map(|(offset, _)| *offset)
+            .or_else(|| self.kind.offset())
+    }
+
+    /// The offending instruction, if known.
+    pub fn instruction(&self) -> Option<&Instruction> {
+        self.instruction
+            .as_ref()
+            .map(|(_, instruction)| instruction.as_ref())
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (in method {}", self.kind, self.method)?;
+        if let Some((offset, instruction)) = &self.instruction {
+            write!(f, ", at {}: {}", offset, instruction)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Verify the code of every method of `class_file`, returning the errors of the methods that
+/// fail verification.
+pub fn verify_class(
+    class_file: &ClassFile,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), Vec<VerifyError>> {
+    let errors: Vec<_> = class_file
+        .methods
+        .iter()
+        .filter_map(|method| verify_method(class_file, method, hierarchy).err())
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Verify the code of `method`, a method of `class_file`. Methods without a `Code` attribute
+/// pass trivially.
+///
+/// `hierarchy` must know the superclass of `class_file`, and the superclasses of the classes
+/// whose values its code assigns to variables of other classes.
+pub fn verify_method(
+    class_file: &ClassFile,
+    method: &MethodInfo,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), VerifyError> {
+    let constant_pool = &class_file.constant_pool;
+    let name = constant_pool.get_utf8(method.name_index);
+    let descriptor = constant_pool.get_utf8(method.descriptor_index);
+    let method_name = format!(
+        "{}{}",
+        name.as_deref().unwrap_or("<invalid>"),
+        descriptor.as_deref().unwrap_or("")
+    );
+    let fail = |kind: AnalysisError| VerifyError::new(method_name.clone(), kind);
+
+    let (max_stack, max_locals, code, exception_table, code_attributes) =
+        match method.attributes.iter().find_map(|attr| match attr {
+            AttributeInfo::Code {
+                max_stack,
+                max_locals,
+                code,
+                exception_table,
+                code_attributes,
+                ..
+            } => Some((
+                *max_stack,
+                *max_locals,
+                code,
+                exception_table,
+                code_attributes,
+            )),
+            _ => None,
+        }) {
+            Some(code) => code,
+            None => return Ok(()),
+        };
+
+    let name = name.map_err(|err| fail(err.into()))?;
+    let descriptor = descriptor.map_err(|err| fail(err.into()))?;
+    let this_class = constant_pool
+        .get_class_name(class_file.this_class)
+        .map_err(|err| fail(err.into()))?;
+    let initial = Frame::initial(
+        &this_class,
+        &name,
+        &descriptor,
+        method.access_flags & ACC_STATIC != 0,
+    )
+    .map_err(fail)?;
+    let return_type = MethodDescriptor::parse(&descriptor)
+        .map_err(|err| fail(err.into()))?
+        .return_type;
+
+    let instructions = bytecode::decode(code).map_err(|err| fail(err.into()))?;
+    let mut positions = vec![None; code.len()];
+    for (pos, (offset, _)) in instructions.iter().enumerate() {
+        positions[*offset] = Some(pos);
+    }
+
+    let verifier = Verifier {
+        interpreter: Interpreter {
+            code,
+            constant_pool,
+            this_class: &this_class,
+            hierarchy: Some(hierarchy),
+        },
+        hierarchy,
+        instructions: &instructions,
+        positions,
+        max_stack: max_stack as usize,
+        max_locals: max_locals as usize,
+        return_type,
+        is_init: name == "<init>",
+        inferred: class_file.major_version < TYPE_CHECKING_VERSION,
+    };
+
+    let handlers = verifier
+        .handlers(exception_table)
+        .map_err(|kind| verifier.error(fail(kind)))?;
+    let frames = if !verifier.inferred {
+        let entries = code_attributes.iter().find_map(|attr| match attr {
+            AttributeInfo::StackMapTable { entries, .. } => Some(entries.as_slice()),
+            _ => None,
+        });
+        verifier
+            .recorded_frames(&initial, entries.unwrap_or_default())
+            .map_err(fail)?
+    } else {
+        infer_frames(&verifier.interpreter, exception_table, &initial, hierarchy)
+            .map(|inference| inference.frames)
+            .map_err(|kind| verifier.error(fail(kind)))?
+    };
+
+    if initial.locals.len() > verifier.max_locals {
+        return Err(fail(AnalysisError::LocalsOverflow { offset: 0 }));
+    }
+    verifier
+        .check(initial, &frames, &handlers)
+        .map_err(|(pos, kind)| {
+            let (offset, instruction) = &instructions[pos];
+            fail(kind).with_instruction(*offset, instruction.clone())
+        })
+}
+
+struct Verifier<'a> {
+    interpreter: Interpreter<'a>,
+    hierarchy: &'a dyn ClassHierarchy,
+    instructions: &'a [(usize, Instruction)],
+    /// The position of the instruction starting at each offset.
+    positions: Vec<Option<usize>>,
+    max_stack: usize,
+    max_locals: usize,
+    /// The return type of the method, or `None` if it is `void`.
+    return_type: Option<FieldType>,
+    /// Whether the method is an instance initialization method.
+    is_init: bool,
+    /// Whether the frames are inferred rather than recorded, so that instructions without one
+    /// are unreachable.
+    inferred: bool,
+}
+
+impl Verifier<'_> {
+    fn constant_pool(&self) -> &ConstantPool {
+        self.interpreter.constant_pool
+    }
+
+    /// Return the position of the instruction starting at `offset`, if any.
+    fn position(&self, offset: i64) -> Option<usize> {
+        usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.positions.get(offset).copied().flatten())
+    }
+
+    /// Record the instruction at the offset of `err`, if any, in it.
+    fn error(&self, err: VerifyError) -> VerifyError {
+        match err.offset().and_then(|offset| self.position(offset as i64)) {
+            Some(pos) => {
+                let (offset, instruction) = &self.instructions[pos];
+                err.with_instruction(*offset, instruction.clone())
+            }
+            None => err,
+        }
+    }
+
+    /// Check the ranges and catch types of the exception table.
+    fn handlers(&self, exception_table: &[ExceptionHandler]) -> AnalysisResult<Vec<Handler>> {
+        let throwable = VerificationType::object("java/lang/Throwable");
+
+        let mut handlers = Vec::with_capacity(exception_table.len());
+        for (index, entry) in exception_table.iter().enumerate() {
+            let invalid = AnalysisError::InvalidExceptionHandler { index };
+            let start = self
+                .position(entry.start_pc as i64)
+                .ok_or(invalid.clone())?;
+            let end = match entry.end_pc as usize {
+                end if end == self.positions.len() => self.instructions.len(),
+                end => self.position(end as i64).ok_or(invalid.clone())?,
+            };
+            let handler = self
+                .position(entry.handler_pc as i64)
+                .ok_or(invalid.clone())?;
+            if start >= end {
+                return Err(invalid);
+            }
+
+            let catch_type = match entry.catch_type {
+                0 => throwable.clone(),
+                index => VerificationType::object(&self.constant_pool().get_class_name(index)?),
+            };
+            if !catch_type.is_assignable_to(&throwable, self.hierarchy) {
+                return Err(AnalysisError::NotAssignable {
+                    offset: entry.handler_pc as usize,
+                    expected: throwable,
+                    found: catch_type,
+                });
+            }
+
+            handlers.push(Handler {
+                start,
+                end,
+                handler,
+                catch_type,
+            });
+        }
+        Ok(handlers)
+    }
+
+    /// Expand the `StackMapTable` entries into the frame recorded at each instruction, if any.
+    fn recorded_frames(
+        &self,
+        initial: &Frame,
+        entries: &[StackMapFrame],
+    ) -> AnalysisResult<Vec<Option<Frame>>> {
+        let mut frames = vec![None; self.instructions.len()];
+        let mut locals = initial.compact_locals();
+        let mut previous: Option<usize> = None;
+
+        for (index, entry) in entries.iter().enumerate() {
+            let invalid = AnalysisError::InvalidStackMapFrame { index };
+            let (offset_delta, stack) = match entry {
+                StackMapFrame::SameFrame { frame_type } => (*frame_type as u16, Vec::new()),
+                StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => {
+                    let offset_delta = frame_type
+                        .checked_sub(64)
+                        .ok_or(AnalysisError::InvalidStackMapFrame { index })?;
+                    (offset_delta as u16, self.types(stack)?)
+                }
+                StackMapFrame::SameLocals1StackItemFrameExtended {
+                    offset_delta,
+                    stack,
+                    ..
+                } => (*offset_delta, self.types(stack)?),
+                StackMapFrame::ChopFrame {
+                    frame_type,
+                    offset_delta,
+                } => {
+                    let chopped = 251usize.saturating_sub(*frame_type as usize);
+                    if chopped > locals.len() {
+                        return Err(invalid);
+                    }
+                    locals.truncate(locals.len() - chopped);
+                    (*offset_delta, Vec::new())
+                }
+                StackMapFrame::SameFrameExtended { offset_delta, .. } => {
+                    (*offset_delta, Vec::new())
+                }
+                StackMapFrame::AppendFrame {
+                    offset_delta,
+                    locals: appended,
+                    ..
+                } => {
+                    locals.extend(self.types(appended)?);
+                    (*offset_delta, Vec::new())
+                }
+                StackMapFrame::FullFrame {
+                    offset_delta,
+                    locals: full_locals,
+                    stack,
+                    ..
+                } => {
+                    locals = self.types(full_locals)?;
+                    (*offset_delta, self.types(stack)?)
+                }
+            };
+
+            // each frame after the first is at least one byte after the previous one
+            let offset = match previous {
+                Some(previous) => previous + offset_delta as usize + 1,
+                None => offset_delta as usize,
+            };
+            previous = Some(offset);
+
+            let frame = Frame::from_compact(&locals, &stack);
+            if frame.locals.len() > self.max_locals || frame.stack.len() > self.max_stack {
+                return Err(invalid);
+            }
+            let pos = self.position(offset as i64).ok_or(invalid)?;
+            frames[pos] = Some(frame);
+        }
+
+        Ok(frames)
+    }
+
+    fn types(&self, infos: &[VerificationTypeInfo]) -> AnalysisResult<Vec<VerificationType>> {
+        infos
+            .iter()
+            .map(|info| VerificationType::from_info(info, self.constant_pool()))
+            .collect::<Result<_, _>>()
+            .map_err(AnalysisError::from)
+    }
+
+    /// Check every instruction in order, starting from `initial`. Errors come with the position
+    /// of the offending instruction.
+    fn check(
+        &self,
+        initial: Frame,
+        frames: &[Option<Frame>],
+        handlers: &[Handler],
+    ) -> Result<(), (usize, AnalysisError)> {
+        let mut incoming = Some(initial);
+        for pos in 0..self.instructions.len() {
+            incoming = self
+                .check_instruction(pos, incoming, frames, handlers)
+                .map_err(|err| (pos, err))?;
+        }
+        Ok(())
+    }
+
+    /// Check the instruction at position `pos`, which is reached with the frame `incoming`
+    /// unless it follows an unconditional branch. Return the frame it passes to the next
+    /// instruction, if any.
+    fn check_instruction(
+        &self,
+        pos: usize,
+        incoming: Option<Frame>,
+        frames: &[Option<Frame>],
+        handlers: &[Handler],
+    ) -> AnalysisResult<Option<Frame>> {
+        let (offset, instruction) = &self.instructions[pos];
+        let offset = *offset;
+        let mut frame = match (incoming, &frames[pos]) {
+            (Some(frame), Some(recorded)) => {
+                if !frame.is_assignable_to(recorded, self.hierarchy) {
+                    return Err(AnalysisError::IncompatibleFrame { offset });
+                }
+                recorded.clone()
+            }
+            (None, Some(recorded)) => recorded.clone(),
+            (Some(frame), None) => frame,
+            (None, None) if self.inferred => return Ok(None),
+            (None, None) => return Err(AnalysisError::MissingFrame { offset }),
+        };
+
+        for handler in handlers {
+            if (handler.start..handler.end).contains(&pos) {
+                let handler_frame = Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![handler.catch_type.clone()],
+                };
+                self.check_target(handler.handler, &handler_frame, frames)?;
+            }
+        }
+
+        self.check_operands(offset, instruction)?;
+        self.check_return(offset, instruction, &frame)?;
+        self.interpreter.execute(offset, instruction, &mut frame)?;
+        if frame.stack.len() > self.max_stack {
+            return Err(AnalysisError::StackOverflow { offset });
+        }
+        if frame.locals.len() > self.max_locals {
+            return Err(AnalysisError::LocalsOverflow { offset });
+        }
+
+        let (targets, falls_through) = successors(offset, instruction);
+        for target in targets {
+            let target = self
+                .position(target)
+                .ok_or(AnalysisError::InvalidBranchTarget { offset, target })?;
+            self.check_target(target, &frame, frames)?;
+        }
+
+        match falls_through {
+            true if pos + 1 == self.instructions.len() => {
+                Err(AnalysisError::FallsOffEnd { offset })
+            }
+            true => Ok(Some(frame)),
+            false => Ok(None),
+        }
+    }
+
+    /// Check that `frame` is assignable to the frame recorded at the instruction at position
+    /// `target`, a branch target or exception handler.
+    fn check_target(
+        &self,
+        target: usize,
+        frame: &Frame,
+        frames: &[Option<Frame>],
+    ) -> AnalysisResult<()> {
+        let offset = self.instructions[target].0;
+        match &frames[target] {
+            Some(recorded) if frame.is_assignable_to(recorded, self.hierarchy) => Ok(()),
+            Some(_) => Err(AnalysisError::IncompatibleFrame { offset }),
+            None => Err(AnalysisError::MissingFrame { offset }),
+        }
+    }
+
+    /// Check the operands that the decoder accepts but JVMS 4.10.1 constrains further: the count
+    /// of an `invokeinterface`, and the order of the keys of a `lookupswitch`.
+    fn check_operands(&self, offset: usize, instruction: &Instruction) -> AnalysisResult<()> {
+        match instruction {
+            Instruction::Invokeinterface { index, count } => {
+                let descriptor = self.constant_pool().get_member_ref(*index)?.descriptor;
+                let expected = MethodDescriptor::parse(&descriptor)?.parameter_slots() + 1;
+                if *count as u16 != expected {
+                    return Err(AnalysisError::InconsistentArgsCount {
+                        offset,
+                        expected,
+                        found: *count as u16,
+                    });
+                }
+                Ok(())
+            }
+            Instruction::Lookupswitch { pairs, .. }
+                if pairs.windows(2).any(|pair| pair[0].0 >= pair[1].0) =>
+            {
+                Err(AnalysisError::UnsortedLookupswitch { offset })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a return instruction matches the return type of the method, and that
+    /// instance initialization methods only return once `this` is initialized.
+    fn check_return(
+        &self,
+        offset: usize,
+        instruction: &Instruction,
+        frame: &Frame,
+    ) -> AnalysisResult<()> {
+        let matches = match instruction {
+            Instruction::Return => {
+                if self.is_init && frame.locals.contains(&VerificationType::UninitializedThis) {
+                    return Err(AnalysisError::TypeMismatch {
+                        offset,
+                        expected: "initialized this",
+                        found: VerificationType::UninitializedThis,
+                    });
+                }
+                self.return_type.is_none()
+            }
+            Instruction::Ireturn => matches!(
+                self.return_type,
+                Some(
+                    FieldType::Byte
+                        | FieldType::Char
+                        | FieldType::Int
+                        | FieldType::Short
+                        | FieldType::Boolean
+                )
+            ),
+            Instruction::Lreturn => self.return_type == Some(FieldType::Long),
+            Instruction::Freturn => self.return_type == Some(FieldType::Float),
+            Instruction::Dreturn => self.return_type == Some(FieldType::Double),
+            Instruction::Areturn => {
+                let expected = self
+                    .return_type
+                    .as_ref()
+                    .filter(|ty| ty.is_reference())
+                    .map(VerificationType::from)
+                    .ok_or(AnalysisError::InvalidReturn { offset })?;
+                // values of other kinds are rejected by the interpreter
+                match frame.stack.last() {
+                    Some(value @ (VerificationType::Null | VerificationType::Object(_)))
+                        if !value.is_assignable_to(&expected, self.hierarchy) =>
+                    {
+                        return Err(AnalysisError::NotAssignable {
+                            offset,
+                            expected,
+                            found: value.clone(),
+                        });
+                    }
+                    _ => true,
+                }
+            }
+            _ => true,
+        };
+
+        if matches {
+            Ok(())
+        } else {
+            Err(AnalysisError::InvalidReturn { offset })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::{SimpleClassHierarchy, StackMap},
+        model::constant_pool::{ConstantPoolBuilder, MemberRefKind},
+    };
+
+    /// Return the class `Test` with the static method `f`, whose code is `instructions` and
+    /// whose `StackMapTable` records `frames`.
+    fn class_file(
+        mut constant_pool: ConstantPoolBuilder,
+        descriptor: &str,
+        max_stack: u16,
+        instructions: &[Instruction],
+        frames: &[(u16, Frame)],
+    ) -> ClassFile {
+        let this_class = constant_pool.class("Test").unwrap();
+        let name_index = constant_pool.utf8("f").unwrap();
+        let descriptor_index = constant_pool.utf8(descriptor).unwrap();
+        let attribute_name_index = constant_pool.utf8("Code").unwrap();
+
+        let mut code_attributes = Vec::new();
+        if !frames.is_empty() {
+            let stack_map = StackMap {
+                initial: Frame::initial("Test", "f", descriptor, true).unwrap(),
+                frames: frames.to_vec(),
+                unreachable: Vec::new(),
+            };
+            code_attributes.push(stack_map.to_attribute(&mut constant_pool).unwrap());
+        }
+
+        let code = bytecode::encode(instructions);
+        ClassFile {
+            major_version: 61,
+            this_class,
+            constant_pool: constant_pool.build(),
+            methods: vec![MethodInfo {
+                access_flags: ACC_STATIC,
+                name_index,
+                descriptor_index,
+                attributes_count: 1,
+                attributes: vec![AttributeInfo::Code {
+                    attribute_name_index,
+                    attribute_length: 0,
+                    max_stack,
+                    max_locals: 2,
+                    code_length: code.len() as u32,
+                    code,
+                    exception_table_length: 0,
+                    exception_table: Vec::new(),
+                    code_attributes_count: code_attributes.len() as u16,
+                    code_attributes,
+                }],
+            }],
+            ..ClassFile::default()
+        }
+    }
+
+    fn verify(class_file: &ClassFile, hierarchy: &dyn ClassHierarchy) -> AnalysisResult<()> {
+        verify_method(class_file, &class_file.methods[0], hierarchy)
+            .map_err(|err| err.kind().clone())
+    }
+
+    #[test]
+    fn test_verify_branches() {
+        use VerificationType::*;
+
+        let hierarchy = SimpleClassHierarchy::new();
+        // static int max(int a, int b) { return a > b ? a : b; }
+        let max = |descriptor, max_stack, frames: &[(u16, Frame)]| {
+            let instructions = [
+                Instruction::Iload0,
+                Instruction::Iload1,
+                Instruction::IfIcmple(5),
+                Instruction::Iload0,
+                Instruction::Ireturn,
+                Instruction::Iload1,
+                Instruction::Ireturn,
+            ];
+            let constant_pool = ConstantPoolBuilder::new();
+            class_file(constant_pool, descriptor, max_stack, &instructions, frames)
+        };
+        let frame = |locals: &[VerificationType]| Frame {
+            locals: locals.to_vec(),
+            stack: Vec::new(),
+        };
+
+        let class_file = max("(II)I", 2, &[(7, frame(&[Integer, Integer]))]);
+        assert_eq!(verify_class(&class_file, &hierarchy), Ok(()));
+
+        let class_file = max("(II)I", 2, &[]);
+        let err = verify_method(&class_file, &class_file.methods[0], &hierarchy).unwrap_err();
+        assert_eq!(err.kind(), &AnalysisError::MissingFrame { offset: 7 });
+        assert_eq!(err.method(), "f(II)I");
+        assert_eq!(err.offset(), Some(2));
+        assert_eq!(err.instruction(), Some(&Instruction::IfIcmple(5)));
+        assert_eq!(
+            err.to_string(),
+            "missing stack map frame at offset 7 (in method f(II)I, at 2: if_icmple +5)"
+        );
+
+        let verify_max = |descriptor, max_stack, frames: &[(u16, Frame)]| {
+            verify(&max(descriptor, max_stack, frames), &hierarchy)
+        };
+        assert_eq!(
+            verify_max("(II)I", 2, &[(7, frame(&[Integer, Float]))]),
+            Err(AnalysisError::IncompatibleFrame { offset: 7 })
+        );
+        assert_eq!(
+            verify_max("(II)I", 2, &[(3, frame(&[Integer, Integer]))]),
+            Err(AnalysisError::InvalidStackMapFrame { index: 0 })
+        );
+        assert_eq!(
+            verify_max("(II)I", 1, &[(7, frame(&[Integer, Integer]))]),
+            Err(AnalysisError::StackOverflow { offset: 1 })
+        );
+        assert_eq!(
+            verify_max("(IF)I", 2, &[(7, frame(&[Integer, Float]))]),
+            Err(AnalysisError::TypeMismatch {
+                offset: 1,
+                expected: "int",
+                found: Float,
+            })
+        );
+        assert_eq!(
+            verify_max("(II)J", 2, &[(7, frame(&[Integer, Integer]))]),
+            Err(AnalysisError::InvalidReturn { offset: 6 })
+        );
+    }
+
+    #[test]
+    fn test_verify_unreachable_code() {
+        let hierarchy = SimpleClassHierarchy::new();
+        let instructions = [Instruction::Return, Instruction::Pop, Instruction::Return];
+
+        // without a StackMapTable, code that is never reached is never checked
+        let mut old = class_file(ConstantPoolBuilder::new(), "()V", 0, &instructions, &[]);
+        old.major_version = 49;
+        assert_eq!(verify_class(&old, &hierarchy), Ok(()));
+
+        let throwable = Frame {
+            locals: Vec::new(),
+            stack: vec![VerificationType::object("java/lang/Throwable")],
+        };
+        let new = class_file(
+            ConstantPoolBuilder::new(),
+            "()V",
+            1,
+            &instructions,
+            &[(1, throwable)],
+        );
+        assert_eq!(verify_class(&new, &hierarchy), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_invalid_frames() {
+        use VerificationType::*;
+
+        let hierarchy = SimpleClassHierarchy::new();
+        let instructions = [Instruction::Return, Instruction::Pop, Instruction::Return];
+        let frame = Frame {
+            locals: Vec::new(),
+            stack: vec![Top],
+        };
+        let class_file = class_file(
+            ConstantPoolBuilder::new(),
+            "()V",
+            1,
+            &instructions,
+            &[(1, frame)],
+        );
+        assert_eq!(
+            verify(&class_file, &hierarchy),
+            Err(AnalysisError::TypeMismatch {
+                offset: 1,
+                expected: "category 1 value",
+                found: Top,
+            })
+        );
+
+        // a same_locals_1_stack_item frame type below 64
+        let mut class_file = class_file;
+        match &mut class_file.methods[0].attributes[0] {
+            AttributeInfo::Code {
+                code_attributes, ..
+            } => match &mut code_attributes[0] {
+                AttributeInfo::StackMapTable { entries, .. } => {
+                    entries[0] = StackMapFrame::SameLocals1StackItemFrame {
+                        frame_type: 1,
+                        stack: vec![VerificationTypeInfo::IntegerVariableInfo { tag: 1 }],
+                    };
+                }
+                attribute => panic!("unexpected attribute {:?}", attribute),
+            },
+            attribute => panic!("unexpected attribute {:?}", attribute),
+        }
+        assert_eq!(
+            verify(&class_file, &hierarchy),
+            Err(AnalysisError::InvalidStackMapFrame { index: 0 })
+        );
+    }
+
+    #[test]
+    fn test_verify_references() {
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let string = constant_pool.class("java/lang/String").unwrap();
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy
+            .add_class("java/lang/String", "java/lang/Object")
+            .add_class("java/lang/Throwable", "java/lang/Object");
+
+        // static String f(Object o) { return (String) o; }
+        let instructions = [
+            Instruction::Aload0,
+            Instruction::Checkcast(string),
+            Instruction::Areturn,
+        ];
+        let descriptor = "(Ljava/lang/Object;)Ljava/lang/String;";
+        let cast = class_file(constant_pool.clone(), descriptor, 1, &instructions, &[]);
+        assert_eq!(verify(&cast, &hierarchy), Ok(()));
+
+        let instructions = [Instruction::Aload0, Instruction::Areturn];
+        let no_cast = class_file(constant_pool.clone(), descriptor, 1, &instructions, &[]);
+        assert_eq!(
+            verify(&no_cast, &hierarchy),
+            Err(AnalysisError::NotAssignable {
+                offset: 1,
+                expected: VerificationType::object("java/lang/String"),
+                found: VerificationType::object("java/lang/Object"),
+            })
+        );
+
+        let instructions = [Instruction::Aload0, Instruction::Athrow];
+        let descriptor = "(Ljava/lang/String;)V";
+        let throw = class_file(constant_pool.clone(), descriptor, 1, &instructions, &[]);
+        assert_eq!(
+            verify(&throw, &hierarchy),
+            Err(AnalysisError::NotAssignable {
+                offset: 1,
+                expected: VerificationType::object("java/lang/Throwable"),
+                found: VerificationType::object("java/lang/String"),
+            })
+        );
+
+        let instructions = [Instruction::Return];
+        let mut catch = class_file(constant_pool, "()V", 1, &instructions, &[]);
+        if let AttributeInfo::Code {
+            exception_table, ..
+        } = &mut catch.methods[0].attributes[0]
+        {
+            exception_table.push(ExceptionHandler::new(0, 1, 0, string));
+        }
+        assert_eq!(
+            verify(&catch, &hierarchy),
+            Err(AnalysisError::NotAssignable {
+                offset: 0,
+                expected: VerificationType::object("java/lang/Throwable"),
+                found: VerificationType::object("java/lang/String"),
+            })
+        );
+    }
+
+    #[test]
+    fn test_verify_operands() {
+        use VerificationType::*;
+
+        let hierarchy = SimpleClassHierarchy::new();
+
+        // static void f(Comparable c) { c.compareTo(c); }
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let compare_to = constant_pool
+            .member_ref(
+                MemberRefKind::InterfaceMethod,
+                "java/lang/Comparable",
+                "compareTo",
+                "(Ljava/lang/Object;)I",
+            )
+            .unwrap();
+        let invoke = |count| {
+            let instructions = [
+                Instruction::Aload0,
+                Instruction::Aload0,
+                Instruction::Invokeinterface {
+                    index: compare_to,
+                    count,
+                },
+                Instruction::Pop,
+                Instruction::Return,
+            ];
+            let descriptor = "(Ljava/lang/Comparable;)V";
+            class_file(constant_pool.clone(), descriptor, 2, &instructions, &[])
+        };
+        assert_eq!(verify(&invoke(2), &hierarchy), Ok(()));
+        assert_eq!(
+            verify(&invoke(1), &hierarchy),
+            Err(AnalysisError::InconsistentArgsCount {
+                offset: 2,
+                expected: 2,
+                found: 1,
+            })
+        );
+
+        // static void f(int i) { switch (i) { case 1: case 2: } }
+        let switch = |pairs: &[(i32, i32)]| {
+            let instructions = [
+                Instruction::Iload0,
+                Instruction::Lookupswitch {
+                    default: 27,
+                    pairs: pairs.to_vec(),
+                },
+                Instruction::Return,
+            ];
+            let frame = Frame {
+                locals: vec![Integer],
+                stack: Vec::new(),
+            };
+            let constant_pool = ConstantPoolBuilder::new();
+            class_file(constant_pool, "(I)V", 1, &instructions, &[(28, frame)])
+        };
+        assert_eq!(verify(&switch(&[(1, 27), (2, 27)]), &hierarchy), Ok(()));
+        for pairs in [[(2, 27), (1, 27)], [(1, 27), (1, 27)]] {
+            assert_eq!(
+                verify(&switch(&pairs), &hierarchy),
+                Err(AnalysisError::UnsortedLookupswitch { offset: 1 })
+            );
+        }
+    }
+}
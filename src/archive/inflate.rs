@@ -0,0 +1,453 @@
+//! A decoder for raw DEFLATE data (RFC 1951), as used by the entries of ZIP archives.
+
+use std::{error::Error, fmt};
+
+pub type InflateResult<T> = Result<T, InflateError>;
+
+/// Error type for malformed DEFLATE data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InflateError {
+    /// The data ended before the final block was complete.
+    UnexpectedEof,
+    /// A block has the reserved block type 3.
+    InvalidBlockType,
+    /// The length of a stored block does not match its one's complement.
+    InvalidStoredLength,
+    /// The code lengths of a dynamic block do not describe a valid Huffman code.
+    InvalidCodeLengths,
+    /// A Huffman code, length or distance symbol is not defined.
+    InvalidCode,
+    /// A back reference points before the start of the output.
+    InvalidDistance(usize),
+    /// The data inflates to more than the expected number of bytes.
+    TooLarge(usize),
+}
+
+impl fmt::Display for InflateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InflateError::UnexpectedEof => write!(f, "unexpected end of deflated data"),
+            InflateError::InvalidBlockType => write!(f, "invalid block type"),
+            InflateError::InvalidStoredLength => write!(f, "invalid stored block length"),
+            InflateError::InvalidCodeLengths => write!(f, "invalid Huffman code lengths"),
+            InflateError::InvalidCode => write!(f, "invalid Huffman code"),
+            InflateError::InvalidDistance(distance) => {
+                write!(f, "back reference distance {} is too far back", distance)
+            }
+            InflateError::TooLarge(max_size) => {
+                write!(f, "inflated data is larger than {} bytes", max_size)
+            }
+        }
+    }
+}
+
+impl Error for InflateError {}
+
+/// The most bits in a Huffman code.
+const MAX_BITS: usize = 15;
+
+/// The number of bits decoded with a single table lookup. Longer codes are decoded bit by bit.
+const FAST_BITS: u32 = 10;
+
+/// The base lengths and number of extra bits of the length symbols 257..=285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// The base distances and number of extra bits of the distance symbols 0..=29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which the code length code lengths of a dynamic block are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Reads the bits of the input, least significant bit of each byte first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u64,
+    count: u32,
+    /// The number of zero bits added to `buf` past the end of the input, so that codes near the
+    /// end can be looked up in full.
+    padding: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            buf: 0,
+            count: 0,
+            padding: 0,
+        }
+    }
+
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let byte = match self.data.get(self.pos) {
+                Some(&byte) => {
+                    self.pos += 1;
+                    byte
+                }
+                None => {
+                    self.padding += 8;
+                    0
+                }
+            };
+            self.buf |= (byte as u64) << self.count;
+            self.count += 8;
+        }
+    }
+
+    /// The next `n` bits, without consuming them.
+    fn peek(&mut self, n: u32) -> u32 {
+        if self.count < n {
+            self.fill();
+        }
+        (self.buf & ((1 << n) - 1)) as u32
+    }
+
+    fn consume(&mut self, n: u32) -> InflateResult<()> {
+        self.buf >>= n;
+        self.count -= n;
+        if self.padding > self.count {
+            return Err(InflateError::UnexpectedEof);
+        }
+        Ok(())
+    }
+
+    fn bits(&mut self, n: u32) -> InflateResult<u32> {
+        let bits = self.peek(n);
+        self.consume(n)?;
+        Ok(bits)
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        self.buf >>= self.count % 8;
+        self.count -= self.count % 8;
+    }
+}
+
+/// A canonical Huffman code.
+struct Huffman {
+    /// The number of codes of each length.
+    counts: [u16; MAX_BITS + 1],
+    /// The symbols, ordered by code.
+    symbols: Vec<u16>,
+    /// The symbol and length of each code of at most `FAST_BITS` bits, indexed by the next
+    /// `FAST_BITS` bits of the input, as `symbol << 4 | length`. Zero if the code is longer.
+    fast: Vec<u16>,
+}
+
+impl Huffman {
+    /// Build the code from the code length of each symbol, where zero means the symbol is
+    /// unused. Incomplete codes are accepted, but over-subscribed ones are not.
+    fn new(lengths: &[u8]) -> InflateResult<Self> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(InflateError::InvalidCodeLengths);
+            }
+        }
+
+        // the first code and the first index into `symbols` of each length
+        let mut next_code = [0u32; MAX_BITS + 1];
+        let mut offsets = [0usize; MAX_BITS + 1];
+        for length in 1..MAX_BITS {
+            next_code[length + 1] = (next_code[length] + counts[length] as u32) << 1;
+            offsets[length + 1] = offsets[length] + counts[length] as usize;
+        }
+
+        let mut symbols = vec![0; offsets[MAX_BITS] + counts[MAX_BITS] as usize];
+        let mut fast = vec![0; 1 << FAST_BITS];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                continue;
+            }
+            let length = length as usize;
+            symbols[offsets[length]] = symbol as u16;
+            offsets[length] += 1;
+
+            let code = next_code[length];
+            next_code[length] += 1;
+            if length as u32 <= FAST_BITS {
+                // the input holds codes starting with their most significant bit
+                let reversed = code.reverse_bits() >> (32 - length);
+                let entry = (symbol as u16) << 4 | length as u16;
+                let mut index = reversed as usize;
+                while index < fast.len() {
+                    fast[index] = entry;
+                    index += 1 << length;
+                }
+            }
+        }
+
+        Ok(Huffman {
+            counts,
+            symbols,
+            fast,
+        })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> InflateResult<u16> {
+        let entry = self.fast[reader.peek(FAST_BITS) as usize];
+        if entry != 0 {
+            reader.consume((entry & 0xf) as u32)?;
+            return Ok(entry >> 4);
+        }
+
+        // decode the code bit by bit
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(InflateError::InvalidCode)
+    }
+}
+
+/// The codes of blocks compressed with fixed Huffman codes.
+fn fixed_codes() -> InflateResult<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+/// Read the codes of a block compressed with dynamic Huffman codes.
+fn dynamic_codes(reader: &mut BitReader) -> InflateResult<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(InflateError::InvalidCodeLengths);
+    }
+
+    let mut code_length_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_length_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut idx = 0;
+    while idx < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => match idx.checked_sub(1) {
+                Some(prev) => (lengths[prev], 3 + reader.bits(2)? as usize),
+                None => return Err(InflateError::InvalidCodeLengths),
+            },
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if idx + repeat > lengths.len() {
+            return Err(InflateError::InvalidCodeLengths);
+        }
+        lengths[idx..idx + repeat].fill(length);
+        idx += repeat;
+    }
+
+    // a block cannot end without a code for the end of block symbol
+    if lengths[256] == 0 {
+        return Err(InflateError::InvalidCodeLengths);
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+/// Decode the symbols of a compressed block into `out`.
+fn inflate_block(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    out: &mut Vec<u8>,
+    max_size: usize,
+) -> InflateResult<()> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                if out.len() >= max_size {
+                    return Err(InflateError::TooLarge(max_size));
+                }
+                out.push(symbol as u8);
+            }
+
+            256 => return Ok(()),
+
+            257..=285 => {
+                let symbol = symbol - 257;
+                let length = LENGTH_BASE[symbol] as usize
+                    + reader.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+
+                let symbol = distances.decode(reader)? as usize;
+                if symbol >= DISTANCE_BASE.len() {
+                    return Err(InflateError::InvalidCode);
+                }
+                let distance = DISTANCE_BASE[symbol] as usize
+                    + reader.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+
+                if distance > out.len() {
+                    return Err(InflateError::InvalidDistance(distance));
+                }
+                if out.len() + length > max_size {
+                    return Err(InflateError::TooLarge(max_size));
+                }
+
+                // the source and destination overlap if the distance is less than the length
+                let start = out.len() - distance;
+                for idx in start..start + length {
+                    out.push(out[idx]);
+                }
+            }
+
+            _ => return Err(InflateError::InvalidCode),
+        }
+    }
+}
+
+/// Decode the raw DEFLATE data in `data`, failing if it inflates to more than `max_size` bytes.
+pub fn inflate(data: &[u8], max_size: usize) -> InflateResult<Vec<u8>> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::with_capacity(max_size.min(1 << 20));
+
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let len = reader.bits(16)?;
+                let nlen = reader.bits(16)?;
+                if len != !nlen & 0xffff {
+                    return Err(InflateError::InvalidStoredLength);
+                }
+                if out.len() + len as usize > max_size {
+                    return Err(InflateError::TooLarge(max_size));
+                }
+                for _ in 0..len {
+                    out.push(reader.bits(8)? as u8);
+                }
+            }
+
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                inflate_block(&mut reader, &literals, &distances, &mut out, max_size)?;
+            }
+
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &literals, &distances, &mut out, max_size)?;
+            }
+
+            _ => return Err(InflateError::InvalidBlockType),
+        }
+
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_and_fixed_blocks() {
+        // a stored block followed by the final block, compressed with the fixed codes
+        let mut data = vec![0x00, 0x05, 0x00, 0xfa, 0xff];
+        data.extend_from_slice(b"hello");
+        data.extend_from_slice(&[
+            0x2b, 0xc9, 0x57, 0x48, 0x4a, 0x55, 0xc8, 0x2f, 0x52, 0xc8, 0xcb, 0x2f, 0x51, 0x28,
+            0x01, 0x71, 0x74, 0x14, 0x4a, 0x32, 0x12, 0x4b, 0x14, 0x32, 0x8b, 0x81, 0x74, 0xaa,
+            0x42, 0x61, 0x69, 0x6a, 0x71, 0x49, 0x66, 0x7e, 0x1e, 0x00,
+        ]);
+
+        assert_eq!(
+            inflate(&data, 1024).unwrap(),
+            b"helloto be or not to be, that is the question"
+        );
+        assert_eq!(inflate(&data, 20), Err(InflateError::TooLarge(20)));
+        assert_eq!(
+            inflate(&data[..data.len() - 1], 1024),
+            Err(InflateError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_dynamic_block() {
+        let data = [
+            0x85, 0xd1, 0xbb, 0x0d, 0x03, 0x31, 0x0c, 0x03, 0xd0, 0x95, 0xe2, 0x9f, 0xac, 0x1b,
+            0xc7, 0xb9, 0xfd, 0x77, 0x48, 0x80, 0x54, 0x7a, 0x45, 0xce, 0x1d, 0x21, 0x8a, 0x22,
+            0xe9, 0x73, 0x7e, 0xef, 0xfd, 0x7d, 0xf7, 0xfd, 0x3a, 0x05, 0xb6, 0x0a, 0x67, 0x85,
+            0x17, 0xe4, 0xa8, 0xb8, 0xaf, 0x8a, 0x07, 0xf3, 0xc9, 0x7e, 0x20, 0x9f, 0x5c, 0x1f,
+            0xc8, 0x43, 0x9f, 0xbb, 0xe2, 0xdd, 0xe1, 0xa3, 0x86, 0x7a, 0x30, 0xbf, 0x74, 0xcf,
+            0xf9, 0x6d, 0x55, 0xec, 0x2f, 0xf8, 0x97, 0xe9, 0x2d, 0xd3, 0xae, 0xdd, 0x87, 0xbf,
+            0xb8, 0x9f, 0xc4, 0xc1, 0x7e, 0xa7, 0x9d, 0x84, 0xbf, 0x90, 0xef, 0xc4, 0x49, 0x3f,
+            0xcb, 0xcf, 0xe1, 0x5e, 0x63, 0x9e, 0xc4, 0x0f, 0xeb, 0xc0, 0xcf, 0xe0, 0x7e, 0x33,
+            0x1f, 0xed, 0xd0, 0x56, 0x62, 0x67, 0x63, 0x7f, 0x3b, 0x1f, 0x0f, 0xf8, 0x41, 0xcf,
+            0x7b, 0xfa, 0x89, 0xff, 0x69, 0x4c, 0x6b, 0x1b, 0xb6, 0x95, 0xf1, 0x01,
+        ];
+        let expected: String = (0..60)
+            .map(|i| format!("aaaaaaaabbbbcc{}", i * i % 97))
+            .collect();
+
+        assert_eq!(inflate(&data, 4096).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        // block type 3 is reserved
+        assert_eq!(inflate(&[0x07], 16), Err(InflateError::InvalidBlockType));
+        // the length of a stored block must match its complement
+        assert_eq!(
+            inflate(&[0x01, 0x05, 0x00, 0x00, 0x00], 16),
+            Err(InflateError::InvalidStoredLength)
+        );
+        // a fixed block starting with a back reference (length 3, distance 1)
+        assert_eq!(
+            inflate(&[0x03, 0x02, 0x00], 16),
+            Err(InflateError::InvalidDistance(1))
+        );
+        assert_eq!(inflate(&[], 16), Err(InflateError::UnexpectedEof));
+    }
+}
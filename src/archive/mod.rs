@@ -0,0 +1,824 @@
+//! Reading class files from JAR and ZIP archives.
+//!
+//! `Archive` reads the central directory of a ZIP archive from any `Read + Seek` source, and
+//! `Archive::classes` deserializes its `.class` entries, which may be stored or deflated. A
+//! corrupt entry is reported as an error for that entry only, so callers can skip it and carry
+//! on with the rest of the archive.
+//!
+//! Multi-release JARs (those whose manifest has `Multi-Release: true`) keep classes for later
+//! Java releases under `META-INF/versions/N/`. These are only read when a release is chosen
+//! with `Archive::with_release`, in which case the class for the highest version not above
+//! that release replaces the one at the root of the archive, as with
+//! `java.util.jar.JarFile`. An archive whose manifest cannot be read (e.g. because it is
+//! corrupt or encrypted) is not a multi-release JAR.
+
+pub mod inflate;
+
+use crate::{
+    deserializer::{Deserializer, SupportedVersions},
+    error::DeserializeError,
+    model::ClassFile,
+    rw::reader::Reader,
+};
+use inflate::InflateError;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt, io,
+    io::{Cursor, Read, Seek, SeekFrom},
+};
+
+pub type ArchiveResult<T> = Result<T, ArchiveError>;
+
+/// Error type for reading JAR and ZIP archives. Errors concerning a single entry give its name.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The archive is malformed, e.g. it has no end of central directory record.
+    InvalidArchive(&'static str),
+    /// There is no entry at the index.
+    NoSuchEntry(usize),
+    /// The local header of the entry is missing or malformed.
+    InvalidEntry(String),
+    /// The entry is compressed with a method other than stored or deflated.
+    UnsupportedCompression { entry: String, method: u16 },
+    /// The entry is encrypted.
+    Encrypted(String),
+    /// The deflated data of the entry is malformed.
+    Inflate { entry: String, source: InflateError },
+    /// The data of the entry does not have the size recorded in the archive.
+    SizeMismatch {
+        entry: String,
+        expected: u64,
+        actual: u64,
+    },
+    /// The CRC-32 of the data of the entry does not match the one recorded in the archive.
+    ChecksumMismatch(String),
+    /// The entry is not a valid class file.
+    Deserialize {
+        entry: String,
+        source: DeserializeError,
+    },
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Io(err) => write!(f, "I/O error: {}", err),
+            ArchiveError::InvalidArchive(reason) => write!(f, "invalid archive: {}", reason),
+            ArchiveError::NoSuchEntry(idx) => write!(f, "no entry at index {}", idx),
+            ArchiveError::InvalidEntry(entry) => write!(f, "invalid local header (in {})", entry),
+            ArchiveError::UnsupportedCompression { entry, method } => write!(
+                f,
+                "unsupported compression method {} (in {})",
+                method, entry
+            ),
+            ArchiveError::Encrypted(entry) => write!(f, "encrypted entry (in {})", entry),
+            ArchiveError::Inflate { entry, source } => write!(f, "{} (in {})", source, entry),
+            ArchiveError::SizeMismatch {
+                entry,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "expected {} bytes, found {} (in {})",
+                expected, actual, entry
+            ),
+            ArchiveError::ChecksumMismatch(entry) => {
+                write!(f, "CRC-32 mismatch (in {})", entry)
+            }
+            ArchiveError::Deserialize { entry, source } => {
+                write!(f, "{} (in {})", source, entry)
+            }
+        }
+    }
+}
+
+impl Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ArchiveError::Io(err) => Some(err),
+            ArchiveError::Inflate { source, .. } => Some(source),
+            ArchiveError::Deserialize { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(err: io::Error) -> Self {
+        ArchiveError::Io(err)
+    }
+}
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+
+/// The tag of the extra field holding the 64-bit sizes and offset of an entry.
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+
+/// The sizes of the fixed parts of the records.
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+const ZIP64_LOCATOR_SIZE: usize = 20;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 56;
+
+/// The longest comment that can follow the end of central directory record.
+const MAX_COMMENT_SIZE: usize = 0xffff;
+
+const FLAG_ENCRYPTED: u16 = 1 << 0;
+
+pub const MANIFEST_NAME: &str = "META-INF/MANIFEST.MF";
+
+/// The directory of the classes for later releases in a multi-release JAR.
+pub const VERSIONS_DIR: &str = "META-INF/versions/";
+
+/// The first release whose classes may be kept under `VERSIONS_DIR`.
+const FIRST_VERSIONED_RELEASE: u16 = 9;
+
+/// How the data of an entry is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionMethod {
+    Stored,
+    Deflated,
+    /// Any other method, which is not supported.
+    Other(u16),
+}
+
+impl From<u16> for CompressionMethod {
+    fn from(method: u16) -> Self {
+        match method {
+            0 => CompressionMethod::Stored,
+            8 => CompressionMethod::Deflated,
+            _ => CompressionMethod::Other(method),
+        }
+    }
+}
+
+/// An entry in the central directory of an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// The path of the entry, e.g. `java/lang/Object.class`.
+    pub name: String,
+    pub compression_method: CompressionMethod,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub crc32: u32,
+    pub encrypted: bool,
+    local_header_offset: u64,
+}
+
+impl Entry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    pub fn is_class(&self) -> bool {
+        !self.is_dir() && self.name.ends_with(".class")
+    }
+
+    /// The release and path within it of an entry under `VERSIONS_DIR`, e.g. `(11, "a/B.class")`
+    /// for `META-INF/versions/11/a/B.class`.
+    fn versioned_name(&self) -> Option<(u16, &str)> {
+        let (version, name) = self.name.strip_prefix(VERSIONS_DIR)?.split_once('/')?;
+        Some((version.parse().ok()?, name))
+    }
+}
+
+/// The table of CRC-32 remainders of each byte value.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut idx = 0;
+    while idx < 256 {
+        let mut crc = idx as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[idx] = crc;
+        idx += 1;
+    }
+    table
+};
+
+/// The CRC-32 of `data`, as recorded for the entries of an archive.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from(u32_at(bytes, offset)) | u64::from(u32_at(bytes, offset + 4)) << 32
+}
+
+/// Read `len` bytes at `offset` of `reader`, failing with `err` if there are fewer.
+fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+    err: impl FnOnce() -> ArchiveError,
+) -> ArchiveResult<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::new();
+    reader.take(len).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < len {
+        return Err(err());
+    }
+    Ok(bytes)
+}
+
+/// Read the entries of the central directory of the archive in `reader`.
+fn read_central_directory<R: Read + Seek>(reader: &mut R) -> ArchiveResult<Vec<Entry>> {
+    let archive_len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = archive_len.min((END_OF_CENTRAL_DIRECTORY_SIZE + MAX_COMMENT_SIZE) as u64);
+    let tail = read_at(reader, archive_len - tail_len, tail_len, || {
+        ArchiveError::InvalidArchive("truncated archive")
+    })?;
+
+    // the end of central directory record is followed only by the archive comment
+    let eocd = (0..=tail.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+        .rev()
+        .find(|&offset| {
+            tail.len() >= END_OF_CENTRAL_DIRECTORY_SIZE
+                && u32_at(&tail, offset) == END_OF_CENTRAL_DIRECTORY_SIGNATURE
+        })
+        .ok_or(ArchiveError::InvalidArchive(
+            "missing end of central directory record",
+        ))?;
+    let mut entry_count = u64::from(u16_at(&tail, eocd + 10));
+    let mut directory_size = u64::from(u32_at(&tail, eocd + 12));
+    let mut directory_offset = u64::from(u32_at(&tail, eocd + 16));
+
+    // ZIP64 archives keep the actual values in a record located just before this one
+    let eocd_offset = archive_len - tail_len + eocd as u64;
+    if eocd_offset >= ZIP64_LOCATOR_SIZE as u64 {
+        let locator = read_at(
+            reader,
+            eocd_offset - ZIP64_LOCATOR_SIZE as u64,
+            ZIP64_LOCATOR_SIZE as u64,
+            || ArchiveError::InvalidArchive("truncated archive"),
+        )?;
+        if u32_at(&locator, 0) == ZIP64_LOCATOR_SIGNATURE {
+            let record = read_at(
+                reader,
+                u64_at(&locator, 8),
+                ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE as u64,
+                || ArchiveError::InvalidArchive("truncated ZIP64 end of central directory"),
+            )?;
+            if u32_at(&record, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE {
+                return Err(ArchiveError::InvalidArchive(
+                    "missing ZIP64 end of central directory record",
+                ));
+            }
+            entry_count = u64_at(&record, 32);
+            directory_size = u64_at(&record, 40);
+            directory_offset = u64_at(&record, 48);
+        }
+    }
+
+    let directory = read_at(reader, directory_offset, directory_size, || {
+        ArchiveError::InvalidArchive("truncated central directory")
+    })?;
+    let truncated = || ArchiveError::InvalidArchive("truncated central directory");
+
+    let mut entries = Vec::with_capacity(entry_count.min(1 << 16) as usize);
+    let mut offset = 0;
+    for _ in 0..entry_count {
+        let header = directory
+            .get(offset..offset + CENTRAL_HEADER_SIZE)
+            .ok_or_else(truncated)?;
+        if u32_at(header, 0) != CENTRAL_HEADER_SIGNATURE {
+            return Err(ArchiveError::InvalidArchive(
+                "invalid central directory header",
+            ));
+        }
+
+        let flags = u16_at(header, 8);
+        let method = u16_at(header, 10);
+        let crc32 = u32_at(header, 16);
+        let mut compressed_size = u64::from(u32_at(header, 20));
+        let mut uncompressed_size = u64::from(u32_at(header, 24));
+        let name_len = u16_at(header, 28) as usize;
+        let extra_len = u16_at(header, 30) as usize;
+        let comment_len = u16_at(header, 32) as usize;
+        let mut local_header_offset = u64::from(u32_at(header, 42));
+
+        let name_offset = offset + CENTRAL_HEADER_SIZE;
+        let extra_offset = name_offset + name_len;
+        let name = directory
+            .get(name_offset..extra_offset)
+            .ok_or_else(truncated)?;
+        let extra = directory
+            .get(extra_offset..extra_offset + extra_len)
+            .ok_or_else(truncated)?;
+
+        // the ZIP64 extra field holds, in order, those of the values that do not fit
+        let mut extra_idx = 0;
+        while extra_idx + 4 <= extra.len() {
+            let tag = u16_at(extra, extra_idx);
+            let len = u16_at(extra, extra_idx + 2) as usize;
+            let field = extra
+                .get(extra_idx + 4..extra_idx + 4 + len)
+                .ok_or_else(truncated)?;
+            if tag == ZIP64_EXTRA_FIELD {
+                let mut values = field.chunks_exact(8).map(|value| u64_at(value, 0));
+                for value in [
+                    &mut uncompressed_size,
+                    &mut compressed_size,
+                    &mut local_header_offset,
+                ] {
+                    if *value == u64::from(u32::MAX) {
+                        *value = values.next().ok_or_else(truncated)?;
+                    }
+                }
+            }
+            extra_idx += 4 + len;
+        }
+
+        entries.push(Entry {
+            name: String::from_utf8_lossy(name).into_owned(),
+            compression_method: method.into(),
+            compressed_size,
+            uncompressed_size,
+            crc32,
+            encrypted: flags & FLAG_ENCRYPTED != 0,
+            local_header_offset,
+        });
+        offset = extra_offset + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+/// Whether the manifest declares a multi-release JAR.
+fn is_multi_release_manifest(manifest: &[u8]) -> bool {
+    String::from_utf8_lossy(manifest).lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("Multi-Release")
+                && value.trim().eq_ignore_ascii_case("true")
+        })
+    })
+}
+
+/// A JAR or ZIP archive.
+pub struct Archive<R: Read + Seek> {
+    reader: R,
+    entries: Vec<Entry>,
+    multi_release: bool,
+    release: Option<u16>,
+    versions: SupportedVersions,
+}
+
+impl<R: Read + Seek> Archive<R> {
+    /// Open the archive in `reader`, reading its central directory and manifest. Only I/O errors
+    /// are returned for the manifest; if it is malformed, the archive is read as a plain JAR.
+    pub fn new(reader: R) -> ArchiveResult<Self> {
+        let mut reader = reader;
+        let entries = read_central_directory(&mut reader)?;
+
+        let mut archive = Archive {
+            reader,
+            entries,
+            multi_release: false,
+            release: None,
+            versions: SupportedVersions::default(),
+        };
+        if let Some(idx) = archive.find(MANIFEST_NAME) {
+            archive.multi_release = match archive.read(idx) {
+                Ok(manifest) => is_multi_release_manifest(&manifest),
+                Err(ArchiveError::Io(err)) => return Err(ArchiveError::Io(err)),
+                Err(_) => false,
+            };
+        }
+
+        Ok(archive)
+    }
+
+    /// Read the classes of a multi-release JAR for the given Java release (e.g. 17). Defaults to
+    /// `None`, which reads only the classes at the root of the archive.
+    pub fn with_release(mut self, release: u16) -> Self {
+        self.release = Some(release);
+        self
+    }
+
+    /// Accept only class files whose version lies in `versions`. Defaults to
+    /// `SupportedVersions::default()`.
+    pub fn with_supported_versions(mut self, versions: SupportedVersions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// All entries of the archive, in the order of the central directory.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Whether the archive is a multi-release JAR.
+    pub fn is_multi_release(&self) -> bool {
+        self.multi_release
+    }
+
+    /// The index of the entry named `name`.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.name == name)
+    }
+
+    /// Read and decompress the data of the entry at `idx`, checking its size and CRC-32.
+    pub fn read(&mut self, idx: usize) -> ArchiveResult<Vec<u8>> {
+        let entry = self
+            .entries
+            .get(idx)
+            .ok_or(ArchiveError::NoSuchEntry(idx))?;
+        if entry.encrypted {
+            return Err(ArchiveError::Encrypted(entry.name.clone()));
+        }
+
+        let invalid_entry = || ArchiveError::InvalidEntry(entry.name.clone());
+        let header = read_at(
+            &mut self.reader,
+            entry.local_header_offset,
+            LOCAL_HEADER_SIZE as u64,
+            invalid_entry,
+        )?;
+        if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE {
+            return Err(invalid_entry());
+        }
+        // the name and extra field may differ from those in the central directory
+        let data_offset = entry.local_header_offset
+            + LOCAL_HEADER_SIZE as u64
+            + u64::from(u16_at(&header, 26))
+            + u64::from(u16_at(&header, 28));
+        let data = read_at(
+            &mut self.reader,
+            data_offset,
+            entry.compressed_size,
+            invalid_entry,
+        )?;
+
+        let data = match entry.compression_method {
+            CompressionMethod::Stored => data,
+            CompressionMethod::Deflated => {
+                inflate::inflate(&data, entry.uncompressed_size as usize).map_err(|err| {
+                    ArchiveError::Inflate {
+                        entry: entry.name.clone(),
+                        source: err,
+                    }
+                })?
+            }
+            CompressionMethod::Other(method) => {
+                return Err(ArchiveError::UnsupportedCompression {
+                    entry: entry.name.clone(),
+                    method,
+                })
+            }
+        };
+
+        if data.len() as u64 != entry.uncompressed_size {
+            return Err(ArchiveError::SizeMismatch {
+                entry: entry.name.clone(),
+                expected: entry.uncompressed_size,
+                actual: data.len() as u64,
+            });
+        }
+        if crc32(&data) != entry.crc32 {
+            return Err(ArchiveError::ChecksumMismatch(entry.name.clone()));
+        }
+
+        Ok(data)
+    }
+
+    /// The indices of the class entries to read, in archive order. Entries under
+    /// `VERSIONS_DIR` replace the entries of the same name at the root of a multi-release JAR
+    /// if their version is at most the chosen release, and are skipped otherwise.
+    pub fn class_entries(&self) -> Vec<usize> {
+        let release = self.release.filter(|_| self.multi_release);
+
+        // the version (0 for the root) and index of the entry chosen for each class
+        let mut chosen: HashMap<&str, (u16, usize)> = HashMap::new();
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if !entry.is_class() {
+                continue;
+            }
+
+            let (version, name) = match entry.versioned_name() {
+                Some((version, name)) => match release {
+                    Some(release) if (FIRST_VERSIONED_RELEASE..=release).contains(&version) => {
+                        (version, name)
+                    }
+                    _ => continue,
+                },
+                None if entry.name.starts_with(VERSIONS_DIR) => continue,
+                None => (0, entry.name.as_str()),
+            };
+
+            let current = chosen.entry(name).or_insert((version, idx));
+            if version > current.0 {
+                *current = (version, idx);
+            }
+        }
+
+        let mut indices: Vec<usize> = chosen.into_values().map(|(_, idx)| idx).collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// Read and deserialize the entry at `idx` as a class file.
+    pub fn read_class(&mut self, idx: usize) -> ArchiveResult<ClassFile> {
+        let bytes = self.read(idx)?;
+        Deserializer::new(Reader::new(Cursor::new(bytes)))
+            .with_supported_versions(self.versions)
+            .deserialize()
+            .map_err(|err| ArchiveError::Deserialize {
+                entry: self.entries[idx].name.clone(),
+                source: err,
+            })
+    }
+
+    /// An iterator over the class files in the archive, yielding the name of each entry along
+    /// with its class file, or the error for an entry that could not be read. Entries that are
+    /// not class files are skipped.
+    pub fn classes(&mut self) -> Classes<'_, R> {
+        Classes {
+            indices: self.class_entries().into_iter(),
+            archive: self,
+        }
+    }
+}
+
+/// An iterator over the class files in an `Archive`, created by `Archive::classes`.
+pub struct Classes<'a, R: Read + Seek> {
+    archive: &'a mut Archive<R>,
+    indices: std::vec::IntoIter<usize>,
+}
+
+impl<R: Read + Seek> Iterator for Classes<'_, R> {
+    type Item = ArchiveResult<(String, ClassFile)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let idx = self.indices.next()?;
+        Some(
+            self.archive
+                .read_class(idx)
+                .map(|classfile| (self.archive.entries[idx].name.clone(), classfile)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::ClassBuilder, error::DeserializeErrorKind, rw::writer::Writer,
+        serializer::Serializer,
+    };
+
+    /// An entry to write with `zip`: its name, compression method, uncompressed data and the
+    /// data as written to the archive.
+    type TestEntry<'a> = (&'a str, u16, &'a [u8], &'a [u8]);
+
+    fn zip(entries: &[TestEntry]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+
+        for &(name, method, data, compressed) in entries {
+            let offset = archive.len() as u32;
+            for (buf, signature) in [
+                (&mut archive, LOCAL_HEADER_SIGNATURE),
+                (&mut directory, CENTRAL_HEADER_SIGNATURE),
+            ] {
+                let is_central = signature == CENTRAL_HEADER_SIGNATURE;
+                buf.extend_from_slice(&signature.to_le_bytes());
+                if is_central {
+                    buf.extend_from_slice(&20u16.to_le_bytes()); // version made by
+                }
+                buf.extend_from_slice(&[20, 0, 0, 0]); // version needed, flags
+                buf.extend_from_slice(&method.to_le_bytes());
+                buf.extend_from_slice(&[0; 4]); // modification time and date
+                buf.extend_from_slice(&crc32(data).to_le_bytes());
+                buf.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+                buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+                buf.extend_from_slice(&[0; 2]); // extra field length
+                if is_central {
+                    buf.extend_from_slice(&[0; 10]); // comment length, disk, attributes
+                    buf.extend_from_slice(&offset.to_le_bytes());
+                }
+                buf.extend_from_slice(name.as_bytes());
+            }
+            archive.extend_from_slice(compressed);
+        }
+
+        let directory_offset = archive.len() as u32;
+        archive.extend_from_slice(&directory);
+        archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        archive.extend_from_slice(&[0; 4]); // disk numbers
+        archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(directory.len() as u32).to_le_bytes());
+        archive.extend_from_slice(&directory_offset.to_le_bytes());
+        archive.extend_from_slice(&[0; 2]); // comment length
+        archive
+    }
+
+    fn class_bytes(name: &str) -> Vec<u8> {
+        let classfile = ClassBuilder::new(name).build().unwrap();
+        let mut bytes = Vec::new();
+        Serializer::new(Writer::new(&mut bytes))
+            .serialize(&classfile)
+            .unwrap();
+        bytes
+    }
+
+    /// `data` as a deflated entry made of a single stored block.
+    fn deflate_stored(data: &[u8]) -> Vec<u8> {
+        let len = data.len() as u16;
+        let mut deflated = vec![0x01];
+        deflated.extend_from_slice(&len.to_le_bytes());
+        deflated.extend_from_slice(&(!len).to_le_bytes());
+        deflated.extend_from_slice(data);
+        deflated
+    }
+
+    fn class_names<R: Read + Seek>(archive: &mut Archive<R>) -> Vec<(String, String)> {
+        archive
+            .classes()
+            .map(|res| {
+                let (entry, classfile) = res.unwrap();
+                let name = classfile
+                    .constant_pool
+                    .get_class_name(classfile.this_class)
+                    .unwrap()
+                    .into_owned();
+                (entry, name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"hello, world"), 0xffab_723a);
+    }
+
+    #[test]
+    fn test_classes() {
+        let a = class_bytes("a/A");
+        let b = class_bytes("b/B");
+        let b_deflated = deflate_stored(&b);
+        let bytes = zip(&[
+            ("a/", 0, b"", b""),
+            ("a/A.class", 0, &a, &a),
+            ("README.md", 0, b"# hello", b"# hello"),
+            ("b/B.class", 8, &b, &b_deflated),
+        ]);
+
+        let mut archive = Archive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.entries().len(), 4);
+        assert!(!archive.is_multi_release());
+        assert_eq!(
+            archive.entries()[3].compression_method,
+            CompressionMethod::Deflated
+        );
+        assert_eq!(archive.read(2).unwrap(), b"# hello");
+        assert!(matches!(archive.read(4), Err(ArchiveError::NoSuchEntry(4))));
+        assert!(matches!(
+            archive.read_class(4),
+            Err(ArchiveError::NoSuchEntry(4))
+        ));
+        assert_eq!(
+            class_names(&mut archive),
+            [
+                ("a/A.class".to_string(), "a/A".to_string()),
+                ("b/B.class".to_string(), "b/B".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_corrupt_entries() {
+        let a = class_bytes("A");
+        let mut corrupt = a.clone();
+        corrupt[20] ^= 0xff;
+        let bytes = zip(&[
+            ("A.class", 0, &a, &corrupt),
+            ("B.class", 0, b"not a class", b"not a class"),
+            ("C.class", 12, &a, &a),
+            ("D.class", 8, &a, &[0x07]),
+            ("E.class", 0, &a, &a),
+        ]);
+
+        let mut archive = Archive::new(Cursor::new(bytes)).unwrap();
+        let results: Vec<_> = archive.classes().collect();
+        assert!(matches!(
+            &results[0],
+            Err(ArchiveError::ChecksumMismatch(entry)) if entry == "A.class"
+        ));
+        assert!(matches!(
+            &results[1],
+            Err(ArchiveError::Deserialize { entry, source })
+                if entry == "B.class" && source.kind() == &DeserializeErrorKind::InvalidMagic(0x6e6f7420)
+        ));
+        assert!(matches!(
+            &results[2],
+            Err(ArchiveError::UnsupportedCompression { method: 12, .. })
+        ));
+        assert!(matches!(&results[3], Err(ArchiveError::Inflate { .. })));
+        assert!(matches!(&results[4], Ok((entry, _)) if entry == "E.class"));
+
+        assert!(matches!(
+            Archive::new(Cursor::new(b"PK not an archive".to_vec())),
+            Err(ArchiveError::InvalidArchive(_))
+        ));
+    }
+
+    #[test]
+    fn test_multi_release() {
+        let manifest = b"Manifest-Version: 1.0\r\nMulti-Release: true\r\n";
+        let base = class_bytes("Base");
+        let v9 = class_bytes("V9");
+        let v21 = class_bytes("V21");
+        let only9 = class_bytes("Only9");
+        let entries: [TestEntry; 5] = [
+            (MANIFEST_NAME, 0, manifest, manifest),
+            ("p/A.class", 0, &base, &base),
+            ("META-INF/versions/9/p/A.class", 0, &v9, &v9),
+            ("META-INF/versions/9/p/B.class", 0, &only9, &only9),
+            ("META-INF/versions/21/p/A.class", 0, &v21, &v21),
+        ];
+        let bytes = zip(&entries);
+
+        let mut archive = Archive::new(Cursor::new(bytes.clone())).unwrap();
+        assert!(archive.is_multi_release());
+        assert_eq!(
+            class_names(&mut archive),
+            [("p/A.class".to_string(), "Base".to_string())]
+        );
+
+        let mut archive = Archive::new(Cursor::new(bytes)).unwrap().with_release(17);
+        assert_eq!(
+            class_names(&mut archive),
+            [
+                (
+                    "META-INF/versions/9/p/A.class".to_string(),
+                    "V9".to_string()
+                ),
+                (
+                    "META-INF/versions/9/p/B.class".to_string(),
+                    "Only9".to_string()
+                )
+            ]
+        );
+
+        // the versioned classes are ignored if the manifest does not declare a multi-release JAR
+        let mut archive = Archive::new(Cursor::new(zip(&entries[1..])))
+            .unwrap()
+            .with_release(17);
+        assert!(!archive.is_multi_release());
+        assert_eq!(
+            class_names(&mut archive),
+            [("p/A.class".to_string(), "Base".to_string())]
+        );
+
+        // so are they if the manifest cannot be read, but the archive still opens
+        let mut corrupt = manifest.to_vec();
+        corrupt[0] ^= 0xff;
+        let mut entries = entries;
+        entries[0] = (MANIFEST_NAME, 0, manifest, &corrupt);
+        let mut archive = Archive::new(Cursor::new(zip(&entries)))
+            .unwrap()
+            .with_release(17);
+        assert!(!archive.is_multi_release());
+        assert_eq!(
+            class_names(&mut archive),
+            [("p/A.class".to_string(), "Base".to_string())]
+        );
+        entries[0] = (MANIFEST_NAME, 12, manifest, manifest);
+        let archive = Archive::new(Cursor::new(zip(&entries))).unwrap();
+        assert!(!archive.is_multi_release());
+    }
+}
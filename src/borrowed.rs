@@ -0,0 +1,548 @@
+//! Zero-copy deserialization of class files held in memory.
+//!
+//! `SliceDeserializer` reads a class file from a byte slice into a `ClassFile<'a>`, which
+//! borrows the contents of `CONSTANT_Utf8_info` entries and the info of attributes from the
+//! input instead of copying them. Attributes are kept as raw bytes, except that the `Code`
+//! attribute of a method can be decoded with `MethodInfo::code`, again borrowing its bytecode.
+//! This makes it much cheaper than `Deserializer` for scanning large numbers of classes.
+
+use crate::{
+    deserializer::{constant_width, read_constant, SupportedVersions, MAGIC},
+    error::{DeserializeError, DeserializeErrorKind, PathSegment},
+    model::{
+        attributes::{predefined_attributes, ExceptionHandler},
+        constant_pool::{
+            self, decode_modified_utf8, tags::*, types, ConstantPoolError, ConstantPoolResult,
+        },
+    },
+    rw::reader::SliceReader,
+};
+use std::borrow::Cow;
+
+pub type DeserializeResult<T> = Result<T, DeserializeError>;
+
+/// An entry in a `ConstantPool<'a>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpInfo<'a> {
+    /// The bytes of a `CONSTANT_Utf8_info` entry.
+    Utf8(&'a [u8]),
+    /// Any other entry, which does not refer to the input.
+    Other(types::CpInfo),
+}
+
+impl CpInfo<'_> {
+    /// The name of the kind of entry, e.g. `Class`.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            CpInfo::Utf8(_) => "Utf8",
+            CpInfo::Other(cp_info) => cp_info.kind_name(),
+        }
+    }
+}
+
+/// The constant pool of a `ClassFile<'a>`, with entries stored at their constant pool index like
+/// in `model::constant_pool::ConstantPool`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConstantPool<'a> {
+    entries: Vec<Option<CpInfo<'a>>>,
+}
+
+impl<'a> ConstantPool<'a> {
+    /// The entries of the pool, indexed by their constant pool index.
+    pub fn entries(&self) -> &[Option<CpInfo<'a>>] {
+        &self.entries
+    }
+
+    /// The `constant_pool_count` of the class file, i.e., one more than the largest index.
+    pub fn count(&self) -> u16 {
+        self.entries.len().max(1) as u16
+    }
+
+    /// Get the entry at `index`.
+    pub fn entry(&self, index: u16) -> ConstantPoolResult<&CpInfo<'a>> {
+        match self.entries.get(index as usize) {
+            Some(Some(cp_info)) => Ok(cp_info),
+            _ => Err(ConstantPoolError::InvalidIndex(index)),
+        }
+    }
+
+    fn unexpected<T>(
+        &self,
+        index: u16,
+        expected: &'static str,
+        found: &CpInfo,
+    ) -> ConstantPoolResult<T> {
+        Err(ConstantPoolError::UnexpectedEntry {
+            index,
+            expected,
+            found: found.kind_name(),
+        })
+    }
+
+    /// Get the raw bytes of the `CONSTANT_Utf8_info` entry at `index`.
+    pub fn get_utf8_bytes(&self, index: u16) -> ConstantPoolResult<&'a [u8]> {
+        match self.entry(index)? {
+            CpInfo::Utf8(bytes) => Ok(bytes),
+            cp_info => self.unexpected(index, "Utf8", cp_info),
+        }
+    }
+
+    /// Get the string stored in the `CONSTANT_Utf8_info` entry at `index`, which is borrowed
+    /// from the input unless it contains NUL or supplementary characters.
+    pub fn get_utf8(&self, index: u16) -> ConstantPoolResult<Cow<'a, str>> {
+        let bytes = self.get_utf8_bytes(index)?;
+        decode_modified_utf8(bytes).map_err(|_| ConstantPoolError::InvalidUtf8(index))
+    }
+
+    /// Get the binary name (e.g., `java/lang/Object`) of the `CONSTANT_Class_info` entry at
+    /// `index`.
+    pub fn get_class_name(&self, index: u16) -> ConstantPoolResult<Cow<'a, str>> {
+        match self.entry(index)? {
+            CpInfo::Other(types::CpInfo::ConstantClassInfo { name_index, .. }) => {
+                self.get_utf8(*name_index)
+            }
+            cp_info => self.unexpected(index, "Class", cp_info),
+        }
+    }
+
+    /// Copy the pool into a `model::constant_pool::ConstantPool`.
+    pub fn to_owned_pool(&self) -> constant_pool::ConstantPool {
+        self.entries
+            .iter()
+            .map(|entry| {
+                entry.as_ref().map(|cp_info| match cp_info {
+                    CpInfo::Utf8(bytes) => types::CpInfo::ConstantUtf8Info {
+                        tag: CONSTANT_UTF8,
+                        length: bytes.len() as u16,
+                        bytes: bytes.to_vec(),
+                    },
+                    CpInfo::Other(cp_info) => cp_info.clone(),
+                })
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// An attribute whose info has not been decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeInfo<'a> {
+    pub attribute_name_index: u16,
+    pub info: &'a [u8],
+    /// The offset of `info` in the class file.
+    pub offset: usize,
+}
+
+impl<'a> AttributeInfo<'a> {
+    /// The name of the attribute, e.g. `Code`.
+    pub fn name(&self, constant_pool: &ConstantPool<'a>) -> ConstantPoolResult<Cow<'a, str>> {
+        constant_pool.get_utf8(self.attribute_name_index)
+    }
+}
+
+/// A decoded `Code` attribute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Code<'a> {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: &'a [u8],
+    pub exception_table: Vec<ExceptionHandler>,
+    pub attributes: Vec<AttributeInfo<'a>>,
+}
+
+impl<'a> Code<'a> {
+    /// Decode the `Code` attribute `attribute`.
+    pub fn parse(attribute: &AttributeInfo<'a>) -> DeserializeResult<Self> {
+        let mut reader = SliceReader::at_position(attribute.info, attribute.offset);
+        let max_stack = reader.read_unsigned_short()?;
+        let max_locals = reader.read_unsigned_short()?;
+
+        let code_length_offset = reader.position();
+        let code_length = reader.read_unsigned_int()?;
+        if code_length == 0 || code_length > u16::MAX as u32 {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::InvalidCodeLength(code_length),
+                code_length_offset,
+            ));
+        }
+        let code = reader.read_bytes(code_length as usize)?;
+
+        let exception_table_length = reader.read_unsigned_short()?;
+        let mut exception_table = Vec::with_capacity(exception_table_length as usize);
+        for _ in 0..exception_table_length {
+            let start_pc = reader.read_unsigned_short()?;
+            let end_pc = reader.read_unsigned_short()?;
+            let handler_pc = reader.read_unsigned_short()?;
+            let catch_type = reader.read_unsigned_short()?;
+            exception_table.push(ExceptionHandler::new(
+                start_pc, end_pc, handler_pc, catch_type,
+            ));
+        }
+
+        let attributes = read_attributes(&mut reader)?;
+        Ok(Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInfo<'a> {
+    pub access_flags: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<AttributeInfo<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodInfo<'a> {
+    pub access_flags: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<AttributeInfo<'a>>,
+}
+
+impl<'a> MethodInfo<'a> {
+    /// Decode the `Code` attribute of the method, or return `None` if it has none.
+    pub fn code(&self, constant_pool: &ConstantPool<'a>) -> DeserializeResult<Option<Code<'a>>> {
+        self.attributes
+            .iter()
+            .find(|attribute| {
+                constant_pool.get_utf8_bytes(attribute.attribute_name_index)
+                    == Ok(predefined_attributes::CODE.as_bytes())
+            })
+            .map(Code::parse)
+            .transpose()
+    }
+}
+
+/// A class file whose strings and attributes are borrowed from the bytes it was read from.
+///
+/// The counts of the class file are given by the lengths of the vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassFile<'a> {
+    pub minor_version: u16,
+    pub major_version: u16,
+    pub constant_pool: ConstantPool<'a>,
+    pub access_flags: u16,
+    pub this_class: u16,
+    pub super_class: u16,
+    pub interfaces: Vec<u16>,
+    pub fields: Vec<FieldInfo<'a>>,
+    pub methods: Vec<MethodInfo<'a>>,
+    pub attributes: Vec<AttributeInfo<'a>>,
+}
+
+/// Read the attributes, preceded by their count, that follow in `reader`.
+fn read_attributes<'a>(reader: &mut SliceReader<'a>) -> DeserializeResult<Vec<AttributeInfo<'a>>> {
+    let attributes_count = reader.read_unsigned_short()?;
+    let mut attributes = Vec::with_capacity(attributes_count as usize);
+    for _ in 0..attributes_count {
+        let attribute_name_index = reader.read_unsigned_short()?;
+        let attribute_length = reader.read_unsigned_int()?;
+        let offset = reader.position();
+        let info = reader.read_bytes(attribute_length as usize)?;
+        attributes.push(AttributeInfo {
+            attribute_name_index,
+            info,
+            offset,
+        });
+    }
+
+    Ok(attributes)
+}
+
+/// The SliceDeserializer reads a class file from a byte slice into a `ClassFile<'a>` borrowing
+/// from it.
+pub struct SliceDeserializer<'a> {
+    reader: SliceReader<'a>,
+    path: Vec<PathSegment>,
+    versions: SupportedVersions,
+}
+
+impl<'a> SliceDeserializer<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SliceDeserializer {
+            reader: SliceReader::new(bytes),
+            path: Vec::new(),
+            versions: SupportedVersions::default(),
+        }
+    }
+
+    /// Accept only class files whose version lies in `versions`. Defaults to
+    /// `SupportedVersions::default()`.
+    pub fn with_supported_versions(mut self, versions: SupportedVersions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Run `f` with `segment` appended to the structural path, so that any error raised while
+    /// deserializing that part of the class file records where it occurred.
+    fn scoped<T>(
+        &mut self,
+        segment: PathSegment,
+        f: impl FnOnce(&mut Self) -> DeserializeResult<T>,
+    ) -> DeserializeResult<T> {
+        self.path.push(segment);
+        let res = f(self).map_err(|err| err.with_path(&self.path));
+        self.path.pop();
+        res
+    }
+
+    /// Read the attributes of the class file or a member, checking that their names are
+    /// `CONSTANT_Utf8_info` entries.
+    fn deserialize_attributes(
+        &mut self,
+        constant_pool: &ConstantPool<'a>,
+    ) -> DeserializeResult<Vec<AttributeInfo<'a>>> {
+        let attributes = read_attributes(&mut self.reader)?;
+        for attribute in &attributes {
+            if constant_pool
+                .get_utf8_bytes(attribute.attribute_name_index)
+                .is_err()
+            {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidAttributeNameIndex(attribute.attribute_name_index),
+                    attribute.offset - 6,
+                ));
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    /// Deserialize the contents of the Constant Pool.
+    fn deserialize_constant_pool(
+        &mut self,
+        constant_pool_count: u16,
+    ) -> DeserializeResult<ConstantPool<'a>> {
+        let mut entries = vec![None; constant_pool_count as usize];
+        let mut cp_idx = 1usize; // the first cell is not used
+
+        while cp_idx < constant_pool_count as usize {
+            let (cp_info, width) =
+                self.scoped(PathSegment::ConstantPool(cp_idx as u16), |this| {
+                    let offset = this.reader.position();
+                    let tag = this.reader.read_unsigned_byte()?;
+                    if tag == CONSTANT_UTF8 {
+                        let length = this.reader.read_unsigned_short()?;
+                        return Ok((CpInfo::Utf8(this.reader.read_bytes(length as usize)?), 1));
+                    }
+
+                    let cp_info = read_constant(&mut this.reader, tag, offset)?;
+                    let width = constant_width(&cp_info, cp_idx, constant_pool_count, offset)?;
+                    Ok((CpInfo::Other(cp_info), width))
+                })?;
+            entries[cp_idx] = Some(cp_info);
+            cp_idx += width;
+        }
+
+        Ok(ConstantPool { entries })
+    }
+
+    /// Deserialize the class file, borrowing from the input.
+    pub fn deserialize(&mut self) -> DeserializeResult<ClassFile<'a>> {
+        // Headers
+        let magic = self.reader.read_unsigned_int()?;
+        if magic != MAGIC {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::InvalidMagic(magic),
+                0,
+            ));
+        }
+
+        let version_offset = self.reader.position();
+        let minor_version = self.reader.read_unsigned_short()?;
+        let major_version = self.reader.read_unsigned_short()?;
+        if !self.versions.accepts(major_version, minor_version) {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::UnsupportedVersion {
+                    major: major_version,
+                    minor: minor_version,
+                },
+                version_offset,
+            ));
+        }
+
+        // Constant Pool
+        let constant_pool_count_offset = self.reader.position();
+        let constant_pool_count = self.reader.read_unsigned_short()?;
+        if constant_pool_count == 0 {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::InvalidConstantPoolCount(constant_pool_count),
+                constant_pool_count_offset,
+            ));
+        }
+        let constant_pool = self.deserialize_constant_pool(constant_pool_count)?;
+
+        let access_flags = self.reader.read_unsigned_short()?;
+        let this_class = self.reader.read_unsigned_short()?;
+        let super_class = self.reader.read_unsigned_short()?;
+
+        let interfaces_count = self.reader.read_unsigned_short()?;
+        let mut interfaces = Vec::with_capacity(interfaces_count as usize);
+        for _ in 0..interfaces_count {
+            interfaces.push(self.reader.read_unsigned_short()?);
+        }
+
+        // Fields
+        let fields_count = self.reader.read_unsigned_short()?;
+        let mut fields = Vec::with_capacity(fields_count as usize);
+        for field_idx in 0..fields_count {
+            let field = self.scoped(PathSegment::Field(field_idx), |this| {
+                Ok(FieldInfo {
+                    access_flags: this.reader.read_unsigned_short()?,
+                    name_index: this.reader.read_unsigned_short()?,
+                    descriptor_index: this.reader.read_unsigned_short()?,
+                    attributes: this.deserialize_attributes(&constant_pool)?,
+                })
+            })?;
+            fields.push(field);
+        }
+
+        // methods
+        let methods_count = self.reader.read_unsigned_short()?;
+        let mut methods = Vec::with_capacity(methods_count as usize);
+        for method_idx in 0..methods_count {
+            let method = self.scoped(PathSegment::Method(method_idx), |this| {
+                Ok(MethodInfo {
+                    access_flags: this.reader.read_unsigned_short()?,
+                    name_index: this.reader.read_unsigned_short()?,
+                    descriptor_index: this.reader.read_unsigned_short()?,
+                    attributes: this.deserialize_attributes(&constant_pool)?,
+                })
+            })?;
+            methods.push(method);
+        }
+
+        // class attributes
+        let attributes = self.deserialize_attributes(&constant_pool)?;
+
+        Ok(ClassFile {
+            minor_version,
+            major_version,
+            constant_pool,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deserializer::Deserializer, fixtures::class_bytes,
+        model::attributes::AttributeInfo as OwnedAttributeInfo, rw::reader::Reader,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn test_deserialize() {
+        let bytes = class_bytes();
+        let classfile = SliceDeserializer::new(&bytes).deserialize().unwrap();
+        let owned = Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .deserialize()
+            .unwrap();
+
+        let constant_pool = &classfile.constant_pool;
+        assert_eq!(constant_pool.count(), owned.constant_pool_count);
+        for (idx, entry) in constant_pool.entries().iter().enumerate() {
+            let owned_entry = &owned.constant_pool.entries()[idx];
+            match (entry, owned_entry) {
+                (
+                    Some(CpInfo::Utf8(bytes)),
+                    Some(types::CpInfo::ConstantUtf8Info {
+                        bytes: owned_bytes, ..
+                    }),
+                ) => {
+                    assert_eq!(bytes, owned_bytes)
+                }
+                (Some(CpInfo::Other(cp_info)), Some(owned_cp_info)) => {
+                    assert_eq!(cp_info, owned_cp_info)
+                }
+                (None, None) => {}
+                entries => panic!("mismatched entries {:?}", entries),
+            }
+        }
+        assert_eq!(
+            constant_pool.get_class_name(classfile.this_class).unwrap(),
+            "Fixture"
+        );
+        // a string with NUL has to be decoded into a new string
+        let field_name = constant_pool
+            .get_utf8(classfile.fields[1].name_index)
+            .unwrap();
+        assert!(matches!(field_name, Cow::Owned(name) if name == "name\0"));
+
+        let method = &classfile.methods[0];
+        let code = method.code(constant_pool).unwrap().unwrap();
+        match &owned.methods[0].attributes[0] {
+            OwnedAttributeInfo::Code {
+                max_stack,
+                max_locals,
+                code: owned_code,
+                exception_table,
+                ..
+            } => {
+                assert_eq!((code.max_stack, code.max_locals), (*max_stack, *max_locals));
+                assert_eq!(code.code, &owned_code[..]);
+                assert_eq!(&code.exception_table, exception_table);
+            }
+            attribute => panic!("unexpected attribute {:?}", attribute),
+        }
+        assert!(classfile.fields[1].attributes.is_empty());
+
+        // the bytecode and the source file name are borrowed from the input
+        let input = bytes.as_ptr_range();
+        assert!(input.contains(&code.code.as_ptr()));
+        let source_file = &classfile.attributes[0];
+        assert_eq!(source_file.name(constant_pool).unwrap(), "SourceFile");
+        assert_eq!(source_file.info.len(), 2);
+        let sourcefile_index = u16::from_be_bytes([source_file.info[0], source_file.info[1]]);
+        let name = constant_pool.get_utf8(sourcefile_index).unwrap();
+        assert!(matches!(name, Cow::Borrowed(name) if input.contains(&name.as_ptr())));
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let bytes = class_bytes();
+        for len in [0, 9, bytes.len() - 1] {
+            let err = SliceDeserializer::new(&bytes[..len])
+                .deserialize()
+                .unwrap_err();
+            assert_eq!(err.kind(), &DeserializeErrorKind::UnexpectedEof);
+            assert_eq!(err.offset(), Some(len));
+        }
+
+        // a Code attribute claiming more bytecode than it contains
+        let classfile = SliceDeserializer::new(&bytes).deserialize().unwrap();
+        let mut code = classfile.methods[0].attributes[0].clone();
+        code.info = &code.info[..10];
+        let err = Code::parse(&code).unwrap_err();
+        assert_eq!(err.kind(), &DeserializeErrorKind::UnexpectedEof);
+        assert_eq!(err.offset(), Some(code.offset + 10));
+
+        // a Long entry in the last slot of the constant pool
+        let bytes = [
+            0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x02, 0x05, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x01,
+        ];
+        let err = SliceDeserializer::new(&bytes).deserialize().unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &DeserializeErrorKind::InvalidConstantPoolCount(2)
+        );
+        assert_eq!(err.offset(), Some(10));
+        assert_eq!(err.path(), &[PathSegment::ConstantPool(1)]);
+    }
+}
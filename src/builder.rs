@@ -0,0 +1,801 @@
+//! A high-level builder for class files.
+//!
+//! `ClassBuilder` adds the names, descriptors and constants of the class and its members to the
+//! constant pool, assembles the code of methods, and fills in the counts, attribute lengths and
+//! `max_stack` and `max_locals` items, producing a `ClassFile` that can be passed to
+//! `Serializer::serialize` as is.
+//!
+//! Attributes given with `attribute` are added as they are, so their `attribute_length` must be
+//! set by the caller.
+
+use crate::{
+    analysis::{
+        compute_frames, compute_max_locals, compute_max_stack, verifier::TYPE_CHECKING_VERSION,
+        AnalysisError, ClassHierarchy,
+    },
+    bytecode::{AssembleError, AssembledCode, CodeBuilder},
+    deserializer::MAGIC,
+    model::{
+        access_flags::{ACC_MODULE, ACC_PUBLIC, ACC_STATIC, ACC_SUPER},
+        attributes::{predefined_attributes::*, AttributeInfo},
+        constant_pool::{ConstantPoolBuilder, ConstantPoolError},
+        ClassFile, FieldInfo, MethodInfo,
+    },
+};
+use std::{error::Error, fmt};
+
+pub type BuildResult<T> = Result<T, BuildError>;
+
+/// Error type for `ClassBuilder::build`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// An entry could not be added to the constant pool.
+    ConstantPool(ConstantPoolError),
+    /// The code of the method, given by its name and descriptor, could not be assembled.
+    Assemble {
+        method: String,
+        source: AssembleError,
+    },
+    /// The `max_stack`, `max_locals` or stack map frames of the method, given by its name and
+    /// descriptor, could not be computed.
+    Analysis {
+        method: String,
+        source: AnalysisError,
+    },
+    /// The class has more than 65535 interfaces, fields, methods or attributes.
+    TooManyItems(&'static str),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::ConstantPool(err) => write!(f, "{}", err),
+            BuildError::Assemble { method, source } => {
+                write!(f, "{} (in method {})", source, method)
+            }
+            BuildError::Analysis { method, source } => {
+                write!(f, "{} (in method {})", source, method)
+            }
+            BuildError::TooManyItems(items) => write!(f, "too many {}", items),
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BuildError::ConstantPool(err) => Some(err),
+            BuildError::Assemble { source, .. } => Some(source),
+            BuildError::Analysis { source, .. } => Some(source),
+            BuildError::TooManyItems(_) => None,
+        }
+    }
+}
+
+impl From<ConstantPoolError> for BuildError {
+    fn from(err: ConstantPoolError) -> Self {
+        BuildError::ConstantPool(err)
+    }
+}
+
+/// The class file version used by `build_with_frames` unless another is set with
+/// `ClassBuilder::version` (Java 8).
+const DEFAULT_MAJOR_VERSION: u16 = 52;
+
+/// The class file version used by `build` unless another is set with `ClassBuilder::version`
+/// (Java 5), the last one whose methods do not need `StackMapTable`s.
+const DEFAULT_MAJOR_VERSION_WITHOUT_FRAMES: u16 = TYPE_CHECKING_VERSION - 1;
+
+/// The value of a `ConstantValue` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Integer(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+}
+
+/// Describes a field to be added with `ClassBuilder::field`.
+#[derive(Debug, Default)]
+pub struct FieldBuilder {
+    access_flags: u16,
+    name: String,
+    descriptor: String,
+    constant_value: Option<Constant>,
+    signature: Option<String>,
+    attributes: Vec<AttributeInfo>,
+}
+
+impl FieldBuilder {
+    pub fn new(access_flags: u16, name: &str, descriptor: &str) -> Self {
+        FieldBuilder {
+            access_flags,
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            ..FieldBuilder::default()
+        }
+    }
+
+    /// Initialize the (static) field to `value` with a `ConstantValue` attribute.
+    pub fn constant_value(mut self, value: Constant) -> Self {
+        self.constant_value = Some(value);
+        self
+    }
+
+    /// Add a `Signature` attribute with the generic type `signature`.
+    pub fn signature(mut self, signature: &str) -> Self {
+        self.signature = Some(signature.to_string());
+        self
+    }
+
+    /// Add `attribute` as it is.
+    pub fn attribute(mut self, attribute: AttributeInfo) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+}
+
+/// Describes a method to be added with `ClassBuilder::method`.
+#[derive(Debug, Default)]
+pub struct MethodBuilder {
+    access_flags: u16,
+    name: String,
+    descriptor: String,
+    code: Option<CodeBuilder>,
+    exceptions: Vec<String>,
+    signature: Option<String>,
+    attributes: Vec<AttributeInfo>,
+}
+
+impl MethodBuilder {
+    pub fn new(access_flags: u16, name: &str, descriptor: &str) -> Self {
+        MethodBuilder {
+            access_flags,
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            ..MethodBuilder::default()
+        }
+    }
+
+    /// Set the code of the method. Constant pool indices in its instructions refer to the pool
+    /// of the `ClassBuilder`, see `ClassBuilder::constant_pool`.
+    pub fn code(mut self, code: CodeBuilder) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Declare that the method throws the checked exception class `class`, in an `Exceptions`
+    /// attribute.
+    pub fn exception(mut self, class: &str) -> Self {
+        self.exceptions.push(class.to_string());
+        self
+    }
+
+    /// Add a `Signature` attribute with the generic signature `signature`.
+    pub fn signature(mut self, signature: &str) -> Self {
+        self.signature = Some(signature.to_string());
+        self
+    }
+
+    /// Add `attribute` as it is.
+    pub fn attribute(mut self, attribute: AttributeInfo) -> Self {
+        self.attributes.push(attribute);
+        self
+    }
+}
+
+/// The assembled code of a method, whose `Code` attribute is created once the constant pool is
+/// complete.
+#[derive(Debug)]
+struct PendingCode {
+    /// The index of the method in `ClassBuilder::methods`.
+    method: usize,
+    attribute_name_index: u16,
+    code: AssembledCode,
+}
+
+/// Builds a `ClassFile`, managing its constant pool, counts and lengths.
+///
+/// Errors (e.g. a full constant pool or code that cannot be assembled) are reported by `build`.
+#[derive(Debug)]
+pub struct ClassBuilder {
+    constant_pool: ConstantPoolBuilder,
+    minor_version: u16,
+    /// The version set with `version`, if any.
+    major_version: Option<u16>,
+    access_flags: u16,
+    this_class: u16,
+    is_object: bool,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<FieldInfo>,
+    methods: Vec<MethodInfo>,
+    attributes: Vec<AttributeInfo>,
+    code: Vec<PendingCode>,
+    /// The first error, which is returned by `build`.
+    error: Option<BuildError>,
+}
+
+impl ClassBuilder {
+    /// Start building the public class `name` (an internal name, e.g. `com/example/Main`),
+    /// extending `java/lang/Object`, in version 52.0 class files if built with frames and in
+    /// version 49.0 class files otherwise.
+    pub fn new(name: &str) -> Self {
+        let mut builder = ClassBuilder {
+            constant_pool: ConstantPoolBuilder::new(),
+            minor_version: 0,
+            major_version: None,
+            access_flags: ACC_PUBLIC | ACC_SUPER,
+            this_class: 0,
+            is_object: name == "java/lang/Object",
+            super_class: 0,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            code: Vec::new(),
+            error: None,
+        };
+        builder.this_class = builder.add(|cp| cp.class(name));
+        if !builder.is_object {
+            builder.super_class = builder.add(|cp| cp.class("java/lang/Object"));
+        }
+        builder
+    }
+
+    /// The constant pool of the class, e.g. to add the entries referenced by instructions.
+    pub fn constant_pool(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.constant_pool
+    }
+
+    pub fn version(&mut self, major_version: u16, minor_version: u16) -> &mut Self {
+        self.major_version = Some(major_version);
+        self.minor_version = minor_version;
+        self
+    }
+
+    pub fn access_flags(&mut self, access_flags: u16) -> &mut Self {
+        self.access_flags = access_flags;
+        self
+    }
+
+    /// Set the direct superclass. `java/lang/Object` and modules have none.
+    pub fn super_class(&mut self, name: &str) -> &mut Self {
+        self.super_class = self.add(|cp| cp.class(name));
+        self
+    }
+
+    /// Add `name` to the direct superinterfaces.
+    pub fn interface(&mut self, name: &str) -> &mut Self {
+        let index = self.add(|cp| cp.class(name));
+        self.interfaces.push(index);
+        self
+    }
+
+    /// Add a `SourceFile` attribute with the file name `name`, e.g. `Main.java`.
+    pub fn source_file(&mut self, name: &str) -> &mut Self {
+        let attribute_name_index = self.add(|cp| cp.utf8(SOURCE_FILE));
+        let sourcefile_index = self.add(|cp| cp.utf8(name));
+        self.attributes.push(AttributeInfo::SourceFile {
+            attribute_name_index,
+            attribute_length: 2,
+            sourcefile_index,
+        });
+        self
+    }
+
+    /// Add a `Signature` attribute with the generic class signature `signature`.
+    pub fn signature(&mut self, signature: &str) -> &mut Self {
+        let attribute = self.signature_attribute(signature);
+        self.attributes.push(attribute);
+        self
+    }
+
+    /// Add `attribute` to the attributes of the class as it is.
+    pub fn attribute(&mut self, attribute: AttributeInfo) -> &mut Self {
+        self.attributes.push(attribute);
+        self
+    }
+
+    pub fn field(&mut self, field: FieldBuilder) -> &mut Self {
+        let name_index = self.add(|cp| cp.utf8(&field.name));
+        let descriptor_index = self.add(|cp| cp.utf8(&field.descriptor));
+
+        let mut attributes = Vec::new();
+        if let Some(value) = &field.constant_value {
+            let attribute_name_index = self.add(|cp| cp.utf8(CONSTANT_VALUE));
+            let constantvalue_index = self.add(|cp| match value {
+                Constant::Integer(value) => cp.integer(*value),
+                Constant::Long(value) => cp.long(*value),
+                Constant::Float(value) => cp.float(*value),
+                Constant::Double(value) => cp.double(*value),
+                Constant::String(value) => cp.string(value),
+            });
+            attributes.push(AttributeInfo::ConstantValue {
+                attribute_name_index,
+                attribute_length: 2,
+                constantvalue_index,
+            });
+        }
+        if let Some(signature) = &field.signature {
+            attributes.push(self.signature_attribute(signature));
+        }
+        attributes.extend(field.attributes);
+
+        self.fields.push(FieldInfo {
+            access_flags: field.access_flags,
+            name_index,
+            descriptor_index,
+            attributes_count: 0,
+            attributes,
+        });
+        self
+    }
+
+    pub fn method(&mut self, method: MethodBuilder) -> &mut Self {
+        let name_index = self.add(|cp| cp.utf8(&method.name));
+        let descriptor_index = self.add(|cp| cp.utf8(&method.descriptor));
+
+        if let Some(code) = method.code {
+            match code.build() {
+                Ok(code) => {
+                    let attribute_name_index = self.add(|cp| cp.utf8(CODE));
+                    self.code.push(PendingCode {
+                        method: self.methods.len(),
+                        attribute_name_index,
+                        code,
+                    });
+                }
+                Err(source) => self.fail(BuildError::Assemble {
+                    method: format!("{}{}", method.name, method.descriptor),
+                    source,
+                }),
+            }
+        }
+
+        let mut attributes = Vec::new();
+        if !method.exceptions.is_empty() {
+            let attribute_name_index = self.add(|cp| cp.utf8(EXCEPTIONS));
+            let exception_index_table: Vec<u16> = method
+                .exceptions
+                .iter()
+                .map(|class| self.add(|cp| cp.class(class)))
+                .collect();
+            attributes.push(AttributeInfo::Exceptions {
+                attribute_name_index,
+                attribute_length: 2 + 2 * exception_index_table.len() as u32,
+                number_of_exceptions: exception_index_table.len() as u16,
+                exception_index_table,
+            });
+        }
+        if let Some(signature) = &method.signature {
+            attributes.push(self.signature_attribute(signature));
+        }
+        attributes.extend(method.attributes);
+
+        self.methods.push(MethodInfo {
+            access_flags: method.access_flags,
+            name_index,
+            descriptor_index,
+            attributes_count: 0,
+            attributes,
+        });
+        self
+    }
+
+    /// Build the class file, computing the `max_stack` and `max_locals` of each method's code.
+    ///
+    /// No `StackMapTable`s are added, so if a version 50 or later is set with `version`, the
+    /// methods that need frames fail type checking.
+    pub fn build(self) -> BuildResult<ClassFile> {
+        self.finish(None)
+    }
+
+    /// Build the class file like `build`, and also add a `StackMapTable` to the code of each
+    /// method that needs one (in version 50 and later class files), using `hierarchy` to merge
+    /// reference types.
+    pub fn build_with_frames(self, hierarchy: &dyn ClassHierarchy) -> BuildResult<ClassFile> {
+        self.finish(Some(hierarchy))
+    }
+
+    fn finish(mut self, hierarchy: Option<&dyn ClassHierarchy>) -> BuildResult<ClassFile> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+
+        let super_class = if self.is_object || self.access_flags & ACC_MODULE != 0 {
+            0
+        } else {
+            self.super_class
+        };
+        let major_version = self.major_version.unwrap_or(match hierarchy {
+            Some(_) => DEFAULT_MAJOR_VERSION,
+            None => DEFAULT_MAJOR_VERSION_WITHOUT_FRAMES,
+        });
+        let mut class_file = ClassFile {
+            magic: MAGIC,
+            minor_version: self.minor_version,
+            major_version,
+            constant_pool: self.constant_pool.clone().build(),
+            access_flags: self.access_flags,
+            this_class: self.this_class,
+            super_class,
+            interfaces: self.interfaces,
+            fields: self.fields,
+            methods: self.methods,
+            attributes: self.attributes,
+            ..ClassFile::default()
+        };
+
+        // the Code attributes go first, as javac puts them
+        for pending in self.code {
+            let method = &class_file.methods[pending.method];
+            let fail = |source| BuildError::Analysis {
+                method: method_name(&class_file, method),
+                source,
+            };
+            let constant_pool = &class_file.constant_pool;
+            let code = pending.code;
+            let descriptor = constant_pool.get_utf8(method.descriptor_index)?;
+            let is_static = method.access_flags & ACC_STATIC != 0;
+            let max_stack = compute_max_stack(&code.code, &code.exception_table, constant_pool)
+                .map_err(fail)?;
+            let max_locals =
+                compute_max_locals(&code.code, &descriptor, is_static).map_err(fail)?;
+
+            let attribute =
+                code.into_attribute(pending.attribute_name_index, max_stack, max_locals);
+            class_file.methods[pending.method]
+                .attributes
+                .insert(0, attribute);
+        }
+
+        if let Some(hierarchy) = hierarchy.filter(|_| major_version >= TYPE_CHECKING_VERSION) {
+            let mut stack_maps = Vec::new();
+            for (idx, method) in class_file.methods.iter().enumerate() {
+                let stack_map =
+                    compute_frames(&class_file, method, hierarchy).map_err(|source| {
+                        BuildError::Analysis {
+                            method: method_name(&class_file, method),
+                            source,
+                        }
+                    })?;
+                if let Some(stack_map) = stack_map.filter(|stack_map| !stack_map.frames.is_empty())
+                {
+                    let attribute = stack_map.to_attribute(&mut self.constant_pool)?;
+                    stack_maps.push((idx, stack_map, attribute));
+                }
+            }
+            class_file.constant_pool = self.constant_pool.build();
+
+            for (idx, stack_map, attribute) in stack_maps {
+                if let Some(AttributeInfo::Code {
+                    attribute_length,
+                    max_stack,
+                    code,
+                    exception_table_length,
+                    exception_table,
+                    code_attributes_count,
+                    code_attributes,
+                    ..
+                }) = class_file.methods[idx].attributes.first_mut()
+                {
+                    if !stack_map.unreachable.is_empty() {
+                        stack_map.replace_unreachable_code(code, exception_table);
+                        *max_stack = (*max_stack).max(1);
+                        *attribute_length -= 8 * *exception_table_length as u32;
+                        *exception_table_length = exception_table.len() as u16;
+                        *attribute_length += 8 * *exception_table_length as u32;
+                    }
+
+                    *attribute_length += 6 + attribute.attribute_length();
+                    *code_attributes_count += 1;
+                    code_attributes.push(attribute);
+                }
+            }
+        }
+
+        class_file.constant_pool_count = class_file.constant_pool.count();
+        class_file.interfaces_count = count(&class_file.interfaces, "interfaces")?;
+        class_file.fields_count = count(&class_file.fields, "fields")?;
+        class_file.methods_count = count(&class_file.methods, "methods")?;
+        class_file.attributes_count = count(&class_file.attributes, "attributes")?;
+        for field in &mut class_file.fields {
+            field.attributes_count = count(&field.attributes, "attributes")?;
+        }
+        for method in &mut class_file.methods {
+            method.attributes_count = count(&method.attributes, "attributes")?;
+        }
+
+        Ok(class_file)
+    }
+
+    /// Run `add` on the constant pool, recording the error if it fails.
+    fn add(
+        &mut self,
+        add: impl FnOnce(&mut ConstantPoolBuilder) -> Result<u16, ConstantPoolError>,
+    ) -> u16 {
+        add(&mut self.constant_pool).unwrap_or_else(|err| {
+            self.fail(err.into());
+            0
+        })
+    }
+
+    /// Record `err`, unless an earlier error has been recorded.
+    fn fail(&mut self, err: BuildError) {
+        self.error.get_or_insert(err);
+    }
+
+    fn signature_attribute(&mut self, signature: &str) -> AttributeInfo {
+        AttributeInfo::Signature {
+            attribute_name_index: self.add(|cp| cp.utf8(SIGNATURE)),
+            attribute_length: 2,
+            signature_index: self.add(|cp| cp.utf8(signature)),
+        }
+    }
+}
+
+/// Return the name and descriptor of `method`, e.g. `main([Ljava/lang/String;)V`.
+fn method_name(class_file: &ClassFile, method: &MethodInfo) -> String {
+    let constant_pool = &class_file.constant_pool;
+    let name = constant_pool
+        .get_utf8(method.name_index)
+        .unwrap_or_default();
+    let descriptor = constant_pool
+        .get_utf8(method.descriptor_index)
+        .unwrap_or_default();
+    format!("{}{}", name, descriptor)
+}
+
+/// Return the number of `items`, which must fit in a `u16`.
+fn count<T>(items: &[T], name: &'static str) -> BuildResult<u16> {
+    u16::try_from(items.len()).map_err(|_| BuildError::TooManyItems(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis::{verify_class, SimpleClassHierarchy},
+        bytecode::{opcodes::*, Instruction},
+        deserializer::Deserializer,
+        model::access_flags::*,
+        rw::{reader::Reader, writer::Writer},
+        serializer::Serializer,
+        validator::validate,
+    };
+    use std::io::Cursor;
+
+    /// Build `public class Counter implements Runnable`, with a constant, a constructor and a
+    /// static method containing a loop.
+    fn counter() -> ClassBuilder {
+        let mut builder = ClassBuilder::new("Counter");
+        let object_init = builder
+            .constant_pool()
+            .method_ref("java/lang/Object", "<init>", "()V")
+            .unwrap();
+
+        let mut init = CodeBuilder::new();
+        init.emit(Instruction::Aload0)
+            .emit(Instruction::Invokespecial(object_init))
+            .emit(Instruction::Return);
+
+        // static int count(int n) { int i = 0; while (i < n) { i++; } return i; }
+        let mut count = CodeBuilder::new();
+        let (head, exit) = (count.label(), count.label());
+        count
+            .emit(Instruction::Iconst0)
+            .emit(Instruction::Istore1)
+            .place(head)
+            .emit(Instruction::Iload1)
+            .emit(Instruction::Iload0)
+            .branch(IF_ICMPGE, exit)
+            .emit(Instruction::Iinc {
+                index: 1,
+                constant: 1,
+            })
+            .branch(GOTO, head)
+            .place(exit)
+            .emit(Instruction::Iload1)
+            .emit(Instruction::Ireturn);
+
+        builder
+            .interface("java/lang/Runnable")
+            .source_file("Counter.java")
+            .field(
+                FieldBuilder::new(ACC_PUBLIC | ACC_STATIC | ACC_FINAL, "LIMIT", "J")
+                    .constant_value(Constant::Long(1 << 40)),
+            )
+            .field(
+                FieldBuilder::new(ACC_PRIVATE, "names", "Ljava/util/List;")
+                    .signature("Ljava/util/List<Ljava/lang/String;>;"),
+            )
+            .method(MethodBuilder::new(ACC_PUBLIC, "<init>", "()V").code(init))
+            .method(MethodBuilder::new(ACC_STATIC, "count", "(I)I").code(count))
+            .method(
+                MethodBuilder::new(ACC_PUBLIC | ACC_ABSTRACT, "run", "()V")
+                    .exception("java/lang/Exception"),
+            );
+        builder.access_flags(ACC_PUBLIC | ACC_SUPER | ACC_ABSTRACT);
+        builder
+    }
+
+    #[test]
+    fn test_build() {
+        let class_file = counter().build().unwrap();
+        let constant_pool = &class_file.constant_pool;
+
+        assert_eq!(class_file.magic, 0xCAFEBABE);
+        assert_eq!(class_file.major_version, 49);
+        assert_eq!(class_file.constant_pool_count, constant_pool.count());
+        assert_eq!(
+            constant_pool.get_class_name(class_file.this_class).unwrap(),
+            "Counter"
+        );
+        assert_eq!(
+            constant_pool
+                .get_class_name(class_file.super_class)
+                .unwrap(),
+            "java/lang/Object"
+        );
+        assert_eq!(
+            (class_file.interfaces_count, class_file.fields_count),
+            (1, 2)
+        );
+        assert_eq!(
+            (class_file.methods_count, class_file.attributes_count),
+            (3, 1)
+        );
+        assert_eq!(class_file.fields[0].attributes_count, 1);
+        match &class_file.methods[2].attributes[..] {
+            [AttributeInfo::Exceptions {
+                attribute_length: 4,
+                exception_index_table,
+                ..
+            }] => assert_eq!(
+                constant_pool
+                    .get_class_name(exception_index_table[0])
+                    .unwrap(),
+                "java/lang/Exception"
+            ),
+            attributes => panic!("unexpected attributes {:?}", attributes),
+        }
+
+        match &class_file.methods[1].attributes[..] {
+            [AttributeInfo::Code {
+                attribute_length,
+                max_stack,
+                max_locals,
+                code_length,
+                code_attributes,
+                ..
+            }] => {
+                assert_eq!((*max_stack, *max_locals), (2, 2));
+                assert_eq!(*attribute_length, 12 + code_length);
+                assert!(code_attributes.is_empty());
+            }
+            attributes => panic!("unexpected attributes {:?}", attributes),
+        }
+        assert_eq!(validate(&class_file), Ok(()));
+
+        // without frames, the loop only verifies in class files older than version 50
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy.add_class("Counter", "java/lang/Object");
+        assert_eq!(verify_class(&class_file, &hierarchy), Ok(()));
+        let mut builder = counter();
+        builder.version(50, 0);
+        let class_file = builder.build().unwrap();
+        assert_eq!(class_file.major_version, 50);
+        assert!(verify_class(&class_file, &hierarchy).is_err());
+    }
+
+    #[test]
+    fn test_build_with_frames() {
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy.add_class("Counter", "java/lang/Object");
+        let class_file = counter().build_with_frames(&hierarchy).unwrap();
+        assert_eq!(class_file.major_version, 52);
+        assert_eq!(verify_class(&class_file, &hierarchy), Ok(()));
+
+        // serialize and read back the class file
+        let mut bytes = Vec::new();
+        Serializer::new(Writer::new(&mut bytes))
+            .serialize(&class_file)
+            .unwrap();
+        let read = Deserializer::new(Reader::new(Cursor::new(bytes)))
+            .deserialize()
+            .unwrap();
+        assert_eq!(read.constant_pool, class_file.constant_pool);
+        match &read.methods[1].attributes[0] {
+            AttributeInfo::Code {
+                attribute_length,
+                code_length,
+                code_attributes,
+                ..
+            } => {
+                let stack_map_length = code_attributes[0].attribute_length();
+                assert_eq!(*attribute_length, 12 + code_length + 6 + stack_map_length);
+            }
+            attribute => panic!("unexpected attribute {:?}", attribute),
+        }
+        assert_eq!(verify_class(&read, &hierarchy), Ok(()));
+    }
+
+    #[test]
+    fn test_build_unreachable_code() {
+        // static void f() { return; }, followed by code that does not type check, all of it
+        // covered by an exception handler
+        let mut code = CodeBuilder::new();
+        let (start, end) = (code.label(), code.label());
+        code.place(start)
+            .emit(Instruction::Return)
+            .emit(Instruction::Pop)
+            .emit(Instruction::Return)
+            .place(end)
+            .emit(Instruction::Athrow)
+            .try_catch(start, end, end, 0);
+
+        let mut builder = counter();
+        builder.method(MethodBuilder::new(ACC_STATIC, "f", "()V").code(code));
+        let mut hierarchy = SimpleClassHierarchy::new();
+        hierarchy.add_class("Counter", "java/lang/Object");
+        let class_file = builder.build_with_frames(&hierarchy).unwrap();
+        assert_eq!(verify_class(&class_file, &hierarchy), Ok(()));
+
+        match &class_file.methods.last().unwrap().attributes[0] {
+            AttributeInfo::Code {
+                attribute_length,
+                max_stack,
+                code,
+                exception_table,
+                code_attributes,
+                ..
+            } => {
+                assert_eq!(*max_stack, 1);
+                assert_eq!(code, &[RETURN, NOP, ATHROW, ATHROW]);
+                assert_eq!(exception_table.len(), 1);
+                assert_eq!(exception_table[0].end_pc, 1);
+                let stack_map_length = code_attributes[0].attribute_length();
+                assert_eq!(*attribute_length, 12 + 4 + 8 + 6 + stack_map_length);
+            }
+            attribute => panic!("unexpected attribute {:?}", attribute),
+        }
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let mut code = CodeBuilder::new();
+        let label = code.label();
+        code.branch(GOTO, label);
+
+        let mut builder = counter();
+        builder.method(MethodBuilder::new(ACC_STATIC, "f", "()V").code(code));
+        assert!(matches!(
+            builder.build(),
+            Err(BuildError::Assemble { method, .. }) if method == "f()V"
+        ));
+
+        // the code pops a value from an empty stack
+        let mut code = CodeBuilder::new();
+        code.emit(Instruction::Pop).emit(Instruction::Return);
+        let mut builder = counter();
+        builder.method(MethodBuilder::new(ACC_STATIC, "g", "()V").code(code));
+        assert_eq!(
+            builder.build().unwrap_err(),
+            BuildError::Analysis {
+                method: "g()V".to_string(),
+                source: AnalysisError::StackUnderflow { offset: 0 },
+            }
+        );
+
+        let long_name = "a".repeat(70000);
+        let mut builder = counter();
+        builder.interface(&long_name);
+        assert_eq!(
+            builder.build().unwrap_err(),
+            BuildError::ConstantPool(ConstantPoolError::StringTooLong(70000))
+        );
+    }
+}
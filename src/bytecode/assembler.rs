@@ -0,0 +1,679 @@
+//! A label-based assembler for the `code` and exception table of a `Code` attribute.
+//!
+//! Branch targets are given as `Label`s, which are resolved to relative offsets when the code is
+//! built. Branches whose target is out of the range of a 16-bit offset are widened: `goto` and
+//! `jsr` become `goto_w` and `jsr_w`, and conditional branches are inverted to jump over a
+//! `goto_w` to the original target.
+
+use super::{
+    instruction::{switch_padding, Instruction},
+    opcodes::*,
+};
+use crate::model::attributes::{AttributeInfo, ExceptionHandler};
+use std::{error::Error, fmt};
+
+pub type AssembleResult<T> = Result<T, AssembleError>;
+
+/// Error type for code that cannot be assembled by a `CodeBuilder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AssembleError {
+    /// A label is used but was never placed (or belongs to another builder).
+    UnplacedLabel(Label),
+    /// A label was placed more than once.
+    LabelPlacedTwice(Label),
+    /// The opcode passed to `CodeBuilder::branch` is not a branch instruction.
+    NotABranch(u8),
+    /// A `tableswitch` has no targets, or its keys overflow.
+    InvalidTableswitch,
+    /// A `lookupswitch` has more than one target for the same key.
+    DuplicateSwitchKey(i32),
+    /// The end of a try range does not come after its start.
+    EmptyTryRange { start: Label, end: Label },
+    /// There are no instructions.
+    EmptyCode,
+    /// The code is longer than the maximum of 65535 bytes.
+    CodeTooLong(usize),
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssembleError::UnplacedLabel(label) => write!(f, "label {} is never placed", label),
+            AssembleError::LabelPlacedTwice(label) => {
+                write!(f, "label {} is placed more than once", label)
+            }
+            AssembleError::NotABranch(opcode) => {
+                write!(f, "opcode {:#04x} is not a branch instruction", opcode)
+            }
+            AssembleError::InvalidTableswitch => write!(f, "invalid tableswitch"),
+            AssembleError::DuplicateSwitchKey(key) => {
+                write!(f, "duplicate lookupswitch key {}", key)
+            }
+            AssembleError::EmptyTryRange { start, end } => {
+                write!(f, "empty try range from {} to {}", start, end)
+            }
+            AssembleError::EmptyCode => write!(f, "code is empty"),
+            AssembleError::CodeTooLong(len) => {
+                write!(f, "code length {} exceeds the maximum of 65535 bytes", len)
+            }
+        }
+    }
+}
+
+impl Error for AssembleError {}
+
+/// A symbolic position in the code, created by `CodeBuilder::label` and placed by
+/// `CodeBuilder::place`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Label(usize);
+
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+enum Item {
+    Instruction(Instruction),
+    Branch {
+        opcode: u8,
+        target: Label,
+        wide: bool,
+    },
+    Tableswitch {
+        low: i32,
+        default: Label,
+        targets: Vec<Label>,
+    },
+    Lookupswitch {
+        default: Label,
+        pairs: Vec<(i32, Label)>,
+    },
+    Label(Label),
+}
+
+impl Item {
+    fn size(&self, offset: usize) -> usize {
+        match self {
+            Item::Instruction(instruction) => instruction.size(offset),
+            Item::Branch {
+                opcode,
+                wide: false,
+                ..
+            } => match *opcode {
+                GOTO_W | JSR_W => 5,
+                _ => 3,
+            },
+            Item::Branch { opcode, .. } => match *opcode {
+                GOTO | JSR | GOTO_W | JSR_W => 5,
+                // inverted conditional branch over a goto_w
+                _ => 8,
+            },
+            Item::Tableswitch { targets, .. } => {
+                1 + switch_padding(offset) + 12 + 4 * targets.len()
+            }
+            Item::Lookupswitch { pairs, .. } => 1 + switch_padding(offset) + 8 + 8 * pairs.len(),
+            Item::Label(_) => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TryCatch {
+    start: Label,
+    end: Label,
+    handler: Label,
+    catch_type_index: u16,
+}
+
+/// Builds the `code` and exception table of a `Code` attribute from instructions and labels.
+#[derive(Debug, Default)]
+pub struct CodeBuilder {
+    items: Vec<Item>,
+    labels: usize,
+    try_catches: Vec<TryCatch>,
+}
+
+impl CodeBuilder {
+    pub fn new() -> Self {
+        CodeBuilder::default()
+    }
+
+    /// Create a new label. It must be placed with `place` before the code is built.
+    pub fn label(&mut self) -> Label {
+        self.labels += 1;
+        Label(self.labels - 1)
+    }
+
+    /// Place `label` at the current position, i.e., before the next instruction.
+    pub fn place(&mut self, label: Label) -> &mut Self {
+        self.items.push(Item::Label(label));
+        self
+    }
+
+    /// Append an instruction. Branch instructions added this way keep their literal offsets.
+    pub fn emit(&mut self, instruction: Instruction) -> &mut Self {
+        self.items.push(Item::Instruction(instruction));
+        self
+    }
+
+    /// Append a branch instruction (`goto`, `jsr`, `if*`, `goto_w`, or `jsr_w`) jumping to
+    /// `target`.
+    pub fn branch(&mut self, opcode: u8, target: Label) -> &mut Self {
+        self.items.push(Item::Branch {
+            opcode,
+            target,
+            wide: false,
+        });
+        self
+    }
+
+    /// Append a `tableswitch` jumping to `targets[key - low]` for keys in
+    /// `low..low + targets.len()`, and to `default` otherwise.
+    pub fn tableswitch(&mut self, low: i32, default: Label, targets: Vec<Label>) -> &mut Self {
+        self.items.push(Item::Tableswitch {
+            low,
+            default,
+            targets,
+        });
+        self
+    }
+
+    /// Append a `lookupswitch` jumping to the label paired with the key, or to `default` if
+    /// there is none. The pairs need not be sorted.
+    pub fn lookupswitch(&mut self, default: Label, mut pairs: Vec<(i32, Label)>) -> &mut Self {
+        pairs.sort_by_key(|&(key, _)| key);
+        self.items.push(Item::Lookupswitch { default, pairs });
+        self
+    }
+
+    /// Add an exception handler at `handler` for the code between `start` (inclusive) and `end`
+    /// (exclusive), catching the class at constant pool index `catch_type_index`, or all
+    /// exceptions if it is 0. Handlers are searched in the order they are added.
+    pub fn try_catch(
+        &mut self,
+        start: Label,
+        end: Label,
+        handler: Label,
+        catch_type_index: u16,
+    ) -> &mut Self {
+        self.try_catches.push(TryCatch {
+            start,
+            end,
+            handler,
+            catch_type_index,
+        });
+        self
+    }
+
+    /// Compute the offset of every item, and of every label.
+    fn layout(&self) -> AssembleResult<(Vec<usize>, Vec<usize>)> {
+        let mut item_offsets = Vec::with_capacity(self.items.len());
+        let mut label_offsets = vec![None; self.labels];
+
+        let mut offset = 0;
+        for item in &self.items {
+            item_offsets.push(offset);
+            if let Item::Label(label) = item {
+                match label_offsets.get_mut(label.0) {
+                    Some(Some(_)) => return Err(AssembleError::LabelPlacedTwice(*label)),
+                    Some(label_offset) => *label_offset = Some(offset),
+                    None => return Err(AssembleError::UnplacedLabel(*label)),
+                }
+            }
+            offset += item.size(offset);
+        }
+
+        let label_offsets = label_offsets
+            .into_iter()
+            .enumerate()
+            .map(|(idx, offset)| offset.ok_or(AssembleError::UnplacedLabel(Label(idx))))
+            .collect::<AssembleResult<_>>()?;
+
+        Ok((item_offsets, label_offsets))
+    }
+
+    /// Fail if `label` was not created by this builder.
+    fn check_label(&self, label: Label) -> AssembleResult<()> {
+        if label.0 < self.labels {
+            Ok(())
+        } else {
+            Err(AssembleError::UnplacedLabel(label))
+        }
+    }
+
+    /// Resolve all labels and assemble the code.
+    pub fn build(mut self) -> AssembleResult<AssembledCode> {
+        for try_catch in &self.try_catches {
+            self.check_label(try_catch.start)?;
+            self.check_label(try_catch.end)?;
+            self.check_label(try_catch.handler)?;
+        }
+
+        for item in &self.items {
+            match item {
+                Item::Branch { target, .. } => self.check_label(*target)?,
+                Item::Tableswitch {
+                    default, targets, ..
+                } => {
+                    for label in targets.iter().chain([default]) {
+                        self.check_label(*label)?;
+                    }
+                }
+                Item::Lookupswitch { default, pairs } => {
+                    for label in pairs.iter().map(|(_, label)| label).chain([default]) {
+                        self.check_label(*label)?;
+                    }
+                }
+                _ => {}
+            }
+
+            match item {
+                Item::Branch { opcode, .. } if !is_branch(*opcode) => {
+                    return Err(AssembleError::NotABranch(*opcode))
+                }
+                Item::Tableswitch { low, targets, .. }
+                    if targets.is_empty()
+                        || (*low as i64 + targets.len() as i64 - 1) > i32::MAX as i64 =>
+                {
+                    return Err(AssembleError::InvalidTableswitch)
+                }
+                Item::Lookupswitch { pairs, .. } => {
+                    if let Some(pair) = pairs.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                        return Err(AssembleError::DuplicateSwitchKey(pair[0].0));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Widening a branch can only grow the code and push other targets out of range, so
+        // repeat until every remaining short branch fits.
+        let (item_offsets, label_offsets) = loop {
+            let (item_offsets, label_offsets) = self.layout()?;
+
+            let mut widened = false;
+            for (item, &offset) in self.items.iter_mut().zip(&item_offsets) {
+                if let Item::Branch {
+                    target,
+                    wide: wide @ false,
+                    ..
+                } = item
+                {
+                    let delta = label_offsets[target.0] as i64 - offset as i64;
+                    if i16::try_from(delta).is_err() {
+                        *wide = true;
+                        widened = true;
+                    }
+                }
+            }
+
+            if !widened {
+                break (item_offsets, label_offsets);
+            }
+        };
+
+        let code_length = item_offsets
+            .last()
+            .zip(self.items.last())
+            .map_or(0, |(&offset, item)| offset + item.size(offset));
+        if code_length == 0 {
+            return Err(AssembleError::EmptyCode);
+        }
+        if code_length > u16::MAX as usize {
+            return Err(AssembleError::CodeTooLong(code_length));
+        }
+
+        let relative =
+            |target: &Label, offset: usize| label_offsets[target.0] as i32 - offset as i32;
+
+        let mut code = Vec::with_capacity(code_length);
+        for (item, &offset) in self.items.iter().zip(&item_offsets) {
+            match item {
+                Item::Instruction(instruction) => instruction.encode(&mut code),
+                Item::Branch {
+                    opcode,
+                    target,
+                    wide: false,
+                } => {
+                    code.push(*opcode);
+                    match *opcode {
+                        GOTO_W | JSR_W => {
+                            code.extend_from_slice(&relative(target, offset).to_be_bytes())
+                        }
+                        _ => {
+                            code.extend_from_slice(&(relative(target, offset) as i16).to_be_bytes())
+                        }
+                    }
+                }
+                Item::Branch { opcode, target, .. } => match *opcode {
+                    GOTO | GOTO_W => {
+                        code.push(GOTO_W);
+                        code.extend_from_slice(&relative(target, offset).to_be_bytes());
+                    }
+                    JSR | JSR_W => {
+                        code.push(JSR_W);
+                        code.extend_from_slice(&relative(target, offset).to_be_bytes());
+                    }
+                    _ => {
+                        code.push(inverse_condition(*opcode));
+                        code.extend_from_slice(&8i16.to_be_bytes());
+                        code.push(GOTO_W);
+                        code.extend_from_slice(&relative(target, offset + 3).to_be_bytes());
+                    }
+                },
+                Item::Tableswitch {
+                    low,
+                    default,
+                    targets,
+                } => Instruction::Tableswitch {
+                    default: relative(default, offset),
+                    low: *low,
+                    high: low + targets.len() as i32 - 1,
+                    offsets: targets
+                        .iter()
+                        .map(|target| relative(target, offset))
+                        .collect(),
+                }
+                .encode(&mut code),
+                Item::Lookupswitch { default, pairs } => Instruction::Lookupswitch {
+                    default: relative(default, offset),
+                    pairs: pairs
+                        .iter()
+                        .map(|(key, target)| (*key, relative(target, offset)))
+                        .collect(),
+                }
+                .encode(&mut code),
+                Item::Label(_) => {}
+            }
+        }
+
+        let mut exception_table = Vec::with_capacity(self.try_catches.len());
+        for try_catch in &self.try_catches {
+            let start_pc = label_offsets[try_catch.start.0];
+            let end_pc = label_offsets[try_catch.end.0];
+            if start_pc >= end_pc {
+                return Err(AssembleError::EmptyTryRange {
+                    start: try_catch.start,
+                    end: try_catch.end,
+                });
+            }
+
+            exception_table.push(ExceptionHandler::new(
+                start_pc as u16,
+                end_pc as u16,
+                label_offsets[try_catch.handler.0] as u16,
+                try_catch.catch_type_index,
+            ));
+        }
+
+        Ok(AssembledCode {
+            code_length: code_length as u32,
+            code,
+            exception_table_length: exception_table.len() as u16,
+            exception_table,
+            label_offsets,
+        })
+    }
+}
+
+/// The output of `CodeBuilder::build`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembledCode {
+    pub code_length: u32,
+    pub code: Vec<u8>,
+    pub exception_table_length: u16,
+    pub exception_table: Vec<ExceptionHandler>,
+    label_offsets: Vec<usize>,
+}
+
+impl AssembledCode {
+    /// The offset in the code at which `label` was placed, e.g., to build a `LineNumberTable`.
+    pub fn offset(&self, label: Label) -> Option<u16> {
+        self.label_offsets.get(label.0).map(|&offset| offset as u16)
+    }
+
+    /// Wrap the code in a `Code` attribute without attributes of its own.
+    pub fn into_attribute(
+        self,
+        attribute_name_index: u16,
+        max_stack: u16,
+        max_locals: u16,
+    ) -> AttributeInfo {
+        AttributeInfo::Code {
+            attribute_name_index,
+            // max_stack, max_locals, code_length, code, exception_table_length,
+            // exception_table, attributes_count
+            attribute_length: 2
+                + 2
+                + 4
+                + self.code_length
+                + 2
+                + 8 * self.exception_table_length as u32
+                + 2,
+            max_stack,
+            max_locals,
+            code_length: self.code_length,
+            code: self.code,
+            exception_table_length: self.exception_table_length,
+            exception_table: self.exception_table,
+            code_attributes_count: 0,
+            code_attributes: vec![],
+        }
+    }
+}
+
+fn is_branch(opcode: u8) -> bool {
+    matches!(opcode, IFEQ..=JSR | IFNULL..=JSR_W)
+}
+
+/// The conditional branch opcode that branches exactly when `opcode` does not.
+fn inverse_condition(opcode: u8) -> u8 {
+    match opcode {
+        IFNULL => IFNONNULL,
+        IFNONNULL => IFNULL,
+        // the conditions from ifeq to if_acmpne come in complementary pairs
+        _ if (opcode - IFEQ).is_multiple_of(2) => opcode + 1,
+        _ => opcode - 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bytecode::instruction::decode;
+
+    #[test]
+    fn test_forward_and_backward_branches() -> AssembleResult<()> {
+        // while (i != 0) { i--; } return;
+        let mut builder = CodeBuilder::new();
+        let (head, exit) = (builder.label(), builder.label());
+        builder
+            .place(head)
+            .emit(Instruction::Iload0)
+            .branch(IFEQ, exit)
+            .emit(Instruction::Iinc {
+                index: 0,
+                constant: -1,
+            })
+            .branch(GOTO, head)
+            .place(exit)
+            .emit(Instruction::Return);
+
+        let code = builder.build()?;
+        assert_eq!(
+            code.code,
+            [0x1a, 0x99, 0x00, 0x09, 0x84, 0x00, 0xff, 0xa7, 0xff, 0xf9, 0xb1]
+        );
+        assert_eq!(code.code_length, 11);
+        assert_eq!(code.offset(exit), Some(10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_branch_widening() -> AssembleResult<()> {
+        let mut builder = CodeBuilder::new();
+        let (start, end) = (builder.label(), builder.label());
+        builder
+            .place(start)
+            .branch(IFNULL, end)
+            .branch(GOTO, end)
+            .branch(IFEQ, start);
+        for _ in 0..40000 {
+            builder.emit(Instruction::Nop);
+        }
+        builder
+            .branch(GOTO, start)
+            .place(end)
+            .emit(Instruction::Return);
+
+        let code = builder.build()?;
+        let instructions = decode(&code.code).unwrap();
+        assert_eq!(
+            &instructions[..4],
+            [
+                (0, Instruction::Ifnonnull(8)),
+                (3, Instruction::GotoW(40018)),
+                (8, Instruction::GotoW(40013)),
+                (13, Instruction::Ifeq(-13)),
+            ]
+        );
+        assert_eq!(
+            &instructions[40004..],
+            [
+                (40016, Instruction::GotoW(-40016)),
+                (40021, Instruction::Return)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_switches() -> AssembleResult<()> {
+        let mut builder = CodeBuilder::new();
+        let labels: Vec<_> = (0..4).map(|_| builder.label()).collect();
+        builder
+            .emit(Instruction::Iload0)
+            .tableswitch(1, labels[0], vec![labels[1], labels[2]])
+            .lookupswitch(labels[0], vec![(100, labels[3]), (-7, labels[2])]);
+        for &label in &labels {
+            builder.place(label).emit(Instruction::Return);
+        }
+
+        let code = builder.build()?;
+        assert_eq!(
+            decode(&code.code).unwrap(),
+            vec![
+                (0, Instruction::Iload0),
+                (
+                    1,
+                    Instruction::Tableswitch {
+                        default: 51,
+                        low: 1,
+                        high: 2,
+                        offsets: vec![52, 53],
+                    }
+                ),
+                (
+                    24,
+                    Instruction::Lookupswitch {
+                        default: 28,
+                        pairs: vec![(-7, 30), (100, 31)],
+                    }
+                ),
+                (52, Instruction::Return),
+                (53, Instruction::Return),
+                (54, Instruction::Return),
+                (55, Instruction::Return),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exception_table() -> AssembleResult<()> {
+        let mut builder = CodeBuilder::new();
+        let (start, end, handler) = (builder.label(), builder.label(), builder.label());
+        builder
+            .try_catch(start, end, handler, 7)
+            .try_catch(start, end, handler, 0)
+            .place(start)
+            .emit(Instruction::Invokestatic(2))
+            .place(end)
+            .emit(Instruction::Return)
+            .place(handler)
+            .emit(Instruction::Athrow);
+
+        let code = builder.build()?;
+        assert_eq!(code.exception_table_length, 2);
+        assert_eq!(code.exception_table[0], ExceptionHandler::new(0, 3, 4, 7));
+        assert_eq!(code.exception_table[0].catch_type, 7);
+        assert_eq!(code.exception_table[1].catch_type, 0);
+
+        match code.into_attribute(9, 1, 0) {
+            AttributeInfo::Code {
+                attribute_length,
+                code_length,
+                ..
+            } => {
+                assert_eq!(code_length, 5);
+                assert_eq!(attribute_length, 12 + 5 + 16);
+            }
+            attr => panic!("expected a Code attribute, found {:?}", attr),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let mut builder = CodeBuilder::new();
+        let label = builder.label();
+        builder.branch(GOTO, label);
+        assert_eq!(builder.build(), Err(AssembleError::UnplacedLabel(label)));
+
+        let mut builder = CodeBuilder::new();
+        let label = builder.label();
+        builder.place(label).place(label).emit(Instruction::Return);
+        assert_eq!(builder.build(), Err(AssembleError::LabelPlacedTwice(label)));
+
+        let mut builder = CodeBuilder::new();
+        let label = builder.label();
+        builder.place(label).branch(RETURN, label);
+        assert_eq!(builder.build(), Err(AssembleError::NotABranch(RETURN)));
+
+        let mut builder = CodeBuilder::new();
+        let label = builder.label();
+        builder
+            .place(label)
+            .lookupswitch(label, vec![(1, label), (1, label)]);
+        assert_eq!(builder.build(), Err(AssembleError::DuplicateSwitchKey(1)));
+
+        let mut builder = CodeBuilder::new();
+        let label = builder.label();
+        builder
+            .place(label)
+            .emit(Instruction::Return)
+            .try_catch(label, label, label, 0);
+        assert_eq!(
+            builder.build(),
+            Err(AssembleError::EmptyTryRange {
+                start: label,
+                end: label
+            })
+        );
+
+        assert_eq!(CodeBuilder::new().build(), Err(AssembleError::EmptyCode));
+
+        let mut builder = CodeBuilder::new();
+        for _ in 0..=u16::MAX {
+            builder.emit(Instruction::Nop);
+        }
+        assert_eq!(builder.build(), Err(AssembleError::CodeTooLong(65536)));
+    }
+}
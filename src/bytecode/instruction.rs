@@ -0,0 +1,1628 @@
+//! The typed representation of JVM instructions (JVMS 6.5), and the conversion between
+//! instructions and the raw `code` bytes of a `Code` attribute.
+
+use super::opcodes::*;
+use std::{error::Error, fmt};
+
+pub type BytecodeResult<T> = Result<T, BytecodeError>;
+
+/// Error type for malformed bytecode in the `code` of a `Code` attribute. Offsets are those of
+/// the offending instruction within the code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BytecodeError {
+    /// The opcode is not defined, or is reserved (e.g. `breakpoint`).
+    InvalidOpcode { offset: usize, opcode: u8 },
+    /// The opcode following a `wide` prefix cannot be widened.
+    InvalidWideOpcode { offset: usize, opcode: u8 },
+    /// The instruction extends past the end of the code.
+    Truncated { offset: usize },
+    /// An operand has an invalid value, e.g. a `tableswitch` whose `low` exceeds its `high`.
+    InvalidOperand { offset: usize },
+}
+
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BytecodeError::InvalidOpcode { offset, opcode } => {
+                write!(f, "invalid opcode {:#04x} at offset {}", opcode, offset)
+            }
+            BytecodeError::InvalidWideOpcode { offset, opcode } => write!(
+                f,
+                "opcode {:#04x} cannot be widened at offset {}",
+                opcode, offset
+            ),
+            BytecodeError::Truncated { offset } => {
+                write!(f, "truncated instruction at offset {}", offset)
+            }
+            BytecodeError::InvalidOperand { offset } => {
+                write!(f, "invalid operand for instruction at offset {}", offset)
+            }
+        }
+    }
+}
+
+impl BytecodeError {
+    /// The offset of the offending instruction within the code.
+    pub fn offset(&self) -> usize {
+        match self {
+            BytecodeError::InvalidOpcode { offset, .. }
+            | BytecodeError::InvalidWideOpcode { offset, .. }
+            | BytecodeError::Truncated { offset }
+            | BytecodeError::InvalidOperand { offset } => *offset,
+        }
+    }
+}
+
+impl Error for BytecodeError {}
+
+/// A load, store, `ret`, or `iinc` instruction that has been widened by a `wide` prefix, so that
+/// its local variable index (and, for `iinc`, its constant) takes up two bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WideInstruction {
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Ret(u16),
+    Iinc { index: u16, constant: i16 },
+}
+
+impl WideInstruction {
+    /// The opcode of the widened instruction.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            WideInstruction::Iload(_) => ILOAD,
+            WideInstruction::Lload(_) => LLOAD,
+            WideInstruction::Fload(_) => FLOAD,
+            WideInstruction::Dload(_) => DLOAD,
+            WideInstruction::Aload(_) => ALOAD,
+            WideInstruction::Istore(_) => ISTORE,
+            WideInstruction::Lstore(_) => LSTORE,
+            WideInstruction::Fstore(_) => FSTORE,
+            WideInstruction::Dstore(_) => DSTORE,
+            WideInstruction::Astore(_) => ASTORE,
+            WideInstruction::Ret(_) => RET,
+            WideInstruction::Iinc { .. } => IINC,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            WideInstruction::Iload(_) => "iload",
+            WideInstruction::Lload(_) => "lload",
+            WideInstruction::Fload(_) => "fload",
+            WideInstruction::Dload(_) => "dload",
+            WideInstruction::Aload(_) => "aload",
+            WideInstruction::Istore(_) => "istore",
+            WideInstruction::Lstore(_) => "lstore",
+            WideInstruction::Fstore(_) => "fstore",
+            WideInstruction::Dstore(_) => "dstore",
+            WideInstruction::Astore(_) => "astore",
+            WideInstruction::Ret(_) => "ret",
+            WideInstruction::Iinc { .. } => "iinc",
+        }
+    }
+
+    /// The local variable index operand.
+    pub fn index(&self) -> u16 {
+        match self {
+            WideInstruction::Iload(index)
+            | WideInstruction::Lload(index)
+            | WideInstruction::Fload(index)
+            | WideInstruction::Dload(index)
+            | WideInstruction::Aload(index)
+            | WideInstruction::Istore(index)
+            | WideInstruction::Lstore(index)
+            | WideInstruction::Fstore(index)
+            | WideInstruction::Dstore(index)
+            | WideInstruction::Astore(index)
+            | WideInstruction::Ret(index)
+            | WideInstruction::Iinc { index, .. } => *index,
+        }
+    }
+}
+
+/// A single JVM instruction with its operands.
+///
+/// Operands are kept exactly as encoded: constant pool and local variable indices are raw
+/// indices, and branch targets are offsets relative to the start of the branching instruction.
+/// The padding of `tableswitch` and `lookupswitch` is not stored, as it is determined by the
+/// offset of the instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    // Constants
+    Nop,
+    AconstNull,
+    IconstM1,
+    Iconst0,
+    Iconst1,
+    Iconst2,
+    Iconst3,
+    Iconst4,
+    Iconst5,
+    Lconst0,
+    Lconst1,
+    Fconst0,
+    Fconst1,
+    Fconst2,
+    Dconst0,
+    Dconst1,
+    Bipush(i8),
+    Sipush(i16),
+    Ldc(u8),
+    LdcW(u16),
+    Ldc2W(u16),
+
+    // Loads
+    Iload(u8),
+    Lload(u8),
+    Fload(u8),
+    Dload(u8),
+    Aload(u8),
+    Iload0,
+    Iload1,
+    Iload2,
+    Iload3,
+    Lload0,
+    Lload1,
+    Lload2,
+    Lload3,
+    Fload0,
+    Fload1,
+    Fload2,
+    Fload3,
+    Dload0,
+    Dload1,
+    Dload2,
+    Dload3,
+    Aload0,
+    Aload1,
+    Aload2,
+    Aload3,
+    Iaload,
+    Laload,
+    Faload,
+    Daload,
+    Aaload,
+    Baload,
+    Caload,
+    Saload,
+
+    // Stores
+    Istore(u8),
+    Lstore(u8),
+    Fstore(u8),
+    Dstore(u8),
+    Astore(u8),
+    Istore0,
+    Istore1,
+    Istore2,
+    Istore3,
+    Lstore0,
+    Lstore1,
+    Lstore2,
+    Lstore3,
+    Fstore0,
+    Fstore1,
+    Fstore2,
+    Fstore3,
+    Dstore0,
+    Dstore1,
+    Dstore2,
+    Dstore3,
+    Astore0,
+    Astore1,
+    Astore2,
+    Astore3,
+    Iastore,
+    Lastore,
+    Fastore,
+    Dastore,
+    Aastore,
+    Bastore,
+    Castore,
+    Sastore,
+
+    // Stack
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+
+    // Math
+    Iadd,
+    Ladd,
+    Fadd,
+    Dadd,
+    Isub,
+    Lsub,
+    Fsub,
+    Dsub,
+    Imul,
+    Lmul,
+    Fmul,
+    Dmul,
+    Idiv,
+    Ldiv,
+    Fdiv,
+    Ddiv,
+    Irem,
+    Lrem,
+    Frem,
+    Drem,
+    Ineg,
+    Lneg,
+    Fneg,
+    Dneg,
+    Ishl,
+    Lshl,
+    Ishr,
+    Lshr,
+    Iushr,
+    Lushr,
+    Iand,
+    Land,
+    Ior,
+    Lor,
+    Ixor,
+    Lxor,
+    Iinc {
+        index: u8,
+        constant: i8,
+    },
+
+    // Conversions
+    I2l,
+    I2f,
+    I2d,
+    L2i,
+    L2f,
+    L2d,
+    F2i,
+    F2l,
+    F2d,
+    D2i,
+    D2l,
+    D2f,
+    I2b,
+    I2c,
+    I2s,
+
+    // Comparisons
+    Lcmp,
+    Fcmpl,
+    Fcmpg,
+    Dcmpl,
+    Dcmpg,
+    Ifeq(i16),
+    Ifne(i16),
+    Iflt(i16),
+    Ifge(i16),
+    Ifgt(i16),
+    Ifle(i16),
+    IfIcmpeq(i16),
+    IfIcmpne(i16),
+    IfIcmplt(i16),
+    IfIcmpge(i16),
+    IfIcmpgt(i16),
+    IfIcmple(i16),
+    IfAcmpeq(i16),
+    IfAcmpne(i16),
+
+    // Control
+    Goto(i16),
+    Jsr(i16),
+    Ret(u8),
+    Tableswitch {
+        default: i32,
+        low: i32,
+        high: i32,
+        offsets: Vec<i32>,
+    },
+    Lookupswitch {
+        default: i32,
+        pairs: Vec<(i32, i32)>,
+    },
+    Ireturn,
+    Lreturn,
+    Freturn,
+    Dreturn,
+    Areturn,
+    Return,
+
+    // References
+    Getstatic(u16),
+    Putstatic(u16),
+    Getfield(u16),
+    Putfield(u16),
+    Invokevirtual(u16),
+    Invokespecial(u16),
+    Invokestatic(u16),
+    Invokeinterface {
+        index: u16,
+        count: u8,
+    },
+    Invokedynamic(u16),
+    New(u16),
+    Newarray(u8),
+    Anewarray(u16),
+    Arraylength,
+    Athrow,
+    Checkcast(u16),
+    Instanceof(u16),
+    Monitorenter,
+    Monitorexit,
+
+    // Extended
+    Wide(WideInstruction),
+    Multianewarray {
+        index: u16,
+        dimensions: u8,
+    },
+    Ifnull(i16),
+    Ifnonnull(i16),
+    GotoW(i32),
+    JsrW(i32),
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => NOP,
+            Instruction::AconstNull => ACONST_NULL,
+            Instruction::IconstM1 => ICONST_M1,
+            Instruction::Iconst0 => ICONST_0,
+            Instruction::Iconst1 => ICONST_1,
+            Instruction::Iconst2 => ICONST_2,
+            Instruction::Iconst3 => ICONST_3,
+            Instruction::Iconst4 => ICONST_4,
+            Instruction::Iconst5 => ICONST_5,
+            Instruction::Lconst0 => LCONST_0,
+            Instruction::Lconst1 => LCONST_1,
+            Instruction::Fconst0 => FCONST_0,
+            Instruction::Fconst1 => FCONST_1,
+            Instruction::Fconst2 => FCONST_2,
+            Instruction::Dconst0 => DCONST_0,
+            Instruction::Dconst1 => DCONST_1,
+            Instruction::Bipush(_) => BIPUSH,
+            Instruction::Sipush(_) => SIPUSH,
+            Instruction::Ldc(_) => LDC,
+            Instruction::LdcW(_) => LDC_W,
+            Instruction::Ldc2W(_) => LDC2_W,
+            Instruction::Iload(_) => ILOAD,
+            Instruction::Lload(_) => LLOAD,
+            Instruction::Fload(_) => FLOAD,
+            Instruction::Dload(_) => DLOAD,
+            Instruction::Aload(_) => ALOAD,
+            Instruction::Iload0 => ILOAD_0,
+            Instruction::Iload1 => ILOAD_1,
+            Instruction::Iload2 => ILOAD_2,
+            Instruction::Iload3 => ILOAD_3,
+            Instruction::Lload0 => LLOAD_0,
+            Instruction::Lload1 => LLOAD_1,
+            Instruction::Lload2 => LLOAD_2,
+            Instruction::Lload3 => LLOAD_3,
+            Instruction::Fload0 => FLOAD_0,
+            Instruction::Fload1 => FLOAD_1,
+            Instruction::Fload2 => FLOAD_2,
+            Instruction::Fload3 => FLOAD_3,
+            Instruction::Dload0 => DLOAD_0,
+            Instruction::Dload1 => DLOAD_1,
+            Instruction::Dload2 => DLOAD_2,
+            Instruction::Dload3 => DLOAD_3,
+            Instruction::Aload0 => ALOAD_0,
+            Instruction::Aload1 => ALOAD_1,
+            Instruction::Aload2 => ALOAD_2,
+            Instruction::Aload3 => ALOAD_3,
+            Instruction::Iaload => IALOAD,
+            Instruction::Laload => LALOAD,
+            Instruction::Faload => FALOAD,
+            Instruction::Daload => DALOAD,
+            Instruction::Aaload => AALOAD,
+            Instruction::Baload => BALOAD,
+            Instruction::Caload => CALOAD,
+            Instruction::Saload => SALOAD,
+            Instruction::Istore(_) => ISTORE,
+            Instruction::Lstore(_) => LSTORE,
+            Instruction::Fstore(_) => FSTORE,
+            Instruction::Dstore(_) => DSTORE,
+            Instruction::Astore(_) => ASTORE,
+            Instruction::Istore0 => ISTORE_0,
+            Instruction::Istore1 => ISTORE_1,
+            Instruction::Istore2 => ISTORE_2,
+            Instruction::Istore3 => ISTORE_3,
+            Instruction::Lstore0 => LSTORE_0,
+            Instruction::Lstore1 => LSTORE_1,
+            Instruction::Lstore2 => LSTORE_2,
+            Instruction::Lstore3 => LSTORE_3,
+            Instruction::Fstore0 => FSTORE_0,
+            Instruction::Fstore1 => FSTORE_1,
+            Instruction::Fstore2 => FSTORE_2,
+            Instruction::Fstore3 => FSTORE_3,
+            Instruction::Dstore0 => DSTORE_0,
+            Instruction::Dstore1 => DSTORE_1,
+            Instruction::Dstore2 => DSTORE_2,
+            Instruction::Dstore3 => DSTORE_3,
+            Instruction::Astore0 => ASTORE_0,
+            Instruction::Astore1 => ASTORE_1,
+            Instruction::Astore2 => ASTORE_2,
+            Instruction::Astore3 => ASTORE_3,
+            Instruction::Iastore => IASTORE,
+            Instruction::Lastore => LASTORE,
+            Instruction::Fastore => FASTORE,
+            Instruction::Dastore => DASTORE,
+            Instruction::Aastore => AASTORE,
+            Instruction::Bastore => BASTORE,
+            Instruction::Castore => CASTORE,
+            Instruction::Sastore => SASTORE,
+            Instruction::Pop => POP,
+            Instruction::Pop2 => POP2,
+            Instruction::Dup => DUP,
+            Instruction::DupX1 => DUP_X1,
+            Instruction::DupX2 => DUP_X2,
+            Instruction::Dup2 => DUP2,
+            Instruction::Dup2X1 => DUP2_X1,
+            Instruction::Dup2X2 => DUP2_X2,
+            Instruction::Swap => SWAP,
+            Instruction::Iadd => IADD,
+            Instruction::Ladd => LADD,
+            Instruction::Fadd => FADD,
+            Instruction::Dadd => DADD,
+            Instruction::Isub => ISUB,
+            Instruction::Lsub => LSUB,
+            Instruction::Fsub => FSUB,
+            Instruction::Dsub => DSUB,
+            Instruction::Imul => IMUL,
+            Instruction::Lmul => LMUL,
+            Instruction::Fmul => FMUL,
+            Instruction::Dmul => DMUL,
+            Instruction::Idiv => IDIV,
+            Instruction::Ldiv => LDIV,
+            Instruction::Fdiv => FDIV,
+            Instruction::Ddiv => DDIV,
+            Instruction::Irem => IREM,
+            Instruction::Lrem => LREM,
+            Instruction::Frem => FREM,
+            Instruction::Drem => DREM,
+            Instruction::Ineg => INEG,
+            Instruction::Lneg => LNEG,
+            Instruction::Fneg => FNEG,
+            Instruction::Dneg => DNEG,
+            Instruction::Ishl => ISHL,
+            Instruction::Lshl => LSHL,
+            Instruction::Ishr => ISHR,
+            Instruction::Lshr => LSHR,
+            Instruction::Iushr => IUSHR,
+            Instruction::Lushr => LUSHR,
+            Instruction::Iand => IAND,
+            Instruction::Land => LAND,
+            Instruction::Ior => IOR,
+            Instruction::Lor => LOR,
+            Instruction::Ixor => IXOR,
+            Instruction::Lxor => LXOR,
+            Instruction::Iinc { .. } => IINC,
+            Instruction::I2l => I2L,
+            Instruction::I2f => I2F,
+            Instruction::I2d => I2D,
+            Instruction::L2i => L2I,
+            Instruction::L2f => L2F,
+            Instruction::L2d => L2D,
+            Instruction::F2i => F2I,
+            Instruction::F2l => F2L,
+            Instruction::F2d => F2D,
+            Instruction::D2i => D2I,
+            Instruction::D2l => D2L,
+            Instruction::D2f => D2F,
+            Instruction::I2b => I2B,
+            Instruction::I2c => I2C,
+            Instruction::I2s => I2S,
+            Instruction::Lcmp => LCMP,
+            Instruction::Fcmpl => FCMPL,
+            Instruction::Fcmpg => FCMPG,
+            Instruction::Dcmpl => DCMPL,
+            Instruction::Dcmpg => DCMPG,
+            Instruction::Ifeq(_) => IFEQ,
+            Instruction::Ifne(_) => IFNE,
+            Instruction::Iflt(_) => IFLT,
+            Instruction::Ifge(_) => IFGE,
+            Instruction::Ifgt(_) => IFGT,
+            Instruction::Ifle(_) => IFLE,
+            Instruction::IfIcmpeq(_) => IF_ICMPEQ,
+            Instruction::IfIcmpne(_) => IF_ICMPNE,
+            Instruction::IfIcmplt(_) => IF_ICMPLT,
+            Instruction::IfIcmpge(_) => IF_ICMPGE,
+            Instruction::IfIcmpgt(_) => IF_ICMPGT,
+            Instruction::IfIcmple(_) => IF_ICMPLE,
+            Instruction::IfAcmpeq(_) => IF_ACMPEQ,
+            Instruction::IfAcmpne(_) => IF_ACMPNE,
+            Instruction::Goto(_) => GOTO,
+            Instruction::Jsr(_) => JSR,
+            Instruction::Ret(_) => RET,
+            Instruction::Tableswitch { .. } => TABLESWITCH,
+            Instruction::Lookupswitch { .. } => LOOKUPSWITCH,
+            Instruction::Ireturn => IRETURN,
+            Instruction::Lreturn => LRETURN,
+            Instruction::Freturn => FRETURN,
+            Instruction::Dreturn => DRETURN,
+            Instruction::Areturn => ARETURN,
+            Instruction::Return => RETURN,
+            Instruction::Getstatic(_) => GETSTATIC,
+            Instruction::Putstatic(_) => PUTSTATIC,
+            Instruction::Getfield(_) => GETFIELD,
+            Instruction::Putfield(_) => PUTFIELD,
+            Instruction::Invokevirtual(_) => INVOKEVIRTUAL,
+            Instruction::Invokespecial(_) => INVOKESPECIAL,
+            Instruction::Invokestatic(_) => INVOKESTATIC,
+            Instruction::Invokeinterface { .. } => INVOKEINTERFACE,
+            Instruction::Invokedynamic(_) => INVOKEDYNAMIC,
+            Instruction::New(_) => NEW,
+            Instruction::Newarray(_) => NEWARRAY,
+            Instruction::Anewarray(_) => ANEWARRAY,
+            Instruction::Arraylength => ARRAYLENGTH,
+            Instruction::Athrow => ATHROW,
+            Instruction::Checkcast(_) => CHECKCAST,
+            Instruction::Instanceof(_) => INSTANCEOF,
+            Instruction::Monitorenter => MONITORENTER,
+            Instruction::Monitorexit => MONITOREXIT,
+            Instruction::Wide(_) => WIDE,
+            Instruction::Multianewarray { .. } => MULTIANEWARRAY,
+            Instruction::Ifnull(_) => IFNULL,
+            Instruction::Ifnonnull(_) => IFNONNULL,
+            Instruction::GotoW(_) => GOTO_W,
+            Instruction::JsrW(_) => JSR_W,
+        }
+    }
+
+    /// The mnemonic of the instruction as used in the JVM specification, e.g. `invokevirtual`.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "nop",
+            Instruction::AconstNull => "aconst_null",
+            Instruction::IconstM1 => "iconst_m1",
+            Instruction::Iconst0 => "iconst_0",
+            Instruction::Iconst1 => "iconst_1",
+            Instruction::Iconst2 => "iconst_2",
+            Instruction::Iconst3 => "iconst_3",
+            Instruction::Iconst4 => "iconst_4",
+            Instruction::Iconst5 => "iconst_5",
+            Instruction::Lconst0 => "lconst_0",
+            Instruction::Lconst1 => "lconst_1",
+            Instruction::Fconst0 => "fconst_0",
+            Instruction::Fconst1 => "fconst_1",
+            Instruction::Fconst2 => "fconst_2",
+            Instruction::Dconst0 => "dconst_0",
+            Instruction::Dconst1 => "dconst_1",
+            Instruction::Bipush(_) => "bipush",
+            Instruction::Sipush(_) => "sipush",
+            Instruction::Ldc(_) => "ldc",
+            Instruction::LdcW(_) => "ldc_w",
+            Instruction::Ldc2W(_) => "ldc2_w",
+            Instruction::Iload(_) => "iload",
+            Instruction::Lload(_) => "lload",
+            Instruction::Fload(_) => "fload",
+            Instruction::Dload(_) => "dload",
+            Instruction::Aload(_) => "aload",
+            Instruction::Iload0 => "iload_0",
+            Instruction::Iload1 => "iload_1",
+            Instruction::Iload2 => "iload_2",
+            Instruction::Iload3 => "iload_3",
+            Instruction::Lload0 => "lload_0",
+            Instruction::Lload1 => "lload_1",
+            Instruction::Lload2 => "lload_2",
+            Instruction::Lload3 => "lload_3",
+            Instruction::Fload0 => "fload_0",
+            Instruction::Fload1 => "fload_1",
+            Instruction::Fload2 => "fload_2",
+            Instruction::Fload3 => "fload_3",
+            Instruction::Dload0 => "dload_0",
+            Instruction::Dload1 => "dload_1",
+            Instruction::Dload2 => "dload_2",
+            Instruction::Dload3 => "dload_3",
+            Instruction::Aload0 => "aload_0",
+            Instruction::Aload1 => "aload_1",
+            Instruction::Aload2 => "aload_2",
+            Instruction::Aload3 => "aload_3",
+            Instruction::Iaload => "iaload",
+            Instruction::Laload => "laload",
+            Instruction::Faload => "faload",
+            Instruction::Daload => "daload",
+            Instruction::Aaload => "aaload",
+            Instruction::Baload => "baload",
+            Instruction::Caload => "caload",
+            Instruction::Saload => "saload",
+            Instruction::Istore(_) => "istore",
+            Instruction::Lstore(_) => "lstore",
+            Instruction::Fstore(_) => "fstore",
+            Instruction::Dstore(_) => "dstore",
+            Instruction::Astore(_) => "astore",
+            Instruction::Istore0 => "istore_0",
+            Instruction::Istore1 => "istore_1",
+            Instruction::Istore2 => "istore_2",
+            Instruction::Istore3 => "istore_3",
+            Instruction::Lstore0 => "lstore_0",
+            Instruction::Lstore1 => "lstore_1",
+            Instruction::Lstore2 => "lstore_2",
+            Instruction::Lstore3 => "lstore_3",
+            Instruction::Fstore0 => "fstore_0",
+            Instruction::Fstore1 => "fstore_1",
+            Instruction::Fstore2 => "fstore_2",
+            Instruction::Fstore3 => "fstore_3",
+            Instruction::Dstore0 => "dstore_0",
+            Instruction::Dstore1 => "dstore_1",
+            Instruction::Dstore2 => "dstore_2",
+            Instruction::Dstore3 => "dstore_3",
+            Instruction::Astore0 => "astore_0",
+            Instruction::Astore1 => "astore_1",
+            Instruction::Astore2 => "astore_2",
+            Instruction::Astore3 => "astore_3",
+            Instruction::Iastore => "iastore",
+            Instruction::Lastore => "lastore",
+            Instruction::Fastore => "fastore",
+            Instruction::Dastore => "dastore",
+            Instruction::Aastore => "aastore",
+            Instruction::Bastore => "bastore",
+            Instruction::Castore => "castore",
+            Instruction::Sastore => "sastore",
+            Instruction::Pop => "pop",
+            Instruction::Pop2 => "pop2",
+            Instruction::Dup => "dup",
+            Instruction::DupX1 => "dup_x1",
+            Instruction::DupX2 => "dup_x2",
+            Instruction::Dup2 => "dup2",
+            Instruction::Dup2X1 => "dup2_x1",
+            Instruction::Dup2X2 => "dup2_x2",
+            Instruction::Swap => "swap",
+            Instruction::Iadd => "iadd",
+            Instruction::Ladd => "ladd",
+            Instruction::Fadd => "fadd",
+            Instruction::Dadd => "dadd",
+            Instruction::Isub => "isub",
+            Instruction::Lsub => "lsub",
+            Instruction::Fsub => "fsub",
+            Instruction::Dsub => "dsub",
+            Instruction::Imul => "imul",
+            Instruction::Lmul => "lmul",
+            Instruction::Fmul => "fmul",
+            Instruction::Dmul => "dmul",
+            Instruction::Idiv => "idiv",
+            Instruction::Ldiv => "ldiv",
+            Instruction::Fdiv => "fdiv",
+            Instruction::Ddiv => "ddiv",
+            Instruction::Irem => "irem",
+            Instruction::Lrem => "lrem",
+            Instruction::Frem => "frem",
+            Instruction::Drem => "drem",
+            Instruction::Ineg => "ineg",
+            Instruction::Lneg => "lneg",
+            Instruction::Fneg => "fneg",
+            Instruction::Dneg => "dneg",
+            Instruction::Ishl => "ishl",
+            Instruction::Lshl => "lshl",
+            Instruction::Ishr => "ishr",
+            Instruction::Lshr => "lshr",
+            Instruction::Iushr => "iushr",
+            Instruction::Lushr => "lushr",
+            Instruction::Iand => "iand",
+            Instruction::Land => "land",
+            Instruction::Ior => "ior",
+            Instruction::Lor => "lor",
+            Instruction::Ixor => "ixor",
+            Instruction::Lxor => "lxor",
+            Instruction::Iinc { .. } => "iinc",
+            Instruction::I2l => "i2l",
+            Instruction::I2f => "i2f",
+            Instruction::I2d => "i2d",
+            Instruction::L2i => "l2i",
+            Instruction::L2f => "l2f",
+            Instruction::L2d => "l2d",
+            Instruction::F2i => "f2i",
+            Instruction::F2l => "f2l",
+            Instruction::F2d => "f2d",
+            Instruction::D2i => "d2i",
+            Instruction::D2l => "d2l",
+            Instruction::D2f => "d2f",
+            Instruction::I2b => "i2b",
+            Instruction::I2c => "i2c",
+            Instruction::I2s => "i2s",
+            Instruction::Lcmp => "lcmp",
+            Instruction::Fcmpl => "fcmpl",
+            Instruction::Fcmpg => "fcmpg",
+            Instruction::Dcmpl => "dcmpl",
+            Instruction::Dcmpg => "dcmpg",
+            Instruction::Ifeq(_) => "ifeq",
+            Instruction::Ifne(_) => "ifne",
+            Instruction::Iflt(_) => "iflt",
+            Instruction::Ifge(_) => "ifge",
+            Instruction::Ifgt(_) => "ifgt",
+            Instruction::Ifle(_) => "ifle",
+            Instruction::IfIcmpeq(_) => "if_icmpeq",
+            Instruction::IfIcmpne(_) => "if_icmpne",
+            Instruction::IfIcmplt(_) => "if_icmplt",
+            Instruction::IfIcmpge(_) => "if_icmpge",
+            Instruction::IfIcmpgt(_) => "if_icmpgt",
+            Instruction::IfIcmple(_) => "if_icmple",
+            Instruction::IfAcmpeq(_) => "if_acmpeq",
+            Instruction::IfAcmpne(_) => "if_acmpne",
+            Instruction::Goto(_) => "goto",
+            Instruction::Jsr(_) => "jsr",
+            Instruction::Ret(_) => "ret",
+            Instruction::Tableswitch { .. } => "tableswitch",
+            Instruction::Lookupswitch { .. } => "lookupswitch",
+            Instruction::Ireturn => "ireturn",
+            Instruction::Lreturn => "lreturn",
+            Instruction::Freturn => "freturn",
+            Instruction::Dreturn => "dreturn",
+            Instruction::Areturn => "areturn",
+            Instruction::Return => "return",
+            Instruction::Getstatic(_) => "getstatic",
+            Instruction::Putstatic(_) => "putstatic",
+            Instruction::Getfield(_) => "getfield",
+            Instruction::Putfield(_) => "putfield",
+            Instruction::Invokevirtual(_) => "invokevirtual",
+            Instruction::Invokespecial(_) => "invokespecial",
+            Instruction::Invokestatic(_) => "invokestatic",
+            Instruction::Invokeinterface { .. } => "invokeinterface",
+            Instruction::Invokedynamic(_) => "invokedynamic",
+            Instruction::New(_) => "new",
+            Instruction::Newarray(_) => "newarray",
+            Instruction::Anewarray(_) => "anewarray",
+            Instruction::Arraylength => "arraylength",
+            Instruction::Athrow => "athrow",
+            Instruction::Checkcast(_) => "checkcast",
+            Instruction::Instanceof(_) => "instanceof",
+            Instruction::Monitorenter => "monitorenter",
+            Instruction::Monitorexit => "monitorexit",
+            Instruction::Wide(_) => "wide",
+            Instruction::Multianewarray { .. } => "multianewarray",
+            Instruction::Ifnull(_) => "ifnull",
+            Instruction::Ifnonnull(_) => "ifnonnull",
+            Instruction::GotoW(_) => "goto_w",
+            Instruction::JsrW(_) => "jsr_w",
+        }
+    }
+
+    /// The number of bytes the instruction takes up when it starts at `offset` in the code.
+    pub fn size(&self, offset: usize) -> usize {
+        match self {
+            Instruction::Tableswitch { offsets, .. } => {
+                1 + switch_padding(offset) + 12 + 4 * offsets.len()
+            }
+            Instruction::Lookupswitch { pairs, .. } => {
+                1 + switch_padding(offset) + 8 + 8 * pairs.len()
+            }
+            Instruction::Wide(WideInstruction::Iinc { .. }) => 6,
+            Instruction::Wide(_) => 4,
+            Instruction::GotoW(_)
+            | Instruction::JsrW(_)
+            | Instruction::Invokeinterface { .. }
+            | Instruction::Invokedynamic(_) => 5,
+            Instruction::Multianewarray { .. } => 4,
+            Instruction::Sipush(_)
+            | Instruction::LdcW(_)
+            | Instruction::Ldc2W(_)
+            | Instruction::Getstatic(_)
+            | Instruction::Putstatic(_)
+            | Instruction::Getfield(_)
+            | Instruction::Putfield(_)
+            | Instruction::Invokevirtual(_)
+            | Instruction::Invokespecial(_)
+            | Instruction::Invokestatic(_)
+            | Instruction::New(_)
+            | Instruction::Anewarray(_)
+            | Instruction::Checkcast(_)
+            | Instruction::Instanceof(_)
+            | Instruction::Ifeq(_)
+            | Instruction::Ifne(_)
+            | Instruction::Iflt(_)
+            | Instruction::Ifge(_)
+            | Instruction::Ifgt(_)
+            | Instruction::Ifle(_)
+            | Instruction::IfIcmpeq(_)
+            | Instruction::IfIcmpne(_)
+            | Instruction::IfIcmplt(_)
+            | Instruction::IfIcmpge(_)
+            | Instruction::IfIcmpgt(_)
+            | Instruction::IfIcmple(_)
+            | Instruction::IfAcmpeq(_)
+            | Instruction::IfAcmpne(_)
+            | Instruction::Goto(_)
+            | Instruction::Jsr(_)
+            | Instruction::Ifnull(_)
+            | Instruction::Ifnonnull(_)
+            | Instruction::Iinc { .. } => 3,
+            Instruction::Bipush(_)
+            | Instruction::Ldc(_)
+            | Instruction::Iload(_)
+            | Instruction::Lload(_)
+            | Instruction::Fload(_)
+            | Instruction::Dload(_)
+            | Instruction::Aload(_)
+            | Instruction::Istore(_)
+            | Instruction::Lstore(_)
+            | Instruction::Fstore(_)
+            | Instruction::Dstore(_)
+            | Instruction::Astore(_)
+            | Instruction::Ret(_)
+            | Instruction::Newarray(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Decode the instruction starting at `offset` in `code`.
+    pub fn decode(code: &[u8], offset: usize) -> BytecodeResult<Instruction> {
+        let mut cursor = Cursor {
+            code,
+            start: offset,
+            pos: offset,
+        };
+
+        let opcode = cursor.u8()?;
+        let instruction = match opcode {
+            NOP => Instruction::Nop,
+            ACONST_NULL => Instruction::AconstNull,
+            ICONST_M1 => Instruction::IconstM1,
+            ICONST_0 => Instruction::Iconst0,
+            ICONST_1 => Instruction::Iconst1,
+            ICONST_2 => Instruction::Iconst2,
+            ICONST_3 => Instruction::Iconst3,
+            ICONST_4 => Instruction::Iconst4,
+            ICONST_5 => Instruction::Iconst5,
+            LCONST_0 => Instruction::Lconst0,
+            LCONST_1 => Instruction::Lconst1,
+            FCONST_0 => Instruction::Fconst0,
+            FCONST_1 => Instruction::Fconst1,
+            FCONST_2 => Instruction::Fconst2,
+            DCONST_0 => Instruction::Dconst0,
+            DCONST_1 => Instruction::Dconst1,
+            BIPUSH => Instruction::Bipush(cursor.i8()?),
+            SIPUSH => Instruction::Sipush(cursor.i16()?),
+            LDC => Instruction::Ldc(cursor.u8()?),
+            LDC_W => Instruction::LdcW(cursor.u16()?),
+            LDC2_W => Instruction::Ldc2W(cursor.u16()?),
+            ILOAD => Instruction::Iload(cursor.u8()?),
+            LLOAD => Instruction::Lload(cursor.u8()?),
+            FLOAD => Instruction::Fload(cursor.u8()?),
+            DLOAD => Instruction::Dload(cursor.u8()?),
+            ALOAD => Instruction::Aload(cursor.u8()?),
+            ILOAD_0 => Instruction::Iload0,
+            ILOAD_1 => Instruction::Iload1,
+            ILOAD_2 => Instruction::Iload2,
+            ILOAD_3 => Instruction::Iload3,
+            LLOAD_0 => Instruction::Lload0,
+            LLOAD_1 => Instruction::Lload1,
+            LLOAD_2 => Instruction::Lload2,
+            LLOAD_3 => Instruction::Lload3,
+            FLOAD_0 => Instruction::Fload0,
+            FLOAD_1 => Instruction::Fload1,
+            FLOAD_2 => Instruction::Fload2,
+            FLOAD_3 => Instruction::Fload3,
+            DLOAD_0 => Instruction::Dload0,
+            DLOAD_1 => Instruction::Dload1,
+            DLOAD_2 => Instruction::Dload2,
+            DLOAD_3 => Instruction::Dload3,
+            ALOAD_0 => Instruction::Aload0,
+            ALOAD_1 => Instruction::Aload1,
+            ALOAD_2 => Instruction::Aload2,
+            ALOAD_3 => Instruction::Aload3,
+            IALOAD => Instruction::Iaload,
+            LALOAD => Instruction::Laload,
+            FALOAD => Instruction::Faload,
+            DALOAD => Instruction::Daload,
+            AALOAD => Instruction::Aaload,
+            BALOAD => Instruction::Baload,
+            CALOAD => Instruction::Caload,
+            SALOAD => Instruction::Saload,
+            ISTORE => Instruction::Istore(cursor.u8()?),
+            LSTORE => Instruction::Lstore(cursor.u8()?),
+            FSTORE => Instruction::Fstore(cursor.u8()?),
+            DSTORE => Instruction::Dstore(cursor.u8()?),
+            ASTORE => Instruction::Astore(cursor.u8()?),
+            ISTORE_0 => Instruction::Istore0,
+            ISTORE_1 => Instruction::Istore1,
+            ISTORE_2 => Instruction::Istore2,
+            ISTORE_3 => Instruction::Istore3,
+            LSTORE_0 => Instruction::Lstore0,
+            LSTORE_1 => Instruction::Lstore1,
+            LSTORE_2 => Instruction::Lstore2,
+            LSTORE_3 => Instruction::Lstore3,
+            FSTORE_0 => Instruction::Fstore0,
+            FSTORE_1 => Instruction::Fstore1,
+            FSTORE_2 => Instruction::Fstore2,
+            FSTORE_3 => Instruction::Fstore3,
+            DSTORE_0 => Instruction::Dstore0,
+            DSTORE_1 => Instruction::Dstore1,
+            DSTORE_2 => Instruction::Dstore2,
+            DSTORE_3 => Instruction::Dstore3,
+            ASTORE_0 => Instruction::Astore0,
+            ASTORE_1 => Instruction::Astore1,
+            ASTORE_2 => Instruction::Astore2,
+            ASTORE_3 => Instruction::Astore3,
+            IASTORE => Instruction::Iastore,
+            LASTORE => Instruction::Lastore,
+            FASTORE => Instruction::Fastore,
+            DASTORE => Instruction::Dastore,
+            AASTORE => Instruction::Aastore,
+            BASTORE => Instruction::Bastore,
+            CASTORE => Instruction::Castore,
+            SASTORE => Instruction::Sastore,
+            POP => Instruction::Pop,
+            POP2 => Instruction::Pop2,
+            DUP => Instruction::Dup,
+            DUP_X1 => Instruction::DupX1,
+            DUP_X2 => Instruction::DupX2,
+            DUP2 => Instruction::Dup2,
+            DUP2_X1 => Instruction::Dup2X1,
+            DUP2_X2 => Instruction::Dup2X2,
+            SWAP => Instruction::Swap,
+            IADD => Instruction::Iadd,
+            LADD => Instruction::Ladd,
+            FADD => Instruction::Fadd,
+            DADD => Instruction::Dadd,
+            ISUB => Instruction::Isub,
+            LSUB => Instruction::Lsub,
+            FSUB => Instruction::Fsub,
+            DSUB => Instruction::Dsub,
+            IMUL => Instruction::Imul,
+            LMUL => Instruction::Lmul,
+            FMUL => Instruction::Fmul,
+            DMUL => Instruction::Dmul,
+            IDIV => Instruction::Idiv,
+            LDIV => Instruction::Ldiv,
+            FDIV => Instruction::Fdiv,
+            DDIV => Instruction::Ddiv,
+            IREM => Instruction::Irem,
+            LREM => Instruction::Lrem,
+            FREM => Instruction::Frem,
+            DREM => Instruction::Drem,
+            INEG => Instruction::Ineg,
+            LNEG => Instruction::Lneg,
+            FNEG => Instruction::Fneg,
+            DNEG => Instruction::Dneg,
+            ISHL => Instruction::Ishl,
+            LSHL => Instruction::Lshl,
+            ISHR => Instruction::Ishr,
+            LSHR => Instruction::Lshr,
+            IUSHR => Instruction::Iushr,
+            LUSHR => Instruction::Lushr,
+            IAND => Instruction::Iand,
+            LAND => Instruction::Land,
+            IOR => Instruction::Ior,
+            LOR => Instruction::Lor,
+            IXOR => Instruction::Ixor,
+            LXOR => Instruction::Lxor,
+            I2L => Instruction::I2l,
+            I2F => Instruction::I2f,
+            I2D => Instruction::I2d,
+            L2I => Instruction::L2i,
+            L2F => Instruction::L2f,
+            L2D => Instruction::L2d,
+            F2I => Instruction::F2i,
+            F2L => Instruction::F2l,
+            F2D => Instruction::F2d,
+            D2I => Instruction::D2i,
+            D2L => Instruction::D2l,
+            D2F => Instruction::D2f,
+            I2B => Instruction::I2b,
+            I2C => Instruction::I2c,
+            I2S => Instruction::I2s,
+            LCMP => Instruction::Lcmp,
+            FCMPL => Instruction::Fcmpl,
+            FCMPG => Instruction::Fcmpg,
+            DCMPL => Instruction::Dcmpl,
+            DCMPG => Instruction::Dcmpg,
+            IFEQ => Instruction::Ifeq(cursor.i16()?),
+            IFNE => Instruction::Ifne(cursor.i16()?),
+            IFLT => Instruction::Iflt(cursor.i16()?),
+            IFGE => Instruction::Ifge(cursor.i16()?),
+            IFGT => Instruction::Ifgt(cursor.i16()?),
+            IFLE => Instruction::Ifle(cursor.i16()?),
+            IF_ICMPEQ => Instruction::IfIcmpeq(cursor.i16()?),
+            IF_ICMPNE => Instruction::IfIcmpne(cursor.i16()?),
+            IF_ICMPLT => Instruction::IfIcmplt(cursor.i16()?),
+            IF_ICMPGE => Instruction::IfIcmpge(cursor.i16()?),
+            IF_ICMPGT => Instruction::IfIcmpgt(cursor.i16()?),
+            IF_ICMPLE => Instruction::IfIcmple(cursor.i16()?),
+            IF_ACMPEQ => Instruction::IfAcmpeq(cursor.i16()?),
+            IF_ACMPNE => Instruction::IfAcmpne(cursor.i16()?),
+            GOTO => Instruction::Goto(cursor.i16()?),
+            JSR => Instruction::Jsr(cursor.i16()?),
+            RET => Instruction::Ret(cursor.u8()?),
+            IRETURN => Instruction::Ireturn,
+            LRETURN => Instruction::Lreturn,
+            FRETURN => Instruction::Freturn,
+            DRETURN => Instruction::Dreturn,
+            ARETURN => Instruction::Areturn,
+            RETURN => Instruction::Return,
+            GETSTATIC => Instruction::Getstatic(cursor.u16()?),
+            PUTSTATIC => Instruction::Putstatic(cursor.u16()?),
+            GETFIELD => Instruction::Getfield(cursor.u16()?),
+            PUTFIELD => Instruction::Putfield(cursor.u16()?),
+            INVOKEVIRTUAL => Instruction::Invokevirtual(cursor.u16()?),
+            INVOKESPECIAL => Instruction::Invokespecial(cursor.u16()?),
+            INVOKESTATIC => Instruction::Invokestatic(cursor.u16()?),
+            NEW => Instruction::New(cursor.u16()?),
+            NEWARRAY => Instruction::Newarray(cursor.u8()?),
+            ANEWARRAY => Instruction::Anewarray(cursor.u16()?),
+            ARRAYLENGTH => Instruction::Arraylength,
+            ATHROW => Instruction::Athrow,
+            CHECKCAST => Instruction::Checkcast(cursor.u16()?),
+            INSTANCEOF => Instruction::Instanceof(cursor.u16()?),
+            MONITORENTER => Instruction::Monitorenter,
+            MONITOREXIT => Instruction::Monitorexit,
+            IFNULL => Instruction::Ifnull(cursor.i16()?),
+            IFNONNULL => Instruction::Ifnonnull(cursor.i16()?),
+            GOTO_W => Instruction::GotoW(cursor.i32()?),
+            JSR_W => Instruction::JsrW(cursor.i32()?),
+            IINC => Instruction::Iinc {
+                index: cursor.u8()?,
+                constant: cursor.i8()?,
+            },
+
+            TABLESWITCH => {
+                cursor.skip_padding()?;
+                let default = cursor.i32()?;
+                let low = cursor.i32()?;
+                let high = cursor.i32()?;
+                if low > high {
+                    return Err(BytecodeError::InvalidOperand { offset });
+                }
+
+                let count = (high as i64 - low as i64 + 1) as usize;
+                cursor.ensure(4 * count)?;
+                let mut offsets = Vec::with_capacity(count);
+                for _ in 0..count {
+                    offsets.push(cursor.i32()?);
+                }
+
+                Instruction::Tableswitch {
+                    default,
+                    low,
+                    high,
+                    offsets,
+                }
+            }
+
+            LOOKUPSWITCH => {
+                cursor.skip_padding()?;
+                let default = cursor.i32()?;
+                let npairs = cursor.i32()?;
+                if npairs < 0 {
+                    return Err(BytecodeError::InvalidOperand { offset });
+                }
+
+                cursor.ensure(8 * npairs as usize)?;
+                let mut pairs = Vec::with_capacity(npairs as usize);
+                for _ in 0..npairs {
+                    pairs.push((cursor.i32()?, cursor.i32()?));
+                }
+
+                Instruction::Lookupswitch { default, pairs }
+            }
+
+            INVOKEINTERFACE => {
+                let index = cursor.u16()?;
+                let count = cursor.u8()?;
+                if cursor.u8()? != 0 {
+                    return Err(BytecodeError::InvalidOperand { offset });
+                }
+                Instruction::Invokeinterface { index, count }
+            }
+
+            INVOKEDYNAMIC => {
+                let index = cursor.u16()?;
+                if cursor.u16()? != 0 {
+                    return Err(BytecodeError::InvalidOperand { offset });
+                }
+                Instruction::Invokedynamic(index)
+            }
+
+            MULTIANEWARRAY => Instruction::Multianewarray {
+                index: cursor.u16()?,
+                dimensions: cursor.u8()?,
+            },
+
+            WIDE => {
+                let opcode = cursor.u8()?;
+                let index = cursor.u16()?;
+                Instruction::Wide(match opcode {
+                    ILOAD => WideInstruction::Iload(index),
+                    LLOAD => WideInstruction::Lload(index),
+                    FLOAD => WideInstruction::Fload(index),
+                    DLOAD => WideInstruction::Dload(index),
+                    ALOAD => WideInstruction::Aload(index),
+                    ISTORE => WideInstruction::Istore(index),
+                    LSTORE => WideInstruction::Lstore(index),
+                    FSTORE => WideInstruction::Fstore(index),
+                    DSTORE => WideInstruction::Dstore(index),
+                    ASTORE => WideInstruction::Astore(index),
+                    RET => WideInstruction::Ret(index),
+                    IINC => WideInstruction::Iinc {
+                        index,
+                        constant: cursor.i16()?,
+                    },
+                    _ => return Err(BytecodeError::InvalidWideOpcode { offset, opcode }),
+                })
+            }
+
+            _ => return Err(BytecodeError::InvalidOpcode { offset, opcode }),
+        };
+
+        Ok(instruction)
+    }
+
+    /// Append the encoded instruction to `code`. The instruction is assumed to start at
+    /// `code.len()`, which determines the padding of `tableswitch` and `lookupswitch`.
+    pub fn encode(&self, code: &mut Vec<u8>) {
+        let offset = code.len();
+        code.push(self.opcode());
+
+        match self {
+            Instruction::Bipush(value) => code.push(*value as u8),
+            Instruction::Sipush(value) => code.extend_from_slice(&value.to_be_bytes()),
+            Instruction::Ldc(index)
+            | Instruction::Iload(index)
+            | Instruction::Lload(index)
+            | Instruction::Fload(index)
+            | Instruction::Dload(index)
+            | Instruction::Aload(index)
+            | Instruction::Istore(index)
+            | Instruction::Lstore(index)
+            | Instruction::Fstore(index)
+            | Instruction::Dstore(index)
+            | Instruction::Astore(index)
+            | Instruction::Ret(index)
+            | Instruction::Newarray(index) => code.push(*index),
+            Instruction::LdcW(index)
+            | Instruction::Ldc2W(index)
+            | Instruction::Getstatic(index)
+            | Instruction::Putstatic(index)
+            | Instruction::Getfield(index)
+            | Instruction::Putfield(index)
+            | Instruction::Invokevirtual(index)
+            | Instruction::Invokespecial(index)
+            | Instruction::Invokestatic(index)
+            | Instruction::New(index)
+            | Instruction::Anewarray(index)
+            | Instruction::Checkcast(index)
+            | Instruction::Instanceof(index) => code.extend_from_slice(&index.to_be_bytes()),
+            Instruction::Ifeq(branch)
+            | Instruction::Ifne(branch)
+            | Instruction::Iflt(branch)
+            | Instruction::Ifge(branch)
+            | Instruction::Ifgt(branch)
+            | Instruction::Ifle(branch)
+            | Instruction::IfIcmpeq(branch)
+            | Instruction::IfIcmpne(branch)
+            | Instruction::IfIcmplt(branch)
+            | Instruction::IfIcmpge(branch)
+            | Instruction::IfIcmpgt(branch)
+            | Instruction::IfIcmple(branch)
+            | Instruction::IfAcmpeq(branch)
+            | Instruction::IfAcmpne(branch)
+            | Instruction::Goto(branch)
+            | Instruction::Jsr(branch)
+            | Instruction::Ifnull(branch)
+            | Instruction::Ifnonnull(branch) => code.extend_from_slice(&branch.to_be_bytes()),
+            Instruction::GotoW(branch) | Instruction::JsrW(branch) => {
+                code.extend_from_slice(&branch.to_be_bytes())
+            }
+            Instruction::Iinc { index, constant } => {
+                code.push(*index);
+                code.push(*constant as u8);
+            }
+            Instruction::Tableswitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                code.resize(code.len() + switch_padding(offset), 0);
+                code.extend_from_slice(&default.to_be_bytes());
+                code.extend_from_slice(&low.to_be_bytes());
+                code.extend_from_slice(&high.to_be_bytes());
+                for branch in offsets {
+                    code.extend_from_slice(&branch.to_be_bytes());
+                }
+            }
+            Instruction::Lookupswitch { default, pairs } => {
+                code.resize(code.len() + switch_padding(offset), 0);
+                code.extend_from_slice(&default.to_be_bytes());
+                code.extend_from_slice(&(pairs.len() as i32).to_be_bytes());
+                for (key, branch) in pairs {
+                    code.extend_from_slice(&key.to_be_bytes());
+                    code.extend_from_slice(&branch.to_be_bytes());
+                }
+            }
+            Instruction::Invokeinterface { index, count } => {
+                code.extend_from_slice(&index.to_be_bytes());
+                code.push(*count);
+                code.push(0);
+            }
+            Instruction::Invokedynamic(index) => {
+                code.extend_from_slice(&index.to_be_bytes());
+                code.extend_from_slice(&[0, 0]);
+            }
+            Instruction::Multianewarray { index, dimensions } => {
+                code.extend_from_slice(&index.to_be_bytes());
+                code.push(*dimensions);
+            }
+            Instruction::Wide(wide) => {
+                code.push(wide.opcode());
+                code.extend_from_slice(&wide.index().to_be_bytes());
+                if let WideInstruction::Iinc { constant, .. } = wide {
+                    code.extend_from_slice(&constant.to_be_bytes());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+
+        match self {
+            Instruction::Bipush(value) => write!(f, " {}", value),
+            Instruction::Sipush(value) => write!(f, " {}", value),
+            Instruction::Iload(index)
+            | Instruction::Lload(index)
+            | Instruction::Fload(index)
+            | Instruction::Dload(index)
+            | Instruction::Aload(index)
+            | Instruction::Istore(index)
+            | Instruction::Lstore(index)
+            | Instruction::Fstore(index)
+            | Instruction::Dstore(index)
+            | Instruction::Astore(index)
+            | Instruction::Ret(index)
+            | Instruction::Newarray(index) => write!(f, " {}", index),
+            Instruction::Ldc(index) => write!(f, " #{}", index),
+            Instruction::LdcW(index)
+            | Instruction::Ldc2W(index)
+            | Instruction::Getstatic(index)
+            | Instruction::Putstatic(index)
+            | Instruction::Getfield(index)
+            | Instruction::Putfield(index)
+            | Instruction::Invokevirtual(index)
+            | Instruction::Invokespecial(index)
+            | Instruction::Invokestatic(index)
+            | Instruction::New(index)
+            | Instruction::Anewarray(index)
+            | Instruction::Checkcast(index)
+            | Instruction::Instanceof(index)
+            | Instruction::Invokedynamic(index) => write!(f, " #{}", index),
+            Instruction::Ifeq(branch)
+            | Instruction::Ifne(branch)
+            | Instruction::Iflt(branch)
+            | Instruction::Ifge(branch)
+            | Instruction::Ifgt(branch)
+            | Instruction::Ifle(branch)
+            | Instruction::IfIcmpeq(branch)
+            | Instruction::IfIcmpne(branch)
+            | Instruction::IfIcmplt(branch)
+            | Instruction::IfIcmpge(branch)
+            | Instruction::IfIcmpgt(branch)
+            | Instruction::IfIcmple(branch)
+            | Instruction::IfAcmpeq(branch)
+            | Instruction::IfAcmpne(branch)
+            | Instruction::Goto(branch)
+            | Instruction::Jsr(branch)
+            | Instruction::Ifnull(branch)
+            | Instruction::Ifnonnull(branch) => write!(f, " {:+}", branch),
+            Instruction::GotoW(branch) | Instruction::JsrW(branch) => write!(f, " {:+}", branch),
+            Instruction::Iinc { index, constant } => write!(f, " {}, {}", index, constant),
+            Instruction::Tableswitch {
+                default, low, high, ..
+            } => write!(f, " {} to {}, default {:+}", low, high, default),
+            Instruction::Lookupswitch { default, pairs } => {
+                write!(f, " {} pairs, default {:+}", pairs.len(), default)
+            }
+            Instruction::Invokeinterface { index, count } => write!(f, " #{}, {}", index, count),
+            Instruction::Multianewarray { index, dimensions } => {
+                write!(f, " #{}, {}", index, dimensions)
+            }
+            Instruction::Wide(WideInstruction::Iinc { index, constant }) => {
+                write!(f, " iinc {}, {}", index, constant)
+            }
+            Instruction::Wide(wide) => write!(f, " {} {}", wide.mnemonic(), wide.index()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The number of padding bytes following a `tableswitch` or `lookupswitch` opcode at `offset`,
+/// so that its operands start at a multiple of four.
+pub(crate) fn switch_padding(offset: usize) -> usize {
+    3 - (offset % 4)
+}
+
+/// A bounds-checked reader over the operands of the instruction starting at `start`.
+struct Cursor<'a> {
+    code: &'a [u8],
+    start: usize,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// Fail unless there are at least `len` more bytes.
+    fn ensure(&self, len: usize) -> BytecodeResult<()> {
+        match self.pos.checked_add(len) {
+            Some(end) if end <= self.code.len() => Ok(()),
+            _ => Err(BytecodeError::Truncated { offset: self.start }),
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> BytecodeResult<[u8; N]> {
+        self.ensure(N)?;
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.code[self.pos..self.pos + N]);
+        self.pos += N;
+        Ok(bytes)
+    }
+
+    fn skip_padding(&mut self) -> BytecodeResult<()> {
+        let padding = switch_padding(self.start);
+        self.ensure(padding)?;
+        self.pos += padding;
+        Ok(())
+    }
+
+    fn u8(&mut self) -> BytecodeResult<u8> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    fn i8(&mut self) -> BytecodeResult<i8> {
+        Ok(self.u8()? as i8)
+    }
+
+    fn u16(&mut self) -> BytecodeResult<u16> {
+        Ok(u16::from_be_bytes(self.bytes()?))
+    }
+
+    fn i16(&mut self) -> BytecodeResult<i16> {
+        Ok(i16::from_be_bytes(self.bytes()?))
+    }
+
+    fn i32(&mut self) -> BytecodeResult<i32> {
+        Ok(i32::from_be_bytes(self.bytes()?))
+    }
+}
+
+/// An iterator over the instructions in the `code` of a `Code` attribute, yielding each
+/// instruction together with its offset. Iteration stops after the first error.
+pub struct Instructions<'a> {
+    code: &'a [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(code: &'a [u8]) -> Self {
+        Instructions {
+            code,
+            offset: 0,
+            failed: false,
+        }
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = BytecodeResult<(usize, Instruction)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.offset >= self.code.len() {
+            return None;
+        }
+
+        match Instruction::decode(self.code, self.offset) {
+            Ok(instruction) => {
+                let offset = self.offset;
+                self.offset += instruction.size(offset);
+                Some(Ok((offset, instruction)))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Decode all the instructions in `code`, along with their offsets.
+pub fn decode(code: &[u8]) -> BytecodeResult<Vec<(usize, Instruction)>> {
+    Instructions::new(code).collect()
+}
+
+/// Encode `instructions` into the `code` of a `Code` attribute.
+pub fn encode(instructions: &[Instruction]) -> Vec<u8> {
+    let mut code = Vec::new();
+    for instruction in instructions {
+        instruction.encode(&mut code);
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(code: &[u8], offset: usize) -> Instruction {
+        let mut padded = vec![NOP; offset];
+        padded.extend_from_slice(code);
+
+        let instruction = Instruction::decode(&padded, offset).unwrap();
+        assert_eq!(instruction.size(offset), code.len());
+        assert_eq!(instruction.opcode(), code[0]);
+
+        let mut encoded = vec![NOP; offset];
+        instruction.encode(&mut encoded);
+        assert_eq!(&encoded[offset..], code);
+
+        instruction
+    }
+
+    #[test]
+    fn test_round_trip_all_opcodes() {
+        for opcode in 0..=u8::MAX {
+            let code = [opcode, 0x12, 0x34, 0x00, 0x00];
+            match opcode {
+                TABLESWITCH | LOOKUPSWITCH | WIDE => continue,
+                BREAKPOINT..=IMPDEP2 => assert_eq!(
+                    Instruction::decode(&code, 0),
+                    Err(BytecodeError::InvalidOpcode { offset: 0, opcode })
+                ),
+                _ => {
+                    let instruction = Instruction::decode(&code, 0).unwrap();
+                    let size = instruction.size(0);
+                    round_trip(&code[..size], 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_wide() {
+        for opcode in [
+            ILOAD, LLOAD, FLOAD, DLOAD, ALOAD, ISTORE, LSTORE, FSTORE, DSTORE, ASTORE, RET,
+        ] {
+            let instruction = round_trip(&[WIDE, opcode, 0x01, 0x2c], 0);
+            assert!(instruction.to_string().ends_with(" 300"));
+        }
+
+        assert_eq!(
+            round_trip(&[WIDE, IINC, 0x01, 0x2c, 0xff, 0x38], 0),
+            Instruction::Wide(WideInstruction::Iinc {
+                index: 300,
+                constant: -200
+            })
+        );
+        assert_eq!(
+            Instruction::decode(&[WIDE, GOTO, 0x00, 0x01], 0),
+            Err(BytecodeError::InvalidWideOpcode {
+                offset: 0,
+                opcode: GOTO
+            })
+        );
+    }
+
+    #[test]
+    fn test_round_trip_switches() {
+        for offset in 0..4 {
+            let mut code = vec![TABLESWITCH];
+            code.resize(1 + 3 - offset % 4, 0);
+            for value in [20i32, -1, 1, 8, 12, 16] {
+                code.extend_from_slice(&value.to_be_bytes());
+            }
+            assert_eq!(
+                round_trip(&code, offset),
+                Instruction::Tableswitch {
+                    default: 20,
+                    low: -1,
+                    high: 1,
+                    offsets: vec![8, 12, 16],
+                }
+            );
+
+            let mut code = vec![LOOKUPSWITCH];
+            code.resize(1 + 3 - offset % 4, 0);
+            for value in [28i32, 2, -5, 20, 100, 24] {
+                code.extend_from_slice(&value.to_be_bytes());
+            }
+            assert_eq!(
+                round_trip(&code, offset),
+                Instruction::Lookupswitch {
+                    default: 28,
+                    pairs: vec![(-5, 20), (100, 24)],
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_malformed() {
+        assert_eq!(
+            Instruction::decode(&[SIPUSH, 0x01], 0),
+            Err(BytecodeError::Truncated { offset: 0 })
+        );
+        assert_eq!(
+            Instruction::decode(&[INVOKEDYNAMIC, 0x00, 0x01, 0x00, 0x01], 0),
+            Err(BytecodeError::InvalidOperand { offset: 0 })
+        );
+
+        // low > high
+        let mut code = vec![TABLESWITCH, 0, 0, 0];
+        for value in [0i32, 1, 0] {
+            code.extend_from_slice(&value.to_be_bytes());
+        }
+        assert_eq!(
+            Instruction::decode(&code, 0),
+            Err(BytecodeError::InvalidOperand { offset: 0 })
+        );
+
+        // a huge jump table that is not actually there
+        let mut code = vec![TABLESWITCH, 0, 0, 0];
+        for value in [0i32, i32::MIN, i32::MAX] {
+            code.extend_from_slice(&value.to_be_bytes());
+        }
+        assert_eq!(
+            Instruction::decode(&code, 0),
+            Err(BytecodeError::Truncated { offset: 0 })
+        );
+    }
+
+    #[test]
+    fn test_decode_encode() {
+        // aload_0; invokespecial #1; iload_1; ifeq +5; iinc 1, -1; return
+        let code = [
+            0x2a, 0xb7, 0x00, 0x01, 0x1b, 0x99, 0x00, 0x06, 0x84, 0x01, 0xff, 0xb1,
+        ];
+        let instructions = decode(&code).unwrap();
+        assert_eq!(
+            instructions,
+            vec![
+                (0, Instruction::Aload0),
+                (1, Instruction::Invokespecial(1)),
+                (4, Instruction::Iload1),
+                (5, Instruction::Ifeq(6)),
+                (
+                    8,
+                    Instruction::Iinc {
+                        index: 1,
+                        constant: -1
+                    }
+                ),
+                (11, Instruction::Return),
+            ]
+        );
+        assert_eq!(
+            instructions
+                .iter()
+                .map(|(_, instruction)| instruction.to_string())
+                .collect::<Vec<_>>(),
+            [
+                "aload_0",
+                "invokespecial #1",
+                "iload_1",
+                "ifeq +6",
+                "iinc 1, -1",
+                "return"
+            ]
+        );
+
+        let instructions: Vec<_> = instructions.into_iter().map(|(_, i)| i).collect();
+        assert_eq!(encode(&instructions), code);
+    }
+}
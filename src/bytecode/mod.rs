@@ -0,0 +1,10 @@
+//! Typed access to the bytecode in the `code` of a `Code` attribute.
+
+pub mod assembler;
+pub mod instruction;
+pub mod opcodes;
+
+pub use assembler::{AssembleError, AssembledCode, CodeBuilder, Label};
+pub use instruction::{
+    decode, encode, BytecodeError, BytecodeResult, Instruction, Instructions, WideInstruction,
+};
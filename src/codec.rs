@@ -0,0 +1,134 @@
+//! Pluggable support for custom (non-standard) attributes.
+//!
+//! An `AttributeCodec` knows how to decode the raw bytes of a named attribute into a
+//! `CustomAttribute` value, and how to encode it back. Codecs are collected in an
+//! `AttributeRegistry`, which is then handed to the `Deserializer` and the `Serializer`.
+
+use crate::{
+    deserializer::DeserializeResult, model::attributes::CustomAttribute,
+    model::constant_pool::ConstantPool, serializer::SerializeResult,
+};
+use std::collections::HashMap;
+
+/// Decoder and encoder for a single named custom attribute. Codecs must be `Send + Sync` so that
+/// a registry can be shared between threads.
+pub trait AttributeCodec: Send + Sync {
+    /// The name of the attribute, as stored in the `CONSTANT_Utf8_info` entry referred to by
+    /// its `attribute_name_index`.
+    fn name(&self) -> &str;
+
+    /// Decode the raw `info` bytes of the attribute (without the name index and length).
+    fn decode(
+        &self,
+        info: &[u8],
+        constant_pool: &ConstantPool,
+    ) -> DeserializeResult<Box<dyn CustomAttribute>>;
+
+    /// Encode the attribute back into its raw `info` bytes (without the name index and length).
+    fn encode(
+        &self,
+        value: &dyn CustomAttribute,
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<Vec<u8>>;
+}
+
+/// A collection of `AttributeCodec`s, keyed by attribute name.
+#[derive(Default)]
+pub struct AttributeRegistry {
+    codecs: HashMap<String, Box<dyn AttributeCodec>>,
+}
+
+impl AttributeRegistry {
+    pub fn new() -> Self {
+        AttributeRegistry::default()
+    }
+
+    /// Register a codec. Any codec previously registered under the same name is replaced.
+    pub fn register<C: AttributeCodec + 'static>(&mut self, codec: C) -> &mut Self {
+        self.codecs.insert(codec.name().to_owned(), Box::new(codec));
+        self
+    }
+
+    /// Get the codec registered for the given attribute name.
+    pub fn get(&self, name: &str) -> Option<&dyn AttributeCodec> {
+        self.codecs.get(name).map(|codec| codec.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        deserializer::Deserializer,
+        error::SerializeError,
+        rw::{reader::Reader, writer::Writer},
+        serializer::Serializer,
+    };
+    use std::{io::Cursor, sync::Arc, thread};
+
+    #[derive(Debug, PartialEq)]
+    struct Marker(u8);
+
+    struct MarkerCodec;
+
+    impl AttributeCodec for MarkerCodec {
+        fn name(&self) -> &str {
+            "Marker"
+        }
+
+        fn decode(
+            &self,
+            info: &[u8],
+            _constant_pool: &ConstantPool,
+        ) -> DeserializeResult<Box<dyn CustomAttribute>> {
+            Ok(Box::new(Marker(info[0])))
+        }
+
+        fn encode(
+            &self,
+            value: &dyn CustomAttribute,
+            _constant_pool: &ConstantPool,
+        ) -> SerializeResult<Vec<u8>> {
+            match value.downcast_ref::<Marker>() {
+                Some(Marker(b)) => Ok(vec![*b]),
+                None => Err(SerializeError::new("not a Marker attribute".to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_register_and_get() {
+        let mut registry = AttributeRegistry::new();
+        registry.register(MarkerCodec);
+
+        assert!(registry.get("Unregistered").is_none());
+
+        let codec = registry.get("Marker").unwrap();
+        let value = codec.decode(&[42], &ConstantPool::new()).unwrap();
+        assert_eq!(value.downcast_ref::<Marker>(), Some(&Marker(42)));
+        assert_eq!(
+            codec.encode(value.as_ref(), &ConstantPool::new()).unwrap(),
+            vec![42]
+        );
+    }
+
+    #[test]
+    fn test_registry_shared_between_threads() {
+        let mut registry = AttributeRegistry::new();
+        registry.register(MarkerCodec);
+        let registry = Arc::new(registry);
+
+        let deserializer = Deserializer::new(Reader::new(Cursor::new(Vec::new())))
+            .with_attribute_registry(Arc::clone(&registry));
+        thread::spawn(move || drop(deserializer)).join().unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let serializer = Serializer::new(Writer::new(&mut bytes))
+                .with_attribute_registry(Arc::clone(&registry));
+            drop(serializer);
+            registry.get("Marker").is_some()
+        });
+        assert!(handle.join().unwrap());
+    }
+}
@@ -0,0 +1,481 @@
+//! Field and method descriptors (JVMS 4.3), the strings like `[Ljava/lang/String;` and
+//! `(IJ)V` referenced by the `descriptor_index` of fields, methods and constant pool entries.
+//!
+//! Descriptors are parsed with `FieldType::parse` and `MethodDescriptor::parse` (or `str::parse`),
+//! and printed back with their `Display` implementations.
+
+use std::{error::Error, fmt, str::FromStr};
+
+/// The maximum number of dimensions of an array type.
+pub const MAX_ARRAY_DIMENSIONS: usize = 255;
+
+/// The maximum number of local variable slots taken by the parameters of a method, including
+/// `this` for instance methods.
+pub const MAX_PARAMETER_SLOTS: u16 = 255;
+
+pub type DescriptorResult<T> = Result<T, DescriptorError>;
+
+/// The different kinds of malformed field and method descriptors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DescriptorErrorKind {
+    /// The descriptor ends in the middle of a type.
+    UnexpectedEnd,
+    /// The character at byte `offset` cannot appear there.
+    UnexpectedCharacter { offset: usize, found: char },
+    /// The class name of the object type starting at byte `offset` is malformed.
+    InvalidClassName { offset: usize },
+    /// The array type starting at byte `offset` has more than 255 dimensions.
+    TooManyDimensions { offset: usize },
+    /// The parameters of the method take more than 255 local variable slots.
+    TooManyParameterSlots,
+    /// An instance or class initialization method does not return `void`.
+    NonVoidInitializer,
+}
+
+impl fmt::Display for DescriptorErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DescriptorErrorKind::UnexpectedEnd => write!(f, "unexpected end"),
+            DescriptorErrorKind::UnexpectedCharacter { offset, found } => {
+                write!(f, "unexpected character {:?} at index {}", found, offset)
+            }
+            DescriptorErrorKind::InvalidClassName { offset } => {
+                write!(f, "invalid class name at index {}", offset)
+            }
+            DescriptorErrorKind::TooManyDimensions { offset } => {
+                write!(f, "too many array dimensions at index {}", offset)
+            }
+            DescriptorErrorKind::TooManyParameterSlots => {
+                write!(f, "parameters take more than 255 slots")
+            }
+            DescriptorErrorKind::NonVoidInitializer => {
+                write!(f, "initialization methods must return void")
+            }
+        }
+    }
+}
+
+/// Error type for malformed field and method descriptors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescriptorError {
+    descriptor: String,
+    kind: DescriptorErrorKind,
+}
+
+impl DescriptorError {
+    pub fn new(descriptor: &str, kind: DescriptorErrorKind) -> Self {
+        DescriptorError {
+            descriptor: descriptor.to_string(),
+            kind,
+        }
+    }
+
+    /// The malformed descriptor.
+    pub fn descriptor(&self) -> &str {
+        &self.descriptor
+    }
+
+    pub fn kind(&self) -> &DescriptorErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for DescriptorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid descriptor {:?}: {}", self.descriptor, self.kind)
+    }
+}
+
+impl Error for DescriptorError {}
+
+/// The type of a field, local variable, parameter or return value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    /// An instance of the class with the given internal name, e.g. `java/lang/String`.
+    Object(String),
+    /// An array with elements of the given type.
+    Array(Box<FieldType>),
+}
+
+impl FieldType {
+    /// Parse the field descriptor `descriptor`, e.g. `[Ljava/lang/String;`.
+    pub fn parse(descriptor: &str) -> DescriptorResult<FieldType> {
+        let mut parser = Parser {
+            descriptor,
+            offset: 0,
+        };
+        let ty = parser.field_type()?;
+        parser.end()?;
+        Ok(ty)
+    }
+
+    /// Return the type of instances of the class `name`.
+    pub fn object(name: &str) -> FieldType {
+        FieldType::Object(name.to_string())
+    }
+
+    /// Return the type of arrays with elements of type `element`.
+    pub fn array(element: FieldType) -> FieldType {
+        FieldType::Array(Box::new(element))
+    }
+
+    /// The number of local variable or operand stack slots taken by a value of this type.
+    pub fn slots(&self) -> u16 {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+
+    /// The number of dimensions of this type, or 0 if it is not an array type.
+    pub fn dimensions(&self) -> usize {
+        let mut dimensions = 0;
+        let mut ty = self;
+        while let FieldType::Array(element) = ty {
+            dimensions += 1;
+            ty = element;
+        }
+        dimensions
+    }
+
+    /// Return the name of this type as used in `CONSTANT_Class_info` entries: the internal name
+    /// of a class, or the descriptor of an array type. Primitive types have no such name.
+    pub fn class_name(&self) -> Option<String> {
+        match self {
+            FieldType::Object(name) => Some(name.clone()),
+            FieldType::Array(_) => Some(self.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = DescriptorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FieldType::parse(s)
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(name) => write!(f, "L{};", name),
+            FieldType::Array(element) => write!(f, "[{}", element),
+        }
+    }
+}
+
+/// The parameter and return types of a method.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    /// The return type, or `None` for `void` methods.
+    pub return_type: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+    pub fn new(parameters: Vec<FieldType>, return_type: Option<FieldType>) -> Self {
+        MethodDescriptor {
+            parameters,
+            return_type,
+        }
+    }
+
+    /// Parse the method descriptor `descriptor`, e.g. `([Ljava/lang/String;I)V`. Descriptors
+    /// whose parameters take more than 255 slots are rejected.
+    pub fn parse(descriptor: &str) -> DescriptorResult<MethodDescriptor> {
+        let mut parser = Parser {
+            descriptor,
+            offset: 0,
+        };
+        parser.expect('(')?;
+
+        let mut parameters = Vec::new();
+        let mut slots = 0;
+        while parser.peek() != Some(')') {
+            let ty = parser.field_type()?;
+            slots += ty.slots();
+            if slots > MAX_PARAMETER_SLOTS {
+                return Err(parser.error(DescriptorErrorKind::TooManyParameterSlots));
+            }
+            parameters.push(ty);
+        }
+        parser.expect(')')?;
+
+        let return_type = if parser.peek() == Some('V') {
+            parser.offset += 1;
+            None
+        } else {
+            Some(parser.field_type()?)
+        };
+        parser.end()?;
+
+        Ok(MethodDescriptor {
+            parameters,
+            return_type,
+        })
+    }
+
+    /// The number of local variable slots taken by the parameters, excluding `this`.
+    pub fn parameter_slots(&self) -> u16 {
+        self.parameters.iter().map(FieldType::slots).sum()
+    }
+
+    /// The number of operand stack slots taken by the return value.
+    pub fn return_slots(&self) -> u16 {
+        self.return_type.as_ref().map_or(0, FieldType::slots)
+    }
+}
+
+impl FromStr for MethodDescriptor {
+    type Err = DescriptorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MethodDescriptor::parse(s)
+    }
+}
+
+impl fmt::Display for MethodDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        match &self.return_type {
+            Some(return_type) => write!(f, "){}", return_type),
+            None => write!(f, ")V"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    descriptor: &'a str,
+    /// The offset of the next character in `descriptor`.
+    offset: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, kind: DescriptorErrorKind) -> DescriptorError {
+        DescriptorError::new(self.descriptor, kind)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.descriptor[self.offset..].chars().next()
+    }
+
+    /// Consume the next character, failing if there is none.
+    fn next(&mut self) -> DescriptorResult<char> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error(DescriptorErrorKind::UnexpectedEnd))?;
+        self.offset += c.len_utf8();
+        Ok(c)
+    }
+
+    fn unexpected(&self, offset: usize, found: char) -> DescriptorError {
+        self.error(DescriptorErrorKind::UnexpectedCharacter { offset, found })
+    }
+
+    fn expect(&mut self, expected: char) -> DescriptorResult<()> {
+        let offset = self.offset;
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(self.unexpected(offset, c)),
+        }
+    }
+
+    /// Fail if there are characters left.
+    fn end(&self) -> DescriptorResult<()> {
+        match self.peek() {
+            Some(c) => Err(self.unexpected(self.offset, c)),
+            None => Ok(()),
+        }
+    }
+
+    fn field_type(&mut self) -> DescriptorResult<FieldType> {
+        let start = self.offset;
+        let mut dimensions = 0;
+        while self.peek() == Some('[') {
+            self.offset += 1;
+            dimensions += 1;
+        }
+        if dimensions > MAX_ARRAY_DIMENSIONS {
+            return Err(self.error(DescriptorErrorKind::TooManyDimensions { offset: start }));
+        }
+
+        let offset = self.offset;
+        let mut ty = match self.next()? {
+            'B' => FieldType::Byte,
+            'C' => FieldType::Char,
+            'D' => FieldType::Double,
+            'F' => FieldType::Float,
+            'I' => FieldType::Int,
+            'J' => FieldType::Long,
+            'S' => FieldType::Short,
+            'Z' => FieldType::Boolean,
+            'L' => {
+                let rest = &self.descriptor[self.offset..];
+                let end = rest
+                    .find(';')
+                    .ok_or_else(|| self.error(DescriptorErrorKind::UnexpectedEnd))?;
+                let name = &rest[..end];
+                if !is_class_name(name) {
+                    return Err(self.error(DescriptorErrorKind::InvalidClassName { offset }));
+                }
+                self.offset += end + 1;
+                FieldType::object(name)
+            }
+            c => return Err(self.unexpected(offset, c)),
+        };
+
+        for _ in 0..dimensions {
+            ty = FieldType::array(ty);
+        }
+        Ok(ty)
+    }
+}
+
+/// Return whether `name` is a binary class name in internal form: non-empty identifiers
+/// separated by `/`, without `.`, `;` or `[` (JVMS 4.2.1).
+fn is_class_name(name: &str) -> bool {
+    name.split('/')
+        .all(|part| !part.is_empty() && !part.contains(['.', ';', '[']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_print() {
+        let descriptor = "([Ljava/lang/String;IJ[[D)Ljava/lang/Object;";
+        let method = MethodDescriptor::parse(descriptor).unwrap();
+        assert_eq!(
+            method,
+            MethodDescriptor::new(
+                vec![
+                    FieldType::array(FieldType::object("java/lang/String")),
+                    FieldType::Int,
+                    FieldType::Long,
+                    FieldType::array(FieldType::array(FieldType::Double)),
+                ],
+                Some(FieldType::object("java/lang/Object")),
+            )
+        );
+        assert_eq!(method.to_string(), descriptor);
+        assert_eq!(method.parameter_slots(), 5);
+        assert_eq!(method.return_slots(), 1);
+        assert_eq!(method.parameters[3].dimensions(), 2);
+        assert_eq!(
+            method.parameters[0].class_name().as_deref(),
+            Some("[Ljava/lang/String;")
+        );
+
+        let void = "()V".parse::<MethodDescriptor>().unwrap();
+        assert_eq!(void, MethodDescriptor::default());
+        assert_eq!(void.to_string(), "()V");
+        assert_eq!(void.return_slots(), 0);
+
+        for descriptor in ["I", "J", "[Z", "Ljava/util/Map$Entry;", "[[[Lé;"] {
+            let ty = FieldType::parse(descriptor).unwrap();
+            assert_eq!(ty.to_string(), descriptor);
+        }
+        assert_eq!(FieldType::Double.slots(), 2);
+        assert_eq!(FieldType::array(FieldType::Long).slots(), 1);
+    }
+
+    #[test]
+    fn test_errors() {
+        use DescriptorErrorKind::*;
+
+        let kind = |result: DescriptorResult<FieldType>| result.unwrap_err().kind().clone();
+        assert_eq!(kind(FieldType::parse("")), UnexpectedEnd);
+        assert_eq!(
+            kind(FieldType::parse("V")),
+            UnexpectedCharacter {
+                offset: 0,
+                found: 'V'
+            }
+        );
+        assert_eq!(
+            kind(FieldType::parse("II")),
+            UnexpectedCharacter {
+                offset: 1,
+                found: 'I'
+            }
+        );
+        assert_eq!(kind(FieldType::parse("[[Ljava/lang/String")), UnexpectedEnd);
+        assert_eq!(
+            kind(FieldType::parse("[L;")),
+            InvalidClassName { offset: 1 }
+        );
+        assert_eq!(
+            kind(FieldType::parse("Ljava.lang.String;")),
+            InvalidClassName { offset: 0 }
+        );
+        assert_eq!(
+            kind(FieldType::parse(&format!("{}I", "[".repeat(256)))),
+            TooManyDimensions { offset: 0 }
+        );
+        assert!(FieldType::parse(&format!("{}I", "[".repeat(255))).is_ok());
+
+        let kind = |result: DescriptorResult<MethodDescriptor>| result.unwrap_err().kind().clone();
+        assert_eq!(
+            kind(MethodDescriptor::parse("(V)V")),
+            UnexpectedCharacter {
+                offset: 1,
+                found: 'V'
+            }
+        );
+        assert_eq!(
+            kind(MethodDescriptor::parse("I)V")),
+            UnexpectedCharacter {
+                offset: 0,
+                found: 'I'
+            }
+        );
+        assert_eq!(kind(MethodDescriptor::parse("(I")), UnexpectedEnd);
+        assert_eq!(kind(MethodDescriptor::parse("()")), UnexpectedEnd);
+        assert_eq!(
+            kind(MethodDescriptor::parse("()VV")),
+            UnexpectedCharacter {
+                offset: 3,
+                found: 'V'
+            }
+        );
+        assert_eq!(
+            kind(MethodDescriptor::parse(&format!("({})V", "J".repeat(128)))),
+            TooManyParameterSlots
+        );
+        assert!(MethodDescriptor::parse(&format!("({}I)V", "J".repeat(127))).is_ok());
+
+        let err = MethodDescriptor::parse("(I;)V").unwrap_err();
+        assert_eq!(err.descriptor(), "(I;)V");
+        assert_eq!(
+            err.to_string(),
+            "invalid descriptor \"(I;)V\": unexpected character ';' at index 2"
+        );
+    }
+}
@@ -1,27 +1,158 @@
 //! Module to read a Java (JVM) class file and construct the object model from the raw bytes.
 
 use crate::{
-    error::DeserializeError,
+    bytecode::Instructions,
+    codec::AttributeRegistry,
+    error::{DeserializeError, DeserializeErrorKind, PathSegment},
     model::{
         attributes::*,
-        constant_pool::{tags::*, types::CpInfo},
+        constant_pool::{tags::*, types::CpInfo, ConstantPool},
         ClassFile, FieldInfo, MethodInfo,
     },
-    rw::reader::Reader,
+    rw::reader::{ByteReader, Reader},
+    visitor::{AttributeOwner, ClassVisitor},
 };
 use std::io::Read;
+use std::sync::Arc;
 
 pub type DeserializeResult<T> = Result<T, DeserializeError>;
 
+/// The maximum depth to which attributes (e.g., `Code` attributes inside `Code` attributes) and
+/// annotation element values may be nested before the class file is rejected.
+const MAX_NESTING_DEPTH: usize = 128;
+
+/// The magic number every class file starts with.
+pub const MAGIC: u32 = 0xCAFEBABE;
+
+/// The `minor_version` of class files that depend on preview features of their Java SE release.
+pub const PREVIEW_MINOR_VERSION: u16 = 0xFFFF;
+
+/// The range of class file versions accepted by the deserializer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupportedVersions {
+    /// The lowest accepted `major_version`.
+    pub min_major: u16,
+    /// The highest accepted `major_version`.
+    pub max_major: u16,
+    /// Whether to accept class files with a `minor_version` of `0xFFFF`, i.e., those compiled
+    /// with preview features enabled.
+    pub allow_preview: bool,
+}
+
+impl SupportedVersions {
+    /// Check whether the given class file version is accepted.
+    ///
+    /// From Java SE 12 (major version 56) onwards, the minor version must be either 0 or, for
+    /// preview features, 0xFFFF. Before that, any minor version is valid.
+    pub fn accepts(&self, major_version: u16, minor_version: u16) -> bool {
+        if major_version < self.min_major || major_version > self.max_major {
+            return false;
+        }
+
+        if major_version >= 56 {
+            match minor_version {
+                0 => true,
+                PREVIEW_MINOR_VERSION => self.allow_preview,
+                _ => false,
+            }
+        } else {
+            true
+        }
+    }
+}
+
+impl Default for SupportedVersions {
+    /// JDK 1.0.2 (45) through Java SE 25 (69), without preview features.
+    fn default() -> Self {
+        SupportedVersions {
+            min_major: 45,
+            max_major: 69,
+            allow_preview: false,
+        }
+    }
+}
+
+/// The items of a class file up to and including the constant pool.
+struct Header {
+    magic: u32,
+    minor_version: u16,
+    major_version: u16,
+    constant_pool_count: u16,
+    constant_pool: ConstantPool,
+}
+
 /// The Deserializer reads a class file byte stream and converts it into the
 /// object model repreensting the class file.
 pub struct Deserializer<R: Read> {
     reader: Reader<R>,
+    registry: Option<Arc<AttributeRegistry>>,
+    path: Vec<PathSegment>,
+    depth: usize,
+    versions: SupportedVersions,
 }
 
 impl<R: Read> Deserializer<R> {
     pub fn new(reader: Reader<R>) -> Self {
-        Deserializer { reader }
+        Deserializer {
+            reader,
+            registry: None,
+            path: Vec::new(),
+            depth: 0,
+            versions: SupportedVersions::default(),
+        }
+    }
+
+    /// Accept only class files whose version lies in `versions`. Defaults to
+    /// `SupportedVersions::default()`.
+    pub fn with_supported_versions(mut self, versions: SupportedVersions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Use the codecs in `registry` to decode custom attributes. Attributes that are neither
+    /// predefined nor registered are still preserved as `AttributeInfo::Unknown`.
+    pub fn with_attribute_registry(mut self, registry: Arc<AttributeRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Record errors as occurring inside the structure at `path`, e.g. when deserializing an
+    /// attribute of a method on its own.
+    pub(crate) fn with_path(mut self, path: Vec<PathSegment>) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// Run `f` with `segment` appended to the structural path, so that any error raised while
+    /// deserializing that part of the class file records where it occurred.
+    fn scoped<T>(
+        &mut self,
+        segment: PathSegment,
+        f: impl FnOnce(&mut Self) -> DeserializeResult<T>,
+    ) -> DeserializeResult<T> {
+        self.path.push(segment);
+        let res = f(self).map_err(|err| err.with_path(&self.path));
+        self.path.pop();
+        res
+    }
+
+    /// Run `f` one nesting level deeper, failing instead of overflowing the stack on
+    /// pathologically nested input.
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> DeserializeResult<T>,
+    ) -> DeserializeResult<T> {
+        if self.depth >= MAX_NESTING_DEPTH {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::NestingTooDeep(MAX_NESTING_DEPTH),
+                self.reader.position(),
+            ));
+        }
+
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        res
     }
 
     fn deserialize_target_info(&mut self, target_type: u8) -> DeserializeResult<TargetInfo> {
@@ -48,7 +179,7 @@ impl<R: Read> Deserializer<R> {
                 }
             }
 
-            0x13 | 0x14 | 0x15 => TargetInfo::EmptyTarget,
+            0x13..=0x15 => TargetInfo::EmptyTarget,
             0x16 => {
                 let formal_parameter_index = self.reader.read_unsigned_byte()?;
                 TargetInfo::FormalParameterTarget {
@@ -86,12 +217,12 @@ impl<R: Read> Deserializer<R> {
                     exception_table_index,
                 }
             }
-            043 | 0x44 | 0x45 | 0x46 => {
+            0x43..=0x46 => {
                 let offset = self.reader.read_unsigned_short()?;
                 TargetInfo::OffsetTarget { offset }
             }
 
-            0x47 | 0x48 | 0x49 | 0x4A | 0x4B => {
+            0x47..=0x4B => {
                 let offset = self.reader.read_unsigned_short()?;
                 let type_argument_index = self.reader.read_unsigned_byte()?;
                 TargetInfo::TypeArgumentTarget {
@@ -99,7 +230,13 @@ impl<R: Read> Deserializer<R> {
                     type_argument_index,
                 }
             }
-            _ => unreachable!(),
+            // the target type byte has just been read by the caller
+            _ => {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidTargetType(target_type),
+                    self.reader.position() - 1,
+                ))
+            }
         };
 
         Ok(target_info)
@@ -144,6 +281,7 @@ impl<R: Read> Deserializer<R> {
     }
 
     fn deserialize_element_value(&mut self) -> DeserializeResult<ElementValue> {
+        let offset = self.reader.position();
         let tag = self.reader.read_unsigned_byte()?;
         let element_value = match tag {
             // byte
@@ -173,7 +311,7 @@ impl<R: Read> Deserializer<R> {
             }
 
             b'@' => {
-                let annotation = self.deserialize_annotation()?;
+                let annotation = self.nested(|this| this.deserialize_annotation())?;
                 ElementValue::AnnotationValue { tag, annotation }
             }
 
@@ -181,7 +319,7 @@ impl<R: Read> Deserializer<R> {
                 let num_values = self.reader.read_unsigned_short()?;
                 let mut values = Vec::new();
                 for _ in 0..num_values {
-                    values.push(self.deserialize_element_value()?);
+                    values.push(self.nested(|this| this.deserialize_element_value())?);
                 }
 
                 ElementValue::ArrayValue {
@@ -190,7 +328,12 @@ impl<R: Read> Deserializer<R> {
                     values,
                 }
             }
-            _ => unreachable!(),
+            _ => {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidElementValueTag(tag),
+                    offset,
+                ))
+            }
         };
 
         Ok(element_value)
@@ -223,6 +366,7 @@ impl<R: Read> Deserializer<R> {
     }
 
     fn deserialize_verification_type_info(&mut self) -> DeserializeResult<VerificationTypeInfo> {
+        let offset = self.reader.position();
         let tag = self.reader.read_unsigned_byte()?;
         let ver_type_info = match tag {
             0x00 => VerificationTypeInfo::TopVariableInfo { tag },
@@ -240,973 +384,1077 @@ impl<R: Read> Deserializer<R> {
                 let offset = self.reader.read_unsigned_short()?;
                 VerificationTypeInfo::UninitializedVariableInfo { tag, offset }
             }
-            _ => unreachable!(),
+            _ => {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidVerificationTypeTag(tag),
+                    offset,
+                ))
+            }
         };
 
         Ok(ver_type_info)
     }
 
+    fn deserialize_stack_map_frame(&mut self) -> DeserializeResult<StackMapFrame> {
+        let offset = self.reader.position();
+        let frame_type = self.reader.read_unsigned_byte()?;
+
+        let frame = match frame_type {
+            // 0 - 63
+            0x00..=0x3f => StackMapFrame::SameFrame { frame_type },
+            // 64 - 127
+            0x40..=0x7f => {
+                let stack = vec![self.deserialize_verification_type_info()?];
+                StackMapFrame::SameLocals1StackItemFrame { frame_type, stack }
+            }
+
+            // 128 - 246 are reserved
+            0x80..=0xf6 => {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidStackMapFrameType(frame_type),
+                    offset,
+                ))
+            }
+
+            0xf7 => {
+                let offset_delta = self.reader.read_unsigned_short()?;
+                let stack = vec![self.deserialize_verification_type_info()?];
+
+                StackMapFrame::SameLocals1StackItemFrameExtended {
+                    frame_type,
+                    offset_delta,
+                    stack,
+                }
+            }
+
+            // 248 - 250
+            0xf8..=0xfa => {
+                let offset_delta = self.reader.read_unsigned_short()?;
+                StackMapFrame::ChopFrame {
+                    frame_type,
+                    offset_delta,
+                }
+            }
+
+            // 251
+            0xfb => {
+                let offset_delta = self.reader.read_unsigned_short()?;
+                StackMapFrame::SameFrameExtended {
+                    frame_type,
+                    offset_delta,
+                }
+            }
+
+            0xfc..=0xfe => {
+                let offset_delta = self.reader.read_unsigned_short()?;
+
+                let mut locals = Vec::with_capacity((frame_type - 251) as usize);
+                for _ in 0..frame_type - 251 {
+                    locals.push(self.deserialize_verification_type_info()?);
+                }
+
+                StackMapFrame::AppendFrame {
+                    frame_type,
+                    offset_delta,
+                    locals,
+                }
+            }
+
+            // 255
+            0xff => {
+                let offset_delta = self.reader.read_unsigned_short()?;
+                let number_of_locals = self.reader.read_unsigned_short()?;
+
+                let mut locals = Vec::with_capacity(number_of_locals as usize);
+                for _ in 0..number_of_locals {
+                    locals.push(self.deserialize_verification_type_info()?);
+                }
+
+                let number_of_stack_items = self.reader.read_unsigned_short()?;
+                let mut stack = Vec::with_capacity(number_of_stack_items as usize);
+                for _ in 0..number_of_stack_items {
+                    stack.push(self.deserialize_verification_type_info()?);
+                }
+
+                StackMapFrame::FullFrame {
+                    frame_type,
+                    offset_delta,
+                    number_of_locals,
+                    locals,
+                    number_of_stack_items,
+                    stack,
+                }
+            }
+        };
+
+        Ok(frame)
+    }
+
     /// Deserialize the attributes of the class file.
     fn deserialize_attributes(
         &mut self,
         attributes_count: u16,
-        constant_pool: &Vec<Option<CpInfo>>,
+        constant_pool: &ConstantPool,
     ) -> DeserializeResult<Vec<AttributeInfo>> {
-        let mut attributes = Vec::new();
+        let mut attributes = Vec::with_capacity(attributes_count as usize);
 
         for _ in 0..attributes_count {
+            let offset = self.reader.position();
             let attribute_name_index = self.reader.read_unsigned_short()?;
             let attribute_length = self.reader.read_unsigned_int()?;
 
-            match &constant_pool[attribute_name_index as usize] {
-                Some(CpInfo::ConstantUtf8Info { bytes, .. }) => {
-                    match String::from_utf8_lossy(bytes).into_owned().as_str() {
-                        predefined_attributes::SOURCE_FILE => {
-                            let sourcefile_index = self.reader.read_unsigned_short()?;
-                            attributes.push(AttributeInfo::SourceFile {
-                                attribute_name_index,
-                                attribute_length,
-                                sourcefile_index,
-                            });
-                        }
+            let attribute = self.deserialize_attribute_info(
+                offset,
+                attribute_name_index,
+                attribute_length,
+                constant_pool,
+            )?;
+            attributes.push(attribute);
+        }
 
-                        predefined_attributes::CONSTANT_VALUE => {
-                            let constantvalue_index = self.reader.read_unsigned_short()?;
-                            attributes.push(AttributeInfo::ConstantValue {
-                                attribute_name_index,
-                                attribute_length,
-                                constantvalue_index,
-                            });
-                        }
+        Ok(attributes)
+    }
 
-                        predefined_attributes::CODE => {
-                            let max_stack = self.reader.read_unsigned_short()?;
-                            let max_locals = self.reader.read_unsigned_short()?;
-
-                            let code_length = self.reader.read_unsigned_int()?;
-                            assert!(code_length > 0);
-                            let mut code = Vec::new();
-                            for _ in 0..code_length {
-                                code.push(self.reader.read_unsigned_byte()?);
-                            }
-
-                            let exception_table_length = self.reader.read_unsigned_short()?;
-                            let mut exception_table = Vec::new();
-                            for _ in 0..exception_table_length {
-                                let start_pc = self.reader.read_unsigned_short()?;
-                                let end_pc = self.reader.read_unsigned_short()?;
-                                let handler_pc = self.reader.read_unsigned_short()?;
-                                let mut catch_type = self.reader.read_unsigned_short()?;
-
-                                if catch_type != 0 {
-                                    catch_type -= 1;
-                                }
-
-                                exception_table.push(ExceptionHandler {
-                                    start_pc,
-                                    end_pc,
-                                    handler_pc,
-                                    catch_type,
-                                });
-                            }
-
-                            let code_attributes_count = self.reader.read_unsigned_short()?;
-                            let code_attributes =
-                                self.deserialize_attributes(code_attributes_count, constant_pool)?;
-                            attributes.push(AttributeInfo::Code {
-                                attribute_name_index,
-                                attribute_length,
-                                max_stack,
-                                max_locals,
-                                code_length,
-                                code,
-                                exception_table_length,
-                                exception_table,
-                                code_attributes_count,
-                                code_attributes,
-                            });
-                        }
+    /// Deserialize the info of an attribute, whose name index and length, starting at `offset`,
+    /// have already been read.
+    pub(crate) fn deserialize_attribute_info(
+        &mut self,
+        offset: usize,
+        attribute_name_index: u16,
+        attribute_length: u32,
+        constant_pool: &ConstantPool,
+    ) -> DeserializeResult<AttributeInfo> {
+        let attribute_name = match constant_pool.get_utf8(attribute_name_index) {
+            Ok(attribute_name) => attribute_name.into_owned(),
+            Err(_) => {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidAttributeNameIndex(attribute_name_index),
+                    offset,
+                ))
+            }
+        };
 
-                        predefined_attributes::EXCEPTIONS => {
-                            let number_of_exceptions = self.reader.read_unsigned_short()?;
-                            let mut exception_index_table =
-                                Vec::with_capacity(number_of_exceptions as usize);
-
-                            for _ in 0..number_of_exceptions {
-                                let mut idx = self.reader.read_unsigned_short()?;
-                                if idx != 0 {
-                                    idx -= 1;
-                                }
-                                exception_index_table.push(idx);
-                            }
-                            attributes.push(AttributeInfo::Exceptions {
-                                attribute_name_index,
-                                attribute_length,
-                                number_of_exceptions,
-                                exception_index_table,
-                            });
-                        }
+        self.scoped(PathSegment::Attribute(attribute_name.clone()), |this| {
+            let start = this.reader.position();
+            let attribute = this.nested(|this| {
+                this.deserialize_attribute(
+                    &attribute_name,
+                    attribute_name_index,
+                    attribute_length,
+                    constant_pool,
+                )
+            })?;
+
+            // a body that is shorter or longer than its attribute_length would leave the reader
+            // out of step with the rest of the class file
+            let actual = this.reader.position() - start;
+            if actual != attribute_length as usize {
+                return Err(DeserializeError::at(
+                    DeserializeErrorKind::InvalidAttributeLength {
+                        attribute_length,
+                        actual,
+                    },
+                    offset,
+                ));
+            }
 
-                        predefined_attributes::LINE_NUMBER_TABLE => {
-                            let line_number_table_length = self.reader.read_unsigned_short()?;
-                            let mut line_number_table =
-                                Vec::with_capacity(line_number_table_length as usize);
-
-                            for _ in 0..line_number_table_length {
-                                let start_pc = self.reader.read_unsigned_short()?;
-                                let line_number = self.reader.read_unsigned_short()?;
-                                line_number_table.push(LineNumber {
-                                    start_pc,
-                                    line_number,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::LineNumberTable {
-                                attribute_name_index,
-                                attribute_length,
-                                line_number_table_length,
-                                line_number_table,
-                            });
-                        }
+            Ok(attribute)
+        })
+    }
 
-                        predefined_attributes::LOCAL_VARIABLE_TABLE => {
-                            let local_variable_table_length = self.reader.read_unsigned_short()?;
-                            let mut local_variable_table =
-                                Vec::with_capacity(local_variable_table_length as usize);
-
-                            for _ in 0..local_variable_table_length {
-                                let start_pc = self.reader.read_unsigned_short()?;
-                                let length = self.reader.read_unsigned_short()?;
-                                let name_index = self.reader.read_unsigned_short()?;
-                                let descriptor_index = self.reader.read_unsigned_short()?;
-                                let index = self.reader.read_unsigned_short()?;
-
-                                local_variable_table.push(LocalVariable {
-                                    start_pc,
-                                    length,
-                                    name_index,
-                                    descriptor_index,
-                                    index,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::LocalVariableTable {
-                                attribute_name_index,
-                                attribute_length,
-                                local_variable_table_length,
-                                local_variable_table,
-                            });
-                        }
+    /// Deserialize the body of a single attribute, i.e., everything after its name index and
+    /// length.
+    fn deserialize_attribute(
+        &mut self,
+        attribute_name: &str,
+        attribute_name_index: u16,
+        attribute_length: u32,
+        constant_pool: &ConstantPool,
+    ) -> DeserializeResult<AttributeInfo> {
+        let attribute = match attribute_name {
+            predefined_attributes::SOURCE_FILE => {
+                let sourcefile_index = self.reader.read_unsigned_short()?;
+                AttributeInfo::SourceFile {
+                    attribute_name_index,
+                    attribute_length,
+                    sourcefile_index,
+                }
+            }
 
-                        predefined_attributes::STACK_MAP_TABLE => {
-                            let number_of_entries = self.reader.read_unsigned_short()?;
-                            let mut entries = Vec::new();
-                            for _ in 0..number_of_entries {
-                                let frame_type = self.reader.read_unsigned_byte()?;
-
-                                let frame = match frame_type {
-                                    // 0 - 63
-                                    0x00..=0x3f => StackMapFrame::SameFrame { frame_type },
-                                    // 64 - 127
-                                    0x40..=0x7f => {
-                                        let mut stack = Vec::with_capacity(1);
-                                        stack.push(self.deserialize_verification_type_info()?);
-                                        StackMapFrame::SameLocals1StackItemFrame {
-                                            frame_type,
-                                            stack,
-                                        }
-                                    }
-
-                                    // 128 - 246 are reserved
-                                    0x80..=0xf6 => unreachable!(),
-
-                                    0xf7 => {
-                                        let offset_delta = self.reader.read_unsigned_short()?;
-                                        let mut stack = Vec::with_capacity(1);
-                                        stack.push(self.deserialize_verification_type_info()?);
-
-                                        StackMapFrame::SameLocals1StackItemFrameExtended {
-                                            frame_type,
-                                            offset_delta,
-                                            stack,
-                                        }
-                                    }
-
-                                    // 248 - 250
-                                    0xf8..=0xfa => {
-                                        let offset_delta = self.reader.read_unsigned_short()?;
-                                        StackMapFrame::ChopFrame {
-                                            frame_type,
-                                            offset_delta,
-                                        }
-                                    }
-
-                                    // 251
-                                    0xfb => {
-                                        let offset_delta = self.reader.read_unsigned_short()?;
-                                        StackMapFrame::SameFrameExtended {
-                                            frame_type,
-                                            offset_delta,
-                                        }
-                                    }
-
-                                    0xfc..=0xfe => {
-                                        let offset_delta = self.reader.read_unsigned_short()?;
-                                        let mut locals = Vec::with_capacity(1);
-                                        locals.push(self.deserialize_verification_type_info()?);
-
-                                        StackMapFrame::AppendFrame {
-                                            frame_type,
-                                            offset_delta,
-                                            locals,
-                                        }
-                                    }
-
-                                    // 255
-                                    0xff => {
-                                        let offset_delta = self.reader.read_unsigned_short()?;
-                                        let number_of_locals = self.reader.read_unsigned_short()?;
-
-                                        let mut locals =
-                                            Vec::with_capacity(number_of_locals as usize);
-                                        for _ in 0..number_of_locals {
-                                            locals.push(self.deserialize_verification_type_info()?);
-                                        }
-
-                                        let number_of_stack_items =
-                                            self.reader.read_unsigned_short()?;
-                                        let mut stack =
-                                            Vec::with_capacity(number_of_stack_items as usize);
-                                        for _ in 0..number_of_stack_items {
-                                            stack.push(self.deserialize_verification_type_info()?);
-                                        }
-
-                                        StackMapFrame::FullFrame {
-                                            frame_type,
-                                            offset_delta,
-                                            number_of_locals,
-                                            locals,
-                                            number_of_stack_items,
-                                            stack,
-                                        }
-                                    }
-                                };
-                                entries.push(frame);
-                            }
-
-                            attributes.push(AttributeInfo::StackMapTable {
-                                attribute_name_index,
-                                attribute_length,
-                                number_of_entries,
-                                entries,
-                            });
-                        }
-                        predefined_attributes::INNER_CLASSES => {
-                            let number_of_classes = self.reader.read_unsigned_short()?;
-
-                            let mut classes = Vec::new();
-                            for _ in 0..number_of_classes {
-                                let inner_class_info_index = self.reader.read_unsigned_short()?;
-                                let outer_class_info_index = self.reader.read_unsigned_short()?;
-                                let inner_name_index = self.reader.read_unsigned_short()?;
-                                let inner_class_access_flags = self.reader.read_unsigned_short()?;
-                                classes.push(Class {
-                                    inner_class_info_index,
-                                    outer_class_info_index,
-                                    inner_name_index,
-                                    inner_class_access_flags,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::InnerClasses {
-                                attribute_name_index,
-                                attribute_length,
-                                number_of_classes,
-                                classes,
-                            });
-                        }
+            predefined_attributes::CONSTANT_VALUE => {
+                let constantvalue_index = self.reader.read_unsigned_short()?;
+                AttributeInfo::ConstantValue {
+                    attribute_name_index,
+                    attribute_length,
+                    constantvalue_index,
+                }
+            }
 
-                        predefined_attributes::ENCLOSING_METHOD => {
-                            let class_index = self.reader.read_unsigned_short()?;
-                            let method_index = self.reader.read_unsigned_short()?;
-                            attributes.push(AttributeInfo::EnclosingMethod {
-                                attribute_name_index,
-                                attribute_length,
-                                class_index,
-                                method_index,
-                            });
-                        }
+            predefined_attributes::CODE => {
+                let max_stack = self.reader.read_unsigned_short()?;
+                let max_locals = self.reader.read_unsigned_short()?;
+
+                let code_length_offset = self.reader.position();
+                let code_length = self.reader.read_unsigned_int()?;
+                if code_length == 0 || code_length > u16::MAX as u32 {
+                    return Err(DeserializeError::at(
+                        DeserializeErrorKind::InvalidCodeLength(code_length),
+                        code_length_offset,
+                    ));
+                }
+                let code = self.reader.read_bytes(code_length as usize)?;
 
-                        predefined_attributes::SYNTHETIC => {
-                            attributes.push(AttributeInfo::Synthetic {
-                                attribute_name_index,
-                                attribute_length,
-                            });
-                        }
+                let exception_table_length = self.reader.read_unsigned_short()?;
+                let mut exception_table = Vec::new();
+                for _ in 0..exception_table_length {
+                    let start_pc = self.reader.read_unsigned_short()?;
+                    let end_pc = self.reader.read_unsigned_short()?;
+                    let handler_pc = self.reader.read_unsigned_short()?;
+                    let catch_type = self.reader.read_unsigned_short()?;
 
-                        predefined_attributes::SIGNATURE => {
-                            let signature_index = self.reader.read_unsigned_short()?;
-                            attributes.push(AttributeInfo::Signature {
-                                attribute_name_index,
-                                attribute_length,
-                                signature_index,
-                            });
-                        }
+                    exception_table.push(ExceptionHandler {
+                        start_pc,
+                        end_pc,
+                        handler_pc,
+                        catch_type,
+                    });
+                }
 
-                        predefined_attributes::SOURCE_DEBUG_EXTENSION => {
-                            let mut debug_extension = Vec::new();
-                            for _ in 0..attribute_length {
-                                debug_extension.push(self.reader.read_unsigned_byte()?);
-                            }
-                            attributes.push(AttributeInfo::SourceDebugExtension {
-                                attribute_name_index,
-                                attribute_length,
-                                debug_extension,
-                            });
-                        }
+                let code_attributes_count = self.reader.read_unsigned_short()?;
+                let code_attributes =
+                    self.deserialize_attributes(code_attributes_count, constant_pool)?;
+                AttributeInfo::Code {
+                    attribute_name_index,
+                    attribute_length,
+                    max_stack,
+                    max_locals,
+                    code_length,
+                    code,
+                    exception_table_length,
+                    exception_table,
+                    code_attributes_count,
+                    code_attributes,
+                }
+            }
 
-                        predefined_attributes::LOCAL_VARIABLE_TYPE_TABLE => {
-                            let local_variable_type_table_length =
-                                self.reader.read_unsigned_short()?;
-
-                            let mut local_variable_type_table = Vec::new();
-                            for _ in 0..local_variable_type_table_length {
-                                let start_pc = self.reader.read_unsigned_short()?;
-                                let length = self.reader.read_unsigned_short()?;
-                                let name_index = self.reader.read_unsigned_short()?;
-                                let signature_index = self.reader.read_unsigned_short()?;
-                                let index = self.reader.read_unsigned_short()?;
-
-                                local_variable_type_table.push(LocalVariableType {
-                                    start_pc,
-                                    length,
-                                    name_index,
-                                    signature_index,
-                                    index,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::LocalVariableTypeTable {
-                                attribute_name_index,
-                                attribute_length,
-                                local_variable_type_table_length,
-                                local_variable_type_table,
-                            });
-                        }
+            predefined_attributes::EXCEPTIONS => {
+                let number_of_exceptions = self.reader.read_unsigned_short()?;
+                let mut exception_index_table = Vec::with_capacity(number_of_exceptions as usize);
 
-                        predefined_attributes::DEPRECATED => {
-                            attributes.push(AttributeInfo::Deprecated {
-                                attribute_name_index,
-                                attribute_length,
-                            });
-                        }
+                for _ in 0..number_of_exceptions {
+                    let idx = self.reader.read_unsigned_short()?;
+                    exception_index_table.push(idx);
+                }
+                AttributeInfo::Exceptions {
+                    attribute_name_index,
+                    attribute_length,
+                    number_of_exceptions,
+                    exception_index_table,
+                }
+            }
 
-                        predefined_attributes::RUNTIME_VISIBLE_ANNOTATIONS => {
-                            let num_annotations = self.reader.read_unsigned_short()?;
+            predefined_attributes::LINE_NUMBER_TABLE => {
+                let line_number_table_length = self.reader.read_unsigned_short()?;
+                let mut line_number_table = Vec::with_capacity(line_number_table_length as usize);
 
-                            let mut annotations = Vec::new();
-                            for _ in 0..num_annotations {
-                                annotations.push(self.deserialize_annotation()?);
-                            }
+                for _ in 0..line_number_table_length {
+                    let start_pc = self.reader.read_unsigned_short()?;
+                    let line_number = self.reader.read_unsigned_short()?;
+                    line_number_table.push(LineNumber {
+                        start_pc,
+                        line_number,
+                    });
+                }
 
-                            attributes.push(AttributeInfo::RuntimeVisibleAnnotations {
-                                attribute_name_index,
-                                attribute_length,
-                                num_annotations,
-                                annotations,
-                            });
-                        }
+                AttributeInfo::LineNumberTable {
+                    attribute_name_index,
+                    attribute_length,
+                    line_number_table_length,
+                    line_number_table,
+                }
+            }
 
-                        predefined_attributes::RUNTIME_INVISIBLE_ANNOTATIONS => {
-                            let num_annotations = self.reader.read_unsigned_short()?;
+            predefined_attributes::LOCAL_VARIABLE_TABLE => {
+                let local_variable_table_length = self.reader.read_unsigned_short()?;
+                let mut local_variable_table =
+                    Vec::with_capacity(local_variable_table_length as usize);
 
-                            let mut annotations = Vec::new();
-                            for _ in 0..num_annotations {
-                                annotations.push(self.deserialize_annotation()?);
-                            }
+                for _ in 0..local_variable_table_length {
+                    let start_pc = self.reader.read_unsigned_short()?;
+                    let length = self.reader.read_unsigned_short()?;
+                    let name_index = self.reader.read_unsigned_short()?;
+                    let descriptor_index = self.reader.read_unsigned_short()?;
+                    let index = self.reader.read_unsigned_short()?;
 
-                            attributes.push(AttributeInfo::RuntimeInvisibleAnnotations {
-                                attribute_name_index,
-                                attribute_length,
-                                num_annotations,
-                                annotations,
-                            });
-                        }
+                    local_variable_table.push(LocalVariable {
+                        start_pc,
+                        length,
+                        name_index,
+                        descriptor_index,
+                        index,
+                    });
+                }
 
-                        predefined_attributes::RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS => {
-                            let num_parameters = self.reader.read_unsigned_byte()?;
-
-                            let mut parameter_annotations = Vec::new();
-                            for _ in 0..num_parameters {
-                                let num_annotations = self.reader.read_unsigned_short()?;
-                                let mut annotations = Vec::new();
-                                for _ in 0..num_annotations {
-                                    annotations.push(self.deserialize_annotation()?);
-                                }
-                                parameter_annotations.push(ParameterAnnotation {
-                                    num_annotations,
-                                    annotations,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::RuntimeVisibleParameterAnnotations {
-                                attribute_name_index,
-                                attribute_length,
-                                num_parameters,
-                                parameter_annotations,
-                            });
-                        }
+                AttributeInfo::LocalVariableTable {
+                    attribute_name_index,
+                    attribute_length,
+                    local_variable_table_length,
+                    local_variable_table,
+                }
+            }
 
-                        predefined_attributes::RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS => {
-                            let num_parameters = self.reader.read_unsigned_byte()?;
-
-                            let mut parameter_annotations = Vec::new();
-                            for _ in 0..num_parameters {
-                                let num_annotations = self.reader.read_unsigned_short()?;
-                                let mut annotations = Vec::new();
-                                for _ in 0..num_annotations {
-                                    annotations.push(self.deserialize_annotation()?);
-                                }
-                                parameter_annotations.push(ParameterAnnotation {
-                                    num_annotations,
-                                    annotations,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::RuntimeInvisibleParameterAnnotations {
-                                attribute_name_index,
-                                attribute_length,
-                                num_parameters,
-                                parameter_annotations,
-                            });
-                        }
+            predefined_attributes::STACK_MAP_TABLE => {
+                let number_of_entries = self.reader.read_unsigned_short()?;
+                let mut entries = Vec::new();
+                for entry_idx in 0..number_of_entries {
+                    let frame = self.scoped(PathSegment::Entry(entry_idx), |this| {
+                        this.deserialize_stack_map_frame()
+                    })?;
+                    entries.push(frame);
+                }
 
-                        predefined_attributes::RUNTIME_VISIBLE_TYPE_ANNOTATIONS => {
-                            let num_annotations = self.reader.read_unsigned_short()?;
+                AttributeInfo::StackMapTable {
+                    attribute_name_index,
+                    attribute_length,
+                    number_of_entries,
+                    entries,
+                }
+            }
+            predefined_attributes::INNER_CLASSES => {
+                let number_of_classes = self.reader.read_unsigned_short()?;
+
+                let mut classes = Vec::new();
+                for _ in 0..number_of_classes {
+                    let inner_class_info_index = self.reader.read_unsigned_short()?;
+                    let outer_class_info_index = self.reader.read_unsigned_short()?;
+                    let inner_name_index = self.reader.read_unsigned_short()?;
+                    let inner_class_access_flags = self.reader.read_unsigned_short()?;
+                    classes.push(Class {
+                        inner_class_info_index,
+                        outer_class_info_index,
+                        inner_name_index,
+                        inner_class_access_flags,
+                    });
+                }
 
-                            let mut annotations = Vec::new();
-                            for _ in 0..num_annotations {
-                                annotations.push(self.deserialize_type_annotation()?);
-                            }
+                AttributeInfo::InnerClasses {
+                    attribute_name_index,
+                    attribute_length,
+                    number_of_classes,
+                    classes,
+                }
+            }
 
-                            attributes.push(AttributeInfo::RuntimeVisibleTypeAnnotations {
-                                attribute_name_index,
-                                attribute_length,
-                                num_annotations,
-                                annotations,
-                            })
-                        }
+            predefined_attributes::ENCLOSING_METHOD => {
+                let class_index = self.reader.read_unsigned_short()?;
+                let method_index = self.reader.read_unsigned_short()?;
+                AttributeInfo::EnclosingMethod {
+                    attribute_name_index,
+                    attribute_length,
+                    class_index,
+                    method_index,
+                }
+            }
 
-                        predefined_attributes::RUNTIME_INVISIBLE_TYPE_ANNOTATIONS => {
-                            let num_annotations = self.reader.read_unsigned_short()?;
+            predefined_attributes::SYNTHETIC => AttributeInfo::Synthetic {
+                attribute_name_index,
+                attribute_length,
+            },
+
+            predefined_attributes::SIGNATURE => {
+                let signature_index = self.reader.read_unsigned_short()?;
+                AttributeInfo::Signature {
+                    attribute_name_index,
+                    attribute_length,
+                    signature_index,
+                }
+            }
 
-                            let mut annotations = Vec::new();
-                            for _ in 0..num_annotations {
-                                annotations.push(self.deserialize_type_annotation()?);
-                            }
+            predefined_attributes::SOURCE_DEBUG_EXTENSION => {
+                let debug_extension = self.reader.read_bytes(attribute_length as usize)?;
+                AttributeInfo::SourceDebugExtension {
+                    attribute_name_index,
+                    attribute_length,
+                    debug_extension,
+                }
+            }
 
-                            attributes.push(AttributeInfo::RuntimeInvisibleTypeAnnotations {
-                                attribute_name_index,
-                                attribute_length,
-                                num_annotations,
-                                annotations,
-                            })
-                        }
+            predefined_attributes::LOCAL_VARIABLE_TYPE_TABLE => {
+                let local_variable_type_table_length = self.reader.read_unsigned_short()?;
 
-                        predefined_attributes::ANNOTATION_DEFAULT => {
-                            let default_value = self.deserialize_element_value()?;
-                            attributes.push(AttributeInfo::AnnotationDefault {
-                                attribute_name_index,
-                                attribute_length,
-                                default_value,
-                            });
-                        }
+                let mut local_variable_type_table = Vec::new();
+                for _ in 0..local_variable_type_table_length {
+                    let start_pc = self.reader.read_unsigned_short()?;
+                    let length = self.reader.read_unsigned_short()?;
+                    let name_index = self.reader.read_unsigned_short()?;
+                    let signature_index = self.reader.read_unsigned_short()?;
+                    let index = self.reader.read_unsigned_short()?;
 
-                        predefined_attributes::BOOTSTRAP_METHODS => {
-                            let num_bootstrap_methods = self.reader.read_unsigned_short()?;
-
-                            let mut bootstrap_methods = Vec::new();
-                            for _ in 0..num_bootstrap_methods {
-                                let bootstrap_method_ref = self.reader.read_unsigned_short()?;
-
-                                let num_bootstrap_arguments = self.reader.read_unsigned_short()?;
-                                let mut bootstrap_arguments = Vec::new();
-                                for _ in 0..num_bootstrap_arguments {
-                                    bootstrap_arguments.push(self.reader.read_unsigned_short()?);
-                                }
-
-                                bootstrap_methods.push(BootstrapMethod {
-                                    bootstrap_method_ref,
-                                    num_bootstrap_arguments,
-                                    bootstrap_arguments,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::BootstrapMethods {
-                                attribute_name_index,
-                                attribute_length,
-                                num_bootstrap_methods,
-                                bootstrap_methods,
-                            });
-                        }
+                    local_variable_type_table.push(LocalVariableType {
+                        start_pc,
+                        length,
+                        name_index,
+                        signature_index,
+                        index,
+                    });
+                }
 
-                        predefined_attributes::METHOD_PARAMETERS => {
-                            let parameters_count = self.reader.read_unsigned_byte()?;
-
-                            let mut parameters = Vec::new();
-                            for _ in 0..parameters_count {
-                                let name_index = self.reader.read_unsigned_short()?;
-                                let access_flags = self.reader.read_unsigned_short()?;
-                                parameters.push(Parameter {
-                                    name_index,
-                                    access_flags,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::MethodParameters {
-                                attribute_name_index,
-                                attribute_length,
-                                parameters_count,
-                                parameters,
-                            });
-                        }
+                AttributeInfo::LocalVariableTypeTable {
+                    attribute_name_index,
+                    attribute_length,
+                    local_variable_type_table_length,
+                    local_variable_type_table,
+                }
+            }
 
-                        predefined_attributes::MODULE => {
-                            let module_name_index = self.reader.read_unsigned_short()?;
-                            let module_flags = self.reader.read_unsigned_short()?;
-                            let module_version_index = self.reader.read_unsigned_short()?;
-
-                            let requires_count = self.reader.read_unsigned_short()?;
-                            let mut requires = Vec::new();
-                            for _ in 0..requires_count {
-                                let requires_index = self.reader.read_unsigned_short()?;
-                                let requires_flags = self.reader.read_unsigned_short()?;
-                                let requires_version_index = self.reader.read_unsigned_short()?;
-
-                                requires.push(Require {
-                                    requires_index,
-                                    requires_flags,
-                                    requires_version_index,
-                                });
-                            }
-
-                            let exports_count = self.reader.read_unsigned_short()?;
-                            let mut exports = Vec::new();
-                            for _ in 0..exports_count {
-                                let exports_index = self.reader.read_unsigned_short()?;
-                                let exports_flags = self.reader.read_unsigned_short()?;
-
-                                let exports_to_count = self.reader.read_unsigned_short()?;
-                                let mut exports_to_index = Vec::new();
-                                for _ in 0..exports_to_count {
-                                    exports_to_index.push(self.reader.read_unsigned_short()?);
-                                }
-
-                                exports.push(Export {
-                                    exports_index,
-                                    exports_flags,
-                                    exports_to_count,
-                                    exports_to_index,
-                                });
-                            }
-
-                            let opens_count = self.reader.read_unsigned_short()?;
-                            let mut opens = Vec::new();
-                            for _ in 0..opens_count {
-                                let opens_index = self.reader.read_unsigned_short()?;
-                                let opens_flags = self.reader.read_unsigned_short()?;
-
-                                let opens_to_count = self.reader.read_unsigned_short()?;
-                                let mut opens_to_index = Vec::new();
-                                for _ in 0..opens_to_count {
-                                    opens_to_index.push(self.reader.read_unsigned_short()?);
-                                }
-
-                                opens.push(Open {
-                                    opens_index,
-                                    opens_flags,
-                                    opens_to_count,
-                                    opens_to_index,
-                                });
-                            }
-
-                            let uses_count = self.reader.read_unsigned_short()?;
-                            let mut uses_index = Vec::new();
-                            for _ in 0..uses_count {
-                                uses_index.push(self.reader.read_unsigned_short()?);
-                            }
-
-                            let provides_count = self.reader.read_unsigned_short()?;
-                            let mut provides = Vec::new();
-                            for _ in 0..provides_count {
-                                let provides_index = self.reader.read_unsigned_short()?;
-
-                                let provides_with_count = self.reader.read_unsigned_short()?;
-                                let mut provides_with_index = Vec::new();
-                                for _ in 0..provides_with_count {
-                                    provides_with_index.push(self.reader.read_unsigned_short()?);
-                                }
-
-                                provides.push(Provide {
-                                    provides_index,
-                                    provides_with_count,
-                                    provides_with_index,
-                                });
-                            }
-                            attributes.push(AttributeInfo::Module {
-                                attribute_name_index,
-                                attribute_length,
-                                module_name_index,
-                                module_flags,
-                                module_version_index,
-                                requires_count,
-                                requires,
-                                exports_count,
-                                exports,
-                                opens_count,
-                                opens,
-                                uses_count,
-                                uses_index,
-                                provides_count,
-                                provides,
-                            })
-                        }
+            predefined_attributes::DEPRECATED => AttributeInfo::Deprecated {
+                attribute_name_index,
+                attribute_length,
+            },
 
-                        predefined_attributes::MODULE_PACKAGES => {
-                            let package_count = self.reader.read_unsigned_short()?;
-                            let mut package_index = Vec::new();
-                            for _ in 0..package_count {
-                                package_index.push(self.reader.read_unsigned_short()?);
-                            }
-
-                            attributes.push(AttributeInfo::ModulePackages {
-                                attribute_name_index,
-                                attribute_length,
-                                package_count,
-                                package_index,
-                            });
-                        }
+            predefined_attributes::RUNTIME_VISIBLE_ANNOTATIONS => {
+                let num_annotations = self.reader.read_unsigned_short()?;
 
-                        predefined_attributes::MODULE_MAIN_CLASS => {
-                            let main_class_index = self.reader.read_unsigned_short()?;
-                            attributes.push(AttributeInfo::ModuleMainClass {
-                                attribute_name_index,
-                                attribute_length,
-                                main_class_index,
-                            });
-                        }
+                let mut annotations = Vec::new();
+                for _ in 0..num_annotations {
+                    annotations.push(self.deserialize_annotation()?);
+                }
 
-                        predefined_attributes::NEST_HOST => {
-                            let host_class_index = self.reader.read_unsigned_short()?;
-                            attributes.push(AttributeInfo::NestHost {
-                                attribute_name_index,
-                                attribute_length,
-                                host_class_index,
-                            });
-                        }
+                AttributeInfo::RuntimeVisibleAnnotations {
+                    attribute_name_index,
+                    attribute_length,
+                    num_annotations,
+                    annotations,
+                }
+            }
 
-                        predefined_attributes::NEST_MEMBERS => {
-                            let number_of_classes = self.reader.read_unsigned_short()?;
-                            let mut classes = Vec::new();
-                            for _ in 0..number_of_classes {
-                                classes.push(self.reader.read_unsigned_short()?);
-                            }
-                            attributes.push(AttributeInfo::NestMembers {
-                                attribute_name_index,
-                                attribute_length,
-                                number_of_classes,
-                                classes,
-                            });
-                        }
+            predefined_attributes::RUNTIME_INVISIBLE_ANNOTATIONS => {
+                let num_annotations = self.reader.read_unsigned_short()?;
 
-                        predefined_attributes::RECORD => {
-                            let components_count = self.reader.read_unsigned_short()?;
-                            let mut components = Vec::new();
-                            for _ in 0..components_count {
-                                let name_index = self.reader.read_unsigned_short()?;
-                                let descriptor_index = self.reader.read_unsigned_short()?;
-
-                                let attributes_count = self.reader.read_unsigned_short()?;
-                                let attributes =
-                                    self.deserialize_attributes(attributes_count, constant_pool)?;
-
-                                components.push(RecordComponentInfo {
-                                    name_index,
-                                    descriptor_index,
-                                    attributes_count,
-                                    attributes,
-                                });
-                            }
-
-                            attributes.push(AttributeInfo::Record {
-                                attribute_name_index,
-                                attribute_length,
-                                components_count,
-                                components,
-                            });
-                        }
+                let mut annotations = Vec::new();
+                for _ in 0..num_annotations {
+                    annotations.push(self.deserialize_annotation()?);
+                }
 
-                        predefined_attributes::PERMITTED_SUBCLASSES => {
-                            let number_of_classes = self.reader.read_unsigned_short()?;
-                            let mut classes = Vec::new();
-                            for _ in 0..number_of_classes {
-                                classes.push(self.reader.read_unsigned_short()?);
-                            }
-
-                            attributes.push(AttributeInfo::PermittedSubclasses {
-                                attribute_name_index,
-                                attribute_length,
-                                number_of_classes,
-                                classes,
-                            });
-                        }
+                AttributeInfo::RuntimeInvisibleAnnotations {
+                    attribute_name_index,
+                    attribute_length,
+                    num_annotations,
+                    annotations,
+                }
+            }
 
-                        _ => {
-                            // simply read the bytes and discard for any unknown attributes
-                            // for now - look into providing a pluggable way to store and
-                            // query for custom attributes.
-                            for _ in 0..attribute_length {
-                                let _ = self.reader.read_unsigned_byte()?;
-                            }
-                        }
+            predefined_attributes::RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS => {
+                let num_parameters = self.reader.read_unsigned_byte()?;
+
+                let mut parameter_annotations = Vec::new();
+                for _ in 0..num_parameters {
+                    let num_annotations = self.reader.read_unsigned_short()?;
+                    let mut annotations = Vec::new();
+                    for _ in 0..num_annotations {
+                        annotations.push(self.deserialize_annotation()?);
                     }
+                    parameter_annotations.push(ParameterAnnotation {
+                        num_annotations,
+                        annotations,
+                    });
+                }
+
+                AttributeInfo::RuntimeVisibleParameterAnnotations {
+                    attribute_name_index,
+                    attribute_length,
+                    num_parameters,
+                    parameter_annotations,
                 }
-                _ => unreachable!(),
             }
-        }
 
-        Ok(attributes)
-    }
+            predefined_attributes::RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS => {
+                let num_parameters = self.reader.read_unsigned_byte()?;
 
-    /// Deserialize the fields of the class file.
-    fn deserialize_fields(
-        &mut self,
-        fields_count: u16,
-        constant_pool: &Vec<Option<CpInfo>>,
-    ) -> DeserializeResult<Vec<FieldInfo>> {
-        let mut fields = Vec::new();
-        for _ in 0..fields_count {
-            let access_flags = self.reader.read_unsigned_short()?;
-            let name_index = self.reader.read_unsigned_short()?;
-            let descriptor_index = self.reader.read_unsigned_short()?;
-            let attributes_count = self.reader.read_unsigned_short()?;
-
-            let attributes = self.deserialize_attributes(attributes_count, constant_pool)?;
-            fields.push(FieldInfo {
-                access_flags,
-                name_index,
-                descriptor_index,
-                attributes_count,
-                attributes,
-            });
-        }
+                let mut parameter_annotations = Vec::new();
+                for _ in 0..num_parameters {
+                    let num_annotations = self.reader.read_unsigned_short()?;
+                    let mut annotations = Vec::new();
+                    for _ in 0..num_annotations {
+                        annotations.push(self.deserialize_annotation()?);
+                    }
+                    parameter_annotations.push(ParameterAnnotation {
+                        num_annotations,
+                        annotations,
+                    });
+                }
 
-        Ok(fields)
-    }
+                AttributeInfo::RuntimeInvisibleParameterAnnotations {
+                    attribute_name_index,
+                    attribute_length,
+                    num_parameters,
+                    parameter_annotations,
+                }
+            }
 
-    /// Deserialize the methods of the class file.
-    fn deserialize_methods(
-        &mut self,
-        methods_count: u16,
-        constant_pool: &Vec<Option<CpInfo>>,
-    ) -> DeserializeResult<Vec<MethodInfo>> {
-        let mut methods = Vec::new();
-
-        for _ in 0..methods_count {
-            let access_flags = self.reader.read_unsigned_short()?;
-            let name_index = self.reader.read_unsigned_short()?;
-            let descriptor_index = self.reader.read_unsigned_short()?;
-            let attributes_count = self.reader.read_unsigned_short()?;
-
-            let attributes = self.deserialize_attributes(attributes_count, constant_pool)?;
-            methods.push(MethodInfo {
-                access_flags,
-                name_index,
-                descriptor_index,
-                attributes_count,
-                attributes,
-            });
-        }
+            predefined_attributes::RUNTIME_VISIBLE_TYPE_ANNOTATIONS => {
+                let num_annotations = self.reader.read_unsigned_short()?;
 
-        Ok(methods)
-    }
+                let mut annotations = Vec::new();
+                for _ in 0..num_annotations {
+                    annotations.push(self.deserialize_type_annotation()?);
+                }
 
-    /// Deserialize the contents of the Constant Pool.
-    fn deserialize_constant_pool(
-        &mut self,
-        constant_pool_count: u16,
-    ) -> DeserializeResult<Vec<Option<CpInfo>>> {
-        let mut constant_pool = vec![None; constant_pool_count as usize];
-        let mut cp_idx = 1usize; // the first cell is not used
+                AttributeInfo::RuntimeVisibleTypeAnnotations {
+                    attribute_name_index,
+                    attribute_length,
+                    num_annotations,
+                    annotations,
+                }
+            }
 
-        while cp_idx < constant_pool_count as usize {
-            let tag = self.reader.read_unsigned_byte()?;
-
-            match tag {
-                CONSTANT_METHOD_REF => {
-                    let class_index = self.reader.read_unsigned_short()?;
-                    let name_and_type_index = self.reader.read_unsigned_short()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantMethodrefInfo {
-                        tag,
-                        class_index,
-                        name_and_type_index,
-                    });
+            predefined_attributes::RUNTIME_INVISIBLE_TYPE_ANNOTATIONS => {
+                let num_annotations = self.reader.read_unsigned_short()?;
+
+                let mut annotations = Vec::new();
+                for _ in 0..num_annotations {
+                    annotations.push(self.deserialize_type_annotation()?);
                 }
 
-                CONSTANT_CLASS => {
-                    let name_index = self.reader.read_unsigned_short()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantClassInfo { tag, name_index });
+                AttributeInfo::RuntimeInvisibleTypeAnnotations {
+                    attribute_name_index,
+                    attribute_length,
+                    num_annotations,
+                    annotations,
                 }
+            }
 
-                CONSTANT_FIELD_REF => {
-                    let class_index = self.reader.read_unsigned_short()?;
-                    let name_and_type_index = self.reader.read_unsigned_short()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantFieldrefInfo {
-                        tag,
-                        class_index,
-                        name_and_type_index,
-                    });
+            predefined_attributes::ANNOTATION_DEFAULT => {
+                let default_value = self.deserialize_element_value()?;
+                AttributeInfo::AnnotationDefault {
+                    attribute_name_index,
+                    attribute_length,
+                    default_value,
                 }
+            }
+
+            predefined_attributes::BOOTSTRAP_METHODS => {
+                let num_bootstrap_methods = self.reader.read_unsigned_short()?;
+
+                let mut bootstrap_methods = Vec::new();
+                for _ in 0..num_bootstrap_methods {
+                    let bootstrap_method_ref = self.reader.read_unsigned_short()?;
 
-                CONSTANT_INTERFACE_METHOD_REF => {
-                    let class_index = self.reader.read_unsigned_short()?;
-                    let name_and_type_index = self.reader.read_unsigned_short()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantInterfaceMethodrefInfo {
-                        tag,
-                        class_index,
-                        name_and_type_index,
+                    let num_bootstrap_arguments = self.reader.read_unsigned_short()?;
+                    let mut bootstrap_arguments = Vec::new();
+                    for _ in 0..num_bootstrap_arguments {
+                        bootstrap_arguments.push(self.reader.read_unsigned_short()?);
+                    }
+
+                    bootstrap_methods.push(BootstrapMethod {
+                        bootstrap_method_ref,
+                        num_bootstrap_arguments,
+                        bootstrap_arguments,
                     });
                 }
 
-                CONSTANT_STRING => {
-                    let string_index = self.reader.read_unsigned_short()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantStringInfo { tag, string_index });
+                AttributeInfo::BootstrapMethods {
+                    attribute_name_index,
+                    attribute_length,
+                    num_bootstrap_methods,
+                    bootstrap_methods,
                 }
+            }
 
-                CONSTANT_INTEGER => {
-                    let bytes = self.reader.read_unsigned_int()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantIntegerInfo { tag, bytes });
+            predefined_attributes::METHOD_PARAMETERS => {
+                let parameters_count = self.reader.read_unsigned_byte()?;
+
+                let mut parameters = Vec::new();
+                for _ in 0..parameters_count {
+                    let name_index = self.reader.read_unsigned_short()?;
+                    let access_flags = self.reader.read_unsigned_short()?;
+                    parameters.push(Parameter {
+                        name_index,
+                        access_flags,
+                    });
                 }
 
-                CONSTANT_FLOAT => {
-                    let bytes = self.reader.read_unsigned_int()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantFloatInfo { tag, bytes });
+                AttributeInfo::MethodParameters {
+                    attribute_name_index,
+                    attribute_length,
+                    parameters_count,
+                    parameters,
                 }
+            }
 
-                CONSTANT_LONG => {
-                    let high_bytes = self.reader.read_unsigned_int()?;
-                    let low_bytes = self.reader.read_unsigned_int()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantLongInfo {
-                        tag,
-                        high_bytes,
-                        low_bytes,
+            predefined_attributes::MODULE => {
+                let module_name_index = self.reader.read_unsigned_short()?;
+                let module_flags = self.reader.read_unsigned_short()?;
+                let module_version_index = self.reader.read_unsigned_short()?;
+
+                let requires_count = self.reader.read_unsigned_short()?;
+                let mut requires = Vec::new();
+                for _ in 0..requires_count {
+                    let requires_index = self.reader.read_unsigned_short()?;
+                    let requires_flags = self.reader.read_unsigned_short()?;
+                    let requires_version_index = self.reader.read_unsigned_short()?;
+
+                    requires.push(Require {
+                        requires_index,
+                        requires_flags,
+                        requires_version_index,
                     });
+                }
 
-                    // Long values take up two consecutive entries in the Constant Pool.
-                    cp_idx += 1;
+                let exports_count = self.reader.read_unsigned_short()?;
+                let mut exports = Vec::new();
+                for _ in 0..exports_count {
+                    let exports_index = self.reader.read_unsigned_short()?;
+                    let exports_flags = self.reader.read_unsigned_short()?;
+
+                    let exports_to_count = self.reader.read_unsigned_short()?;
+                    let mut exports_to_index = Vec::new();
+                    for _ in 0..exports_to_count {
+                        exports_to_index.push(self.reader.read_unsigned_short()?);
+                    }
+
+                    exports.push(Export {
+                        exports_index,
+                        exports_flags,
+                        exports_to_count,
+                        exports_to_index,
+                    });
                 }
 
-                CONSTANT_DOUBLE => {
-                    let high_bytes = self.reader.read_unsigned_int()?;
-                    let low_bytes = self.reader.read_unsigned_int()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantDoubleInfo {
-                        tag,
-                        high_bytes,
-                        low_bytes,
+                let opens_count = self.reader.read_unsigned_short()?;
+                let mut opens = Vec::new();
+                for _ in 0..opens_count {
+                    let opens_index = self.reader.read_unsigned_short()?;
+                    let opens_flags = self.reader.read_unsigned_short()?;
+
+                    let opens_to_count = self.reader.read_unsigned_short()?;
+                    let mut opens_to_index = Vec::new();
+                    for _ in 0..opens_to_count {
+                        opens_to_index.push(self.reader.read_unsigned_short()?);
+                    }
+
+                    opens.push(Open {
+                        opens_index,
+                        opens_flags,
+                        opens_to_count,
+                        opens_to_index,
                     });
+                }
 
-                    // Double values take up two consuective entries in the Constant Pool.
-                    cp_idx += 1;
+                let uses_count = self.reader.read_unsigned_short()?;
+                let mut uses_index = Vec::new();
+                for _ in 0..uses_count {
+                    uses_index.push(self.reader.read_unsigned_short()?);
                 }
 
-                CONSTANT_NAME_AND_TYPE => {
-                    let name_index = self.reader.read_unsigned_short()?;
-                    let descriptor_index = self.reader.read_unsigned_short()?;
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantNameAndTypeInfo {
-                        tag,
-                        name_index,
-                        descriptor_index,
+                let provides_count = self.reader.read_unsigned_short()?;
+                let mut provides = Vec::new();
+                for _ in 0..provides_count {
+                    let provides_index = self.reader.read_unsigned_short()?;
+
+                    let provides_with_count = self.reader.read_unsigned_short()?;
+                    let mut provides_with_index = Vec::new();
+                    for _ in 0..provides_with_count {
+                        provides_with_index.push(self.reader.read_unsigned_short()?);
+                    }
+
+                    provides.push(Provide {
+                        provides_index,
+                        provides_with_count,
+                        provides_with_index,
                     });
                 }
+                AttributeInfo::Module {
+                    attribute_name_index,
+                    attribute_length,
+                    module_name_index,
+                    module_flags,
+                    module_version_index,
+                    requires_count,
+                    requires,
+                    exports_count,
+                    exports,
+                    opens_count,
+                    opens,
+                    uses_count,
+                    uses_index,
+                    provides_count,
+                    provides,
+                }
+            }
 
-                CONSTANT_UTF8 => {
-                    let length = self.reader.read_unsigned_short()?;
-                    let mut bytes = Vec::new();
-                    for _ in 0..length {
-                        bytes.push(self.reader.read_unsigned_byte()?);
-                    }
+            predefined_attributes::MODULE_PACKAGES => {
+                let package_count = self.reader.read_unsigned_short()?;
+                let mut package_index = Vec::new();
+                for _ in 0..package_count {
+                    package_index.push(self.reader.read_unsigned_short()?);
+                }
+
+                AttributeInfo::ModulePackages {
+                    attribute_name_index,
+                    attribute_length,
+                    package_count,
+                    package_index,
+                }
+            }
+
+            predefined_attributes::MODULE_MAIN_CLASS => {
+                let main_class_index = self.reader.read_unsigned_short()?;
+                AttributeInfo::ModuleMainClass {
+                    attribute_name_index,
+                    attribute_length,
+                    main_class_index,
+                }
+            }
 
-                    constant_pool[cp_idx] = Some(CpInfo::ConstantUtf8Info { tag, length, bytes });
+            predefined_attributes::NEST_HOST => {
+                let host_class_index = self.reader.read_unsigned_short()?;
+                AttributeInfo::NestHost {
+                    attribute_name_index,
+                    attribute_length,
+                    host_class_index,
                 }
+            }
 
-                CONSTANT_METHOD_HANDLE => {
-                    let reference_kind = self.reader.read_unsigned_byte()?;
-                    let reference_index = self.reader.read_unsigned_short()?;
-                    constant_pool.push(Some(CpInfo::ConstantMethodHandleInfo {
-                        tag,
-                        reference_kind,
-                        reference_index,
-                    }));
+            predefined_attributes::NEST_MEMBERS => {
+                let number_of_classes = self.reader.read_unsigned_short()?;
+                let mut classes = Vec::new();
+                for _ in 0..number_of_classes {
+                    classes.push(self.reader.read_unsigned_short()?);
                 }
+                AttributeInfo::NestMembers {
+                    attribute_name_index,
+                    attribute_length,
+                    number_of_classes,
+                    classes,
+                }
+            }
 
-                CONSTANT_METHOD_TYPE => {
+            predefined_attributes::RECORD => {
+                let components_count = self.reader.read_unsigned_short()?;
+                let mut components = Vec::new();
+                for _ in 0..components_count {
+                    let name_index = self.reader.read_unsigned_short()?;
                     let descriptor_index = self.reader.read_unsigned_short()?;
-                    constant_pool.push(Some(CpInfo::ConstantMethodTypeInfo {
-                        tag,
+
+                    let attributes_count = self.reader.read_unsigned_short()?;
+                    let attributes =
+                        self.deserialize_attributes(attributes_count, constant_pool)?;
+
+                    components.push(RecordComponentInfo {
+                        name_index,
                         descriptor_index,
-                    }));
+                        attributes_count,
+                        attributes,
+                    });
                 }
 
-                CONSTANT_DYNAMIC => {
-                    let bootstrap_method_attr_index = self.reader.read_unsigned_short()?;
-                    let name_and_type_index = self.reader.read_unsigned_short()?;
-                    constant_pool.push(Some(CpInfo::ConstantDynamicInfo {
-                        tag,
-                        bootstrap_method_attr_index,
-                        name_and_type_index,
-                    }));
+                AttributeInfo::Record {
+                    attribute_name_index,
+                    attribute_length,
+                    components_count,
+                    components,
                 }
+            }
 
-                CONSTANT_INVOKE_DYNAMIC => {
-                    let bootstrap_method_attr_index = self.reader.read_unsigned_short()?;
-                    let name_and_type_index = self.reader.read_unsigned_short()?;
-                    constant_pool.push(Some(CpInfo::ConstantInvokeDynamicInfo {
-                        tag,
-                        bootstrap_method_attr_index,
-                        name_and_type_index,
-                    }));
+            predefined_attributes::PERMITTED_SUBCLASSES => {
+                let number_of_classes = self.reader.read_unsigned_short()?;
+                let mut classes = Vec::new();
+                for _ in 0..number_of_classes {
+                    classes.push(self.reader.read_unsigned_short()?);
                 }
 
-                CONSTANT_MODULE => {
-                    let name_index = self.reader.read_unsigned_short()?;
-                    constant_pool.push(Some(CpInfo::ConstantModuleInfo { tag, name_index }));
+                AttributeInfo::PermittedSubclasses {
+                    attribute_name_index,
+                    attribute_length,
+                    number_of_classes,
+                    classes,
                 }
+            }
 
-                CONSTANT_PACKAGE => {
-                    let name_index = self.reader.read_unsigned_short()?;
-                    constant_pool.push(Some(CpInfo::ConstantPackageInfo { tag, name_index }));
-                }
+            _ => {
+                let info_offset = self.reader.position();
+                let info = self.reader.read_bytes(attribute_length as usize)?;
+
+                let codec = self
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| registry.get(attribute_name));
+
+                match codec {
+                    Some(codec) => {
+                        let value = codec
+                            .decode(&info, constant_pool)
+                            .map_err(|err| err.or_offset(info_offset))?;
+                        AttributeInfo::Custom {
+                            attribute_name_index,
+                            attribute_length,
+                            value,
+                        }
+                    }
 
-                _ => unreachable!(),
+                    // preserve the raw bytes of any attribute that we do not know
+                    // about so that it can be serialized back as-is.
+                    None => AttributeInfo::Unknown {
+                        attribute_name_index,
+                        attribute_length,
+                        info,
+                    },
+                }
             }
-            cp_idx += 1;
+        };
+
+        Ok(attribute)
+    }
+
+    /// Deserialize the fields of the class file.
+    fn deserialize_fields(
+        &mut self,
+        fields_count: u16,
+        constant_pool: &ConstantPool,
+    ) -> DeserializeResult<Vec<FieldInfo>> {
+        let mut fields = Vec::with_capacity(fields_count as usize);
+
+        for field_idx in 0..fields_count {
+            let field = self.scoped(PathSegment::Field(field_idx), |this| {
+                let access_flags = this.reader.read_unsigned_short()?;
+                let name_index = this.reader.read_unsigned_short()?;
+                let descriptor_index = this.reader.read_unsigned_short()?;
+                let attributes_count = this.reader.read_unsigned_short()?;
+
+                let attributes = this.deserialize_attributes(attributes_count, constant_pool)?;
+                Ok(FieldInfo {
+                    access_flags,
+                    name_index,
+                    descriptor_index,
+                    attributes_count,
+                    attributes,
+                })
+            })?;
+            fields.push(field);
         }
 
-        Ok(constant_pool)
+        Ok(fields)
     }
 
-    /// Deserialize the class file into the object model.
-    pub fn deserialize(&mut self) -> DeserializeResult<ClassFile> {
-        // Headers
+    /// Deserialize the methods of the class file.
+    fn deserialize_methods(
+        &mut self,
+        methods_count: u16,
+        constant_pool: &ConstantPool,
+    ) -> DeserializeResult<Vec<MethodInfo>> {
+        let mut methods = Vec::with_capacity(methods_count as usize);
+
+        for method_idx in 0..methods_count {
+            let method = self.scoped(PathSegment::Method(method_idx), |this| {
+                let access_flags = this.reader.read_unsigned_short()?;
+                let name_index = this.reader.read_unsigned_short()?;
+                let descriptor_index = this.reader.read_unsigned_short()?;
+                let attributes_count = this.reader.read_unsigned_short()?;
+
+                let attributes = this.deserialize_attributes(attributes_count, constant_pool)?;
+                Ok(MethodInfo {
+                    access_flags,
+                    name_index,
+                    descriptor_index,
+                    attributes_count,
+                    attributes,
+                })
+            })?;
+            methods.push(method);
+        }
+
+        Ok(methods)
+    }
+
+    /// Deserialize the contents of the Constant Pool.
+    fn deserialize_constant_pool(
+        &mut self,
+        constant_pool_count: u16,
+    ) -> DeserializeResult<ConstantPool> {
+        let mut constant_pool = vec![None; constant_pool_count as usize];
+        let mut cp_idx = 1usize; // the first cell is not used
+
+        while cp_idx < constant_pool_count as usize {
+            let (cp_info, width) =
+                self.scoped(PathSegment::ConstantPool(cp_idx as u16), |this| {
+                    let offset = this.reader.position();
+                    let tag = this.reader.read_unsigned_byte()?;
+                    let cp_info = match tag {
+                        CONSTANT_UTF8 => {
+                            let length = this.reader.read_unsigned_short()?;
+                            let bytes = this.reader.read_bytes(length as usize)?;
+                            CpInfo::ConstantUtf8Info { tag, length, bytes }
+                        }
+                        _ => read_constant(&mut this.reader, tag, offset)?,
+                    };
+                    let width = constant_width(&cp_info, cp_idx, constant_pool_count, offset)?;
+                    Ok((cp_info, width))
+                })?;
+            constant_pool[cp_idx] = Some(cp_info);
+            cp_idx += width;
+        }
+
+        Ok(constant_pool.into())
+    }
+
+    /// Deserialize the magic number, version and constant pool of the class file.
+    fn deserialize_header(&mut self) -> DeserializeResult<Header> {
         let magic = self.reader.read_unsigned_int()?;
+        if magic != MAGIC {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::InvalidMagic(magic),
+                0,
+            ));
+        }
+
+        let version_offset = self.reader.position();
         let minor_version = self.reader.read_unsigned_short()?;
         let major_version = self.reader.read_unsigned_short()?;
+        if !self.versions.accepts(major_version, minor_version) {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::UnsupportedVersion {
+                    major: major_version,
+                    minor: minor_version,
+                },
+                version_offset,
+            ));
+        }
 
         // Constant Pool
+        let constant_pool_count_offset = self.reader.position();
         let constant_pool_count = self.reader.read_unsigned_short()?;
-        assert!(constant_pool_count > 0);
+        if constant_pool_count == 0 {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::InvalidConstantPoolCount(constant_pool_count),
+                constant_pool_count_offset,
+            ));
+        }
         let constant_pool = self.deserialize_constant_pool(constant_pool_count)?;
 
+        Ok(Header {
+            magic,
+            minor_version,
+            major_version,
+            constant_pool_count,
+            constant_pool,
+        })
+    }
+
+    /// Read the attributes that follow, passing each to `visitor` as soon as it is decoded.
+    fn accept_attributes<V: ClassVisitor + ?Sized>(
+        &mut self,
+        owner: AttributeOwner,
+        constant_pool: &ConstantPool,
+        visitor: &mut V,
+    ) -> DeserializeResult<()> {
+        let attributes_count = self.reader.read_unsigned_short()?;
+
+        for _ in 0..attributes_count {
+            let offset = self.reader.position();
+            let attribute_name_index = self.reader.read_unsigned_short()?;
+            let attribute_length = self.reader.read_unsigned_int()?;
+            let attribute = self.deserialize_attribute_info(
+                offset,
+                attribute_name_index,
+                attribute_length,
+                constant_pool,
+            )?;
+
+            if let (AttributeOwner::Method(method_idx), AttributeInfo::Code { code, .. }) =
+                (owner, &attribute)
+            {
+                if visitor.visits_instructions() {
+                    // the code follows the name index, length, max_stack, max_locals and
+                    // code_length of the attribute
+                    let code_offset = offset + 14;
+                    let attribute_path = PathSegment::Attribute(predefined_attributes::CODE.into());
+                    self.scoped(attribute_path, |_| {
+                        for instruction in Instructions::new(code) {
+                            let (instruction_offset, instruction) = instruction.map_err(|err| {
+                                DeserializeError::at(
+                                    DeserializeErrorKind::InvalidBytecode(err.clone()),
+                                    code_offset + err.offset(),
+                                )
+                            })?;
+                            visitor.visit_instruction(method_idx, instruction_offset, &instruction);
+                        }
+                        Ok(())
+                    })?;
+                }
+            }
+
+            visitor.visit_attribute(owner, attribute);
+        }
+
+        Ok(())
+    }
+
+    /// Deserialize the class file, passing its parts to `visitor` as they are read instead of
+    /// building the object model. See the `visitor` module for the order of the events.
+    pub fn accept<V: ClassVisitor + ?Sized>(&mut self, visitor: &mut V) -> DeserializeResult<()> {
+        let Header {
+            minor_version,
+            major_version,
+            constant_pool,
+            ..
+        } = self.deserialize_header()?;
+        visitor.visit_header(minor_version, major_version);
+
+        for (cp_idx, cp_info) in constant_pool.entries().iter().enumerate() {
+            if let Some(cp_info) = cp_info {
+                visitor.visit_constant(cp_idx as u16, cp_info.clone());
+            }
+        }
+
+        let access_flags = self.reader.read_unsigned_short()?;
+        let this_class = self.reader.read_unsigned_short()?;
+        let super_class = self.reader.read_unsigned_short()?;
+        let interfaces_count = self.reader.read_unsigned_short()?;
+        let mut interfaces = Vec::with_capacity(interfaces_count as usize);
+        for _ in 0..interfaces_count {
+            interfaces.push(self.reader.read_unsigned_short()?);
+        }
+        visitor.visit_class(access_flags, this_class, super_class, interfaces);
+
+        let fields_count = self.reader.read_unsigned_short()?;
+        for field_idx in 0..fields_count {
+            self.scoped(PathSegment::Field(field_idx), |this| {
+                let access_flags = this.reader.read_unsigned_short()?;
+                let name_index = this.reader.read_unsigned_short()?;
+                let descriptor_index = this.reader.read_unsigned_short()?;
+                visitor.visit_field(field_idx, access_flags, name_index, descriptor_index);
+                this.accept_attributes(AttributeOwner::Field(field_idx), &constant_pool, visitor)
+            })?;
+        }
+
+        let methods_count = self.reader.read_unsigned_short()?;
+        for method_idx in 0..methods_count {
+            self.scoped(PathSegment::Method(method_idx), |this| {
+                let access_flags = this.reader.read_unsigned_short()?;
+                let name_index = this.reader.read_unsigned_short()?;
+                let descriptor_index = this.reader.read_unsigned_short()?;
+                visitor.visit_method(method_idx, access_flags, name_index, descriptor_index);
+                this.accept_attributes(AttributeOwner::Method(method_idx), &constant_pool, visitor)
+            })?;
+        }
+
+        self.accept_attributes(AttributeOwner::Class, &constant_pool, visitor)?;
+        visitor.visit_end();
+
+        Ok(())
+    }
+
+    /// Deserialize the class file into the object model.
+    pub fn deserialize(&mut self) -> DeserializeResult<ClassFile> {
+        let Header {
+            magic,
+            minor_version,
+            major_version,
+            constant_pool_count,
+            constant_pool,
+        } = self.deserialize_header()?;
+
         let access_flags = self.reader.read_unsigned_short()?;
         let this_class = self.reader.read_unsigned_short()?;
 
@@ -1255,10 +1503,143 @@ impl<R: Read> Deserializer<R> {
     }
 }
 
+/// Read the rest of the constant pool entry with the tag `tag`, which starts at `offset`. The
+/// contents of `CONSTANT_Utf8_info` entries are read by the callers, which store them differently.
+pub(crate) fn read_constant<R: ByteReader>(
+    reader: &mut R,
+    tag: u8,
+    offset: usize,
+) -> DeserializeResult<CpInfo> {
+    let cp_info = match tag {
+        CONSTANT_INTEGER => CpInfo::ConstantIntegerInfo {
+            tag,
+            bytes: reader.read_unsigned_int()?,
+        },
+        CONSTANT_FLOAT => CpInfo::ConstantFloatInfo {
+            tag,
+            bytes: reader.read_unsigned_int()?,
+        },
+        CONSTANT_LONG => CpInfo::ConstantLongInfo {
+            tag,
+            high_bytes: reader.read_unsigned_int()?,
+            low_bytes: reader.read_unsigned_int()?,
+        },
+        CONSTANT_DOUBLE => CpInfo::ConstantDoubleInfo {
+            tag,
+            high_bytes: reader.read_unsigned_int()?,
+            low_bytes: reader.read_unsigned_int()?,
+        },
+        CONSTANT_CLASS => CpInfo::ConstantClassInfo {
+            tag,
+            name_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_STRING => CpInfo::ConstantStringInfo {
+            tag,
+            string_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_FIELD_REF => CpInfo::ConstantFieldrefInfo {
+            tag,
+            class_index: reader.read_unsigned_short()?,
+            name_and_type_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_METHOD_REF => CpInfo::ConstantMethodrefInfo {
+            tag,
+            class_index: reader.read_unsigned_short()?,
+            name_and_type_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_INTERFACE_METHOD_REF => CpInfo::ConstantInterfaceMethodrefInfo {
+            tag,
+            class_index: reader.read_unsigned_short()?,
+            name_and_type_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_NAME_AND_TYPE => CpInfo::ConstantNameAndTypeInfo {
+            tag,
+            name_index: reader.read_unsigned_short()?,
+            descriptor_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_METHOD_HANDLE => CpInfo::ConstantMethodHandleInfo {
+            tag,
+            reference_kind: reader.read_unsigned_byte()?,
+            reference_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_METHOD_TYPE => CpInfo::ConstantMethodTypeInfo {
+            tag,
+            descriptor_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_DYNAMIC => CpInfo::ConstantDynamicInfo {
+            tag,
+            bootstrap_method_attr_index: reader.read_unsigned_short()?,
+            name_and_type_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_INVOKE_DYNAMIC => CpInfo::ConstantInvokeDynamicInfo {
+            tag,
+            bootstrap_method_attr_index: reader.read_unsigned_short()?,
+            name_and_type_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_MODULE => CpInfo::ConstantModuleInfo {
+            tag,
+            name_index: reader.read_unsigned_short()?,
+        },
+        CONSTANT_PACKAGE => CpInfo::ConstantPackageInfo {
+            tag,
+            name_index: reader.read_unsigned_short()?,
+        },
+        _ => {
+            return Err(DeserializeError::at(
+                DeserializeErrorKind::InvalidConstantPoolTag(tag),
+                offset,
+            ))
+        }
+    };
+
+    Ok(cp_info)
+}
+
+/// Return the number of slots taken by `cp_info`, the entry at `cp_idx` starting at `offset`,
+/// checking that they fit in a constant pool of `constant_pool_count` slots.
+pub(crate) fn constant_width(
+    cp_info: &CpInfo,
+    cp_idx: usize,
+    constant_pool_count: u16,
+    offset: usize,
+) -> DeserializeResult<usize> {
+    // Long and Double values take up two consecutive entries in the Constant Pool.
+    let width = match cp_info {
+        CpInfo::ConstantLongInfo { .. } | CpInfo::ConstantDoubleInfo { .. } => 2,
+        _ => 1,
+    };
+    if cp_idx + width > constant_pool_count as usize {
+        return Err(DeserializeError::at(
+            DeserializeErrorKind::InvalidConstantPoolCount(constant_pool_count),
+            offset,
+        ));
+    }
+    Ok(width)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn test_supported_versions() {
+        let versions = SupportedVersions::default();
+        assert!(versions.accepts(45, 3));
+        assert!(versions.accepts(52, 0));
+        assert!(versions.accepts(65, 0));
+        assert!(!versions.accepts(44, 0));
+        assert!(!versions.accepts(70, 0));
+        assert!(!versions.accepts(65, 1));
+        assert!(!versions.accepts(65, PREVIEW_MINOR_VERSION));
+
+        let versions = SupportedVersions {
+            allow_preview: true,
+            ..SupportedVersions::default()
+        };
+        assert!(versions.accepts(65, PREVIEW_MINOR_VERSION));
+        assert!(!versions.accepts(65, 1));
+    }
+
     //Classfile /Users/z0ltan/dev/playground/Minimal.class
     //  Last modified 01-Mar-2023; size 259 bytes
     //  SHA-256 checksum a50a8c17f31dbb5ea4e7d6b919cfa21d7e58a33e235cf516d86533b003f32f82
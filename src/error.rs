@@ -1,5 +1,8 @@
 //! The error types for handling serialization and deserialization of JVM bytecode.
 
+use crate::{
+    analysis::AnalysisError, bytecode::BytecodeError, model::constant_pool::ConstantPoolError,
+};
 use std::error::Error;
 use std::fmt;
 use std::io;
@@ -7,55 +10,175 @@ use std::io;
 /// Error type for errors encountered while reading JVM bytecode from a raw bytestream.
 #[derive(Debug)]
 pub struct ReadError {
-    message: String,
+    offset: usize,
+    source: io::Error,
 }
 
 impl fmt::Display for ReadError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(
+            f,
+            "error while reading at byte offset {}: {}",
+            self.offset, self.source
+        )
     }
 }
 
 impl ReadError {
-    pub fn new(message: String) -> Self {
-        ReadError { message }
+    pub fn new(offset: usize, source: io::Error) -> Self {
+        ReadError { offset, source }
     }
-}
 
-impl Error for ReadError {}
+    /// The byte offset in the stream at which the read failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 
-impl From<io::Error> for ReadError {
-    fn from(io_err: io::Error) -> Self {
-        ReadError {
-            message: io_err.to_string(),
-        }
+    /// The kind of the underlying I/O error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.source.kind()
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
     }
 }
 
 /// Error type for errors encountered while reading a raw JVM bytecode stream.
 #[derive(Debug)]
 pub struct WriteError {
-    message: String,
+    offset: usize,
+    source: io::Error,
 }
 
 impl WriteError {
-    pub fn new(message: String) -> Self {
-        WriteError { message }
+    pub fn new(offset: usize, source: io::Error) -> Self {
+        WriteError { offset, source }
+    }
+
+    /// The byte offset in the stream at which the write failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The kind of the underlying I/O error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.source.kind()
     }
 }
 
 impl fmt::Display for WriteError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(
+            f,
+            "error while writing at byte offset {}: {}",
+            self.offset, self.source
+        )
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// A single step in the structural path to the part of a class file where an error occurred,
+/// e.g. `method #3 -> Code -> StackMapTable -> entry #7`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// An entry in the constant pool, by index.
+    ConstantPool(u16),
+    /// A field of the class, by position (starting at 0).
+    Field(u16),
+    /// A method of the class, by position (starting at 0).
+    Method(u16),
+    /// An attribute, by name.
+    Attribute(String),
+    /// An entry in a table of an attribute (frames, annotations, components, etc.), by position
+    /// (starting at 0).
+    Entry(u16),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::ConstantPool(idx) => write!(f, "constant pool #{}", idx),
+            PathSegment::Field(idx) => write!(f, "field #{}", idx),
+            PathSegment::Method(idx) => write!(f, "method #{}", idx),
+            PathSegment::Attribute(name) => write!(f, "{}", name),
+            PathSegment::Entry(idx) => write!(f, "entry #{}", idx),
+        }
     }
 }
 
-impl Error for WriteError {}
+pub(crate) fn fmt_path(f: &mut fmt::Formatter<'_>, path: &[PathSegment]) -> fmt::Result {
+    if path.is_empty() {
+        return Ok(());
+    }
 
-impl From<io::Error> for WriteError {
-    fn from(io_err: io::Error) -> Self {
-        WriteError {
-            message: io_err.to_string(),
+    write!(f, " (in ")?;
+    for (idx, segment) in path.iter().enumerate() {
+        if idx > 0 {
+            write!(f, " -> ")?;
+        }
+        write!(f, "{}", segment)?;
+    }
+    write!(f, ")")
+}
+
+/// The different kinds of errors that can occur during serialization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerializeErrorKind {
+    /// The underlying writer failed. The `io::Error` is available via `Error::source`.
+    Io,
+    /// An attribute name index does not refer to a `CONSTANT_Utf8_info` entry.
+    InvalidAttributeNameIndex(u16),
+    /// There is no codec registered for a custom attribute with this name.
+    MissingAttributeCodec(String),
+    /// A constant pool lookup failed.
+    ConstantPool(ConstantPoolError),
+    /// The `max_stack` and `max_locals` of a method's code could not be computed.
+    Analysis(AnalysisError),
+    /// The stored value of a count or length disagrees with the data (`CountMode::Strict`), or a
+    /// `constant_pool_count` of 0 is written as is (`CountMode::Preserve`).
+    CountMismatch {
+        field: &'static str,
+        stored: u32,
+        actual: usize,
+    },
+    /// The value of a count or length derived from the data does not fit in its item.
+    CountOverflow { field: &'static str, actual: usize },
+    /// Any other error, described by the message.
+    Other(String),
+}
+
+impl fmt::Display for SerializeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeErrorKind::Io => write!(f, "I/O error"),
+            SerializeErrorKind::InvalidAttributeNameIndex(idx) => write!(
+                f,
+                "attribute name index {} does not refer to a CONSTANT_Utf8 entry",
+                idx
+            ),
+            SerializeErrorKind::MissingAttributeCodec(name) => {
+                write!(f, "no codec registered for custom attribute {}", name)
+            }
+            SerializeErrorKind::ConstantPool(err) => write!(f, "{}", err),
+            SerializeErrorKind::Analysis(err) => write!(f, "{}", err),
+            SerializeErrorKind::CountMismatch {
+                field,
+                stored,
+                actual,
+            } => write!(f, "{} is {}, but the data has {}", field, stored, actual),
+            SerializeErrorKind::CountOverflow { field, actual } => {
+                write!(f, "{} of {} is too large", field, actual)
+            }
+            SerializeErrorKind::Other(message) => write!(f, "{}", message),
         }
     }
 }
@@ -64,27 +187,207 @@ impl From<io::Error> for WriteError {
 /// bytecode into a raw bytecode stream.
 #[derive(Debug)]
 pub struct SerializeError {
-    message: String,
+    kind: SerializeErrorKind,
+    offset: Option<usize>,
+    path: Vec<PathSegment>,
+    source: Option<io::Error>,
 }
 
 impl SerializeError {
     pub fn new(message: String) -> Self {
-        SerializeError { message }
+        SerializeError::from_kind(SerializeErrorKind::Other(message))
+    }
+
+    pub fn from_kind(kind: SerializeErrorKind) -> Self {
+        SerializeError {
+            kind,
+            offset: None,
+            path: Vec::new(),
+            source: None,
+        }
+    }
+
+    pub(crate) fn at(kind: SerializeErrorKind, offset: usize) -> Self {
+        SerializeError {
+            offset: Some(offset),
+            ..SerializeError::from_kind(kind)
+        }
+    }
+
+    /// Record the structural path of the error, unless a (more specific) path is already set.
+    pub(crate) fn with_path(mut self, path: &[PathSegment]) -> Self {
+        if self.path.is_empty() {
+            self.path = path.to_vec();
+        }
+        self
+    }
+
+    /// Record the byte offset of the error, unless it is already set.
+    pub(crate) fn or_offset(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    pub fn kind(&self) -> &SerializeErrorKind {
+        &self.kind
+    }
+
+    /// The byte offset in the output stream at which the error occurred, if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The structural path to the part of the class file being written when the error occurred.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
     }
 }
 
 impl fmt::Display for SerializeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}", self.kind)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte offset {}", offset)?;
+        }
+        fmt_path(f, &self.path)
     }
 }
 
-impl Error for SerializeError {}
+impl Error for SerializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|err| err as &(dyn Error + 'static))
+    }
+}
+
+impl From<ConstantPoolError> for SerializeError {
+    fn from(cp_err: ConstantPoolError) -> Self {
+        SerializeError::from_kind(SerializeErrorKind::ConstantPool(cp_err))
+    }
+}
+
+impl From<AnalysisError> for SerializeError {
+    fn from(analysis_err: AnalysisError) -> Self {
+        SerializeError::from_kind(SerializeErrorKind::Analysis(analysis_err))
+    }
+}
 
 impl From<WriteError> for SerializeError {
     fn from(write_err: WriteError) -> Self {
         SerializeError {
-            message: write_err.to_string(),
+            kind: SerializeErrorKind::Io,
+            offset: Some(write_err.offset),
+            path: Vec::new(),
+            source: Some(write_err.source),
+        }
+    }
+}
+
+/// The different kinds of errors that can occur during deserialization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeserializeErrorKind {
+    /// The class file ended before the structure being read was complete.
+    UnexpectedEof,
+    /// The underlying reader failed. The `io::Error` is available via `Error::source`.
+    Io,
+    /// The file does not start with the `0xCAFEBABE` magic number.
+    InvalidMagic(u32),
+    /// The class file version is outside the range accepted by the deserializer.
+    UnsupportedVersion { major: u16, minor: u16 },
+    /// A constant pool entry has an unknown tag.
+    InvalidConstantPoolTag(u8),
+    /// An attribute name index does not refer to a `CONSTANT_Utf8_info` entry.
+    InvalidAttributeNameIndex(u16),
+    /// The constant pool count is zero, or leaves no room for the second slot of a final `Long`
+    /// or `Double` entry.
+    InvalidConstantPoolCount(u16),
+    /// The length of a `Code` attribute's bytecode is not in the range 1..65536.
+    InvalidCodeLength(u32),
+    /// The info of a predefined attribute does not take up exactly `attribute_length` bytes.
+    InvalidAttributeLength {
+        attribute_length: u32,
+        actual: usize,
+    },
+    /// A type annotation has an unknown target type.
+    InvalidTargetType(u8),
+    /// An annotation element value has an unknown tag.
+    InvalidElementValueTag(u8),
+    /// A verification type in a `StackMapTable` has an unknown tag.
+    InvalidVerificationTypeTag(u8),
+    /// A `StackMapTable` frame has a reserved frame type.
+    InvalidStackMapFrameType(u8),
+    /// Attributes or annotations are nested deeper than the deserializer supports.
+    NestingTooDeep(usize),
+    /// The code of a `Code` attribute could not be decoded into instructions.
+    InvalidBytecode(BytecodeError),
+    /// A constant pool lookup failed.
+    ConstantPool(ConstantPoolError),
+    /// Any other error, described by the message.
+    Other(String),
+}
+
+impl fmt::Display for DeserializeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeErrorKind::UnexpectedEof => write!(f, "unexpected end of class file"),
+            DeserializeErrorKind::Io => write!(f, "I/O error"),
+            DeserializeErrorKind::InvalidMagic(magic) => write!(
+                f,
+                "invalid magic number {:#010x}, expected 0xcafebabe",
+                magic
+            ),
+            DeserializeErrorKind::UnsupportedVersion { major, minor } => {
+                write!(f, "unsupported class file version {}.{}", major, minor)
+            }
+            DeserializeErrorKind::InvalidConstantPoolTag(tag) => {
+                write!(f, "invalid constant pool tag {}", tag)
+            }
+            DeserializeErrorKind::InvalidAttributeNameIndex(idx) => write!(
+                f,
+                "attribute name index {} does not refer to a CONSTANT_Utf8 entry",
+                idx
+            ),
+            DeserializeErrorKind::InvalidConstantPoolCount(count) => {
+                write!(f, "invalid constant pool count {}", count)
+            }
+            DeserializeErrorKind::InvalidCodeLength(len) => {
+                write!(f, "invalid code length {}", len)
+            }
+            DeserializeErrorKind::InvalidAttributeLength {
+                attribute_length,
+                actual,
+            } => write!(
+                f,
+                "attribute length is {}, but its info takes up {} bytes",
+                attribute_length, actual
+            ),
+            DeserializeErrorKind::InvalidTargetType(target_type) => {
+                write!(
+                    f,
+                    "invalid type annotation target type {:#04x}",
+                    target_type
+                )
+            }
+            DeserializeErrorKind::InvalidElementValueTag(tag) => {
+                write!(f, "invalid element value tag {}", tag)
+            }
+            DeserializeErrorKind::InvalidVerificationTypeTag(tag) => {
+                write!(f, "invalid verification type tag {}", tag)
+            }
+            DeserializeErrorKind::InvalidStackMapFrameType(frame_type) => {
+                write!(f, "reserved stack map frame type {}", frame_type)
+            }
+            DeserializeErrorKind::NestingTooDeep(max_depth) => {
+                write!(f, "structures nested more than {} levels deep", max_depth)
+            }
+            DeserializeErrorKind::InvalidBytecode(err) => write!(f, "{}", err),
+            DeserializeErrorKind::ConstantPool(err) => write!(f, "{}", err),
+            DeserializeErrorKind::Other(message) => write!(f, "{}", message),
         }
     }
 }
@@ -93,27 +396,136 @@ impl From<WriteError> for SerializeError {
 /// representation.
 #[derive(Debug)]
 pub struct DeserializeError {
-    message: String,
+    kind: DeserializeErrorKind,
+    offset: Option<usize>,
+    path: Vec<PathSegment>,
+    source: Option<io::Error>,
 }
 
 impl DeserializeError {
     pub fn new(message: String) -> Self {
-        DeserializeError { message }
+        DeserializeError::from_kind(DeserializeErrorKind::Other(message))
+    }
+
+    pub fn from_kind(kind: DeserializeErrorKind) -> Self {
+        DeserializeError {
+            kind,
+            offset: None,
+            path: Vec::new(),
+            source: None,
+        }
+    }
+
+    pub(crate) fn at(kind: DeserializeErrorKind, offset: usize) -> Self {
+        DeserializeError {
+            offset: Some(offset),
+            ..DeserializeError::from_kind(kind)
+        }
+    }
+
+    /// Record the structural path of the error, unless a (more specific) path is already set.
+    pub(crate) fn with_path(mut self, path: &[PathSegment]) -> Self {
+        if self.path.is_empty() {
+            self.path = path.to_vec();
+        }
+        self
+    }
+
+    /// Record the byte offset of the error, unless it is already set.
+    pub(crate) fn or_offset(mut self, offset: usize) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    pub fn kind(&self) -> &DeserializeErrorKind {
+        &self.kind
+    }
+
+    /// The byte offset in the input at which the error occurred, if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// The structural path to the part of the class file being read when the error occurred.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
     }
 }
 
 impl fmt::Display for DeserializeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+        write!(f, "{}", self.kind)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at byte offset {}", offset)?;
+        }
+        fmt_path(f, &self.path)
     }
 }
 
-impl Error for DeserializeError {}
+impl Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|err| err as &(dyn Error + 'static))
+    }
+}
+
+impl From<ConstantPoolError> for DeserializeError {
+    fn from(cp_err: ConstantPoolError) -> Self {
+        DeserializeError::from_kind(DeserializeErrorKind::ConstantPool(cp_err))
+    }
+}
 
 impl From<ReadError> for DeserializeError {
     fn from(read_err: ReadError) -> Self {
+        let kind = match read_err.kind() {
+            io::ErrorKind::UnexpectedEof => DeserializeErrorKind::UnexpectedEof,
+            _ => DeserializeErrorKind::Io,
+        };
+
         DeserializeError {
-            message: read_err.to_string(),
+            kind,
+            offset: Some(read_err.offset),
+            path: Vec::new(),
+            source: Some(read_err.source),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_error_from_read_error() {
+        let read_err = ReadError::new(42, io::Error::from(io::ErrorKind::UnexpectedEof));
+        let err = DeserializeError::from(read_err).with_path(&[
+            PathSegment::Method(3),
+            PathSegment::Attribute("Code".to_string()),
+            PathSegment::Attribute("StackMapTable".to_string()),
+            PathSegment::Entry(7),
+        ]);
+
+        assert_eq!(err.kind(), &DeserializeErrorKind::UnexpectedEof);
+        assert_eq!(err.offset(), Some(42));
+        assert!(err.source().is_some());
+        assert_eq!(
+            err.to_string(),
+            "unexpected end of class file: unexpected end of file at byte offset 42 \
+             (in method #3 -> Code -> StackMapTable -> entry #7)"
+        );
+    }
+
+    #[test]
+    fn test_with_path_keeps_innermost_path() {
+        let err = DeserializeError::at(DeserializeErrorKind::InvalidConstantPoolTag(99), 10)
+            .with_path(&[PathSegment::ConstantPool(4)])
+            .with_path(&[]);
+
+        assert_eq!(err.path(), &[PathSegment::ConstantPool(4)]);
+        assert!(err.source().is_none());
+    }
+}
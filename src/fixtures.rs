@@ -0,0 +1,62 @@
+//! Class files shared by the unit tests of the deserializers.
+
+use crate::{
+    builder::{ClassBuilder, Constant, FieldBuilder, MethodBuilder},
+    bytecode::{CodeBuilder, Instruction},
+    model::{access_flags::*, ClassFile},
+    rw::writer::Writer,
+    serializer::Serializer,
+};
+
+/// Serialize `classfile` with the default settings.
+pub(crate) fn serialize(classfile: &ClassFile) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    Serializer::new(Writer::new(&mut bytes))
+        .serialize(classfile)
+        .unwrap();
+    bytes
+}
+
+/// Return the bytes of the class `Fixture`, with the source file `Fixture.java` and:
+///
+/// - the field `static final int LIMIT = 10`,
+/// - the field `private String name\0`, whose name has to be decoded from modified UTF-8,
+/// - the method `static int f()`, with a `Signature` attribute, whose code is
+///   `iconst_1; ireturn` followed by a handler for `RuntimeException`.
+pub(crate) fn class_bytes() -> Vec<u8> {
+    let mut builder = ClassBuilder::new("Fixture");
+    let catch_type = builder
+        .constant_pool()
+        .class("java/lang/RuntimeException")
+        .unwrap();
+
+    let mut code = CodeBuilder::new();
+    let (start, end, handler) = (code.label(), code.label(), code.label());
+    code.place(start)
+        .emit(Instruction::Iconst1)
+        .emit(Instruction::Ireturn)
+        .place(end)
+        .place(handler)
+        .emit(Instruction::Astore0)
+        .emit(Instruction::Iconst0)
+        .emit(Instruction::Ireturn)
+        .try_catch(start, end, handler, catch_type);
+
+    builder
+        .source_file("Fixture.java")
+        .field(
+            FieldBuilder::new(ACC_STATIC | ACC_FINAL, "LIMIT", "I")
+                .constant_value(Constant::Integer(10)),
+        )
+        .field(FieldBuilder::new(
+            ACC_PRIVATE,
+            "name\0",
+            "Ljava/lang/String;",
+        ))
+        .method(
+            MethodBuilder::new(ACC_STATIC, "f", "()I")
+                .signature("()I")
+                .code(code),
+        );
+    serialize(&builder.build().unwrap())
+}
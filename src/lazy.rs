@@ -0,0 +1,408 @@
+//! On-demand decoding of the attributes of class files held in memory.
+//!
+//! `LazyDeserializer` reads a class file like `borrowed::SliceDeserializer`, recording where the
+//! info of each attribute lies in the input. An attribute is decoded into an `AttributeInfo` of
+//! the object model when it is first accessed with `LazyAttribute::decode`, so scanning classes
+//! for their names, superclasses and members skips the bulk of a class file, which is usually
+//! its `Code`, `StackMapTable` and annotation attributes. `LazyClassFile::into_class_file`
+//! decodes the remaining attributes, giving the same `ClassFile` as `Deserializer`.
+
+use crate::{
+    borrowed::{self, ConstantPool, SliceDeserializer},
+    codec::AttributeRegistry,
+    deserializer::{DeserializeResult, Deserializer, SupportedVersions, MAGIC},
+    error::PathSegment,
+    model::{attributes::AttributeInfo, constant_pool, ClassFile, FieldInfo, MethodInfo},
+    rw::reader::Reader,
+};
+use std::{borrow::Cow, cell::OnceCell, fmt, sync::Arc};
+
+/// An attribute that is decoded when first accessed.
+#[derive(Debug)]
+pub struct LazyAttribute<'a> {
+    pub attribute_name_index: u16,
+    /// The undecoded info of the attribute.
+    pub info: &'a [u8],
+    /// The offset of `info` in the class file.
+    pub offset: usize,
+    /// The field or method the attribute belongs to, if any.
+    member: Option<PathSegment>,
+    decoded: OnceCell<AttributeInfo>,
+}
+
+impl<'a> LazyAttribute<'a> {
+    fn new(attribute: borrowed::AttributeInfo<'a>, member: Option<PathSegment>) -> Self {
+        LazyAttribute {
+            attribute_name_index: attribute.attribute_name_index,
+            info: attribute.info,
+            offset: attribute.offset,
+            member,
+            decoded: OnceCell::new(),
+        }
+    }
+
+    /// The name of the attribute, e.g. `Code`.
+    pub fn name(&self, constant_pool: &ConstantPool<'a>) -> Cow<'a, str> {
+        // the name index was checked when the class file was read
+        constant_pool
+            .get_utf8(self.attribute_name_index)
+            .unwrap_or_default()
+    }
+
+    fn is_named(&self, constant_pool: &ConstantPool<'a>, name: &str) -> bool {
+        constant_pool.get_utf8_bytes(self.attribute_name_index) == Ok(name.as_bytes())
+    }
+
+    /// Whether the attribute has been decoded.
+    pub fn is_decoded(&self) -> bool {
+        self.decoded.get().is_some()
+    }
+
+    /// Decode the attribute, using the constant pool and attribute registry of `class_file`, the
+    /// class file the attribute belongs to. The attribute is only decoded once.
+    pub fn decode(&self, class_file: &LazyClassFile<'a>) -> DeserializeResult<&AttributeInfo> {
+        if let Some(attribute) = self.decoded.get() {
+            return Ok(attribute);
+        }
+
+        let attribute = self.decode_info(class_file)?;
+        Ok(self.decoded.get_or_init(|| attribute))
+    }
+
+    fn decode_info(&self, class_file: &LazyClassFile<'a>) -> DeserializeResult<AttributeInfo> {
+        let mut deserializer = Deserializer::new(Reader::at_position(self.info, self.offset))
+            .with_path(self.member.iter().cloned().collect());
+        if let Some(registry) = &class_file.registry {
+            deserializer = deserializer.with_attribute_registry(Arc::clone(registry));
+        }
+
+        deserializer.deserialize_attribute_info(
+            self.offset - 6,
+            self.attribute_name_index,
+            self.info.len() as u32,
+            class_file.owned_constant_pool(),
+        )
+    }
+
+    /// Return the decoded attribute, decoding it now if it has not been accessed yet.
+    fn into_attribute_info(
+        mut self,
+        class_file: &LazyClassFile<'a>,
+    ) -> DeserializeResult<AttributeInfo> {
+        match self.decoded.take() {
+            Some(attribute) => Ok(attribute),
+            None => self.decode_info(class_file),
+        }
+    }
+}
+
+/// A field or method whose attributes are decoded when first accessed.
+#[derive(Debug)]
+pub struct LazyMember<'a> {
+    pub access_flags: u16,
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<LazyAttribute<'a>>,
+}
+
+impl<'a> LazyMember<'a> {
+    /// Decode the first attribute of the member named `name` (e.g. `Code`), or return `None` if
+    /// there is none.
+    pub fn attribute(
+        &self,
+        class_file: &LazyClassFile<'a>,
+        name: &str,
+    ) -> DeserializeResult<Option<&AttributeInfo>> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.is_named(&class_file.constant_pool, name))
+            .map(|attribute| attribute.decode(class_file))
+            .transpose()
+    }
+}
+
+/// A class file whose attributes are decoded when first accessed.
+///
+/// The counts of the class file are given by the lengths of the vectors.
+pub struct LazyClassFile<'a> {
+    pub minor_version: u16,
+    pub major_version: u16,
+    pub constant_pool: ConstantPool<'a>,
+    pub access_flags: u16,
+    pub this_class: u16,
+    pub super_class: u16,
+    pub interfaces: Vec<u16>,
+    pub fields: Vec<LazyMember<'a>>,
+    pub methods: Vec<LazyMember<'a>>,
+    pub attributes: Vec<LazyAttribute<'a>>,
+    /// The constant pool copied into the object model, made when the first attribute is decoded.
+    owned_constant_pool: OnceCell<constant_pool::ConstantPool>,
+    registry: Option<Arc<AttributeRegistry>>,
+}
+
+impl<'a> LazyClassFile<'a> {
+    fn owned_constant_pool(&self) -> &constant_pool::ConstantPool {
+        self.owned_constant_pool
+            .get_or_init(|| self.constant_pool.to_owned_pool())
+    }
+
+    /// Decode the first attribute of the class named `name` (e.g. `SourceFile`), or return
+    /// `None` if there is none.
+    pub fn attribute(&self, name: &str) -> DeserializeResult<Option<&AttributeInfo>> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.is_named(&self.constant_pool, name))
+            .map(|attribute| attribute.decode(self))
+            .transpose()
+    }
+
+    /// Decode all remaining attributes, returning the complete class file.
+    pub fn into_class_file(mut self) -> DeserializeResult<ClassFile> {
+        let fields = std::mem::take(&mut self.fields);
+        let methods = std::mem::take(&mut self.methods);
+        let attributes = std::mem::take(&mut self.attributes);
+        let decode_all = |attributes: Vec<LazyAttribute<'a>>| {
+            attributes
+                .into_iter()
+                .map(|attribute| attribute.into_attribute_info(&self))
+                .collect::<DeserializeResult<Vec<_>>>()
+        };
+
+        let fields = fields
+            .into_iter()
+            .map(|field| {
+                let attributes = decode_all(field.attributes)?;
+                Ok(FieldInfo {
+                    access_flags: field.access_flags,
+                    name_index: field.name_index,
+                    descriptor_index: field.descriptor_index,
+                    attributes_count: attributes.len() as u16,
+                    attributes,
+                })
+            })
+            .collect::<DeserializeResult<Vec<_>>>()?;
+        let methods = methods
+            .into_iter()
+            .map(|method| {
+                let attributes = decode_all(method.attributes)?;
+                Ok(MethodInfo {
+                    access_flags: method.access_flags,
+                    name_index: method.name_index,
+                    descriptor_index: method.descriptor_index,
+                    attributes_count: attributes.len() as u16,
+                    attributes,
+                })
+            })
+            .collect::<DeserializeResult<Vec<_>>>()?;
+        let attributes = decode_all(attributes)?;
+        let constant_pool = match self.owned_constant_pool.take() {
+            Some(constant_pool) => constant_pool,
+            None => self.constant_pool.to_owned_pool(),
+        };
+
+        Ok(ClassFile {
+            magic: MAGIC,
+            minor_version: self.minor_version,
+            major_version: self.major_version,
+            constant_pool_count: self.constant_pool.count(),
+            constant_pool,
+            access_flags: self.access_flags,
+            this_class: self.this_class,
+            super_class: self.super_class,
+            interfaces_count: self.interfaces.len() as u16,
+            interfaces: self.interfaces,
+            fields_count: fields.len() as u16,
+            fields,
+            methods_count: methods.len() as u16,
+            methods,
+            attributes_count: attributes.len() as u16,
+            attributes,
+        })
+    }
+}
+
+impl fmt::Debug for LazyClassFile<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyClassFile")
+            .field("minor_version", &self.minor_version)
+            .field("major_version", &self.major_version)
+            .field("constant_pool", &self.constant_pool)
+            .field("access_flags", &self.access_flags)
+            .field("this_class", &self.this_class)
+            .field("super_class", &self.super_class)
+            .field("interfaces", &self.interfaces)
+            .field("fields", &self.fields)
+            .field("methods", &self.methods)
+            .field("attributes", &self.attributes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wrap the attributes of the class file or of the member at `member` for lazy decoding.
+fn lazy_attributes<'a>(
+    attributes: Vec<borrowed::AttributeInfo<'a>>,
+    member: Option<PathSegment>,
+) -> Vec<LazyAttribute<'a>> {
+    attributes
+        .into_iter()
+        .map(|attribute| LazyAttribute::new(attribute, member.clone()))
+        .collect()
+}
+
+/// The LazyDeserializer reads a class file from a byte slice into a `LazyClassFile`, deferring
+/// the decoding of attributes.
+pub struct LazyDeserializer<'a> {
+    bytes: &'a [u8],
+    registry: Option<Arc<AttributeRegistry>>,
+    versions: SupportedVersions,
+}
+
+impl<'a> LazyDeserializer<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        LazyDeserializer {
+            bytes,
+            registry: None,
+            versions: SupportedVersions::default(),
+        }
+    }
+
+    /// Accept only class files whose version lies in `versions`. Defaults to
+    /// `SupportedVersions::default()`.
+    pub fn with_supported_versions(mut self, versions: SupportedVersions) -> Self {
+        self.versions = versions;
+        self
+    }
+
+    /// Use the codecs in `registry` to decode custom attributes.
+    pub fn with_attribute_registry(mut self, registry: Arc<AttributeRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Deserialize the class file, leaving its attributes undecoded.
+    pub fn deserialize(&self) -> DeserializeResult<LazyClassFile<'a>> {
+        let classfile = SliceDeserializer::new(self.bytes)
+            .with_supported_versions(self.versions)
+            .deserialize()?;
+
+        let fields = classfile
+            .fields
+            .into_iter()
+            .enumerate()
+            .map(|(field_idx, field)| LazyMember {
+                access_flags: field.access_flags,
+                name_index: field.name_index,
+                descriptor_index: field.descriptor_index,
+                attributes: lazy_attributes(
+                    field.attributes,
+                    Some(PathSegment::Field(field_idx as u16)),
+                ),
+            })
+            .collect();
+        let methods = classfile
+            .methods
+            .into_iter()
+            .enumerate()
+            .map(|(method_idx, method)| LazyMember {
+                access_flags: method.access_flags,
+                name_index: method.name_index,
+                descriptor_index: method.descriptor_index,
+                attributes: lazy_attributes(
+                    method.attributes,
+                    Some(PathSegment::Method(method_idx as u16)),
+                ),
+            })
+            .collect();
+
+        Ok(LazyClassFile {
+            minor_version: classfile.minor_version,
+            major_version: classfile.major_version,
+            constant_pool: classfile.constant_pool,
+            access_flags: classfile.access_flags,
+            this_class: classfile.this_class,
+            super_class: classfile.super_class,
+            interfaces: classfile.interfaces,
+            fields,
+            methods,
+            attributes: lazy_attributes(classfile.attributes, None),
+            owned_constant_pool: OnceCell::new(),
+            registry: self.registry.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::DeserializeErrorKind, fixtures::class_bytes,
+        model::attributes::predefined_attributes,
+    };
+    use std::io::Cursor;
+
+    #[test]
+    fn test_decode_on_access() {
+        let bytes = class_bytes();
+        let classfile = LazyDeserializer::new(&bytes).deserialize().unwrap();
+        assert_eq!(
+            classfile
+                .constant_pool
+                .get_class_name(classfile.this_class)
+                .unwrap(),
+            "Fixture"
+        );
+
+        let method = &classfile.methods[0];
+        assert!(method.attributes.iter().all(|attr| !attr.is_decoded()));
+
+        let code = method
+            .attribute(&classfile, predefined_attributes::CODE)
+            .unwrap()
+            .unwrap();
+        assert!(
+            matches!(code, AttributeInfo::Code { code, .. } if code.starts_with(&[0x04, 0xac]))
+        );
+        assert!(method.attributes[0].is_decoded());
+        assert!(!method.attributes[1].is_decoded());
+        assert!(method
+            .attribute(&classfile, "Exceptions")
+            .unwrap()
+            .is_none());
+
+        // decoding the rest gives the same class file as the eager deserializer
+        let decoded = classfile.into_class_file().unwrap();
+        let eager = Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .deserialize()
+            .unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", eager));
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut bytes = class_bytes();
+        let classfile = LazyDeserializer::new(&bytes).deserialize().unwrap();
+        let code = &classfile.methods[0].attributes[0];
+        let code_length_offset = code.offset + 4;
+
+        // a code length of zero is only detected when the attribute is decoded
+        bytes[code_length_offset..code_length_offset + 4].copy_from_slice(&[0; 4]);
+        let classfile = LazyDeserializer::new(&bytes).deserialize().unwrap();
+        let err = classfile.methods[0].attributes[0]
+            .decode(&classfile)
+            .unwrap_err();
+        assert_eq!(err.kind(), &DeserializeErrorKind::InvalidCodeLength(0));
+        assert_eq!(err.offset(), Some(code_length_offset));
+        assert_eq!(
+            err.path(),
+            [
+                PathSegment::Method(0),
+                PathSegment::Attribute("Code".to_string())
+            ]
+        );
+
+        let err = LazyDeserializer::new(&bytes[..bytes.len() - 1])
+            .deserialize()
+            .unwrap_err();
+        assert_eq!(err.kind(), &DeserializeErrorKind::UnexpectedEof);
+    }
+}
@@ -2,9 +2,22 @@
 //! It consists of two main modules:
 //!  - deserializer : read in the raw bytes of a JVM `class` file and construct an object model.
 //!  - serializer : take the object model representation and construct the JVM `class` file bytes
-//!  from it.
+//!    from it.
+pub mod analysis;
+pub mod archive;
+pub mod borrowed;
+pub mod builder;
+pub mod bytecode;
+pub mod codec;
+pub mod descriptor;
 pub mod deserializer;
 pub mod error;
+#[cfg(test)]
+mod fixtures;
+pub mod lazy;
 pub mod model;
 pub mod rw;
 pub mod serializer;
+pub mod signature;
+pub mod validator;
+pub mod visitor;
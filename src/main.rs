@@ -27,4 +27,4 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     serializer.serialize(&classfile)?;
 
     Ok(())
-}
\ No newline at end of file
+}
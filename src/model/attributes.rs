@@ -1,3 +1,6 @@
+use std::any::Any;
+use std::fmt;
+
 #[derive(Debug)]
 pub enum AttributeInfo {
     SourceFile {
@@ -215,6 +218,280 @@ pub enum AttributeInfo {
         number_of_classes: u16,
         classes: Vec<u16>,
     },
+
+    /// Any attribute that is not predefined by the JVM specification (Kotlin metadata, Scala
+    /// signatures, vendor-specific attributes, etc.). The payload is kept as raw bytes so that it
+    /// can be written back unchanged.
+    Unknown {
+        attribute_name_index: u16,
+        attribute_length: u32,
+        info: Vec<u8>,
+    },
+
+    /// A non-standard attribute decoded by an `AttributeCodec` registered with the
+    /// `Deserializer`. The same codec is used by the `Serializer` to encode it back.
+    Custom {
+        attribute_name_index: u16,
+        attribute_length: u32,
+        value: Box<dyn CustomAttribute>,
+    },
+}
+
+impl AttributeInfo {
+    /// The index of the `CONSTANT_Utf8_info` entry holding the name of this attribute.
+    pub fn attribute_name_index(&self) -> u16 {
+        match self {
+            AttributeInfo::SourceFile {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::ConstantValue {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Code {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Exceptions {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::LineNumberTable {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::LocalVariableTable {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::StackMapTable {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::InnerClasses {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::EnclosingMethod {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Synthetic {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Signature {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::SourceDebugExtension {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::LocalVariableTypeTable {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Deprecated {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::RuntimeVisibleAnnotations {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::RuntimeInvisibleAnnotations {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::RuntimeVisibleParameterAnnotations {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::RuntimeInvisibleParameterAnnotations {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::RuntimeVisibleTypeAnnotations {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::RuntimeInvisibleTypeAnnotations {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::AnnotationDefault {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::BootstrapMethods {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::MethodParameters {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Module {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::ModulePackages {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::ModuleMainClass {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::NestHost {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::NestMembers {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Record {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::PermittedSubclasses {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Unknown {
+                attribute_name_index,
+                ..
+            }
+            | AttributeInfo::Custom {
+                attribute_name_index,
+                ..
+            } => *attribute_name_index,
+        }
+    }
+
+    /// The stored length of this attribute, excluding the initial six bytes.
+    pub fn attribute_length(&self) -> u32 {
+        match self {
+            AttributeInfo::SourceFile {
+                attribute_length, ..
+            }
+            | AttributeInfo::ConstantValue {
+                attribute_length, ..
+            }
+            | AttributeInfo::Code {
+                attribute_length, ..
+            }
+            | AttributeInfo::Exceptions {
+                attribute_length, ..
+            }
+            | AttributeInfo::LineNumberTable {
+                attribute_length, ..
+            }
+            | AttributeInfo::LocalVariableTable {
+                attribute_length, ..
+            }
+            | AttributeInfo::StackMapTable {
+                attribute_length, ..
+            }
+            | AttributeInfo::InnerClasses {
+                attribute_length, ..
+            }
+            | AttributeInfo::EnclosingMethod {
+                attribute_length, ..
+            }
+            | AttributeInfo::Synthetic {
+                attribute_length, ..
+            }
+            | AttributeInfo::Signature {
+                attribute_length, ..
+            }
+            | AttributeInfo::SourceDebugExtension {
+                attribute_length, ..
+            }
+            | AttributeInfo::LocalVariableTypeTable {
+                attribute_length, ..
+            }
+            | AttributeInfo::Deprecated {
+                attribute_length, ..
+            }
+            | AttributeInfo::RuntimeVisibleAnnotations {
+                attribute_length, ..
+            }
+            | AttributeInfo::RuntimeInvisibleAnnotations {
+                attribute_length, ..
+            }
+            | AttributeInfo::RuntimeVisibleParameterAnnotations {
+                attribute_length, ..
+            }
+            | AttributeInfo::RuntimeInvisibleParameterAnnotations {
+                attribute_length, ..
+            }
+            | AttributeInfo::RuntimeVisibleTypeAnnotations {
+                attribute_length, ..
+            }
+            | AttributeInfo::RuntimeInvisibleTypeAnnotations {
+                attribute_length, ..
+            }
+            | AttributeInfo::AnnotationDefault {
+                attribute_length, ..
+            }
+            | AttributeInfo::BootstrapMethods {
+                attribute_length, ..
+            }
+            | AttributeInfo::MethodParameters {
+                attribute_length, ..
+            }
+            | AttributeInfo::Module {
+                attribute_length, ..
+            }
+            | AttributeInfo::ModulePackages {
+                attribute_length, ..
+            }
+            | AttributeInfo::ModuleMainClass {
+                attribute_length, ..
+            }
+            | AttributeInfo::NestHost {
+                attribute_length, ..
+            }
+            | AttributeInfo::NestMembers {
+                attribute_length, ..
+            }
+            | AttributeInfo::Record {
+                attribute_length, ..
+            }
+            | AttributeInfo::PermittedSubclasses {
+                attribute_length, ..
+            }
+            | AttributeInfo::Unknown {
+                attribute_length, ..
+            }
+            | AttributeInfo::Custom {
+                attribute_length, ..
+            } => *attribute_length,
+        }
+    }
+}
+
+/// The decoded value of a custom attribute. This is implemented for any `Debug` type, so codecs
+/// can use plain structs for their attributes and recover them with `downcast_ref`.
+pub trait CustomAttribute: fmt::Debug {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + fmt::Debug> CustomAttribute for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<'a> dyn CustomAttribute + 'a {
+    /// Get a reference to the concrete type of the custom attribute, if it is of type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
 }
 
 #[derive(Default, Debug)]
@@ -450,7 +727,8 @@ pub struct LocalVariableType {
     pub index: u16,
 }
 
-#[derive(Default, Debug)]
+/// An entry in the exception table of a `Code` attribute.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct ExceptionHandler {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -458,6 +736,19 @@ pub struct ExceptionHandler {
     pub catch_type: u16,
 }
 
+impl ExceptionHandler {
+    /// Create a handler for the class at constant pool index `catch_type`, or for all exceptions
+    /// if it is 0.
+    pub fn new(start_pc: u16, end_pc: u16, handler_pc: u16, catch_type: u16) -> Self {
+        ExceptionHandler {
+            start_pc,
+            end_pc,
+            handler_pc,
+            catch_type,
+        }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct LineNumber {
     pub start_pc: u16,
@@ -474,38 +765,37 @@ pub struct LocalVariable {
 }
 
 pub mod predefined_attributes {
-    pub const SOURCE_FILE: &'static str = "SourceFile";
-    pub const CONSTANT_VALUE: &'static str = "ConstantValue";
-    pub const CODE: &'static str = "Code";
-    pub const EXCEPTIONS: &'static str = "Exceptions";
-    pub const LINE_NUMBER_TABLE: &'static str = "LineNumberTable";
-    pub const LOCAL_VARIABLE_TABLE: &'static str = "LocalVariableTable";
-    pub const STACK_MAP_TABLE: &'static str = "StackMapTable";
-    pub const INNER_CLASSES: &'static str = "InnerClasses";
-    pub const ENCLOSING_METHOD: &'static str = "EnclosingMethod";
-    pub const SYNTHETIC: &'static str = "Synthetic";
-    pub const SIGNATURE: &'static str = "Signature";
-    pub const SOURCE_DEBUG_EXTENSION: &'static str = "SourceDebugExtension";
-    pub const LOCAL_VARIABLE_TYPE_TABLE: &'static str = "LocalVariableTypeTable";
-    pub const DEPRECATED: &'static str = "Deprecated";
-    pub const RUNTIME_VISIBLE_ANNOTATIONS: &'static str = "RuntimeVisibleAnnotations";
-    pub const RUNTIME_INVISIBLE_ANNOTATIONS: &'static str = "RuntimeInvisibleAnnotations";
-    pub const RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS: &'static str =
-        "RuntimeVisibleParameterAnnotations";
-    pub const RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS: &'static str =
+    pub const SOURCE_FILE: &str = "SourceFile";
+    pub const CONSTANT_VALUE: &str = "ConstantValue";
+    pub const CODE: &str = "Code";
+    pub const EXCEPTIONS: &str = "Exceptions";
+    pub const LINE_NUMBER_TABLE: &str = "LineNumberTable";
+    pub const LOCAL_VARIABLE_TABLE: &str = "LocalVariableTable";
+    pub const STACK_MAP_TABLE: &str = "StackMapTable";
+    pub const INNER_CLASSES: &str = "InnerClasses";
+    pub const ENCLOSING_METHOD: &str = "EnclosingMethod";
+    pub const SYNTHETIC: &str = "Synthetic";
+    pub const SIGNATURE: &str = "Signature";
+    pub const SOURCE_DEBUG_EXTENSION: &str = "SourceDebugExtension";
+    pub const LOCAL_VARIABLE_TYPE_TABLE: &str = "LocalVariableTypeTable";
+    pub const DEPRECATED: &str = "Deprecated";
+    pub const RUNTIME_VISIBLE_ANNOTATIONS: &str = "RuntimeVisibleAnnotations";
+    pub const RUNTIME_INVISIBLE_ANNOTATIONS: &str = "RuntimeInvisibleAnnotations";
+    pub const RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS: &str = "RuntimeVisibleParameterAnnotations";
+    pub const RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS: &str =
         "RuntimeInvisibleParameterAnnotations";
-    pub const RUNTIME_VISIBLE_TYPE_ANNOTATIONS: &'static str = "RuntimeVisibleTypeAnnotations";
-    pub const RUNTIME_INVISIBLE_TYPE_ANNOTATIONS: &'static str = "RuntimeInvisibleTypeAnnotations";
-    pub const ANNOTATION_DEFAULT: &'static str = "AnnotationDefault";
-    pub const BOOTSTRAP_METHODS: &'static str = "BootstrapMethods";
-    pub const METHOD_PARAMETERS: &'static str = "MethodParameters";
-    pub const MODULE: &'static str = "Module";
-    pub const MODULE_PACKAGES: &'static str = "ModulePackages";
-    pub const MODULE_MAIN_CLASS: &'static str = "ModuleMainClass";
-    pub const NEST_HOST: &'static str = "NestHost";
-    pub const NEST_MEMBERS: &'static str = "NestMembers";
-    pub const RECORD: &'static str = "Record";
-    pub const PERMITTED_SUBCLASSES: &'static str = "PermittedSubclasses";
+    pub const RUNTIME_VISIBLE_TYPE_ANNOTATIONS: &str = "RuntimeVisibleTypeAnnotations";
+    pub const RUNTIME_INVISIBLE_TYPE_ANNOTATIONS: &str = "RuntimeInvisibleTypeAnnotations";
+    pub const ANNOTATION_DEFAULT: &str = "AnnotationDefault";
+    pub const BOOTSTRAP_METHODS: &str = "BootstrapMethods";
+    pub const METHOD_PARAMETERS: &str = "MethodParameters";
+    pub const MODULE: &str = "Module";
+    pub const MODULE_PACKAGES: &str = "ModulePackages";
+    pub const MODULE_MAIN_CLASS: &str = "ModuleMainClass";
+    pub const NEST_HOST: &str = "NestHost";
+    pub const NEST_MEMBERS: &str = "NestMembers";
+    pub const RECORD: &str = "Record";
+    pub const PERMITTED_SUBCLASSES: &str = "PermittedSubclasses";
 }
 
 #[cfg(test)]
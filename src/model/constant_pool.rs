@@ -1,3 +1,10 @@
+//! The constant pool of a class file: the tags and raw entries as defined by the JVM
+//! specification, and the `ConstantPool` table holding them.
+
+use std::{borrow::Cow, collections::HashMap, error::Error, fmt, ops::Deref};
+use tags::*;
+use types::CpInfo;
+
 pub mod tags {
     pub const CONSTANT_INVALID_DEFAULT: u8 = 255;
     pub const CONSTANT_CLASS: u8 = 7;
@@ -118,3 +125,958 @@ pub mod types {
         },
     }
 }
+
+pub type ConstantPoolResult<T> = Result<T, ConstantPoolError>;
+
+/// Error type for failed lookups in the constant pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConstantPoolError {
+    /// The index is zero, out of range, or refers to the unusable slot following a `Long` or
+    /// `Double` entry.
+    InvalidIndex(u16),
+    /// The entry at the index is not of the expected kind.
+    UnexpectedEntry {
+        index: u16,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The `CONSTANT_Utf8_info` entry at the index does not hold a valid string.
+    InvalidUtf8(u16),
+    /// Adding an entry would exceed the maximum of 65535 constant pool slots.
+    TooManyEntries,
+    /// A string is too long (in encoded bytes) for a `CONSTANT_Utf8_info` entry.
+    StringTooLong(usize),
+}
+
+impl fmt::Display for ConstantPoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConstantPoolError::InvalidIndex(index) => {
+                write!(f, "invalid constant pool index #{}", index)
+            }
+            ConstantPoolError::UnexpectedEntry {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "constant pool entry #{} is a {} entry, expected {}",
+                index, found, expected
+            ),
+            ConstantPoolError::InvalidUtf8(index) => {
+                write!(f, "constant pool entry #{} is not a valid string", index)
+            }
+            ConstantPoolError::TooManyEntries => {
+                write!(f, "constant pool exceeds the maximum of 65535 entries")
+            }
+            ConstantPoolError::StringTooLong(len) => write!(
+                f,
+                "string of {} bytes exceeds the maximum CONSTANT_Utf8 length of 65535",
+                len
+            ),
+        }
+    }
+}
+
+impl Error for ConstantPoolError {}
+
+/// Error type for byte sequences that are not valid "modified UTF-8" (JVMS 4.4.7).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModifiedUtf8Error {
+    /// The length of the prefix of the input that decoded successfully.
+    pub valid_up_to: usize,
+}
+
+impl fmt::Display for ModifiedUtf8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid modified UTF-8 after byte {}", self.valid_up_to)
+    }
+}
+
+impl Error for ModifiedUtf8Error {}
+
+impl CpInfo {
+    /// The name of the kind of this entry, as used in the JVM specification (without the
+    /// `CONSTANT_` prefix).
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            CpInfo::ConstantClassInfo { .. } => "Class",
+            CpInfo::ConstantFieldrefInfo { .. } => "Fieldref",
+            CpInfo::ConstantMethodrefInfo { .. } => "Methodref",
+            CpInfo::ConstantInterfaceMethodrefInfo { .. } => "InterfaceMethodref",
+            CpInfo::ConstantStringInfo { .. } => "String",
+            CpInfo::ConstantIntegerInfo { .. } => "Integer",
+            CpInfo::ConstantFloatInfo { .. } => "Float",
+            CpInfo::ConstantLongInfo { .. } => "Long",
+            CpInfo::ConstantDoubleInfo { .. } => "Double",
+            CpInfo::ConstantNameAndTypeInfo { .. } => "NameAndType",
+            CpInfo::ConstantUtf8Info { .. } => "Utf8",
+            CpInfo::ConstantMethodHandleInfo { .. } => "MethodHandle",
+            CpInfo::ConstantMethodTypeInfo { .. } => "MethodType",
+            CpInfo::ConstantDynamicInfo { .. } => "Dynamic",
+            CpInfo::ConstantInvokeDynamicInfo { .. } => "InvokeDynamic",
+            CpInfo::ConstantModuleInfo { .. } => "Module",
+            CpInfo::ConstantPackageInfo { .. } => "Package",
+        }
+    }
+}
+
+/// Decode the "modified UTF-8" contents of a `CONSTANT_Utf8_info` entry (JVMS 4.4.7).
+///
+/// Modified UTF-8 differs from standard UTF-8 in that NUL is encoded as `0xC0 0x80` and
+/// supplementary characters are encoded as a surrogate pair of two three-byte sequences.
+/// Strings that contain neither are borrowed from `bytes`. Unpaired surrogates, which cannot be
+/// represented in a Rust string, are reported as errors.
+pub fn decode_modified_utf8(bytes: &[u8]) -> Result<Cow<'_, str>, ModifiedUtf8Error> {
+    // Standard UTF-8 without NUL bytes or four-byte sequences is also valid modified UTF-8.
+    if !bytes.iter().any(|&b| b == 0 || b >= 0xf0) {
+        if let Ok(s) = std::str::from_utf8(bytes) {
+            return Ok(Cow::Borrowed(s));
+        }
+    }
+
+    let mut decoded = String::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let err = ModifiedUtf8Error { valid_up_to: idx };
+        let (code_unit, width) = modified_utf8_code_unit(bytes, idx).ok_or(err)?;
+
+        let ch = match code_unit {
+            // a high surrogate must be followed by a low surrogate
+            0xd800..=0xdbff => match modified_utf8_code_unit(bytes, idx + width) {
+                Some((low @ 0xdc00..=0xdfff, low_width)) => {
+                    idx += low_width;
+                    let code_point =
+                        0x10000 + (((code_unit as u32) - 0xd800) << 10) + (low as u32 - 0xdc00);
+                    char::from_u32(code_point).ok_or(err)?
+                }
+                _ => return Err(err),
+            },
+            _ => char::from_u32(code_unit as u32).ok_or(err)?,
+        };
+
+        decoded.push(ch);
+        idx += width;
+    }
+
+    Ok(Cow::Owned(decoded))
+}
+
+/// Return whether `bytes` are well-formed modified UTF-8. Unlike `decode_modified_utf8`, this
+/// accepts unpaired surrogates, which are valid in the strings of a class file.
+pub(crate) fn is_modified_utf8(bytes: &[u8]) -> bool {
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match modified_utf8_code_unit(bytes, idx) {
+            Some((_, width)) => idx += width,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Decode the single UTF-16 code unit starting at `bytes[idx]`, returning it along with the
+/// number of bytes it was encoded in.
+fn modified_utf8_code_unit(bytes: &[u8], idx: usize) -> Option<(u16, usize)> {
+    let continuation = |offset: usize| match bytes.get(idx + offset) {
+        Some(&b) if b & 0xc0 == 0x80 => Some((b & 0x3f) as u16),
+        _ => None,
+    };
+
+    match *bytes.get(idx)? {
+        b @ 0x01..=0x7f => Some((b as u16, 1)),
+        b @ 0xc0..=0xdf => Some((((b & 0x1f) as u16) << 6 | continuation(1)?, 2)),
+        b @ 0xe0..=0xef => Some((
+            ((b & 0x0f) as u16) << 12 | continuation(1)? << 6 | continuation(2)?,
+            3,
+        )),
+        _ => None,
+    }
+}
+
+/// Encode `s` as "modified UTF-8" for a `CONSTANT_Utf8_info` entry (JVMS 4.4.7). Strings that
+/// contain neither NUL nor supplementary characters are borrowed, as their standard UTF-8
+/// encoding is identical.
+pub fn encode_modified_utf8(s: &str) -> Cow<'_, [u8]> {
+    if !s.bytes().any(|b| b == 0 || b >= 0xf0) {
+        return Cow::Borrowed(s.as_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(s.len() + 2);
+    for code_unit in s.encode_utf16() {
+        match code_unit {
+            0x0001..=0x007f => bytes.push(code_unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (code_unit >> 6) as u8);
+                bytes.push(0x80 | (code_unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (code_unit >> 12) as u8);
+                bytes.push(0x80 | ((code_unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (code_unit & 0x3f) as u8);
+            }
+        }
+    }
+
+    Cow::Owned(bytes)
+}
+
+/// The kind of a member reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberRefKind {
+    Field,
+    Method,
+    InterfaceMethod,
+}
+
+/// A resolved `CONSTANT_NameAndType_info` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameAndType<'a> {
+    pub name: Cow<'a, str>,
+    pub descriptor: Cow<'a, str>,
+}
+
+/// A resolved `CONSTANT_Fieldref_info`, `CONSTANT_Methodref_info`, or
+/// `CONSTANT_InterfaceMethodref_info` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberRef<'a> {
+    pub kind: MemberRefKind,
+    /// The binary name of the class or interface declaring the member.
+    pub owner: Cow<'a, str>,
+    pub name: Cow<'a, str>,
+    pub descriptor: Cow<'a, str>,
+}
+
+/// A resolved `CONSTANT_MethodHandle_info` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodHandle<'a> {
+    pub reference_kind: u8,
+    pub reference: MemberRef<'a>,
+}
+
+/// A resolved `CONSTANT_Dynamic_info` or `CONSTANT_InvokeDynamic_info` entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DynamicRef<'a> {
+    /// Index into the `bootstrap_methods` array of the `BootstrapMethods` attribute.
+    pub bootstrap_method_attr_index: u16,
+    pub name: Cow<'a, str>,
+    pub descriptor: Cow<'a, str>,
+}
+
+/// The constant pool of a class file.
+///
+/// Entries are stored at their constant pool index, so index 0 and the slot following each
+/// `Long` or `Double` entry are `None`. The pool dereferences to the underlying slice of entries,
+/// and the `get_*` methods resolve entries (and the entries they refer to) into typed values,
+/// failing on dangling indices and on entries of the wrong kind.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConstantPool {
+    entries: Vec<Option<CpInfo>>,
+}
+
+impl ConstantPool {
+    pub fn new() -> Self {
+        ConstantPool::default()
+    }
+
+    /// The entries of the pool, indexed by their constant pool index.
+    pub fn entries(&self) -> &[Option<CpInfo>] {
+        &self.entries
+    }
+
+    /// The `constant_pool_count` of a class file with this pool, i.e., one more than the largest
+    /// index.
+    pub fn count(&self) -> u16 {
+        self.entries.len().max(1) as u16
+    }
+
+    /// Get the entry at `index`.
+    pub fn entry(&self, index: u16) -> ConstantPoolResult<&CpInfo> {
+        match self.entries.get(index as usize) {
+            Some(Some(cp_info)) => Ok(cp_info),
+            _ => Err(ConstantPoolError::InvalidIndex(index)),
+        }
+    }
+
+    fn unexpected<T>(
+        &self,
+        index: u16,
+        expected: &'static str,
+        found: &CpInfo,
+    ) -> ConstantPoolResult<T> {
+        Err(ConstantPoolError::UnexpectedEntry {
+            index,
+            expected,
+            found: found.kind_name(),
+        })
+    }
+
+    /// Get the raw bytes of the `CONSTANT_Utf8_info` entry at `index`.
+    pub fn get_utf8_bytes(&self, index: u16) -> ConstantPoolResult<&[u8]> {
+        match self.entry(index)? {
+            CpInfo::ConstantUtf8Info { bytes, .. } => Ok(bytes),
+            cp_info => self.unexpected(index, "Utf8", cp_info),
+        }
+    }
+
+    /// Get the string stored in the `CONSTANT_Utf8_info` entry at `index`.
+    pub fn get_utf8(&self, index: u16) -> ConstantPoolResult<Cow<'_, str>> {
+        let bytes = self.get_utf8_bytes(index)?;
+        decode_modified_utf8(bytes).map_err(|_| ConstantPoolError::InvalidUtf8(index))
+    }
+
+    /// Get the binary name (e.g., `java/lang/Object`) of the `CONSTANT_Class_info` entry at
+    /// `index`.
+    pub fn get_class_name(&self, index: u16) -> ConstantPoolResult<Cow<'_, str>> {
+        match self.entry(index)? {
+            CpInfo::ConstantClassInfo { name_index, .. } => self.get_utf8(*name_index),
+            cp_info => self.unexpected(index, "Class", cp_info),
+        }
+    }
+
+    /// Get the string value of the `CONSTANT_String_info` entry at `index`.
+    pub fn get_string(&self, index: u16) -> ConstantPoolResult<Cow<'_, str>> {
+        match self.entry(index)? {
+            CpInfo::ConstantStringInfo { string_index, .. } => self.get_utf8(*string_index),
+            cp_info => self.unexpected(index, "String", cp_info),
+        }
+    }
+
+    pub fn get_integer(&self, index: u16) -> ConstantPoolResult<i32> {
+        match self.entry(index)? {
+            CpInfo::ConstantIntegerInfo { bytes, .. } => Ok(*bytes as i32),
+            cp_info => self.unexpected(index, "Integer", cp_info),
+        }
+    }
+
+    pub fn get_float(&self, index: u16) -> ConstantPoolResult<f32> {
+        match self.entry(index)? {
+            CpInfo::ConstantFloatInfo { bytes, .. } => Ok(f32::from_bits(*bytes)),
+            cp_info => self.unexpected(index, "Float", cp_info),
+        }
+    }
+
+    pub fn get_long(&self, index: u16) -> ConstantPoolResult<i64> {
+        match self.entry(index)? {
+            CpInfo::ConstantLongInfo {
+                high_bytes,
+                low_bytes,
+                ..
+            } => Ok((((*high_bytes as u64) << 32) | *low_bytes as u64) as i64),
+            cp_info => self.unexpected(index, "Long", cp_info),
+        }
+    }
+
+    pub fn get_double(&self, index: u16) -> ConstantPoolResult<f64> {
+        match self.entry(index)? {
+            CpInfo::ConstantDoubleInfo {
+                high_bytes,
+                low_bytes,
+                ..
+            } => Ok(f64::from_bits(
+                ((*high_bytes as u64) << 32) | *low_bytes as u64,
+            )),
+            cp_info => self.unexpected(index, "Double", cp_info),
+        }
+    }
+
+    pub fn get_name_and_type(&self, index: u16) -> ConstantPoolResult<NameAndType<'_>> {
+        match self.entry(index)? {
+            CpInfo::ConstantNameAndTypeInfo {
+                name_index,
+                descriptor_index,
+                ..
+            } => Ok(NameAndType {
+                name: self.get_utf8(*name_index)?,
+                descriptor: self.get_utf8(*descriptor_index)?,
+            }),
+            cp_info => self.unexpected(index, "NameAndType", cp_info),
+        }
+    }
+
+    /// Get the field or method referred to by the `CONSTANT_Fieldref_info`,
+    /// `CONSTANT_Methodref_info`, or `CONSTANT_InterfaceMethodref_info` entry at `index`.
+    pub fn get_member_ref(&self, index: u16) -> ConstantPoolResult<MemberRef<'_>> {
+        let (kind, class_index, name_and_type_index) = match self.entry(index)? {
+            CpInfo::ConstantFieldrefInfo {
+                class_index,
+                name_and_type_index,
+                ..
+            } => (MemberRefKind::Field, class_index, name_and_type_index),
+            CpInfo::ConstantMethodrefInfo {
+                class_index,
+                name_and_type_index,
+                ..
+            } => (MemberRefKind::Method, class_index, name_and_type_index),
+            CpInfo::ConstantInterfaceMethodrefInfo {
+                class_index,
+                name_and_type_index,
+                ..
+            } => (
+                MemberRefKind::InterfaceMethod,
+                class_index,
+                name_and_type_index,
+            ),
+            cp_info => return self.unexpected(index, "Fieldref or Methodref", cp_info),
+        };
+
+        let NameAndType { name, descriptor } = self.get_name_and_type(*name_and_type_index)?;
+        Ok(MemberRef {
+            kind,
+            owner: self.get_class_name(*class_index)?,
+            name,
+            descriptor,
+        })
+    }
+
+    pub fn get_method_handle(&self, index: u16) -> ConstantPoolResult<MethodHandle<'_>> {
+        match self.entry(index)? {
+            CpInfo::ConstantMethodHandleInfo {
+                reference_kind,
+                reference_index,
+                ..
+            } => Ok(MethodHandle {
+                reference_kind: *reference_kind,
+                reference: self.get_member_ref(*reference_index)?,
+            }),
+            cp_info => self.unexpected(index, "MethodHandle", cp_info),
+        }
+    }
+
+    /// Get the method descriptor of the `CONSTANT_MethodType_info` entry at `index`.
+    pub fn get_method_type(&self, index: u16) -> ConstantPoolResult<Cow<'_, str>> {
+        match self.entry(index)? {
+            CpInfo::ConstantMethodTypeInfo {
+                descriptor_index, ..
+            } => self.get_utf8(*descriptor_index),
+            cp_info => self.unexpected(index, "MethodType", cp_info),
+        }
+    }
+
+    /// Get the `CONSTANT_Dynamic_info` or `CONSTANT_InvokeDynamic_info` entry at `index`.
+    pub fn get_dynamic(&self, index: u16) -> ConstantPoolResult<DynamicRef<'_>> {
+        let (bootstrap_method_attr_index, name_and_type_index) = match self.entry(index)? {
+            CpInfo::ConstantDynamicInfo {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+                ..
+            }
+            | CpInfo::ConstantInvokeDynamicInfo {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+                ..
+            } => (*bootstrap_method_attr_index, *name_and_type_index),
+            cp_info => return self.unexpected(index, "Dynamic or InvokeDynamic", cp_info),
+        };
+
+        let NameAndType { name, descriptor } = self.get_name_and_type(name_and_type_index)?;
+        Ok(DynamicRef {
+            bootstrap_method_attr_index,
+            name,
+            descriptor,
+        })
+    }
+
+    pub fn get_module_name(&self, index: u16) -> ConstantPoolResult<Cow<'_, str>> {
+        match self.entry(index)? {
+            CpInfo::ConstantModuleInfo { name_index, .. } => self.get_utf8(*name_index),
+            cp_info => self.unexpected(index, "Module", cp_info),
+        }
+    }
+
+    pub fn get_package_name(&self, index: u16) -> ConstantPoolResult<Cow<'_, str>> {
+        match self.entry(index)? {
+            CpInfo::ConstantPackageInfo { name_index, .. } => self.get_utf8(*name_index),
+            cp_info => self.unexpected(index, "Package", cp_info),
+        }
+    }
+}
+
+impl Deref for ConstantPool {
+    type Target = [Option<CpInfo>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl From<Vec<Option<CpInfo>>> for ConstantPool {
+    fn from(entries: Vec<Option<CpInfo>>) -> Self {
+        ConstantPool { entries }
+    }
+}
+
+impl From<ConstantPool> for Vec<Option<CpInfo>> {
+    fn from(constant_pool: ConstantPool) -> Self {
+        constant_pool.entries
+    }
+}
+
+/// The largest number of slots (including the unused slot 0) a constant pool can have.
+const MAX_CONSTANT_POOL_COUNT: usize = u16::MAX as usize;
+
+/// Builds a `ConstantPool`, assigning indices to entries as they are added.
+///
+/// Every method returns the index of the requested entry, adding it (and any entries it refers
+/// to) only if an identical entry is not already present. `Long` and `Double` entries take up two
+/// slots.
+#[derive(Debug, Clone)]
+pub struct ConstantPoolBuilder {
+    entries: Vec<Option<CpInfo>>,
+    indices: HashMap<CpInfo, u16>,
+}
+
+impl Default for ConstantPoolBuilder {
+    fn default() -> Self {
+        ConstantPoolBuilder {
+            entries: vec![None],
+            indices: HashMap::new(),
+        }
+    }
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        ConstantPoolBuilder::default()
+    }
+
+    /// Add `cp_info` to the pool, or return the index of an identical entry.
+    pub fn add(&mut self, cp_info: CpInfo) -> ConstantPoolResult<u16> {
+        if let Some(&index) = self.indices.get(&cp_info) {
+            return Ok(index);
+        }
+
+        let slots = match cp_info {
+            CpInfo::ConstantLongInfo { .. } | CpInfo::ConstantDoubleInfo { .. } => 2,
+            _ => 1,
+        };
+        if self.entries.len() + slots > MAX_CONSTANT_POOL_COUNT {
+            return Err(ConstantPoolError::TooManyEntries);
+        }
+
+        let index = self.entries.len() as u16;
+        self.indices.insert(cp_info.clone(), index);
+        self.entries.push(Some(cp_info));
+        if slots == 2 {
+            self.entries.push(None);
+        }
+
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, s: &str) -> ConstantPoolResult<u16> {
+        let bytes = encode_modified_utf8(s).into_owned();
+        let length = u16::try_from(bytes.len())
+            .map_err(|_| ConstantPoolError::StringTooLong(bytes.len()))?;
+
+        self.add(CpInfo::ConstantUtf8Info {
+            tag: CONSTANT_UTF8,
+            length,
+            bytes,
+        })
+    }
+
+    /// Add a `CONSTANT_Class_info` entry for the class with binary name `name`, e.g.,
+    /// `java/lang/Object`.
+    pub fn class(&mut self, name: &str) -> ConstantPoolResult<u16> {
+        let name_index = self.utf8(name)?;
+        self.add(CpInfo::ConstantClassInfo {
+            tag: CONSTANT_CLASS,
+            name_index,
+        })
+    }
+
+    pub fn string(&mut self, s: &str) -> ConstantPoolResult<u16> {
+        let string_index = self.utf8(s)?;
+        self.add(CpInfo::ConstantStringInfo {
+            tag: CONSTANT_STRING,
+            string_index,
+        })
+    }
+
+    pub fn integer(&mut self, value: i32) -> ConstantPoolResult<u16> {
+        self.add(CpInfo::ConstantIntegerInfo {
+            tag: CONSTANT_INTEGER,
+            bytes: value as u32,
+        })
+    }
+
+    pub fn float(&mut self, value: f32) -> ConstantPoolResult<u16> {
+        self.add(CpInfo::ConstantFloatInfo {
+            tag: CONSTANT_FLOAT,
+            bytes: value.to_bits(),
+        })
+    }
+
+    pub fn long(&mut self, value: i64) -> ConstantPoolResult<u16> {
+        self.add(CpInfo::ConstantLongInfo {
+            tag: CONSTANT_LONG,
+            high_bytes: ((value as u64) >> 32) as u32,
+            low_bytes: value as u32,
+        })
+    }
+
+    pub fn double(&mut self, value: f64) -> ConstantPoolResult<u16> {
+        let bits = value.to_bits();
+        self.add(CpInfo::ConstantDoubleInfo {
+            tag: CONSTANT_DOUBLE,
+            high_bytes: (bits >> 32) as u32,
+            low_bytes: bits as u32,
+        })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> ConstantPoolResult<u16> {
+        let name_index = self.utf8(name)?;
+        let descriptor_index = self.utf8(descriptor)?;
+        self.add(CpInfo::ConstantNameAndTypeInfo {
+            tag: CONSTANT_NAME_AND_TYPE,
+            name_index,
+            descriptor_index,
+        })
+    }
+
+    /// Add a `CONSTANT_Fieldref_info`, `CONSTANT_Methodref_info`, or
+    /// `CONSTANT_InterfaceMethodref_info` entry, depending on `kind`.
+    pub fn member_ref(
+        &mut self,
+        kind: MemberRefKind,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        let class_index = self.class(owner)?;
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.add(match kind {
+            MemberRefKind::Field => CpInfo::ConstantFieldrefInfo {
+                tag: CONSTANT_FIELD_REF,
+                class_index,
+                name_and_type_index,
+            },
+            MemberRefKind::Method => CpInfo::ConstantMethodrefInfo {
+                tag: CONSTANT_METHOD_REF,
+                class_index,
+                name_and_type_index,
+            },
+            MemberRefKind::InterfaceMethod => CpInfo::ConstantInterfaceMethodrefInfo {
+                tag: CONSTANT_INTERFACE_METHOD_REF,
+                class_index,
+                name_and_type_index,
+            },
+        })
+    }
+
+    pub fn field_ref(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        self.member_ref(MemberRefKind::Field, owner, name, descriptor)
+    }
+
+    pub fn method_ref(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        self.member_ref(MemberRefKind::Method, owner, name, descriptor)
+    }
+
+    pub fn interface_method_ref(
+        &mut self,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        self.member_ref(MemberRefKind::InterfaceMethod, owner, name, descriptor)
+    }
+
+    /// Add a `CONSTANT_MethodHandle_info` entry with the given `reference_kind` (1 - 9, e.g.
+    /// 6 for `REF_invokeStatic`) referring to the given member.
+    pub fn method_handle(
+        &mut self,
+        reference_kind: u8,
+        kind: MemberRefKind,
+        owner: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        let reference_index = self.member_ref(kind, owner, name, descriptor)?;
+        self.add(CpInfo::ConstantMethodHandleInfo {
+            tag: CONSTANT_METHOD_HANDLE,
+            reference_kind,
+            reference_index,
+        })
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> ConstantPoolResult<u16> {
+        let descriptor_index = self.utf8(descriptor)?;
+        self.add(CpInfo::ConstantMethodTypeInfo {
+            tag: CONSTANT_METHOD_TYPE,
+            descriptor_index,
+        })
+    }
+
+    pub fn dynamic(
+        &mut self,
+        bootstrap_method_attr_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.add(CpInfo::ConstantDynamicInfo {
+            tag: CONSTANT_DYNAMIC,
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method_attr_index: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> ConstantPoolResult<u16> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.add(CpInfo::ConstantInvokeDynamicInfo {
+            tag: CONSTANT_INVOKE_DYNAMIC,
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn module(&mut self, name: &str) -> ConstantPoolResult<u16> {
+        let name_index = self.utf8(name)?;
+        self.add(CpInfo::ConstantModuleInfo {
+            tag: CONSTANT_MODULE,
+            name_index,
+        })
+    }
+
+    pub fn package(&mut self, name: &str) -> ConstantPoolResult<u16> {
+        let name_index = self.utf8(name)?;
+        self.add(CpInfo::ConstantPackageInfo {
+            tag: CONSTANT_PACKAGE,
+            name_index,
+        })
+    }
+
+    /// The `constant_pool_count` of the pool built so far.
+    pub fn count(&self) -> u16 {
+        self.entries.len() as u16
+    }
+
+    pub fn build(self) -> ConstantPool {
+        ConstantPool {
+            entries: self.entries,
+        }
+    }
+}
+
+impl From<ConstantPool> for ConstantPoolBuilder {
+    /// Continue building an existing pool. Existing entries keep their indices.
+    fn from(constant_pool: ConstantPool) -> Self {
+        let mut entries = constant_pool.entries;
+        if entries.is_empty() {
+            entries.push(None);
+        }
+
+        let mut indices = HashMap::new();
+        for (index, cp_info) in entries.iter().enumerate() {
+            if let Some(cp_info) = cp_info {
+                indices.entry(cp_info.clone()).or_insert(index as u16);
+            }
+        }
+
+        ConstantPoolBuilder { entries, indices }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tags::*, *};
+
+    fn utf8(s: &str) -> Option<CpInfo> {
+        Some(CpInfo::ConstantUtf8Info {
+            tag: CONSTANT_UTF8,
+            length: s.len() as u16,
+            bytes: s.as_bytes().to_vec(),
+        })
+    }
+
+    fn constant_pool() -> ConstantPool {
+        vec![
+            None,
+            Some(CpInfo::ConstantMethodrefInfo {
+                tag: CONSTANT_METHOD_REF,
+                class_index: 2,
+                name_and_type_index: 3,
+            }),
+            Some(CpInfo::ConstantClassInfo {
+                tag: CONSTANT_CLASS,
+                name_index: 4,
+            }),
+            Some(CpInfo::ConstantNameAndTypeInfo {
+                tag: CONSTANT_NAME_AND_TYPE,
+                name_index: 5,
+                descriptor_index: 6,
+            }),
+            utf8("java/lang/Object"),
+            utf8("<init>"),
+            utf8("()V"),
+            Some(CpInfo::ConstantLongInfo {
+                tag: CONSTANT_LONG,
+                high_bytes: 0xffff_ffff,
+                low_bytes: 0xffff_fffe,
+            }),
+            None,
+            Some(CpInfo::ConstantDoubleInfo {
+                tag: CONSTANT_DOUBLE,
+                high_bytes: 0x3ff8_0000,
+                low_bytes: 0,
+            }),
+            None,
+            Some(CpInfo::ConstantIntegerInfo {
+                tag: CONSTANT_INTEGER,
+                bytes: 0xffff_ff85,
+            }),
+        ]
+        .into()
+    }
+
+    #[test]
+    fn test_typed_accessors() -> ConstantPoolResult<()> {
+        let cp = constant_pool();
+
+        assert_eq!(cp.get_utf8(5)?, "<init>");
+        assert_eq!(cp.get_class_name(2)?, "java/lang/Object");
+        assert_eq!(
+            cp.get_member_ref(1)?,
+            MemberRef {
+                kind: MemberRefKind::Method,
+                owner: "java/lang/Object".into(),
+                name: "<init>".into(),
+                descriptor: "()V".into(),
+            }
+        );
+        assert_eq!(cp.get_long(7)?, -2);
+        assert_eq!(cp.get_double(9)?, 1.5);
+        assert_eq!(cp.get_integer(11)?, -123);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_utf8() {
+        let cases: [(&str, &[u8]); 4] = [
+            ("java/lang/Object", b"java/lang/Object"),
+            (
+                "caf\u{e9} \u{20ac}",
+                &[0x63, 0x61, 0x66, 0xc3, 0xa9, 0x20, 0xe2, 0x82, 0xac],
+            ),
+            ("a\0b", &[0x61, 0xc0, 0x80, 0x62]),
+            ("\u{1f600}", &[0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]),
+        ];
+
+        for (s, bytes) in cases {
+            assert_eq!(encode_modified_utf8(s), bytes);
+            assert_eq!(decode_modified_utf8(bytes).unwrap(), s);
+        }
+
+        assert!(matches!(
+            decode_modified_utf8(b"Object"),
+            Ok(Cow::Borrowed("Object"))
+        ));
+        assert_eq!(
+            decode_modified_utf8(&[0x61, 0x00]),
+            Err(ModifiedUtf8Error { valid_up_to: 1 })
+        );
+        assert_eq!(
+            decode_modified_utf8(&[0x61, 0xe2, 0x82]),
+            Err(ModifiedUtf8Error { valid_up_to: 1 })
+        );
+        // standard UTF-8 encoding of a supplementary character
+        assert!(decode_modified_utf8(&[0xf0, 0x9f, 0x98, 0x80]).is_err());
+        // unpaired surrogate
+        assert!(decode_modified_utf8(&[0xed, 0xa0, 0xbd]).is_err());
+    }
+
+    #[test]
+    fn test_builder() -> ConstantPoolResult<()> {
+        let mut builder = ConstantPoolBuilder::new();
+        assert_eq!(builder.method_ref("java/lang/Object", "<init>", "()V")?, 6);
+        assert_eq!(builder.long(-2)?, 7);
+        assert_eq!(builder.double(1.5)?, 9);
+        assert_eq!(builder.integer(-123)?, 11);
+
+        // interned
+        assert_eq!(builder.class("java/lang/Object")?, 2);
+        assert_eq!(builder.utf8("()V")?, 4);
+        assert_eq!(builder.long(-2)?, 7);
+        assert_eq!(builder.count(), 12);
+
+        let cp = builder.build();
+        assert_eq!(cp.count(), 12);
+        assert_eq!(cp.entry(8), Err(ConstantPoolError::InvalidIndex(8)));
+        assert_eq!(
+            cp.get_member_ref(6)?,
+            MemberRef {
+                kind: MemberRefKind::Method,
+                owner: "java/lang/Object".into(),
+                name: "<init>".into(),
+                descriptor: "()V".into(),
+            }
+        );
+        assert_eq!(cp.get_long(7)?, -2);
+        assert_eq!(cp.get_double(9)?, 1.5);
+        assert_eq!(cp.get_integer(11)?, -123);
+
+        let mut builder = ConstantPoolBuilder::from(cp);
+        assert_eq!(builder.name_and_type("<init>", "()V")?, 5);
+        assert_eq!(builder.string("<init>")?, 12);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_limits() -> ConstantPoolResult<()> {
+        let mut builder = ConstantPoolBuilder::new();
+        assert_eq!(
+            builder.utf8(&"x".repeat(70000)),
+            Err(ConstantPoolError::StringTooLong(70000))
+        );
+
+        for value in 0..65533 {
+            builder.integer(value)?;
+        }
+        assert_eq!(builder.long(0), Err(ConstantPoolError::TooManyEntries));
+        assert_eq!(builder.integer(65533)?, 65534);
+        assert_eq!(
+            builder.integer(65534),
+            Err(ConstantPoolError::TooManyEntries)
+        );
+        assert_eq!(builder.integer(0)?, 1);
+        assert_eq!(builder.count(), 65535);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_accesses() {
+        let cp = constant_pool();
+
+        assert_eq!(cp.get_utf8(0), Err(ConstantPoolError::InvalidIndex(0)));
+        assert_eq!(cp.get_long(8), Err(ConstantPoolError::InvalidIndex(8)));
+        assert_eq!(cp.get_utf8(100), Err(ConstantPoolError::InvalidIndex(100)));
+        assert_eq!(
+            cp.get_class_name(4),
+            Err(ConstantPoolError::UnexpectedEntry {
+                index: 4,
+                expected: "Class",
+                found: "Utf8",
+            })
+        );
+    }
+}
@@ -5,7 +5,7 @@ pub mod attributes;
 pub mod constant_pool;
 
 use attributes::AttributeInfo;
-use constant_pool::types::CpInfo;
+use constant_pool::ConstantPool;
 
 #[derive(Debug, Default)]
 pub struct ClassFile {
@@ -13,7 +13,7 @@ pub struct ClassFile {
     pub minor_version: u16,
     pub major_version: u16,
     pub constant_pool_count: u16,
-    pub constant_pool: Vec<Option<CpInfo>>,
+    pub constant_pool: ConstantPool,
     pub access_flags: u16,
     pub this_class: u16,
     pub super_class: u16,
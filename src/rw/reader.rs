@@ -3,27 +3,94 @@
 use super::HasBeBytes;
 use crate::error::ReadError;
 
-use std::io::Read;
+use std::io::{self, Read};
 
 pub type ReadResult<T> = Result<T, ReadError>;
 
+/// The largest number of bytes `Reader::read_bytes` allocates ahead of reading them.
+const MAX_PREALLOCATION: usize = 1 << 16;
+
+/// The reads of fixed-size items common to `Reader` and `SliceReader`, so that code decoding
+/// such items can be shared between the deserializers that use them.
+pub(crate) trait ByteReader {
+    fn read_unsigned_byte(&mut self) -> ReadResult<u8>;
+    fn read_unsigned_short(&mut self) -> ReadResult<u16>;
+    fn read_unsigned_int(&mut self) -> ReadResult<u32>;
+}
+
 /// The `Reader` is used to read bytes off of a stream
 /// over raw ``class` file objects.
 pub struct Reader<R: Read> {
     reader: R,
+    position: usize,
 }
 
 impl<R: Read> Reader<R> {
     pub fn new(reader: R) -> Self {
-        Reader { reader }
+        Reader {
+            reader,
+            position: 0,
+        }
+    }
+
+    /// Create a reader over `reader`, which starts at offset `position` of a larger byte
+    /// stream, so that errors report offsets in that stream.
+    pub(crate) fn at_position(reader: R, position: usize) -> Self {
+        Reader { reader, position }
+    }
+
+    /// The number of bytes read from the byte stream so far.
+    pub fn position(&self) -> usize {
+        self.position
     }
 
     fn read_n<T: HasBeBytes<N>, const N: usize>(&mut self) -> ReadResult<T> {
         let mut buf = [0u8; N];
-        self.reader.read_exact(&mut buf)?;
+        self.reader
+            .read_exact(&mut buf)
+            .map_err(|err| ReadError::new(self.position, err))?;
+        self.position += N;
         Ok(T::from_be_bytes(buf))
     }
 
+    /// Read the next `len` bytes from the byte stream.
+    ///
+    /// Lengths read from a class file may be far larger than the file itself, so the buffer
+    /// grows by at most `MAX_PREALLOCATION` bytes at a time as the bytes are actually read.
+    pub fn read_bytes(&mut self, len: usize) -> ReadResult<Vec<u8>> {
+        let mut buf = Vec::new();
+        while buf.len() < len {
+            let filled = buf.len();
+            buf.resize(len.min(filled + MAX_PREALLOCATION), 0);
+            self.fill(&mut buf[filled..])?;
+        }
+
+        Ok(buf)
+    }
+
+    /// Fill `buf` from the byte stream like `Read::read_exact`, but failing with the offset of
+    /// the first byte that could not be read.
+    fn fill(&mut self, mut buf: &mut [u8]) -> ReadResult<()> {
+        while !buf.is_empty() {
+            match self.reader.read(buf) {
+                Ok(0) => {
+                    return Err(ReadError::new(
+                        self.position,
+                        io::Error::from(io::ErrorKind::UnexpectedEof),
+                    ))
+                }
+                Ok(read) => {
+                    self.position += read;
+                    buf = &mut buf[read..];
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(ReadError::new(self.position, err)),
+            }
+        }
+
+        Ok(())
+    }
+
     /// Read an unsigned byte (8 bits) from the byte stream.
     pub fn read_unsigned_byte(&mut self) -> ReadResult<u8> {
         self.read_n::<u8, 1>()
@@ -45,6 +112,101 @@ impl<R: Read> Reader<R> {
     }
 }
 
+impl<R: Read> ByteReader for Reader<R> {
+    fn read_unsigned_byte(&mut self) -> ReadResult<u8> {
+        Reader::read_unsigned_byte(self)
+    }
+
+    fn read_unsigned_short(&mut self) -> ReadResult<u16> {
+        Reader::read_unsigned_short(self)
+    }
+
+    fn read_unsigned_int(&mut self) -> ReadResult<u32> {
+        Reader::read_unsigned_int(self)
+    }
+}
+
+/// The `SliceReader` reads bytes from a byte slice, and can borrow runs of bytes from it
+/// instead of copying them.
+#[derive(Debug, Clone)]
+pub struct SliceReader<'a> {
+    /// The bytes that have not been read yet.
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SliceReader { bytes, position: 0 }
+    }
+
+    /// Create a reader over `bytes`, which start at offset `position` of a larger byte stream,
+    /// so that errors report offsets in that stream.
+    pub(crate) fn at_position(bytes: &'a [u8], position: usize) -> Self {
+        SliceReader { bytes, position }
+    }
+
+    /// The number of bytes read from the byte stream so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Borrow the next `len` bytes.
+    pub fn read_bytes(&mut self, len: usize) -> ReadResult<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(ReadError::new(
+                self.position + self.bytes.len(),
+                io::Error::from(io::ErrorKind::UnexpectedEof),
+            ));
+        }
+
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn read_n<T: HasBeBytes<N>, const N: usize>(&mut self) -> ReadResult<T> {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.read_bytes(N)?);
+        Ok(T::from_be_bytes(buf))
+    }
+
+    /// Read an unsigned byte (8 bits) from the byte stream.
+    pub fn read_unsigned_byte(&mut self) -> ReadResult<u8> {
+        self.read_n::<u8, 1>()
+    }
+
+    /// Read an unsigned short (16 bits) from the byte stream.
+    pub fn read_unsigned_short(&mut self) -> ReadResult<u16> {
+        self.read_n::<u16, 2>()
+    }
+
+    /// Read an unsigned int (32 bits) from the byte stream.
+    pub fn read_unsigned_int(&mut self) -> ReadResult<u32> {
+        self.read_n::<u32, 4>()
+    }
+
+    /// Read an unsigned long (64 bits) from the byte stream.
+    pub fn read_unsigned_long(&mut self) -> ReadResult<u64> {
+        self.read_n::<u64, 8>()
+    }
+}
+
+impl ByteReader for SliceReader<'_> {
+    fn read_unsigned_byte(&mut self) -> ReadResult<u8> {
+        SliceReader::read_unsigned_byte(self)
+    }
+
+    fn read_unsigned_short(&mut self) -> ReadResult<u16> {
+        SliceReader::read_unsigned_short(self)
+    }
+
+    fn read_unsigned_int(&mut self) -> ReadResult<u32> {
+        SliceReader::read_unsigned_int(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -85,4 +247,48 @@ mod tests {
 
         assert_eq!(reader.read_unsigned_long().unwrap(), 0x00000041000f0a00);
     }
+
+    #[test]
+    fn test_read_position() {
+        let bytes = [0xca, 0xfe, 0xba, 0xbe, 0x00];
+        let mut reader = Reader::new(Cursor::new(bytes));
+
+        assert_eq!(reader.read_unsigned_int().unwrap(), 0xcafebabe);
+        assert_eq!(reader.position(), 4);
+
+        let err = reader.read_unsigned_short().unwrap_err();
+        assert_eq!(err.offset(), 4);
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_slice_reader() {
+        let bytes = [0xca, 0xfe, 0xba, 0xbe, 0x00, 0x03, 0x61, 0x62];
+        let mut reader = SliceReader::new(&bytes);
+
+        assert_eq!(reader.read_unsigned_int().unwrap(), 0xcafebabe);
+        let len = reader.read_unsigned_short().unwrap();
+        assert_eq!(reader.position(), 6);
+
+        let err = reader.read_bytes(len as usize).unwrap_err();
+        assert_eq!(err.offset(), 8);
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(reader.read_bytes(2).unwrap(), b"ab");
+        assert_eq!(reader.read_bytes(2).unwrap_err().offset(), 8);
+    }
+
+    #[test]
+    fn test_read_bytes() {
+        let bytes = [0xca, 0xfe, 0xba, 0xbe, 0x00];
+        let mut reader = Reader::new(Cursor::new(bytes));
+
+        assert_eq!(reader.read_bytes(2).unwrap(), [0xca, 0xfe]);
+        assert_eq!(reader.read_bytes(0).unwrap(), []);
+        assert_eq!(reader.position(), 2);
+
+        // a length far beyond the end of the stream does not allocate it up front
+        let err = reader.read_bytes(u32::MAX as usize).unwrap_err();
+        assert_eq!(err.offset(), 5);
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
 }
@@ -9,26 +9,38 @@ pub type WriteResult<T> = Result<T, WriteError>;
 /// The `Writer` is used to write the bytes of a class file to a stream
 pub struct Writer<'a, W: Write> {
     writer: &'a mut W,
+    position: usize,
 }
 
 impl<'a, W: Write> Writer<'a, W> {
     pub fn new(writer: &'a mut W) -> Self {
-        Writer { writer }
+        Writer {
+            writer,
+            position: 0,
+        }
+    }
+
+    /// Create a writer whose position starts at `position`, e.g. to write part of a larger
+    /// stream to a separate buffer.
+    pub(crate) fn at_position(writer: &'a mut W, position: usize) -> Self {
+        Writer { writer, position }
+    }
+
+    /// The number of bytes written to the byte stream so far.
+    pub fn position(&self) -> usize {
+        self.position
     }
 
     fn write_n<T: HasBeBytes<N>, const N: usize>(&mut self, data: T) -> WriteResult<()> {
-        let buf = data.to_be_bytes();
-        let len = std::mem::size_of::<T>();
-
-        if buf.len() != len {
-            return Err(WriteError::new(format!(
-                "error while writing bytes to stream: expected {} bytes, but found {}",
-                len,
-                buf.len()
-            )));
-        }
+        self.write_bytes(&data.to_be_bytes())
+    }
 
-        self.writer.write(&buf)?;
+    /// Write all of `bytes` to the byte stream.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> WriteResult<()> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|err| WriteError::new(self.position, err))?;
+        self.position += bytes.len();
         Ok(())
     }
 
@@ -100,4 +112,51 @@ mod tests {
 
         assert_eq!(buf, &[0x00, 0x00, 0x00, 0x41, 0x00, 0x0f, 0x0a, 0x00]);
     }
+
+    #[test]
+    fn test_write_position() {
+        let mut buf = Vec::new();
+
+        let mut writer = Writer::new(&mut buf);
+        let _ = writer.write_unsigned_short(0xcafe);
+        let _ = writer.write_unsigned_int(0xbabe0000);
+
+        assert_eq!(writer.position(), 6);
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        let mut buf = Vec::new();
+
+        let mut writer = Writer::new(&mut buf);
+        let _ = writer.write_unsigned_byte(0xca);
+        let _ = writer.write_bytes(&[0xfe, 0xba, 0xbe]);
+
+        assert_eq!(writer.position(), 4);
+        assert_eq!(buf, &[0xca, 0xfe, 0xba, 0xbe]);
+    }
+
+    #[test]
+    fn test_partial_writes() {
+        /// A stream that accepts at most one byte per call to `write`.
+        struct Trickle(Vec<u8>);
+
+        impl Write for Trickle {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.extend(buf.iter().take(1));
+                Ok(buf.len().min(1))
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut trickle = Trickle(Vec::new());
+        let mut writer = Writer::new(&mut trickle);
+        writer.write_unsigned_int(0xcafebabe).unwrap();
+        writer.write_bytes(&[0x00, 0x41]).unwrap();
+
+        assert_eq!(trickle.0, &[0xca, 0xfe, 0xba, 0xbe, 0x00, 0x41]);
+    }
 }
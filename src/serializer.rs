@@ -1,23 +1,179 @@
 //! Module to construct a Java (JVM) raw class file bytes from the class fileobject model.
 
 use crate::{
-    error::SerializeError,
-    model::{attributes::*, constant_pool::types::*, *},
+    analysis::{self, Maxs},
+    codec::AttributeRegistry,
+    error::{PathSegment, SerializeError, SerializeErrorKind},
+    model::{
+        attributes::*,
+        constant_pool::{types::*, ConstantPool},
+        *,
+    },
     rw::writer::Writer,
 };
 use std::io::Write;
+use std::sync::Arc;
 
 pub type SerializeResult<T> = Result<T, SerializeError>;
 
+/// The maximum `code_length` of a `Code` attribute (JVMS 4.7.3).
+const MAX_CODE_LENGTH: u32 = 65535;
+
+/// How the serializer treats the counts (e.g. `fields_count` or `num_annotations`) and lengths
+/// (e.g. `attribute_length` or `code_length`) stored in the model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CountMode {
+    /// Write the stored values as they are, even if they disagree with the data.
+    #[default]
+    Preserve,
+    /// Write the values derived from the data, ignoring the stored ones.
+    Recompute,
+    /// Fail with `SerializeErrorKind::CountMismatch` if a stored value disagrees with the data.
+    Strict,
+}
+
 /// The Serializer takes in the JVM class file object model, and writes a stream of valid
 /// JVM bytecode to the supplied writer.
 pub struct Serializer<'a, W: Write> {
     writer: Writer<'a, W>,
+    registry: Option<Arc<AttributeRegistry>>,
+    compute_maxs: bool,
+    method_maxs: Option<Maxs>,
+    count_mode: CountMode,
+    path: Vec<PathSegment>,
 }
 
 impl<'a, W: Write> Serializer<'a, W> {
     pub fn new(writer: Writer<'a, W>) -> Self {
-        Serializer { writer }
+        Serializer {
+            writer,
+            registry: None,
+            compute_maxs: false,
+            method_maxs: None,
+            count_mode: CountMode::Preserve,
+            path: Vec::new(),
+        }
+    }
+
+    /// Use the codecs in `registry` to encode `AttributeInfo::Custom` attributes.
+    pub fn with_attribute_registry(mut self, registry: Arc<AttributeRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set how the counts and lengths stored in the model are treated. Defaults to
+    /// `CountMode::Preserve`.
+    pub fn with_count_mode(mut self, count_mode: CountMode) -> Self {
+        self.count_mode = count_mode;
+        self
+    }
+
+    /// Write the `max_stack` and `max_locals` of each method's `Code` attribute as computed from
+    /// its instructions, exception handlers and descriptor, instead of the values in the model.
+    pub fn with_computed_maxs(mut self, enabled: bool) -> Self {
+        self.compute_maxs = enabled;
+        self
+    }
+
+    /// Return `actual`, the value of the count or length `field` derived from the data, failing
+    /// if it exceeds `max`.
+    fn actual_count(&self, field: &'static str, actual: usize, max: u32) -> SerializeResult<u32> {
+        match u32::try_from(actual) {
+            Ok(count) if count <= max => Ok(count),
+            _ => Err(SerializeError::at(
+                SerializeErrorKind::CountOverflow { field, actual },
+                self.writer.position(),
+            )),
+        }
+    }
+
+    /// Return the value to write for the count or length `field`, given its `stored` value and
+    /// the `actual` one derived from the data, according to the count mode.
+    fn checked_count(
+        &self,
+        field: &'static str,
+        stored: u32,
+        actual: usize,
+        max: u32,
+    ) -> SerializeResult<u32> {
+        match self.count_mode {
+            CountMode::Preserve => Ok(stored),
+            CountMode::Recompute => self.actual_count(field, actual, max),
+            CountMode::Strict if stored as usize == actual => self.actual_count(field, actual, max),
+            CountMode::Strict => Err(SerializeError::at(
+                SerializeErrorKind::CountMismatch {
+                    field,
+                    stored,
+                    actual,
+                },
+                self.writer.position(),
+            )),
+        }
+    }
+
+    fn write_count_u8(
+        &mut self,
+        field: &'static str,
+        stored: u8,
+        actual: usize,
+    ) -> SerializeResult<()> {
+        let count = self.checked_count(field, stored.into(), actual, u8::MAX.into())?;
+        self.writer.write_unsigned_byte(count as u8)?;
+        Ok(())
+    }
+
+    fn write_count_u16(
+        &mut self,
+        field: &'static str,
+        stored: u16,
+        actual: usize,
+    ) -> SerializeResult<()> {
+        let count = self.checked_count(field, stored.into(), actual, u16::MAX.into())?;
+        self.writer.write_unsigned_short(count as u16)?;
+        Ok(())
+    }
+
+    fn write_count_u32(
+        &mut self,
+        field: &'static str,
+        stored: u32,
+        actual: usize,
+    ) -> SerializeResult<()> {
+        let count = self.checked_count(field, stored, actual, u32::MAX)?;
+        self.writer.write_unsigned_int(count)?;
+        Ok(())
+    }
+
+    /// Return the only item of the `stack` of a frame with one stack item. The frame has no count
+    /// to recompute, so a stack of any other size is an error in every count mode.
+    fn single_stack_item<'v>(
+        &self,
+        stack: &'v [VerificationTypeInfo],
+    ) -> SerializeResult<&'v VerificationTypeInfo> {
+        match stack {
+            [item] => Ok(item),
+            _ => Err(SerializeError::at(
+                SerializeErrorKind::CountMismatch {
+                    field: "stack",
+                    stored: 1,
+                    actual: stack.len(),
+                },
+                self.writer.position(),
+            )),
+        }
+    }
+
+    /// Run `f` with `segment` appended to the structural path, so that any error raised while
+    /// serializing that part of the class file records where it occurred.
+    fn scoped(
+        &mut self,
+        segment: PathSegment,
+        f: impl FnOnce(&mut Self) -> SerializeResult<()>,
+    ) -> SerializeResult<()> {
+        self.path.push(segment);
+        let res = f(self).map_err(|err| err.with_path(&self.path));
+        self.path.pop();
+        res
     }
 
     fn serialize_target_info(&mut self, target_info: &TargetInfo) -> SerializeResult<()> {
@@ -52,7 +208,7 @@ impl<'a, W: Write> Serializer<'a, W> {
                 table_length,
                 table,
             } => {
-                self.writer.write_unsigned_short(*table_length)?;
+                self.write_count_u16("table_length", *table_length, table.len())?;
                 for local_var in table {
                     self.writer.write_unsigned_short(local_var.start_pc)?;
                     self.writer.write_unsigned_short(local_var.length)?;
@@ -79,7 +235,7 @@ impl<'a, W: Write> Serializer<'a, W> {
     }
 
     fn serialize_type_path(&mut self, type_path: &TypePath) -> SerializeResult<()> {
-        self.writer.write_unsigned_byte(type_path.path_length)?;
+        self.write_count_u8("path_length", type_path.path_length, type_path.path.len())?;
         for path in &type_path.path {
             self.writer.write_unsigned_byte(path.type_path_kind)?;
             self.writer.write_unsigned_byte(path.type_argument_index)?;
@@ -98,8 +254,11 @@ impl<'a, W: Write> Serializer<'a, W> {
         self.serialize_type_path(&type_annotation.target_path)?;
         self.writer
             .write_unsigned_short(type_annotation.type_index)?;
-        self.writer
-            .write_unsigned_short(type_annotation.num_element_value_pairs)?;
+        self.write_count_u16(
+            "num_element_value_pairs",
+            type_annotation.num_element_value_pairs,
+            type_annotation.element_value_pairs.len(),
+        )?;
 
         for ev_pair in &type_annotation.element_value_pairs {
             self.serialize_element_value_pair(ev_pair)?;
@@ -138,7 +297,7 @@ impl<'a, W: Write> Serializer<'a, W> {
 
             ElementValue::AnnotationValue { tag, annotation } => {
                 self.writer.write_unsigned_byte(*tag)?;
-                self.serialize_annotation(&annotation)?;
+                self.serialize_annotation(annotation)?;
             }
 
             ElementValue::ArrayValue {
@@ -148,7 +307,7 @@ impl<'a, W: Write> Serializer<'a, W> {
             } => {
                 self.writer.write_unsigned_byte(*tag)?;
 
-                self.writer.write_unsigned_short(*num_values)?;
+                self.write_count_u16("num_values", *num_values, values.len())?;
                 for value in values {
                     self.serialize_element_value(value)?;
                 }
@@ -167,11 +326,14 @@ impl<'a, W: Write> Serializer<'a, W> {
 
     fn serialize_annotation(&mut self, annotation: &Annotation) -> SerializeResult<()> {
         self.writer.write_unsigned_short(annotation.type_index)?;
-        self.writer
-            .write_unsigned_short(annotation.num_element_value_pairs)?;
+        self.write_count_u16(
+            "num_element_value_pairs",
+            annotation.num_element_value_pairs,
+            annotation.element_value_pairs.len(),
+        )?;
 
         for ev_pair in &annotation.element_value_pairs {
-            self.serialize_element_value_pair(&ev_pair)?;
+            self.serialize_element_value_pair(ev_pair)?;
         }
 
         Ok(())
@@ -224,790 +386,870 @@ impl<'a, W: Write> Serializer<'a, W> {
         Ok(())
     }
 
+    /// Encode a custom attribute using the codec registered for its name.
+    fn encode_custom_attribute(
+        &self,
+        attribute_name_index: u16,
+        value: &dyn CustomAttribute,
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<Vec<u8>> {
+        let attribute_name = constant_pool.get_utf8(attribute_name_index).map_err(|_| {
+            SerializeError::at(
+                SerializeErrorKind::InvalidAttributeNameIndex(attribute_name_index),
+                self.writer.position(),
+            )
+        })?;
+
+        match self
+            .registry
+            .as_ref()
+            .and_then(|registry| registry.get(&attribute_name))
+        {
+            Some(codec) => codec
+                .encode(value, constant_pool)
+                .map_err(|err| err.or_offset(self.writer.position())),
+            None => Err(SerializeError::at(
+                SerializeErrorKind::MissingAttributeCodec(attribute_name.into_owned()),
+                self.writer.position(),
+            )),
+        }
+    }
+
     /// Serialize the attributes of the class file.
-    fn serialize_attributes(&mut self, attributes: &[AttributeInfo]) -> SerializeResult<()> {
+    fn serialize_attributes(
+        &mut self,
+        attributes: &[AttributeInfo],
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<()> {
         for attribute in attributes {
-            match attribute {
-                AttributeInfo::SourceFile {
-                    attribute_name_index,
-                    attribute_length,
-                    sourcefile_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*sourcefile_index)?;
-                }
+            let attribute_name_index = attribute.attribute_name_index();
+            let segment = match constant_pool.get_utf8(attribute_name_index) {
+                Ok(name) => PathSegment::Attribute(name.into_owned()),
+                Err(_) => PathSegment::Attribute(format!("#{}", attribute_name_index)),
+            };
+
+            self.scoped(segment, |this| {
+                this.serialize_attribute(attribute, constant_pool)
+            })?;
+        }
 
-                AttributeInfo::ConstantValue {
-                    attribute_name_index,
-                    attribute_length,
-                    constantvalue_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*constantvalue_index)?;
-                }
+        Ok(())
+    }
 
-                AttributeInfo::Code {
-                    attribute_name_index,
-                    attribute_length,
-                    max_stack,
-                    max_locals,
-                    code_length,
-                    code,
-                    exception_table_length,
-                    exception_table,
-                    code_attributes_count,
-                    code_attributes,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*max_stack)?;
-                    self.writer.write_unsigned_short(*max_locals)?;
-
-                    self.writer.write_unsigned_int(*code_length)?;
-                    for b in code {
-                        self.writer.write_unsigned_byte(*b)?;
-                    }
+    /// Serialize a single attribute.
+    fn serialize_attribute(
+        &mut self,
+        attribute: &AttributeInfo,
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<()> {
+        self.writer
+            .write_unsigned_short(attribute.attribute_name_index())?;
 
-                    self.writer.write_unsigned_short(*exception_table_length)?;
-                    for ehandler in exception_table {
-                        self.writer.write_unsigned_short(ehandler.start_pc)?;
-                        self.writer.write_unsigned_short(ehandler.end_pc)?;
-                        self.writer.write_unsigned_short(ehandler.handler_pc)?;
+        let is_custom = matches!(attribute, AttributeInfo::Custom { .. });
+        if self.count_mode == CountMode::Preserve && !is_custom {
+            self.writer
+                .write_unsigned_int(attribute.attribute_length())?;
+            return self.serialize_attribute_info(attribute, constant_pool);
+        }
 
-                        let mut catch_type = ehandler.catch_type;
-                        if catch_type != 0 {
-                            catch_type += 1;
-                        }
-                        self.writer.write_unsigned_short(catch_type)?;
-                    }
+        // write the info to a buffer first to find its length
+        let mut info = Vec::new();
+        let mut serializer = Serializer {
+            writer: Writer::at_position(&mut info, self.writer.position() + 4),
+            registry: self.registry.clone(),
+            compute_maxs: self.compute_maxs,
+            method_maxs: self.method_maxs,
+            count_mode: self.count_mode,
+            path: self.path.clone(),
+        };
+        serializer.serialize_attribute_info(attribute, constant_pool)?;
+
+        // the length of custom attributes is always that of their encoding
+        if is_custom {
+            let attribute_length = self.actual_count("attribute_length", info.len(), u32::MAX)?;
+            self.writer.write_unsigned_int(attribute_length)?;
+        } else {
+            self.write_count_u32("attribute_length", attribute.attribute_length(), info.len())?;
+        }
+        self.writer.write_bytes(&info)?;
 
-                    self.writer.write_unsigned_short(*code_attributes_count)?;
-                    self.serialize_attributes(code_attributes)?;
-                }
+        Ok(())
+    }
 
-                AttributeInfo::Exceptions {
-                    attribute_name_index,
-                    attribute_length,
-                    number_of_exceptions,
-                    exception_index_table,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*number_of_exceptions)?;
+    /// Serialize the info of a single attribute, following its name index and length.
+    fn serialize_attribute_info(
+        &mut self,
+        attribute: &AttributeInfo,
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<()> {
+        match attribute {
+            AttributeInfo::SourceFile {
+                sourcefile_index, ..
+            } => {
+                self.writer.write_unsigned_short(*sourcefile_index)?;
+            }
 
-                    for idx in exception_index_table {
-                        self.writer
-                            .write_unsigned_short(if *idx == 0 { 0 } else { idx + 1 })?;
-                    }
+            AttributeInfo::ConstantValue {
+                constantvalue_index,
+                ..
+            } => {
+                self.writer.write_unsigned_short(*constantvalue_index)?;
+            }
+
+            AttributeInfo::Code {
+                max_stack,
+                max_locals,
+                code_length,
+                code,
+                exception_table_length,
+                exception_table,
+                code_attributes_count,
+                code_attributes,
+                ..
+            } => {
+                let maxs = self.method_maxs.take().unwrap_or(Maxs {
+                    max_stack: *max_stack,
+                    max_locals: *max_locals,
+                });
+                self.writer.write_unsigned_short(maxs.max_stack)?;
+                self.writer.write_unsigned_short(maxs.max_locals)?;
+
+                let code_length =
+                    self.checked_count("code_length", *code_length, code.len(), MAX_CODE_LENGTH)?;
+                self.writer.write_unsigned_int(code_length)?;
+                self.writer.write_bytes(code)?;
+
+                self.write_count_u16(
+                    "exception_table_length",
+                    *exception_table_length,
+                    exception_table.len(),
+                )?;
+                for ehandler in exception_table {
+                    self.writer.write_unsigned_short(ehandler.start_pc)?;
+                    self.writer.write_unsigned_short(ehandler.end_pc)?;
+                    self.writer.write_unsigned_short(ehandler.handler_pc)?;
+                    self.writer.write_unsigned_short(ehandler.catch_type)?;
                 }
 
-                AttributeInfo::LineNumberTable {
-                    attribute_name_index,
-                    attribute_length,
-                    line_number_table_length,
-                    line_number_table,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer
-                        .write_unsigned_short(*line_number_table_length)?;
+                self.write_count_u16(
+                    "code_attributes_count",
+                    *code_attributes_count,
+                    code_attributes.len(),
+                )?;
+                self.serialize_attributes(code_attributes, constant_pool)?;
+            }
 
-                    for line_number in line_number_table {
-                        self.writer.write_unsigned_short(line_number.start_pc)?;
-                        self.writer.write_unsigned_short(line_number.line_number)?;
-                    }
+            AttributeInfo::Exceptions {
+                number_of_exceptions,
+                exception_index_table,
+                ..
+            } => {
+                self.write_count_u16(
+                    "number_of_exceptions",
+                    *number_of_exceptions,
+                    exception_index_table.len(),
+                )?;
+
+                for idx in exception_index_table {
+                    self.writer.write_unsigned_short(*idx)?;
                 }
+            }
 
-                AttributeInfo::LocalVariableTable {
-                    attribute_name_index,
-                    attribute_length,
-                    local_variable_table_length,
-                    local_variable_table,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
+            AttributeInfo::LineNumberTable {
+                line_number_table_length,
+                line_number_table,
+                ..
+            } => {
+                self.write_count_u16(
+                    "line_number_table_length",
+                    *line_number_table_length,
+                    line_number_table.len(),
+                )?;
+
+                for line_number in line_number_table {
+                    self.writer.write_unsigned_short(line_number.start_pc)?;
+                    self.writer.write_unsigned_short(line_number.line_number)?;
+                }
+            }
+
+            AttributeInfo::LocalVariableTable {
+                local_variable_table_length,
+                local_variable_table,
+                ..
+            } => {
+                self.write_count_u16(
+                    "local_variable_table_length",
+                    *local_variable_table_length,
+                    local_variable_table.len(),
+                )?;
+
+                for local_var in local_variable_table {
+                    self.writer.write_unsigned_short(local_var.start_pc)?;
+                    self.writer.write_unsigned_short(local_var.length)?;
+                    self.writer.write_unsigned_short(local_var.name_index)?;
                     self.writer
-                        .write_unsigned_short(*local_variable_table_length)?;
-
-                    for local_var in local_variable_table {
-                        self.writer.write_unsigned_short(local_var.start_pc)?;
-                        self.writer.write_unsigned_short(local_var.length)?;
-                        self.writer.write_unsigned_short(local_var.name_index)?;
-                        self.writer
-                            .write_unsigned_short(local_var.descriptor_index)?;
-                        self.writer.write_unsigned_short(local_var.index)?;
-                    }
+                        .write_unsigned_short(local_var.descriptor_index)?;
+                    self.writer.write_unsigned_short(local_var.index)?;
                 }
+            }
 
-                AttributeInfo::StackMapTable {
-                    attribute_name_index,
-                    attribute_length,
-                    number_of_entries,
-                    entries,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*number_of_entries)?;
-                    for entry in entries {
-                        match entry {
-                            StackMapFrame::SameFrame { frame_type } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                            }
+            AttributeInfo::StackMapTable {
+                number_of_entries,
+                entries,
+                ..
+            } => {
+                self.write_count_u16("number_of_entries", *number_of_entries, entries.len())?;
+                for entry in entries {
+                    match entry {
+                        StackMapFrame::SameFrame { frame_type } => {
+                            self.writer.write_unsigned_byte(*frame_type)?;
+                        }
 
-                            StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                                self.serialize_verification_type_info(&stack[0])?;
-                            }
+                        StackMapFrame::SameLocals1StackItemFrame { frame_type, stack } => {
+                            self.writer.write_unsigned_byte(*frame_type)?;
+                            let item = self.single_stack_item(stack)?;
+                            self.serialize_verification_type_info(item)?;
+                        }
 
-                            StackMapFrame::SameLocals1StackItemFrameExtended {
-                                frame_type,
-                                offset_delta,
-                                stack,
-                            } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                                self.writer.write_unsigned_short(*offset_delta)?;
-                                self.serialize_verification_type_info(&stack[0])?;
-                            }
+                        StackMapFrame::SameLocals1StackItemFrameExtended {
+                            frame_type,
+                            offset_delta,
+                            stack,
+                        } => {
+                            self.writer.write_unsigned_byte(*frame_type)?;
+                            self.writer.write_unsigned_short(*offset_delta)?;
+                            let item = self.single_stack_item(stack)?;
+                            self.serialize_verification_type_info(item)?;
+                        }
 
-                            StackMapFrame::ChopFrame {
-                                frame_type,
-                                offset_delta,
-                            } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                                self.writer.write_unsigned_short(*offset_delta)?;
-                            }
+                        StackMapFrame::ChopFrame {
+                            frame_type,
+                            offset_delta,
+                        } => {
+                            self.writer.write_unsigned_byte(*frame_type)?;
+                            self.writer.write_unsigned_short(*offset_delta)?;
+                        }
+
+                        StackMapFrame::SameFrameExtended {
+                            frame_type,
+                            offset_delta,
+                        } => {
+                            self.writer.write_unsigned_byte(*frame_type)?;
+                            self.writer.write_unsigned_short(*offset_delta)?;
+                        }
 
-                            StackMapFrame::SameFrameExtended {
-                                frame_type,
-                                offset_delta,
-                            } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                                self.writer.write_unsigned_short(*offset_delta)?;
+                        StackMapFrame::AppendFrame {
+                            frame_type,
+                            offset_delta,
+                            locals,
+                        } => {
+                            // the frame type of an append frame gives the number of locals
+                            let frame_type = self.checked_count(
+                                "frame_type",
+                                (*frame_type).into(),
+                                251 + locals.len(),
+                                254,
+                            )?;
+                            self.writer.write_unsigned_byte(frame_type as u8)?;
+                            self.writer.write_unsigned_short(*offset_delta)?;
+
+                            for local in locals {
+                                self.serialize_verification_type_info(local)?;
                             }
+                        }
 
-                            StackMapFrame::AppendFrame {
-                                frame_type,
-                                offset_delta,
-                                locals,
-                            } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                                self.writer.write_unsigned_short(*offset_delta)?;
-
-                                for local in locals {
-                                    self.serialize_verification_type_info(local)?;
-                                }
+                        StackMapFrame::FullFrame {
+                            frame_type,
+                            offset_delta,
+                            number_of_locals,
+                            locals,
+                            number_of_stack_items,
+                            stack,
+                        } => {
+                            self.writer.write_unsigned_byte(*frame_type)?;
+                            self.writer.write_unsigned_short(*offset_delta)?;
+
+                            self.write_count_u16(
+                                "number_of_locals",
+                                *number_of_locals,
+                                locals.len(),
+                            )?;
+                            for local in locals {
+                                self.serialize_verification_type_info(local)?;
                             }
 
-                            StackMapFrame::FullFrame {
-                                frame_type,
-                                offset_delta,
-                                number_of_locals,
-                                locals,
-                                number_of_stack_items,
-                                stack,
-                            } => {
-                                self.writer.write_unsigned_byte(*frame_type)?;
-                                self.writer.write_unsigned_short(*offset_delta)?;
-
-                                self.writer.write_unsigned_short(*number_of_locals)?;
-                                for local in locals {
-                                    self.serialize_verification_type_info(local)?;
-                                }
-
-                                self.writer.write_unsigned_short(*number_of_stack_items)?;
-                                for st_item in stack {
-                                    self.serialize_verification_type_info(st_item)?;
-                                }
+                            self.write_count_u16(
+                                "number_of_stack_items",
+                                *number_of_stack_items,
+                                stack.len(),
+                            )?;
+                            for st_item in stack {
+                                self.serialize_verification_type_info(st_item)?;
                             }
                         }
                     }
                 }
+            }
 
-                AttributeInfo::InnerClasses {
-                    attribute_name_index,
-                    attribute_length,
-                    number_of_classes,
-                    classes,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*number_of_classes)?;
-                    for Class {
-                        inner_class_info_index,
-                        outer_class_info_index,
-                        inner_name_index,
-                        inner_class_access_flags,
-                    } in classes
-                    {
-                        self.writer.write_unsigned_short(*inner_class_info_index)?;
-                        self.writer.write_unsigned_short(*outer_class_info_index)?;
-                        self.writer.write_unsigned_short(*inner_name_index)?;
-                        self.writer
-                            .write_unsigned_short(*inner_class_access_flags)?;
-                    }
+            AttributeInfo::InnerClasses {
+                number_of_classes,
+                classes,
+                ..
+            } => {
+                self.write_count_u16("number_of_classes", *number_of_classes, classes.len())?;
+                for Class {
+                    inner_class_info_index,
+                    outer_class_info_index,
+                    inner_name_index,
+                    inner_class_access_flags,
+                } in classes
+                {
+                    self.writer.write_unsigned_short(*inner_class_info_index)?;
+                    self.writer.write_unsigned_short(*outer_class_info_index)?;
+                    self.writer.write_unsigned_short(*inner_name_index)?;
+                    self.writer
+                        .write_unsigned_short(*inner_class_access_flags)?;
                 }
+            }
 
-                AttributeInfo::EnclosingMethod {
-                    attribute_name_index,
-                    attribute_length,
-                    class_index,
-                    method_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*class_index)?;
-                    self.writer.write_unsigned_short(*method_index)?;
-                }
+            AttributeInfo::EnclosingMethod {
+                class_index,
+                method_index,
+                ..
+            } => {
+                self.writer.write_unsigned_short(*class_index)?;
+                self.writer.write_unsigned_short(*method_index)?;
+            }
 
-                AttributeInfo::Synthetic {
-                    attribute_name_index,
-                    attribute_length,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                }
+            AttributeInfo::Synthetic { .. } => {}
 
-                AttributeInfo::Signature {
-                    attribute_name_index,
-                    attribute_length,
-                    signature_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*signature_index)?;
-                }
+            AttributeInfo::Signature {
+                signature_index, ..
+            } => {
+                self.writer.write_unsigned_short(*signature_index)?;
+            }
 
-                AttributeInfo::SourceDebugExtension {
-                    attribute_name_index,
-                    attribute_length,
-                    debug_extension,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
+            AttributeInfo::SourceDebugExtension {
+                debug_extension, ..
+            } => {
+                self.writer.write_bytes(debug_extension)?;
+            }
 
-                    for b in debug_extension {
-                        self.writer.write_unsigned_byte(*b)?;
-                    }
+            AttributeInfo::LocalVariableTypeTable {
+                local_variable_type_table_length,
+                local_variable_type_table,
+                ..
+            } => {
+                self.write_count_u16(
+                    "local_variable_type_table_length",
+                    *local_variable_type_table_length,
+                    local_variable_type_table.len(),
+                )?;
+                for LocalVariableType {
+                    start_pc,
+                    length,
+                    name_index,
+                    signature_index,
+                    index,
+                } in local_variable_type_table
+                {
+                    self.writer.write_unsigned_short(*start_pc)?;
+                    self.writer.write_unsigned_short(*length)?;
+                    self.writer.write_unsigned_short(*name_index)?;
+                    self.writer.write_unsigned_short(*signature_index)?;
+                    self.writer.write_unsigned_short(*index)?;
                 }
+            }
 
-                AttributeInfo::LocalVariableTypeTable {
-                    attribute_name_index,
-                    attribute_length,
-                    local_variable_type_table_length,
-                    local_variable_type_table,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
+            AttributeInfo::Deprecated { .. } => {}
 
-                    self.writer
-                        .write_unsigned_short(*local_variable_type_table_length)?;
-                    for LocalVariableType {
-                        start_pc,
-                        length,
-                        name_index,
-                        signature_index,
-                        index,
-                    } in local_variable_type_table
-                    {
-                        self.writer.write_unsigned_short(*start_pc)?;
-                        self.writer.write_unsigned_short(*length)?;
-                        self.writer.write_unsigned_short(*name_index)?;
-                        self.writer.write_unsigned_short(*signature_index)?;
-                        self.writer.write_unsigned_short(*index)?;
-                    }
+            AttributeInfo::RuntimeVisibleAnnotations {
+                num_annotations,
+                annotations,
+                ..
+            } => {
+                self.write_count_u16("num_annotations", *num_annotations, annotations.len())?;
+                for annotation in annotations {
+                    self.serialize_annotation(annotation)?;
                 }
+            }
 
-                AttributeInfo::Deprecated {
-                    attribute_name_index,
-                    attribute_length,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
+            AttributeInfo::RuntimeInvisibleAnnotations {
+                num_annotations,
+                annotations,
+                ..
+            } => {
+                self.write_count_u16("num_annotations", *num_annotations, annotations.len())?;
+                for annotation in annotations {
+                    self.serialize_annotation(annotation)?;
                 }
+            }
 
-                AttributeInfo::RuntimeVisibleAnnotations {
-                    attribute_name_index,
-                    attribute_length,
-                    num_annotations,
-                    annotations,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*num_annotations)?;
-                    for annotation in annotations {
+            AttributeInfo::RuntimeVisibleParameterAnnotations {
+                num_parameters,
+                parameter_annotations,
+                ..
+            } => {
+                self.write_count_u8(
+                    "num_parameters",
+                    *num_parameters,
+                    parameter_annotations.len(),
+                )?;
+                for parameter_annotation in parameter_annotations {
+                    self.write_count_u16(
+                        "num_annotations",
+                        parameter_annotation.num_annotations,
+                        parameter_annotation.annotations.len(),
+                    )?;
+                    for annotation in &parameter_annotation.annotations {
                         self.serialize_annotation(annotation)?;
                     }
                 }
+            }
 
-                AttributeInfo::RuntimeInvisibleAnnotations {
-                    attribute_name_index,
-                    attribute_length,
-                    num_annotations,
-                    annotations,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*num_annotations)?;
-                    for annotation in annotations {
+            AttributeInfo::RuntimeInvisibleParameterAnnotations {
+                num_parameters,
+                parameter_annotations,
+                ..
+            } => {
+                self.write_count_u8(
+                    "num_parameters",
+                    *num_parameters,
+                    parameter_annotations.len(),
+                )?;
+
+                for parameter_annotation in parameter_annotations {
+                    self.write_count_u16(
+                        "num_annotations",
+                        parameter_annotation.num_annotations,
+                        parameter_annotation.annotations.len(),
+                    )?;
+                    for annotation in &parameter_annotation.annotations {
                         self.serialize_annotation(annotation)?;
                     }
                 }
+            }
 
-                AttributeInfo::RuntimeVisibleParameterAnnotations {
-                    attribute_name_index,
-                    attribute_length,
-                    num_parameters,
-                    parameter_annotations,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_byte(*num_parameters)?;
-                    for parameter_annotation in parameter_annotations {
-                        self.writer
-                            .write_unsigned_short(parameter_annotation.num_annotations)?;
-                        for annotation in &parameter_annotation.annotations {
-                            self.serialize_annotation(annotation)?;
-                        }
-                    }
-                }
-
-                AttributeInfo::RuntimeInvisibleParameterAnnotations {
-                    attribute_name_index,
-                    attribute_length,
-                    num_parameters,
-                    parameter_annotations,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_byte(*num_parameters)?;
+            AttributeInfo::RuntimeVisibleTypeAnnotations {
+                num_annotations,
+                annotations,
+                ..
+            } => {
+                self.write_count_u16("num_annotations", *num_annotations, annotations.len())?;
 
-                    for parameter_annotation in parameter_annotations {
-                        self.writer
-                            .write_unsigned_short(parameter_annotation.num_annotations)?;
-                        for annotation in &parameter_annotation.annotations {
-                            self.serialize_annotation(annotation)?;
-                        }
-                    }
+                for type_annotation in annotations {
+                    self.serialize_type_annotation(type_annotation)?;
                 }
+            }
 
-                AttributeInfo::RuntimeVisibleTypeAnnotations {
-                    attribute_name_index,
-                    attribute_length,
-                    num_annotations,
-                    annotations,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*num_annotations)?;
+            AttributeInfo::RuntimeInvisibleTypeAnnotations {
+                num_annotations,
+                annotations,
+                ..
+            } => {
+                self.write_count_u16("num_annotations", *num_annotations, annotations.len())?;
 
-                    for type_annotation in annotations {
-                        self.serialize_type_annotation(type_annotation)?;
-                    }
+                for type_annotation in annotations {
+                    self.serialize_type_annotation(type_annotation)?;
                 }
+            }
 
-                AttributeInfo::RuntimeInvisibleTypeAnnotations {
-                    attribute_name_index,
-                    attribute_length,
-                    num_annotations,
-                    annotations,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*num_annotations)?;
+            AttributeInfo::AnnotationDefault { default_value, .. } => {
+                self.serialize_element_value(default_value)?;
+            }
 
-                    for type_annotation in annotations {
-                        self.serialize_type_annotation(type_annotation)?;
+            AttributeInfo::BootstrapMethods {
+                num_bootstrap_methods,
+                bootstrap_methods,
+                ..
+            } => {
+                self.write_count_u16(
+                    "num_bootstrap_methods",
+                    *num_bootstrap_methods,
+                    bootstrap_methods.len(),
+                )?;
+                for bootstrap_method in bootstrap_methods {
+                    self.writer
+                        .write_unsigned_short(bootstrap_method.bootstrap_method_ref)?;
+
+                    self.write_count_u16(
+                        "num_bootstrap_arguments",
+                        bootstrap_method.num_bootstrap_arguments,
+                        bootstrap_method.bootstrap_arguments.len(),
+                    )?;
+                    for bootstrap_arg in &bootstrap_method.bootstrap_arguments {
+                        self.writer.write_unsigned_short(*bootstrap_arg)?;
                     }
                 }
+            }
 
-                AttributeInfo::AnnotationDefault {
-                    attribute_name_index,
-                    attribute_length,
-                    default_value,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.serialize_element_value(default_value)?;
+            AttributeInfo::MethodParameters {
+                parameters_count,
+                parameters,
+                ..
+            } => {
+                self.write_count_u8("parameters_count", *parameters_count, parameters.len())?;
+                for param in parameters {
+                    self.writer.write_unsigned_short(param.name_index)?;
+                    self.writer.write_unsigned_short(param.access_flags)?;
                 }
+            }
 
-                AttributeInfo::BootstrapMethods {
-                    attribute_name_index,
-                    attribute_length,
-                    num_bootstrap_methods,
-                    bootstrap_methods,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*num_bootstrap_methods)?;
-                    for bootstrap_method in bootstrap_methods {
-                        self.writer
-                            .write_unsigned_short(bootstrap_method.bootstrap_method_ref)?;
-
-                        self.writer
-                            .write_unsigned_short(bootstrap_method.num_bootstrap_arguments)?;
-                        for bootstrap_arg in &bootstrap_method.bootstrap_arguments {
-                            self.writer.write_unsigned_short(*bootstrap_arg)?;
-                        }
-                    }
+            AttributeInfo::Module {
+                module_name_index,
+                module_flags,
+                module_version_index,
+                requires_count,
+                requires,
+                exports_count,
+                exports,
+                opens_count,
+                opens,
+                uses_count,
+                uses_index,
+                provides_count,
+                provides,
+                ..
+            } => {
+                self.writer.write_unsigned_short(*module_name_index)?;
+                self.writer.write_unsigned_short(*module_flags)?;
+                self.writer.write_unsigned_short(*module_version_index)?;
+
+                self.write_count_u16("requires_count", *requires_count, requires.len())?;
+                for require in requires {
+                    self.writer.write_unsigned_short(require.requires_index)?;
+                    self.writer.write_unsigned_short(require.requires_flags)?;
+                    self.writer
+                        .write_unsigned_short(require.requires_version_index)?;
                 }
 
-                AttributeInfo::MethodParameters {
-                    attribute_name_index,
-                    attribute_length,
-                    parameters_count,
-                    parameters,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_byte(*parameters_count)?;
-                    for param in parameters {
-                        self.writer.write_unsigned_short(param.name_index)?;
-                        self.writer.write_unsigned_short(param.access_flags)?;
+                self.write_count_u16("exports_count", *exports_count, exports.len())?;
+                for export in exports {
+                    self.writer.write_unsigned_short(export.exports_index)?;
+                    self.writer.write_unsigned_short(export.exports_flags)?;
+                    self.write_count_u16(
+                        "exports_to_count",
+                        export.exports_to_count,
+                        export.exports_to_index.len(),
+                    )?;
+
+                    for s in &export.exports_to_index {
+                        self.writer.write_unsigned_short(*s)?;
                     }
                 }
 
-                AttributeInfo::Module {
-                    attribute_name_index,
-                    attribute_length,
-                    module_name_index,
-                    module_flags,
-                    module_version_index,
-                    requires_count,
-                    requires,
-                    exports_count,
-                    exports,
-                    opens_count,
-                    opens,
-                    uses_count,
-                    uses_index,
-                    provides_count,
-                    provides,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*module_name_index)?;
-                    self.writer.write_unsigned_short(*module_flags)?;
-                    self.writer.write_unsigned_short(*module_version_index)?;
-
-                    self.writer.write_unsigned_short(*requires_count)?;
-                    for require in requires {
-                        self.writer.write_unsigned_short(require.requires_index)?;
-                        self.writer.write_unsigned_short(require.requires_flags)?;
-                        self.writer
-                            .write_unsigned_short(require.requires_version_index)?;
-                    }
-
-                    self.writer.write_unsigned_short(*exports_count)?;
-                    for export in exports {
-                        self.writer.write_unsigned_short(export.exports_index)?;
-                        self.writer.write_unsigned_short(export.exports_flags)?;
-                        self.writer.write_unsigned_short(export.exports_to_count)?;
-
-                        for s in &export.exports_to_index {
-                            self.writer.write_unsigned_short(*s)?;
-                        }
-                    }
-
-                    self.writer.write_unsigned_short(*opens_count)?;
-                    for open in opens {
-                        self.writer.write_unsigned_short(open.opens_index)?;
-                        self.writer.write_unsigned_short(open.opens_flags)?;
-                        self.writer.write_unsigned_short(open.opens_to_count)?;
-
-                        for s in &open.opens_to_index {
-                            self.writer.write_unsigned_short(*s)?;
-                        }
-                    }
-
-                    self.writer.write_unsigned_short(*uses_count)?;
-                    for s in uses_index {
+                self.write_count_u16("opens_count", *opens_count, opens.len())?;
+                for open in opens {
+                    self.writer.write_unsigned_short(open.opens_index)?;
+                    self.writer.write_unsigned_short(open.opens_flags)?;
+                    self.write_count_u16(
+                        "opens_to_count",
+                        open.opens_to_count,
+                        open.opens_to_index.len(),
+                    )?;
+
+                    for s in &open.opens_to_index {
                         self.writer.write_unsigned_short(*s)?;
                     }
+                }
 
-                    self.writer.write_unsigned_short(*provides_count)?;
-                    for provide in provides {
-                        self.writer.write_unsigned_short(provide.provides_index)?;
-                        self.writer
-                            .write_unsigned_short(provide.provides_with_count)?;
-
-                        for s in &provide.provides_with_index {
-                            self.writer.write_unsigned_short(*s)?;
-                        }
-                    }
+                self.write_count_u16("uses_count", *uses_count, uses_index.len())?;
+                for s in uses_index {
+                    self.writer.write_unsigned_short(*s)?;
                 }
 
-                AttributeInfo::ModulePackages {
-                    attribute_name_index,
-                    attribute_length,
-                    package_count,
-                    package_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
+                self.write_count_u16("provides_count", *provides_count, provides.len())?;
+                for provide in provides {
+                    self.writer.write_unsigned_short(provide.provides_index)?;
+                    self.write_count_u16(
+                        "provides_with_count",
+                        provide.provides_with_count,
+                        provide.provides_with_index.len(),
+                    )?;
 
-                    self.writer.write_unsigned_short(*package_count)?;
-                    for s in package_index {
+                    for s in &provide.provides_with_index {
                         self.writer.write_unsigned_short(*s)?;
                     }
                 }
+            }
 
-                AttributeInfo::ModuleMainClass {
-                    attribute_name_index,
-                    attribute_length,
-                    main_class_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*main_class_index)?;
+            AttributeInfo::ModulePackages {
+                package_count,
+                package_index,
+                ..
+            } => {
+                self.write_count_u16("package_count", *package_count, package_index.len())?;
+                for s in package_index {
+                    self.writer.write_unsigned_short(*s)?;
                 }
+            }
 
-                AttributeInfo::NestHost {
-                    attribute_name_index,
-                    attribute_length,
-                    host_class_index,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-                    self.writer.write_unsigned_short(*host_class_index)?;
-                }
+            AttributeInfo::ModuleMainClass {
+                main_class_index, ..
+            } => {
+                self.writer.write_unsigned_short(*main_class_index)?;
+            }
 
-                AttributeInfo::NestMembers {
-                    attribute_name_index,
-                    attribute_length,
-                    number_of_classes,
-                    classes,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
+            AttributeInfo::NestHost {
+                host_class_index, ..
+            } => {
+                self.writer.write_unsigned_short(*host_class_index)?;
+            }
 
-                    self.writer.write_unsigned_short(*number_of_classes)?;
-                    for s in classes {
-                        self.writer.write_unsigned_short(*s)?;
-                    }
+            AttributeInfo::NestMembers {
+                number_of_classes,
+                classes,
+                ..
+            } => {
+                self.write_count_u16("number_of_classes", *number_of_classes, classes.len())?;
+                for s in classes {
+                    self.writer.write_unsigned_short(*s)?;
                 }
+            }
 
-                AttributeInfo::Record {
-                    attribute_name_index,
-                    attribute_length,
-                    components_count,
-                    components,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*components_count)?;
-                    for comp in components {
-                        self.writer.write_unsigned_short(comp.name_index)?;
-                        self.writer.write_unsigned_short(comp.descriptor_index)?;
-                        self.writer.write_unsigned_short(comp.attributes_count)?;
-                        self.serialize_attributes(&comp.attributes)?;
-                    }
+            AttributeInfo::Record {
+                components_count,
+                components,
+                ..
+            } => {
+                self.write_count_u16("components_count", *components_count, components.len())?;
+                for comp in components {
+                    self.writer.write_unsigned_short(comp.name_index)?;
+                    self.writer.write_unsigned_short(comp.descriptor_index)?;
+                    self.write_count_u16(
+                        "attributes_count",
+                        comp.attributes_count,
+                        comp.attributes.len(),
+                    )?;
+                    self.serialize_attributes(&comp.attributes, constant_pool)?;
                 }
+            }
 
-                AttributeInfo::PermittedSubclasses {
-                    attribute_name_index,
-                    attribute_length,
-                    number_of_classes,
-                    classes,
-                } => {
-                    self.writer.write_unsigned_short(*attribute_name_index)?;
-                    self.writer.write_unsigned_int(*attribute_length)?;
-
-                    self.writer.write_unsigned_short(*number_of_classes)?;
-                    for s in classes {
-                        self.writer.write_unsigned_short(*s)?;
-                    }
+            AttributeInfo::PermittedSubclasses {
+                number_of_classes,
+                classes,
+                ..
+            } => {
+                self.write_count_u16("number_of_classes", *number_of_classes, classes.len())?;
+                for s in classes {
+                    self.writer.write_unsigned_short(*s)?;
                 }
             }
+
+            AttributeInfo::Unknown { info, .. } => {
+                self.writer.write_bytes(info)?;
+            }
+
+            AttributeInfo::Custom {
+                attribute_name_index,
+                value,
+                ..
+            } => {
+                let info = self.encode_custom_attribute(
+                    *attribute_name_index,
+                    value.as_ref(),
+                    constant_pool,
+                )?;
+
+                self.writer.write_bytes(&info)?;
+            }
         }
 
         Ok(())
     }
 
     /// Serialize the fields of the class file.
-    fn serialize_fields(&mut self, fields: &[FieldInfo]) -> SerializeResult<()> {
-        for field in fields {
-            self.writer.write_unsigned_short(field.access_flags)?;
-            self.writer.write_unsigned_short(field.name_index)?;
-            self.writer.write_unsigned_short(field.descriptor_index)?;
-            self.writer.write_unsigned_short(field.attributes_count)?;
-            self.serialize_attributes(&field.attributes)?;
+    fn serialize_fields(
+        &mut self,
+        fields: &[FieldInfo],
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<()> {
+        for (field_idx, field) in fields.iter().enumerate() {
+            self.scoped(PathSegment::Field(field_idx as u16), |this| {
+                this.writer.write_unsigned_short(field.access_flags)?;
+                this.writer.write_unsigned_short(field.name_index)?;
+                this.writer.write_unsigned_short(field.descriptor_index)?;
+                this.write_count_u16(
+                    "attributes_count",
+                    field.attributes_count,
+                    field.attributes.len(),
+                )?;
+                this.serialize_attributes(&field.attributes, constant_pool)
+            })?;
         }
 
         Ok(())
     }
 
     /// Deserialize the methods of the class file.
-    fn serialize_methods(&mut self, methods: &[MethodInfo]) -> SerializeResult<()> {
-        for method in methods {
-            self.writer.write_unsigned_short(method.access_flags)?;
-            self.writer.write_unsigned_short(method.name_index)?;
-            self.writer.write_unsigned_short(method.descriptor_index)?;
-            self.writer.write_unsigned_short(method.attributes_count)?;
-            self.serialize_attributes(&method.attributes)?;
+    fn serialize_methods(
+        &mut self,
+        methods: &[MethodInfo],
+        constant_pool: &ConstantPool,
+    ) -> SerializeResult<()> {
+        for (method_idx, method) in methods.iter().enumerate() {
+            self.scoped(PathSegment::Method(method_idx as u16), |this| {
+                this.writer.write_unsigned_short(method.access_flags)?;
+                this.writer.write_unsigned_short(method.name_index)?;
+                this.writer.write_unsigned_short(method.descriptor_index)?;
+                this.write_count_u16(
+                    "attributes_count",
+                    method.attributes_count,
+                    method.attributes.len(),
+                )?;
+
+                this.method_maxs = if this.compute_maxs {
+                    analysis::compute_maxs(method, constant_pool)?
+                } else {
+                    None
+                };
+                let res = this.serialize_attributes(&method.attributes, constant_pool);
+                this.method_maxs = None;
+                res
+            })?;
         }
         Ok(())
     }
 
     /// Serialize the contents of the Constant Pool.
-    fn serialize_constant_pool(
-        &mut self,
-        constant_pool: &Vec<Option<CpInfo>>,
-    ) -> SerializeResult<()> {
-        for cp_info in constant_pool {
-            if let Some(cp_info) = cp_info {
-                match cp_info {
-                    CpInfo::ConstantMethodrefInfo {
-                        tag,
-                        class_index,
-                        name_and_type_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*class_index)?;
-                        self.writer.write_unsigned_short(*name_and_type_index)?;
-                    }
+    fn serialize_constant_pool(&mut self, constant_pool: &ConstantPool) -> SerializeResult<()> {
+        for cp_info in constant_pool.iter().flatten() {
+            match cp_info {
+                CpInfo::ConstantMethodrefInfo {
+                    tag,
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*class_index)?;
+                    self.writer.write_unsigned_short(*name_and_type_index)?;
+                }
 
-                    CpInfo::ConstantClassInfo { tag, name_index } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*name_index)?;
-                    }
+                CpInfo::ConstantClassInfo { tag, name_index } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*name_index)?;
+                }
 
-                    CpInfo::ConstantFieldrefInfo {
-                        tag,
-                        class_index,
-                        name_and_type_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*class_index)?;
-                        self.writer.write_unsigned_short(*name_and_type_index)?;
-                    }
+                CpInfo::ConstantFieldrefInfo {
+                    tag,
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*class_index)?;
+                    self.writer.write_unsigned_short(*name_and_type_index)?;
+                }
 
-                    CpInfo::ConstantInterfaceMethodrefInfo {
-                        tag,
-                        class_index,
-                        name_and_type_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*class_index)?;
-                        self.writer.write_unsigned_short(*name_and_type_index)?;
-                    }
+                CpInfo::ConstantInterfaceMethodrefInfo {
+                    tag,
+                    class_index,
+                    name_and_type_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*class_index)?;
+                    self.writer.write_unsigned_short(*name_and_type_index)?;
+                }
 
-                    CpInfo::ConstantStringInfo { tag, string_index } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*string_index)?;
-                    }
+                CpInfo::ConstantStringInfo { tag, string_index } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*string_index)?;
+                }
 
-                    CpInfo::ConstantIntegerInfo { tag, bytes } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_int(*bytes)?;
-                    }
+                CpInfo::ConstantIntegerInfo { tag, bytes } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_int(*bytes)?;
+                }
 
-                    CpInfo::ConstantFloatInfo { tag, bytes } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_int(*bytes)?;
-                    }
+                CpInfo::ConstantFloatInfo { tag, bytes } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_int(*bytes)?;
+                }
 
-                    CpInfo::ConstantLongInfo {
-                        tag,
-                        high_bytes,
-                        low_bytes,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_int(*high_bytes)?;
-                        self.writer.write_unsigned_int(*low_bytes)?;
-                    }
+                CpInfo::ConstantLongInfo {
+                    tag,
+                    high_bytes,
+                    low_bytes,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_int(*high_bytes)?;
+                    self.writer.write_unsigned_int(*low_bytes)?;
+                }
 
-                    CpInfo::ConstantDoubleInfo {
-                        tag,
-                        high_bytes,
-                        low_bytes,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_int(*high_bytes)?;
-                        self.writer.write_unsigned_int(*low_bytes)?;
-                    }
+                CpInfo::ConstantDoubleInfo {
+                    tag,
+                    high_bytes,
+                    low_bytes,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_int(*high_bytes)?;
+                    self.writer.write_unsigned_int(*low_bytes)?;
+                }
 
-                    CpInfo::ConstantNameAndTypeInfo {
-                        tag,
-                        name_index,
-                        descriptor_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*name_index)?;
-                        self.writer.write_unsigned_short(*descriptor_index)?;
-                    }
+                CpInfo::ConstantNameAndTypeInfo {
+                    tag,
+                    name_index,
+                    descriptor_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*name_index)?;
+                    self.writer.write_unsigned_short(*descriptor_index)?;
+                }
 
-                    CpInfo::ConstantUtf8Info { tag, length, bytes } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*length)?;
+                CpInfo::ConstantUtf8Info { tag, length, bytes } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.write_count_u16("length", *length, bytes.len())?;
 
-                        for b in bytes {
-                            self.writer.write_unsigned_byte(*b)?;
-                        }
-                    }
+                    self.writer.write_bytes(bytes)?;
+                }
 
-                    CpInfo::ConstantMethodHandleInfo {
-                        tag,
-                        reference_kind,
-                        reference_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_byte(*reference_kind)?;
-                        self.writer.write_unsigned_short(*reference_index)?;
-                    }
+                CpInfo::ConstantMethodHandleInfo {
+                    tag,
+                    reference_kind,
+                    reference_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_byte(*reference_kind)?;
+                    self.writer.write_unsigned_short(*reference_index)?;
+                }
 
-                    CpInfo::ConstantMethodTypeInfo {
-                        tag,
-                        descriptor_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*descriptor_index)?;
-                    }
+                CpInfo::ConstantMethodTypeInfo {
+                    tag,
+                    descriptor_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*descriptor_index)?;
+                }
 
-                    CpInfo::ConstantDynamicInfo {
-                        tag,
-                        bootstrap_method_attr_index,
-                        name_and_type_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer
-                            .write_unsigned_short(*bootstrap_method_attr_index)?;
-                        self.writer.write_unsigned_short(*name_and_type_index)?;
-                    }
+                CpInfo::ConstantDynamicInfo {
+                    tag,
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer
+                        .write_unsigned_short(*bootstrap_method_attr_index)?;
+                    self.writer.write_unsigned_short(*name_and_type_index)?;
+                }
 
-                    CpInfo::ConstantInvokeDynamicInfo {
-                        tag,
-                        bootstrap_method_attr_index,
-                        name_and_type_index,
-                    } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer
-                            .write_unsigned_short(*bootstrap_method_attr_index)?;
-                        self.writer.write_unsigned_short(*name_and_type_index)?;
-                    }
+                CpInfo::ConstantInvokeDynamicInfo {
+                    tag,
+                    bootstrap_method_attr_index,
+                    name_and_type_index,
+                } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer
+                        .write_unsigned_short(*bootstrap_method_attr_index)?;
+                    self.writer.write_unsigned_short(*name_and_type_index)?;
+                }
 
-                    CpInfo::ConstantModuleInfo { tag, name_index } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*name_index)?;
-                    }
+                CpInfo::ConstantModuleInfo { tag, name_index } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*name_index)?;
+                }
 
-                    CpInfo::ConstantPackageInfo { tag, name_index } => {
-                        self.writer.write_unsigned_byte(*tag)?;
-                        self.writer.write_unsigned_short(*name_index)?;
-                    }
+                CpInfo::ConstantPackageInfo { tag, name_index } => {
+                    self.writer.write_unsigned_byte(*tag)?;
+                    self.writer.write_unsigned_short(*name_index)?;
                 }
             }
         }
@@ -1023,34 +1265,57 @@ impl<'a, W: Write> Serializer<'a, W> {
         self.writer.write_unsigned_short(classfile.major_version)?;
 
         // Constant Pool
-        assert!(classfile.constant_pool_count > 0);
-        self.writer
-            .write_unsigned_short(classfile.constant_pool_count)?;
+        let actual = classfile.constant_pool.entries().len().max(1);
+        if self.count_mode == CountMode::Preserve && classfile.constant_pool_count == 0 {
+            // the count includes the unused entry 0, so 0 cannot be read back
+            return Err(SerializeError::at(
+                SerializeErrorKind::CountMismatch {
+                    field: "constant_pool_count",
+                    stored: 0,
+                    actual,
+                },
+                self.writer.position(),
+            ));
+        }
+        self.write_count_u16("constant_pool_count", classfile.constant_pool_count, actual)?;
         self.serialize_constant_pool(&classfile.constant_pool)?;
 
         self.writer.write_unsigned_short(classfile.access_flags)?;
         self.writer.write_unsigned_short(classfile.this_class)?;
         self.writer.write_unsigned_short(classfile.super_class)?;
 
-        self.writer
-            .write_unsigned_short(classfile.interfaces_count)?;
-        for idx in 0..classfile.interfaces_count as usize {
-            self.writer
-                .write_unsigned_short(classfile.interfaces[idx])?;
+        self.write_count_u16(
+            "interfaces_count",
+            classfile.interfaces_count,
+            classfile.interfaces.len(),
+        )?;
+        for interface in &classfile.interfaces {
+            self.writer.write_unsigned_short(*interface)?;
         }
 
         // Fields
-        self.writer.write_unsigned_short(classfile.fields_count)?;
-        self.serialize_fields(&classfile.fields)?;
+        self.write_count_u16(
+            "fields_count",
+            classfile.fields_count,
+            classfile.fields.len(),
+        )?;
+        self.serialize_fields(&classfile.fields, &classfile.constant_pool)?;
 
         // methods
-        self.writer.write_unsigned_short(classfile.methods_count)?;
-        self.serialize_methods(&classfile.methods)?;
+        self.write_count_u16(
+            "methods_count",
+            classfile.methods_count,
+            classfile.methods.len(),
+        )?;
+        self.serialize_methods(&classfile.methods, &classfile.constant_pool)?;
 
         // class attributes
-        self.writer
-            .write_unsigned_short(classfile.attributes_count)?;
-        self.serialize_attributes(&classfile.attributes)?;
+        self.write_count_u16(
+            "attributes_count",
+            classfile.attributes_count,
+            classfile.attributes.len(),
+        )?;
+        self.serialize_attributes(&classfile.attributes, &classfile.constant_pool)?;
 
         Ok(())
     }
@@ -1212,7 +1477,8 @@ mod test {
                     length: 12,
                     bytes: vec![77, 105, 110, 105, 109, 97, 108, 46, 106, 97, 118, 97],
                 }),
-            ],
+            ]
+            .into(),
             access_flags: 33,
             this_class: 7,
             super_class: 2,
@@ -1288,4 +1554,350 @@ mod test {
         serializer.serialize(&classfile).unwrap();
         assert_eq!(expected_bytes, &bytes[..]);
     }
+
+    #[test]
+    fn test_count_modes() {
+        use crate::{
+            builder::{ClassBuilder, MethodBuilder},
+            bytecode::{CodeBuilder, Instruction},
+            deserializer::Deserializer,
+            model::access_flags::ACC_STATIC,
+            rw::reader::Reader,
+        };
+        use std::io::Cursor;
+
+        let serialize = |classfile: &ClassFile, count_mode| {
+            let mut bytes = Vec::new();
+            Serializer::new(Writer::new(&mut bytes))
+                .with_count_mode(count_mode)
+                .serialize(classfile)
+                .map(|_| bytes)
+        };
+
+        let mut code = CodeBuilder::new();
+        code.emit(Instruction::Return);
+        let mut builder = ClassBuilder::new("Counts");
+        builder
+            .source_file("Counts.java")
+            .method(MethodBuilder::new(ACC_STATIC, "f", "()V").code(code));
+        let mut classfile = builder.build().unwrap();
+        let expected = serialize(&classfile, CountMode::Preserve).unwrap();
+        assert_eq!(serialize(&classfile, CountMode::Strict).unwrap(), expected);
+
+        // stale counts and lengths are rewritten from the data
+        classfile.methods_count = 3;
+        if let AttributeInfo::Code {
+            attribute_length,
+            code_length,
+            ..
+        } = &mut classfile.methods[0].attributes[0]
+        {
+            *attribute_length = 0;
+            *code_length = 7;
+        }
+        assert_eq!(
+            serialize(&classfile, CountMode::Recompute).unwrap(),
+            expected
+        );
+
+        let err = serialize(&classfile, CountMode::Strict).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &SerializeErrorKind::CountMismatch {
+                field: "methods_count",
+                stored: 3,
+                actual: 1,
+            }
+        );
+        classfile.methods_count = 1;
+        let err = serialize(&classfile, CountMode::Strict).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &SerializeErrorKind::CountMismatch {
+                field: "code_length",
+                stored: 7,
+                actual: 1,
+            }
+        );
+        assert_eq!(
+            err.path(),
+            [
+                PathSegment::Method(0),
+                PathSegment::Attribute("Code".to_string())
+            ]
+        );
+
+        // data added without updating the counts is written with the right counts
+        classfile.interfaces.push(classfile.super_class);
+        let bytes = serialize(&classfile, CountMode::Recompute).unwrap();
+        let classfile = Deserializer::new(Reader::new(Cursor::new(bytes)))
+            .deserialize()
+            .unwrap();
+        assert_eq!(classfile.interfaces_count, 1);
+        assert_eq!(classfile.methods[0].attributes[0].attribute_length(), 13);
+
+        // a constant_pool_count of 0 does not even count the unused entry 0
+        let err = serialize(&ClassFile::default(), CountMode::Preserve).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &SerializeErrorKind::CountMismatch {
+                field: "constant_pool_count",
+                stored: 0,
+                actual: 1,
+            }
+        );
+        assert!(serialize(&ClassFile::default(), CountMode::Recompute).is_ok());
+    }
+
+    #[test]
+    fn test_nested_count_modes() {
+        use crate::{
+            analysis::SimpleClassHierarchy,
+            builder::{ClassBuilder, MethodBuilder},
+            bytecode::{CodeBuilder, Instruction},
+            model::{
+                access_flags::ACC_STATIC,
+                attributes::{Annotation, ElementValue, ElementValuePair},
+            },
+        };
+
+        let serialize = |classfile: &ClassFile, count_mode| {
+            let mut bytes = Vec::new();
+            Serializer::new(Writer::new(&mut bytes))
+                .with_count_mode(count_mode)
+                .serialize(classfile)
+                .map(|_| bytes)
+        };
+
+        // @A(1) class Counts { static void f() { try {} catch (Throwable t) { throw t; } } }
+        let build = || {
+            let mut code = CodeBuilder::new();
+            let (start, end, handler) = (code.label(), code.label(), code.label());
+            code.place(start)
+                .emit(Instruction::Nop)
+                .place(end)
+                .emit(Instruction::Return)
+                .place(handler)
+                .emit(Instruction::Athrow)
+                .try_catch(start, end, handler, 0);
+
+            let mut builder = ClassBuilder::new("Counts");
+            let constant_pool = builder.constant_pool();
+            let attribute_name_index = constant_pool.utf8("RuntimeVisibleAnnotations").unwrap();
+            let type_index = constant_pool.utf8("LA;").unwrap();
+            let element_name_index = constant_pool.utf8("value").unwrap();
+            let const_value_index = constant_pool.integer(1).unwrap();
+            builder
+                .attribute(AttributeInfo::RuntimeVisibleAnnotations {
+                    attribute_name_index,
+                    attribute_length: 11,
+                    num_annotations: 1,
+                    annotations: vec![Annotation {
+                        type_index,
+                        num_element_value_pairs: 1,
+                        element_value_pairs: vec![ElementValuePair {
+                            element_name_index,
+                            value: ElementValue::ConstValueIndex {
+                                tag: b'I',
+                                const_value_index,
+                            },
+                        }],
+                    }],
+                })
+                .method(MethodBuilder::new(ACC_STATIC, "f", "()V").code(code));
+            builder
+                .build_with_frames(&SimpleClassHierarchy::new())
+                .unwrap()
+        };
+        let expected = serialize(&build(), CountMode::Preserve).unwrap();
+        assert_eq!(serialize(&build(), CountMode::Strict).unwrap(), expected);
+
+        fn code_attribute(classfile: &mut ClassFile) -> &mut AttributeInfo {
+            &mut classfile.methods[0].attributes[0]
+        }
+        fn stack_map_table(classfile: &mut ClassFile) -> &mut AttributeInfo {
+            match code_attribute(classfile) {
+                AttributeInfo::Code {
+                    code_attributes, ..
+                } => &mut code_attributes[0],
+                attribute => panic!("unexpected attribute {:?}", attribute),
+            }
+        }
+        fn annotations(classfile: &mut ClassFile) -> &mut AttributeInfo {
+            classfile
+                .attributes
+                .iter_mut()
+                .find(|attribute| {
+                    matches!(attribute, AttributeInfo::RuntimeVisibleAnnotations { .. })
+                })
+                .unwrap()
+        }
+        type Corrupt = fn(&mut ClassFile);
+        let cases: [(&str, Corrupt); 6] = [
+            ("constant_pool_count", |classfile| {
+                classfile.constant_pool_count += 1
+            }),
+            ("exception_table_length", |classfile| {
+                if let AttributeInfo::Code {
+                    exception_table_length,
+                    ..
+                } = code_attribute(classfile)
+                {
+                    *exception_table_length = 2;
+                }
+            }),
+            ("number_of_entries", |classfile| {
+                if let AttributeInfo::StackMapTable {
+                    number_of_entries, ..
+                } = stack_map_table(classfile)
+                {
+                    *number_of_entries = 3;
+                }
+            }),
+            ("attribute_length", |classfile| {
+                if let AttributeInfo::StackMapTable {
+                    attribute_length, ..
+                } = stack_map_table(classfile)
+                {
+                    *attribute_length = 0;
+                }
+            }),
+            ("num_annotations", |classfile| {
+                if let AttributeInfo::RuntimeVisibleAnnotations {
+                    num_annotations, ..
+                } = annotations(classfile)
+                {
+                    *num_annotations = 0;
+                }
+            }),
+            ("num_element_value_pairs", |classfile| {
+                if let AttributeInfo::RuntimeVisibleAnnotations { annotations, .. } =
+                    annotations(classfile)
+                {
+                    annotations[0].num_element_value_pairs = 2;
+                }
+            }),
+        ];
+
+        for (field, corrupt) in cases {
+            let mut classfile = build();
+            corrupt(&mut classfile);
+            assert_ne!(
+                serialize(&classfile, CountMode::Preserve).unwrap(),
+                expected,
+                "{}",
+                field
+            );
+            assert_eq!(
+                serialize(&classfile, CountMode::Recompute).unwrap(),
+                expected,
+                "{}",
+                field
+            );
+            let err = serialize(&classfile, CountMode::Strict).unwrap_err();
+            assert!(
+                matches!(err.kind(), SerializeErrorKind::CountMismatch { field: f, .. } if f == &field),
+                "{}: {:?}",
+                field,
+                err
+            );
+        }
+
+        // mismatches inside nested attributes are reported with the path to them
+        let mut classfile = build();
+        cases[3].1(&mut classfile);
+        let err = serialize(&classfile, CountMode::Strict).unwrap_err();
+        assert_eq!(
+            err.path(),
+            [
+                PathSegment::Method(0),
+                PathSegment::Attribute("Code".to_string()),
+                PathSegment::Attribute("StackMapTable".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unencodable_code() {
+        use crate::{
+            analysis::SimpleClassHierarchy,
+            builder::{ClassBuilder, MethodBuilder},
+            bytecode::{CodeBuilder, Instruction},
+            model::access_flags::ACC_STATIC,
+        };
+
+        let serialize = |classfile: &ClassFile, count_mode| {
+            let mut bytes = Vec::new();
+            Serializer::new(Writer::new(&mut bytes))
+                .with_count_mode(count_mode)
+                .serialize(classfile)
+                .map(|_| bytes)
+        };
+
+        // static void f() { try {} catch (Throwable t) { throw t; } }
+        let build = || {
+            let mut code = CodeBuilder::new();
+            let (start, end, handler) = (code.label(), code.label(), code.label());
+            code.place(start)
+                .emit(Instruction::Nop)
+                .place(end)
+                .emit(Instruction::Return)
+                .place(handler)
+                .emit(Instruction::Athrow)
+                .try_catch(start, end, handler, 0);
+            let mut builder = ClassBuilder::new("Frames");
+            builder.method(MethodBuilder::new(ACC_STATIC, "f", "()V").code(code));
+            builder
+                .build_with_frames(&SimpleClassHierarchy::new())
+                .unwrap()
+        };
+
+        // the frame at the handler has a single stack item, and no count to recompute
+        let mut frames = build();
+        let AttributeInfo::Code {
+            code_attributes, ..
+        } = &mut frames.methods[0].attributes[0]
+        else {
+            panic!("expected a Code attribute");
+        };
+        let AttributeInfo::StackMapTable { entries, .. } = &mut code_attributes[0] else {
+            panic!("expected a StackMapTable attribute");
+        };
+        let [StackMapFrame::SameLocals1StackItemFrame { stack, .. }] = &mut entries[..] else {
+            panic!("unexpected frames {:?}", entries);
+        };
+        stack.clear();
+        for count_mode in [CountMode::Preserve, CountMode::Recompute, CountMode::Strict] {
+            let err = serialize(&frames, count_mode).unwrap_err();
+            assert_eq!(
+                err.kind(),
+                &SerializeErrorKind::CountMismatch {
+                    field: "stack",
+                    stored: 1,
+                    actual: 0,
+                }
+            );
+        }
+
+        // code_length may not exceed 65535, even if it matches the code
+        let mut long = build();
+        let AttributeInfo::Code {
+            code_length, code, ..
+        } = &mut long.methods[0].attributes[0]
+        else {
+            panic!("expected a Code attribute");
+        };
+        code.resize(65536, 0);
+        *code_length = 65536;
+        for count_mode in [CountMode::Recompute, CountMode::Strict] {
+            let err = serialize(&long, count_mode).unwrap_err();
+            assert_eq!(
+                err.kind(),
+                &SerializeErrorKind::CountOverflow {
+                    field: "code_length",
+                    actual: 65536,
+                }
+            );
+        }
+    }
 }
@@ -0,0 +1,776 @@
+//! Generic signatures (JVMS 4.7.9.1), the strings referenced by `Signature` attributes that
+//! record the generic types of classes, methods and fields, e.g.
+//! `<T:Ljava/lang/Object;>Ljava/lang/Object;Ljava/lang/Comparable<TT;>;`.
+//!
+//! Signatures are parsed with `ClassSignature::parse`, `MethodSignature::parse` and
+//! `TypeSignature::parse` (or `str::parse`), and printed back with their `Display`
+//! implementations.
+
+use std::{error::Error, fmt, str::FromStr};
+
+pub type SignatureResult<T> = Result<T, SignatureError>;
+
+/// The maximum depth to which array types and type arguments may be nested before the
+/// signature is rejected, which is also the JVM's limit on the dimensions of an array.
+const MAX_NESTING_DEPTH: usize = 255;
+
+/// The different kinds of malformed generic signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignatureErrorKind {
+    /// The signature ends in the middle of a type.
+    UnexpectedEnd,
+    /// The character at byte `offset` cannot appear there.
+    UnexpectedCharacter { offset: usize, found: char },
+    /// Array types and type arguments are nested more than the given number of levels deep.
+    NestingTooDeep(usize),
+}
+
+impl fmt::Display for SignatureErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureErrorKind::UnexpectedEnd => write!(f, "unexpected end"),
+            SignatureErrorKind::UnexpectedCharacter { offset, found } => {
+                write!(f, "unexpected character {:?} at index {}", found, offset)
+            }
+            SignatureErrorKind::NestingTooDeep(max_depth) => {
+                write!(f, "types nested more than {} levels deep", max_depth)
+            }
+        }
+    }
+}
+
+/// Error type for malformed class, method and field signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureError {
+    signature: String,
+    kind: SignatureErrorKind,
+}
+
+impl SignatureError {
+    pub fn new(signature: &str, kind: SignatureErrorKind) -> Self {
+        SignatureError {
+            signature: signature.to_string(),
+            kind,
+        }
+    }
+
+    /// The malformed signature.
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    pub fn kind(&self) -> &SignatureErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid signature {:?}: {}", self.signature, self.kind)
+    }
+}
+
+impl Error for SignatureError {}
+
+/// The type of a field, parameter or return value, with type arguments and type variables.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeSignature {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Int,
+    Long,
+    Short,
+    Boolean,
+    Class(ClassTypeSignature),
+    /// A type variable, e.g. `T` for `TT;`.
+    TypeVariable(String),
+    /// An array with elements of the given type.
+    Array(Box<TypeSignature>),
+}
+
+impl TypeSignature {
+    /// Parse the field signature `signature`, which is a reference type, e.g.
+    /// `Ljava/util/List<Ljava/lang/String;>;`.
+    pub fn parse(signature: &str) -> SignatureResult<TypeSignature> {
+        let mut parser = Parser::new(signature);
+        let ty = parser.reference_type()?;
+        parser.end()?;
+        Ok(ty)
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            TypeSignature::Class(_) | TypeSignature::TypeVariable(_) | TypeSignature::Array(_)
+        )
+    }
+}
+
+impl FromStr for TypeSignature {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TypeSignature::parse(s)
+    }
+}
+
+impl fmt::Display for TypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeSignature::Byte => write!(f, "B"),
+            TypeSignature::Char => write!(f, "C"),
+            TypeSignature::Double => write!(f, "D"),
+            TypeSignature::Float => write!(f, "F"),
+            TypeSignature::Int => write!(f, "I"),
+            TypeSignature::Long => write!(f, "J"),
+            TypeSignature::Short => write!(f, "S"),
+            TypeSignature::Boolean => write!(f, "Z"),
+            TypeSignature::Class(class) => write!(f, "{}", class),
+            TypeSignature::TypeVariable(name) => write!(f, "T{};", name),
+            TypeSignature::Array(element) => write!(f, "[{}", element),
+        }
+    }
+}
+
+/// A class type, e.g. `java/util/Map<TK;TV;>.Entry<TK;TV;>` for
+/// `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassTypeSignature {
+    /// The package of the class in internal form, e.g. `java/util`, or empty for the unnamed
+    /// package.
+    pub package: String,
+    /// The top level class.
+    pub class: SimpleClassTypeSignature,
+    /// The member classes nested in `class`, outermost first.
+    pub inner_classes: Vec<SimpleClassTypeSignature>,
+}
+
+impl fmt::Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L")?;
+        if !self.package.is_empty() {
+            write!(f, "{}/", self.package)?;
+        }
+        write!(f, "{}", self.class)?;
+        for inner_class in &self.inner_classes {
+            write!(f, ".{}", inner_class)?;
+        }
+        write!(f, ";")
+    }
+}
+
+/// The simple name of a class, with its type arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+impl fmt::Display for SimpleClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.type_arguments.is_empty() {
+            write!(f, "<")?;
+            for type_argument in &self.type_arguments {
+                write!(f, "{}", type_argument)?;
+            }
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+/// A type argument of a class type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeArgument {
+    /// The unbounded wildcard `?` (`*`).
+    Any,
+    /// The reference type itself.
+    Exact(TypeSignature),
+    /// A wildcard with an upper bound, `? extends` (`+`).
+    Extends(TypeSignature),
+    /// A wildcard with a lower bound, `? super` (`-`).
+    Super(TypeSignature),
+}
+
+impl fmt::Display for TypeArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeArgument::Any => write!(f, "*"),
+            TypeArgument::Exact(ty) => write!(f, "{}", ty),
+            TypeArgument::Extends(ty) => write!(f, "+{}", ty),
+            TypeArgument::Super(ty) => write!(f, "-{}", ty),
+        }
+    }
+}
+
+/// A type parameter of a generic class or method, e.g. `T extends Number & Comparable<T>` for
+/// `T:Ljava/lang/Number;:Ljava/lang/Comparable<TT;>;`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeParameter {
+    pub name: String,
+    /// The class (or type variable) bound, which is absent if the parameter is only bounded by
+    /// interfaces.
+    pub class_bound: Option<TypeSignature>,
+    pub interface_bounds: Vec<TypeSignature>,
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        if let Some(class_bound) = &self.class_bound {
+            write!(f, "{}", class_bound)?;
+        }
+        for interface_bound in &self.interface_bounds {
+            write!(f, ":{}", interface_bound)?;
+        }
+        Ok(())
+    }
+}
+
+/// The signature of a class: its type parameters, superclass and superinterfaces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub super_class: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>,
+}
+
+impl ClassSignature {
+    /// Parse the class signature `signature`.
+    pub fn parse(signature: &str) -> SignatureResult<ClassSignature> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+        let super_class = parser.class_type()?;
+        let mut interfaces = Vec::new();
+        while parser.peek().is_some() {
+            interfaces.push(parser.class_type()?);
+        }
+
+        Ok(ClassSignature {
+            type_parameters,
+            super_class,
+            interfaces,
+        })
+    }
+}
+
+impl FromStr for ClassSignature {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ClassSignature::parse(s)
+    }
+}
+
+impl fmt::Display for ClassSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_type_parameters(f, &self.type_parameters)?;
+        write!(f, "{}", self.super_class)?;
+        for interface in &self.interfaces {
+            write!(f, "{}", interface)?;
+        }
+        Ok(())
+    }
+}
+
+/// The signature of a method: its type parameters, parameter types, return type and thrown
+/// types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<TypeSignature>,
+    /// The return type, or `None` for `void` methods.
+    pub return_type: Option<TypeSignature>,
+    /// The thrown class types and type variables.
+    pub throws: Vec<TypeSignature>,
+}
+
+impl MethodSignature {
+    /// Parse the method signature `signature`.
+    pub fn parse(signature: &str) -> SignatureResult<MethodSignature> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = parser.type_parameters()?;
+
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while parser.peek() != Some(')') {
+            parameters.push(parser.java_type()?);
+        }
+        parser.expect(')')?;
+
+        let return_type = if parser.peek() == Some('V') {
+            parser.offset += 1;
+            None
+        } else {
+            Some(parser.java_type()?)
+        };
+
+        let mut throws = Vec::new();
+        while parser.peek().is_some() {
+            parser.expect('^')?;
+            let ty = match parser.peek() {
+                Some('T') => parser.type_variable()?,
+                _ => TypeSignature::Class(parser.class_type()?),
+            };
+            throws.push(ty);
+        }
+
+        Ok(MethodSignature {
+            type_parameters,
+            parameters,
+            return_type,
+            throws,
+        })
+    }
+}
+
+impl FromStr for MethodSignature {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MethodSignature::parse(s)
+    }
+}
+
+impl fmt::Display for MethodSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_type_parameters(f, &self.type_parameters)?;
+        write!(f, "(")?;
+        for parameter in &self.parameters {
+            write!(f, "{}", parameter)?;
+        }
+        match &self.return_type {
+            Some(return_type) => write!(f, "){}", return_type)?,
+            None => write!(f, ")V")?,
+        }
+        for ty in &self.throws {
+            write!(f, "^{}", ty)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_type_parameters(
+    f: &mut fmt::Formatter<'_>,
+    type_parameters: &[TypeParameter],
+) -> fmt::Result {
+    if type_parameters.is_empty() {
+        return Ok(());
+    }
+    write!(f, "<")?;
+    for type_parameter in type_parameters {
+        write!(f, "{}", type_parameter)?;
+    }
+    write!(f, ">")
+}
+
+struct Parser<'a> {
+    signature: &'a str,
+    /// The offset of the next character in `signature`.
+    offset: usize,
+    /// The number of array types and type argument lists the parser is currently inside.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(signature: &'a str) -> Self {
+        Parser {
+            signature,
+            offset: 0,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, kind: SignatureErrorKind) -> SignatureError {
+        SignatureError::new(self.signature, kind)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.signature[self.offset..].chars().next()
+    }
+
+    /// Consume the next character, failing if there is none.
+    fn next(&mut self) -> SignatureResult<char> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error(SignatureErrorKind::UnexpectedEnd))?;
+        self.offset += c.len_utf8();
+        Ok(c)
+    }
+
+    fn unexpected(&self, offset: usize, found: char) -> SignatureError {
+        self.error(SignatureErrorKind::UnexpectedCharacter { offset, found })
+    }
+
+    fn expect(&mut self, expected: char) -> SignatureResult<()> {
+        let offset = self.offset;
+        match self.next()? {
+            c if c == expected => Ok(()),
+            c => Err(self.unexpected(offset, c)),
+        }
+    }
+
+    /// Fail if there are characters left.
+    fn end(&self) -> SignatureResult<()> {
+        match self.peek() {
+            Some(c) => Err(self.unexpected(self.offset, c)),
+            None => Ok(()),
+        }
+    }
+
+    /// Consume a non-empty identifier, which extends up to the next `.`, `;`, `[`, `/`, `<`,
+    /// `>` or `:`.
+    fn identifier(&mut self) -> SignatureResult<String> {
+        let rest = &self.signature[self.offset..];
+        let len = rest
+            .find(['.', ';', '[', '/', '<', '>', ':'])
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(match rest.chars().next() {
+                Some(c) => self.unexpected(self.offset, c),
+                None => self.error(SignatureErrorKind::UnexpectedEnd),
+            });
+        }
+        self.offset += len;
+        Ok(rest[..len].to_string())
+    }
+
+    /// Parse the type parameters, if the signature starts with any.
+    fn type_parameters(&mut self) -> SignatureResult<Vec<TypeParameter>> {
+        let mut type_parameters = Vec::new();
+        if self.peek() != Some('<') {
+            return Ok(type_parameters);
+        }
+        self.offset += 1;
+
+        loop {
+            let name = self.identifier()?;
+            self.expect(':')?;
+            let class_bound = match self.peek() {
+                Some(':') | Some('>') => None,
+                _ => Some(self.reference_type()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.peek() == Some(':') {
+                self.offset += 1;
+                interface_bounds.push(self.reference_type()?);
+            }
+            type_parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+
+            if self.peek() == Some('>') {
+                self.offset += 1;
+                return Ok(type_parameters);
+            }
+        }
+    }
+
+    /// Parse a primitive or reference type.
+    fn java_type(&mut self) -> SignatureResult<TypeSignature> {
+        let ty = match self.peek() {
+            Some('B') => TypeSignature::Byte,
+            Some('C') => TypeSignature::Char,
+            Some('D') => TypeSignature::Double,
+            Some('F') => TypeSignature::Float,
+            Some('I') => TypeSignature::Int,
+            Some('J') => TypeSignature::Long,
+            Some('S') => TypeSignature::Short,
+            Some('Z') => TypeSignature::Boolean,
+            _ => return self.reference_type(),
+        };
+        self.offset += 1;
+        Ok(ty)
+    }
+
+    /// Parse a class type, type variable or array type.
+    fn reference_type(&mut self) -> SignatureResult<TypeSignature> {
+        match self.peek() {
+            Some('L') => Ok(TypeSignature::Class(self.class_type()?)),
+            Some('T') => self.type_variable(),
+            Some('[') => {
+                // count the dimensions rather than recursing once per `[`
+                let mut dimensions = 0;
+                while self.peek() == Some('[') {
+                    self.offset += 1;
+                    dimensions += 1;
+                }
+                if self.depth + dimensions > MAX_NESTING_DEPTH {
+                    return Err(self.error(SignatureErrorKind::NestingTooDeep(MAX_NESTING_DEPTH)));
+                }
+                self.depth += dimensions;
+                let mut ty = self.java_type()?;
+                self.depth -= dimensions;
+                for _ in 0..dimensions {
+                    ty = TypeSignature::Array(Box::new(ty));
+                }
+                Ok(ty)
+            }
+            Some(c) => Err(self.unexpected(self.offset, c)),
+            None => Err(self.error(SignatureErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    fn type_variable(&mut self) -> SignatureResult<TypeSignature> {
+        self.expect('T')?;
+        let name = self.identifier()?;
+        self.expect(';')?;
+        Ok(TypeSignature::TypeVariable(name))
+    }
+
+    fn class_type(&mut self) -> SignatureResult<ClassTypeSignature> {
+        self.expect('L')?;
+
+        // the identifiers followed by `/` are the package
+        let mut package = String::new();
+        let mut name = self.identifier()?;
+        while self.peek() == Some('/') {
+            self.offset += 1;
+            if !package.is_empty() {
+                package.push('/');
+            }
+            package.push_str(&name);
+            name = self.identifier()?;
+        }
+
+        let class = self.simple_class_type(name)?;
+        let mut inner_classes = Vec::new();
+        while self.peek() == Some('.') {
+            self.offset += 1;
+            let name = self.identifier()?;
+            inner_classes.push(self.simple_class_type(name)?);
+        }
+        self.expect(';')?;
+
+        Ok(ClassTypeSignature {
+            package,
+            class,
+            inner_classes,
+        })
+    }
+
+    /// Parse the type arguments, if any, of the class `name`.
+    fn simple_class_type(&mut self, name: String) -> SignatureResult<SimpleClassTypeSignature> {
+        let mut type_arguments = Vec::new();
+        if self.peek() == Some('<') {
+            if self.depth >= MAX_NESTING_DEPTH {
+                return Err(self.error(SignatureErrorKind::NestingTooDeep(MAX_NESTING_DEPTH)));
+            }
+            self.depth += 1;
+            self.offset += 1;
+            loop {
+                let type_argument = match self.peek() {
+                    Some('*') => {
+                        self.offset += 1;
+                        TypeArgument::Any
+                    }
+                    Some('+') => {
+                        self.offset += 1;
+                        TypeArgument::Extends(self.reference_type()?)
+                    }
+                    Some('-') => {
+                        self.offset += 1;
+                        TypeArgument::Super(self.reference_type()?)
+                    }
+                    _ => TypeArgument::Exact(self.reference_type()?),
+                };
+                type_arguments.push(type_argument);
+
+                if self.peek() == Some('>') {
+                    self.offset += 1;
+                    break;
+                }
+            }
+            self.depth -= 1;
+        }
+
+        Ok(SimpleClassTypeSignature {
+            name,
+            type_arguments,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(package: &str, name: &str, type_arguments: Vec<TypeArgument>) -> TypeSignature {
+        TypeSignature::Class(ClassTypeSignature {
+            package: package.to_string(),
+            class: SimpleClassTypeSignature {
+                name: name.to_string(),
+                type_arguments,
+            },
+            inner_classes: Vec::new(),
+        })
+    }
+
+    fn variable(name: &str) -> TypeSignature {
+        TypeSignature::TypeVariable(name.to_string())
+    }
+
+    #[test]
+    fn test_class_signature() {
+        // class Node<T extends Number & Comparable<? super T>, U> extends Base implements Iterable<T>
+        let signature = "<T:Ljava/lang/Number;:Ljava/lang/Comparable<-TT;>;U:Ljava/lang/Object;>\
+            LBase;Ljava/lang/Iterable<TT;>;";
+        let parsed = ClassSignature::parse(signature).unwrap();
+
+        assert_eq!(
+            parsed.type_parameters,
+            vec![
+                TypeParameter {
+                    name: "T".to_string(),
+                    class_bound: Some(class("java/lang", "Number", vec![])),
+                    interface_bounds: vec![class(
+                        "java/lang",
+                        "Comparable",
+                        vec![TypeArgument::Super(variable("T"))]
+                    )],
+                },
+                TypeParameter {
+                    name: "U".to_string(),
+                    class_bound: Some(class("java/lang", "Object", vec![])),
+                    interface_bounds: vec![],
+                },
+            ]
+        );
+        assert_eq!(
+            TypeSignature::Class(parsed.super_class.clone()),
+            class("", "Base", vec![])
+        );
+        assert_eq!(parsed.interfaces.len(), 1);
+        assert_eq!(parsed.to_string(), signature);
+
+        // an interface-bounded type parameter has no class bound
+        let signature = "<E::Ljava/lang/Comparable<TE;>;>Ljava/lang/Object;";
+        let parsed = ClassSignature::parse(signature).unwrap();
+        assert_eq!(parsed.type_parameters[0].class_bound, None);
+        assert_eq!(parsed.to_string(), signature);
+    }
+
+    #[test]
+    fn test_method_and_field_signatures() {
+        // <K, E extends Exception> Map.Entry<K, ?>[] f(List<? extends K>, int[]) throws E, IOException
+        let signature = "<K:Ljava/lang/Object;E:Ljava/lang/Exception;>\
+            (Ljava/util/List<+TK;>;[I)[Ljava/util/Map$Entry<TK;*>;^TE;^Ljava/io/IOException;";
+        let parsed = MethodSignature::parse(signature).unwrap();
+        assert_eq!(parsed.type_parameters.len(), 2);
+        assert_eq!(
+            parsed.parameters,
+            vec![
+                class(
+                    "java/util",
+                    "List",
+                    vec![TypeArgument::Extends(variable("K"))]
+                ),
+                TypeSignature::Array(Box::new(TypeSignature::Int)),
+            ]
+        );
+        assert_eq!(
+            parsed.throws,
+            vec![variable("E"), class("java/io", "IOException", vec![])]
+        );
+        assert_eq!(parsed.to_string(), signature);
+
+        let parsed = "(TT;J)V".parse::<MethodSignature>().unwrap();
+        assert_eq!(parsed.return_type, None);
+        assert_eq!(parsed.to_string(), "(TT;J)V");
+
+        // a member class of a generic class
+        let signature = "Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;";
+        let parsed = TypeSignature::parse(signature).unwrap();
+        match &parsed {
+            TypeSignature::Class(class) => {
+                assert_eq!(class.package, "java/util");
+                assert_eq!(class.class.name, "Map");
+                assert_eq!(class.inner_classes[0].name, "Entry");
+                assert_eq!(class.inner_classes[0].type_arguments.len(), 2);
+            }
+            _ => panic!("not a class type: {:?}", parsed),
+        }
+        assert_eq!(parsed.to_string(), signature);
+        assert_eq!(TypeSignature::parse("[[TT;").unwrap().to_string(), "[[TT;");
+    }
+
+    #[test]
+    fn test_errors() {
+        use SignatureErrorKind::*;
+
+        let kind = |err: SignatureError| err.kind().clone();
+        assert_eq!(kind(TypeSignature::parse("").unwrap_err()), UnexpectedEnd);
+        assert_eq!(
+            kind(TypeSignature::parse("I").unwrap_err()),
+            UnexpectedCharacter {
+                offset: 0,
+                found: 'I'
+            }
+        );
+        assert_eq!(
+            kind(TypeSignature::parse("Ljava/util/List<>;").unwrap_err()),
+            UnexpectedCharacter {
+                offset: 16,
+                found: '>'
+            }
+        );
+        assert_eq!(
+            kind(TypeSignature::parse("Ljava//List;").unwrap_err()),
+            UnexpectedCharacter {
+                offset: 6,
+                found: '/'
+            }
+        );
+        assert_eq!(kind(TypeSignature::parse("TT").unwrap_err()), UnexpectedEnd);
+        assert_eq!(
+            kind(ClassSignature::parse("<T>Ljava/lang/Object;").unwrap_err()),
+            UnexpectedCharacter {
+                offset: 2,
+                found: '>'
+            }
+        );
+        assert_eq!(
+            kind(MethodSignature::parse("()V^I").unwrap_err()),
+            UnexpectedCharacter {
+                offset: 4,
+                found: 'I'
+            }
+        );
+
+        let err = MethodSignature::parse("(V)V").unwrap_err();
+        assert_eq!(err.signature(), "(V)V");
+        assert_eq!(
+            err.to_string(),
+            "invalid signature \"(V)V\": unexpected character 'V' at index 1"
+        );
+    }
+
+    #[test]
+    fn test_nesting_too_deep() {
+        let nested = |depth| format!("{}TT;{}", "LA<".repeat(depth), ">;".repeat(depth));
+        assert!(TypeSignature::parse(&nested(255)).is_ok());
+        assert_eq!(
+            TypeSignature::parse(&nested(13000)).unwrap_err().kind(),
+            &SignatureErrorKind::NestingTooDeep(255)
+        );
+
+        let array = |dimensions| format!("{}I", "[".repeat(dimensions));
+        assert!(TypeSignature::parse(&array(255)).is_ok());
+        assert_eq!(
+            TypeSignature::parse(&array(256)).unwrap_err().kind(),
+            &SignatureErrorKind::NestingTooDeep(255)
+        );
+        assert_eq!(
+            TypeSignature::parse(&format!("LA<{}>;", array(255)))
+                .unwrap_err()
+                .kind(),
+            &SignatureErrorKind::NestingTooDeep(255)
+        );
+    }
+}
@@ -0,0 +1,909 @@
+//! Checks of the static constraints of the class file format (JVMS 4) that do not depend on
+//! the code of methods: the kinds of constant pool entries, access flag combinations, names and
+//! descriptors, duplicate members, and where attributes may appear. The code itself is checked
+//! by `analysis::verify_class`.
+
+use crate::{
+    descriptor::{
+        DescriptorError, DescriptorErrorKind, FieldType, MethodDescriptor, MAX_PARAMETER_SLOTS,
+    },
+    error::{fmt_path, PathSegment},
+    model::{
+        access_flags::*,
+        attributes::{predefined_attributes::*, AttributeInfo},
+        constant_pool::{
+            is_modified_utf8, types::CpInfo, ConstantPool, ConstantPoolError, MemberRefKind,
+        },
+        ClassFile,
+    },
+};
+use std::{collections::HashSet, error::Error, fmt};
+
+/// The different kinds of static constraint violations (JVMS 4) found by `validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationErrorKind {
+    /// A constant pool index does not refer to an entry of the expected kind.
+    ConstantPool(ConstantPoolError),
+    /// The constant pool entry kind is not allowed in this class file, e.g. `Dynamic` before
+    /// version 55 or `Module` outside of a module descriptor.
+    UnsupportedConstant {
+        kind: &'static str,
+        major_version: u16,
+    },
+    /// A `CONSTANT_MethodHandle_info` entry has an invalid reference kind, or one that does not
+    /// match the member it refers to.
+    InvalidMethodHandle(u8),
+    /// The access flags contain a combination of flags that is not allowed.
+    InvalidAccessFlags(u16),
+    /// A class, field or method name is malformed.
+    InvalidName(String),
+    /// A field or method descriptor is malformed.
+    InvalidDescriptor(DescriptorError),
+    /// The superclass is missing, or is not allowed (e.g. an interface whose superclass is not
+    /// `java/lang/Object`).
+    InvalidSuperClass,
+    /// Two fields, or two methods, have the same name and descriptor.
+    DuplicateMember { name: String, descriptor: String },
+    /// The attribute is not allowed in this location, e.g. `Code` on a field, or on an
+    /// abstract method.
+    MisplacedAttribute(String),
+    /// The attribute may appear at most once in this location, but appears more than once.
+    DuplicateAttribute(String),
+    /// A method that is neither abstract nor native has no `Code` attribute.
+    MissingCode,
+    /// The constant of a `ConstantValue` attribute, at the index, does not match the type of
+    /// the field.
+    InvalidConstantValue(u16),
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationErrorKind::ConstantPool(err) => write!(f, "{}", err),
+            ValidationErrorKind::UnsupportedConstant {
+                kind,
+                major_version,
+            } => write!(
+                f,
+                "{} constant not allowed in this class file (version {})",
+                kind, major_version
+            ),
+            ValidationErrorKind::InvalidMethodHandle(reference_kind) => {
+                write!(f, "invalid method handle reference kind {}", reference_kind)
+            }
+            ValidationErrorKind::InvalidAccessFlags(flags) => {
+                write!(f, "invalid access flags {:#06x}", flags)
+            }
+            ValidationErrorKind::InvalidName(name) => write!(f, "invalid name {:?}", name),
+            ValidationErrorKind::InvalidDescriptor(err) => write!(f, "{}", err),
+            ValidationErrorKind::InvalidSuperClass => write!(f, "invalid superclass"),
+            ValidationErrorKind::DuplicateMember { name, descriptor } => {
+                write!(f, "duplicate member {} {}", name, descriptor)
+            }
+            ValidationErrorKind::MisplacedAttribute(name) => {
+                write!(f, "attribute {} not allowed here", name)
+            }
+            ValidationErrorKind::DuplicateAttribute(name) => {
+                write!(f, "duplicate attribute {}", name)
+            }
+            ValidationErrorKind::MissingCode => write!(f, "missing Code attribute"),
+            ValidationErrorKind::InvalidConstantValue(index) => write!(
+                f,
+                "constant #{} does not match the type of the field",
+                index
+            ),
+        }
+    }
+}
+
+/// A static constraint violation found by `validate`, with the structural path to the part of
+/// the class file that violates it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    kind: ValidationErrorKind,
+    path: Vec<PathSegment>,
+}
+
+impl ValidationError {
+    pub fn new(kind: ValidationErrorKind, path: &[PathSegment]) -> Self {
+        ValidationError {
+            kind,
+            path: path.to_vec(),
+        }
+    }
+
+    pub fn kind(&self) -> &ValidationErrorKind {
+        &self.kind
+    }
+
+    /// The structural path to the part of the class file that violates the constraint.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        fmt_path(f, &self.path)
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Attributes that may appear in the `attributes` table of a class.
+const CLASS_ATTRIBUTES: &[&str] = &[
+    SOURCE_FILE,
+    INNER_CLASSES,
+    ENCLOSING_METHOD,
+    SOURCE_DEBUG_EXTENSION,
+    BOOTSTRAP_METHODS,
+    MODULE,
+    MODULE_PACKAGES,
+    MODULE_MAIN_CLASS,
+    NEST_HOST,
+    NEST_MEMBERS,
+    RECORD,
+    PERMITTED_SUBCLASSES,
+    SYNTHETIC,
+    DEPRECATED,
+    SIGNATURE,
+    RUNTIME_VISIBLE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_ANNOTATIONS,
+    RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+];
+
+/// Attributes that may appear in the `attributes` table of a field.
+const FIELD_ATTRIBUTES: &[&str] = &[
+    CONSTANT_VALUE,
+    SYNTHETIC,
+    DEPRECATED,
+    SIGNATURE,
+    RUNTIME_VISIBLE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_ANNOTATIONS,
+    RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+];
+
+/// Attributes that may appear in the `attributes` table of a method.
+const METHOD_ATTRIBUTES: &[&str] = &[
+    CODE,
+    EXCEPTIONS,
+    RUNTIME_VISIBLE_PARAMETER_ANNOTATIONS,
+    RUNTIME_INVISIBLE_PARAMETER_ANNOTATIONS,
+    ANNOTATION_DEFAULT,
+    METHOD_PARAMETERS,
+    SYNTHETIC,
+    DEPRECATED,
+    SIGNATURE,
+    RUNTIME_VISIBLE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_ANNOTATIONS,
+    RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+];
+
+/// Attributes that may appear in the `attributes` table of a `Code` attribute.
+const CODE_ATTRIBUTES: &[&str] = &[
+    LINE_NUMBER_TABLE,
+    LOCAL_VARIABLE_TABLE,
+    LOCAL_VARIABLE_TYPE_TABLE,
+    STACK_MAP_TABLE,
+    RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+];
+
+/// Attributes that may appear in the `attributes` table of a record component.
+const RECORD_COMPONENT_ATTRIBUTES: &[&str] = &[
+    SIGNATURE,
+    RUNTIME_VISIBLE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_ANNOTATIONS,
+    RUNTIME_VISIBLE_TYPE_ANNOTATIONS,
+    RUNTIME_INVISIBLE_TYPE_ANNOTATIONS,
+];
+
+/// The predefined attributes that may appear more than once in the same table.
+const REPEATABLE_ATTRIBUTES: &[&str] = &[
+    LINE_NUMBER_TABLE,
+    LOCAL_VARIABLE_TABLE,
+    LOCAL_VARIABLE_TYPE_TABLE,
+];
+
+/// Check `class_file` against the static constraints of the class file format, returning every
+/// violation found rather than only the first.
+pub fn validate(class_file: &ClassFile) -> Result<(), Vec<ValidationError>> {
+    let mut validator = Validator {
+        class_file,
+        constant_pool: &class_file.constant_pool,
+        path: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    validator.validate_constant_pool();
+    validator.validate_class();
+    validator.validate_fields();
+    validator.validate_methods();
+    validator.validate_attributes(&class_file.attributes, CLASS_ATTRIBUTES);
+
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+struct Validator<'a> {
+    class_file: &'a ClassFile,
+    constant_pool: &'a ConstantPool,
+    /// The path to the part of the class file being validated.
+    path: Vec<PathSegment>,
+    errors: Vec<ValidationError>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, kind: ValidationErrorKind) {
+        self.errors.push(ValidationError::new(kind, &self.path));
+    }
+
+    /// Record the error of a failed constant pool lookup, returning the value if it succeeded.
+    fn check<T>(&mut self, result: Result<T, ConstantPoolError>) -> Option<T> {
+        result
+            .map_err(|err| self.error(ValidationErrorKind::ConstantPool(err)))
+            .ok()
+    }
+
+    fn is_interface(&self) -> bool {
+        self.class_file.access_flags & ACC_INTERFACE != 0
+    }
+
+    fn validate_constant_pool(&mut self) {
+        let constant_pool = self.constant_pool;
+        let major_version = self.class_file.major_version;
+        let is_module = self.class_file.access_flags & ACC_MODULE != 0;
+
+        for (index, entry) in constant_pool.entries().iter().enumerate() {
+            let Some(entry) = entry else { continue };
+            let index = index as u16;
+            self.path = vec![PathSegment::ConstantPool(index)];
+
+            // the first version in which each kind of entry may appear
+            let supported = match entry {
+                CpInfo::ConstantMethodHandleInfo { .. }
+                | CpInfo::ConstantMethodTypeInfo { .. }
+                | CpInfo::ConstantInvokeDynamicInfo { .. } => major_version >= 51,
+                CpInfo::ConstantDynamicInfo { .. } => major_version >= 55,
+                CpInfo::ConstantModuleInfo { .. } | CpInfo::ConstantPackageInfo { .. } => {
+                    major_version >= 53 && is_module
+                }
+                _ => true,
+            };
+            if !supported {
+                self.error(ValidationErrorKind::UnsupportedConstant {
+                    kind: entry.kind_name(),
+                    major_version,
+                });
+            }
+
+            match entry {
+                CpInfo::ConstantUtf8Info { bytes, .. } => {
+                    if !is_modified_utf8(bytes) {
+                        self.error(ValidationErrorKind::ConstantPool(
+                            ConstantPoolError::InvalidUtf8(index),
+                        ));
+                    }
+                }
+                CpInfo::ConstantClassInfo { .. } => {
+                    if let Some(name) = self.check(constant_pool.get_class_name(index)) {
+                        if !is_class_or_array_name(&name) {
+                            self.error(ValidationErrorKind::InvalidName(name.into_owned()));
+                        }
+                    }
+                }
+                // string constants may contain unpaired surrogates, which `get_string` rejects
+                CpInfo::ConstantStringInfo { string_index, .. } => {
+                    self.check(constant_pool.get_utf8_bytes(*string_index));
+                }
+                CpInfo::ConstantFieldrefInfo { .. }
+                | CpInfo::ConstantMethodrefInfo { .. }
+                | CpInfo::ConstantInterfaceMethodrefInfo { .. } => {
+                    if let Some(member) = self.check(constant_pool.get_member_ref(index)) {
+                        let is_field = member.kind == MemberRefKind::Field;
+                        self.validate_member(&member.name, &member.descriptor, is_field, false);
+                    }
+                }
+                CpInfo::ConstantNameAndTypeInfo { .. } => {
+                    self.check(constant_pool.get_name_and_type(index));
+                }
+                CpInfo::ConstantMethodHandleInfo { .. } => {
+                    if let Some(handle) = self.check(constant_pool.get_method_handle(index)) {
+                        let kind = handle.reference.kind;
+                        let name = &handle.reference.name;
+                        let valid = match handle.reference_kind {
+                            // getField, getStatic, putField, putStatic
+                            1..=4 => kind == MemberRefKind::Field,
+                            // invokeVirtual
+                            5 => kind == MemberRefKind::Method,
+                            // invokeStatic, invokeSpecial
+                            6 | 7 => {
+                                kind == MemberRefKind::Method
+                                    || (kind == MemberRefKind::InterfaceMethod
+                                        && major_version >= 52)
+                            }
+                            // newInvokeSpecial
+                            8 => kind == MemberRefKind::Method && name == "<init>",
+                            // invokeInterface
+                            9 => kind == MemberRefKind::InterfaceMethod,
+                            _ => false,
+                        };
+                        let initializer = name.starts_with('<') && handle.reference_kind != 8;
+                        if !valid || initializer {
+                            self.error(ValidationErrorKind::InvalidMethodHandle(
+                                handle.reference_kind,
+                            ));
+                        }
+                    }
+                }
+                CpInfo::ConstantMethodTypeInfo { .. } => {
+                    if let Some(descriptor) = self.check(constant_pool.get_method_type(index)) {
+                        self.parse_method_descriptor(&descriptor);
+                    }
+                }
+                CpInfo::ConstantDynamicInfo { .. } | CpInfo::ConstantInvokeDynamicInfo { .. } => {
+                    if let Some(dynamic) = self.check(constant_pool.get_dynamic(index)) {
+                        let is_field = matches!(entry, CpInfo::ConstantDynamicInfo { .. });
+                        self.validate_member(&dynamic.name, &dynamic.descriptor, is_field, false);
+                    }
+                }
+                CpInfo::ConstantModuleInfo { .. } => {
+                    self.check(constant_pool.get_module_name(index));
+                }
+                CpInfo::ConstantPackageInfo { .. } => {
+                    self.check(constant_pool.get_package_name(index));
+                }
+                CpInfo::ConstantIntegerInfo { .. }
+                | CpInfo::ConstantFloatInfo { .. }
+                | CpInfo::ConstantLongInfo { .. }
+                | CpInfo::ConstantDoubleInfo { .. } => {}
+            }
+        }
+        self.path.clear();
+    }
+
+    /// Check the access flags, name, superclass and interfaces of the class.
+    fn validate_class(&mut self) {
+        let class_file = self.class_file;
+        let flags = class_file.access_flags;
+
+        let valid_flags = if flags & ACC_MODULE != 0 {
+            flags == ACC_MODULE
+        } else if flags & ACC_INTERFACE != 0 {
+            flags & ACC_ABSTRACT != 0 && flags & (ACC_FINAL | ACC_SUPER | ACC_ENUM) == 0
+        } else {
+            flags & ACC_ANNOTATION == 0
+                && flags & (ACC_FINAL | ACC_ABSTRACT) != ACC_FINAL | ACC_ABSTRACT
+        };
+        if !valid_flags {
+            self.error(ValidationErrorKind::InvalidAccessFlags(flags));
+        }
+
+        let name = self.check(self.constant_pool.get_class_name(class_file.this_class));
+        if let Some(name) = &name {
+            if !is_class_name(name) {
+                self.error(ValidationErrorKind::InvalidName(name.to_string()));
+            }
+        }
+
+        match class_file.super_class {
+            // only `java/lang/Object` and modules have no superclass
+            0 => {
+                let is_object = name.as_deref() == Some("java/lang/Object");
+                if !is_object && flags & ACC_MODULE == 0 {
+                    self.error(ValidationErrorKind::InvalidSuperClass);
+                }
+            }
+            super_class => {
+                if let Some(super_name) = self.check(self.constant_pool.get_class_name(super_class))
+                {
+                    let valid = if self.is_interface() {
+                        super_name == "java/lang/Object"
+                    } else {
+                        !super_name.starts_with('[')
+                    };
+                    if !valid {
+                        self.error(ValidationErrorKind::InvalidSuperClass);
+                    }
+                }
+            }
+        }
+
+        for &interface in &class_file.interfaces {
+            self.check(self.constant_pool.get_class_name(interface));
+        }
+    }
+
+    fn validate_fields(&mut self) {
+        let mut members = HashSet::new();
+
+        for (idx, field) in self.class_file.fields.iter().enumerate() {
+            self.path = vec![PathSegment::Field(idx as u16)];
+
+            let flags = field.access_flags;
+            let valid_flags = if self.is_interface() {
+                flags & !ACC_SYNTHETIC == ACC_PUBLIC | ACC_STATIC | ACC_FINAL
+            } else {
+                has_one_access_level(flags)
+                    && flags & (ACC_FINAL | ACC_VOLATILE) != ACC_FINAL | ACC_VOLATILE
+            };
+            if !valid_flags {
+                self.error(ValidationErrorKind::InvalidAccessFlags(flags));
+            }
+
+            let name = self.check(self.constant_pool.get_utf8(field.name_index));
+            let descriptor = self.check(self.constant_pool.get_utf8(field.descriptor_index));
+            if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                self.validate_member(&name, &descriptor, true, false);
+                if !members.insert((name.clone(), descriptor.clone())) {
+                    self.error(ValidationErrorKind::DuplicateMember {
+                        name: name.to_string(),
+                        descriptor: descriptor.to_string(),
+                    });
+                }
+
+                for attr in &field.attributes {
+                    if let AttributeInfo::ConstantValue {
+                        constantvalue_index,
+                        ..
+                    } = attr
+                    {
+                        self.validate_constant_value(*constantvalue_index, &descriptor);
+                    }
+                }
+            }
+
+            self.validate_attributes(&field.attributes, FIELD_ATTRIBUTES);
+        }
+        self.path.clear();
+    }
+
+    fn validate_methods(&mut self) {
+        let mut members = HashSet::new();
+
+        for (idx, method) in self.class_file.methods.iter().enumerate() {
+            self.path = vec![PathSegment::Method(idx as u16)];
+
+            let name = self.check(self.constant_pool.get_utf8(method.name_index));
+            let descriptor = self.check(self.constant_pool.get_utf8(method.descriptor_index));
+            let flags = method.access_flags;
+            if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                let is_static = flags & ACC_STATIC != 0;
+                self.validate_member(&name, &descriptor, false, is_static);
+                if !self.valid_method_flags(&name, flags) {
+                    self.error(ValidationErrorKind::InvalidAccessFlags(flags));
+                }
+                if !members.insert((name.clone(), descriptor.clone())) {
+                    self.error(ValidationErrorKind::DuplicateMember {
+                        name: name.to_string(),
+                        descriptor: descriptor.to_string(),
+                    });
+                }
+            }
+
+            // abstract and native methods have no code, and all other methods do
+            let has_code = method
+                .attributes
+                .iter()
+                .any(|attr| matches!(attr, AttributeInfo::Code { .. }));
+            if flags & (ACC_ABSTRACT | ACC_NATIVE) != 0 {
+                if has_code {
+                    self.error(ValidationErrorKind::MisplacedAttribute(CODE.to_string()));
+                }
+            } else if !has_code {
+                self.error(ValidationErrorKind::MissingCode);
+            }
+
+            self.validate_attributes(&method.attributes, METHOD_ATTRIBUTES);
+        }
+        self.path.clear();
+    }
+
+    /// Return whether `flags` are valid access flags for the method `name` (JVMS 4.6).
+    fn valid_method_flags(&self, name: &str, flags: u16) -> bool {
+        let major_version = self.class_file.major_version;
+
+        if name == "<clinit>" {
+            // other flags are ignored
+            return major_version < 51 || flags & ACC_STATIC != 0;
+        }
+        if !has_one_access_level(flags) {
+            return false;
+        }
+        if name == "<init>" {
+            let allowed =
+                ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED | ACC_VARARGS | ACC_STRICT | ACC_SYNTHETIC;
+            return !self.is_interface() && flags & !allowed == 0;
+        }
+
+        if self.is_interface() {
+            let valid = if major_version < 52 {
+                flags & (ACC_PUBLIC | ACC_ABSTRACT) == ACC_PUBLIC | ACC_ABSTRACT
+            } else {
+                flags & (ACC_PUBLIC | ACC_PRIVATE) != 0
+            };
+            if !valid || flags & (ACC_PROTECTED | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE) != 0 {
+                return false;
+            }
+        }
+        if flags & ACC_ABSTRACT != 0 {
+            let mut disallowed =
+                ACC_PRIVATE | ACC_STATIC | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE;
+            if (46..61).contains(&major_version) {
+                disallowed |= ACC_STRICT;
+            }
+            return flags & disallowed == 0;
+        }
+        true
+    }
+
+    /// Check the name and descriptor of a field (if `is_field`) or method.
+    fn validate_member(&mut self, name: &str, descriptor: &str, is_field: bool, is_static: bool) {
+        let valid_name = if is_field {
+            is_unqualified_name(name)
+        } else {
+            is_method_name(name)
+        };
+        if !valid_name {
+            self.error(ValidationErrorKind::InvalidName(name.to_string()));
+        }
+
+        if is_field {
+            self.parse_field_type(descriptor);
+        } else if let Some(method) = self.parse_method_descriptor(descriptor) {
+            // methods take at most 255 slots of arguments, including `this`
+            let kind = if name.starts_with('<') && method.return_type.is_some() {
+                Some(DescriptorErrorKind::NonVoidInitializer)
+            } else if method.parameter_slots() + (!is_static as u16) > MAX_PARAMETER_SLOTS {
+                Some(DescriptorErrorKind::TooManyParameterSlots)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                self.error(ValidationErrorKind::InvalidDescriptor(
+                    DescriptorError::new(descriptor, kind),
+                ));
+            }
+        }
+    }
+
+    /// Parse the field descriptor `descriptor`, recording an error if it is malformed.
+    fn parse_field_type(&mut self, descriptor: &str) -> Option<FieldType> {
+        FieldType::parse(descriptor)
+            .map_err(|err| self.error(ValidationErrorKind::InvalidDescriptor(err)))
+            .ok()
+    }
+
+    /// Parse the method descriptor `descriptor`, recording an error if it is malformed.
+    fn parse_method_descriptor(&mut self, descriptor: &str) -> Option<MethodDescriptor> {
+        MethodDescriptor::parse(descriptor)
+            .map_err(|err| self.error(ValidationErrorKind::InvalidDescriptor(err)))
+            .ok()
+    }
+
+    /// Check that the constant at `index` has the type of a field with descriptor `descriptor`.
+    fn validate_constant_value(&mut self, index: u16, descriptor: &str) {
+        let Some(entry) = self.check(self.constant_pool.entry(index)) else {
+            return;
+        };
+        let valid = match entry {
+            CpInfo::ConstantIntegerInfo { .. } => {
+                matches!(descriptor, "B" | "C" | "I" | "S" | "Z")
+            }
+            CpInfo::ConstantFloatInfo { .. } => descriptor == "F",
+            CpInfo::ConstantLongInfo { .. } => descriptor == "J",
+            CpInfo::ConstantDoubleInfo { .. } => descriptor == "D",
+            CpInfo::ConstantStringInfo { .. } => descriptor == "Ljava/lang/String;",
+            _ => false,
+        };
+        if !valid {
+            self.path
+                .push(PathSegment::Attribute(CONSTANT_VALUE.to_string()));
+            self.error(ValidationErrorKind::InvalidConstantValue(index));
+            self.path.pop();
+        }
+    }
+
+    /// Check that the predefined attributes in `attributes` are among those `allowed`, and
+    /// that each appears at most once, recursing into `Code` and `Record` attributes.
+    fn validate_attributes(&mut self, attributes: &[AttributeInfo], allowed: &[&str]) {
+        let mut seen = HashSet::new();
+
+        for attr in attributes {
+            let Some(name) = self.check(self.constant_pool.get_utf8(attr.attribute_name_index()))
+            else {
+                continue;
+            };
+            let predefined = [
+                CLASS_ATTRIBUTES,
+                FIELD_ATTRIBUTES,
+                METHOD_ATTRIBUTES,
+                CODE_ATTRIBUTES,
+            ]
+            .iter()
+            .any(|names| names.contains(&name.as_ref()));
+            if predefined && !allowed.contains(&name.as_ref()) {
+                self.error(ValidationErrorKind::MisplacedAttribute(name.to_string()));
+            }
+            if predefined
+                && !REPEATABLE_ATTRIBUTES.contains(&name.as_ref())
+                && !seen.insert(name.clone())
+            {
+                self.error(ValidationErrorKind::DuplicateAttribute(name.to_string()));
+            }
+
+            self.path.push(PathSegment::Attribute(name.into_owned()));
+            match attr {
+                AttributeInfo::Code {
+                    code_attributes, ..
+                } => self.validate_attributes(code_attributes, CODE_ATTRIBUTES),
+                AttributeInfo::Record { components, .. } => {
+                    for (idx, component) in components.iter().enumerate() {
+                        self.path.push(PathSegment::Entry(idx as u16));
+                        let name = self.check(self.constant_pool.get_utf8(component.name_index));
+                        let descriptor =
+                            self.check(self.constant_pool.get_utf8(component.descriptor_index));
+                        if let (Some(name), Some(descriptor)) = (name, descriptor) {
+                            self.validate_member(&name, &descriptor, true, false);
+                        }
+                        self.validate_attributes(
+                            &component.attributes,
+                            RECORD_COMPONENT_ATTRIBUTES,
+                        );
+                        self.path.pop();
+                    }
+                }
+                _ => {}
+            }
+            self.path.pop();
+        }
+    }
+}
+
+/// Return whether at most one of `ACC_PUBLIC`, `ACC_PRIVATE` and `ACC_PROTECTED` is set.
+fn has_one_access_level(flags: u16) -> bool {
+    (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() <= 1
+}
+
+/// Return whether `name` is a valid unqualified name of a field or local variable (JVMS 4.2.2).
+fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+/// Return whether `name` is a valid method name: an unqualified name without `<` or `>`, or
+/// one of the special names `<init>` and `<clinit>` (JVMS 4.2.2).
+fn is_method_name(name: &str) -> bool {
+    name == "<init>"
+        || name == "<clinit>"
+        || (is_unqualified_name(name) && !name.contains(['<', '>']))
+}
+
+/// Return whether `name` is a valid binary class name in internal form, e.g.
+/// `java/lang/Object` (JVMS 4.2.1).
+fn is_class_name(name: &str) -> bool {
+    name.split('/').all(is_unqualified_name)
+}
+
+/// Return whether `name` is a valid name for a `CONSTANT_Class_info` entry: a class name, or
+/// the descriptor of an array type with at most 255 dimensions.
+fn is_class_or_array_name(name: &str) -> bool {
+    if name.starts_with('[') {
+        FieldType::parse(name).is_ok()
+    } else {
+        is_class_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{constant_pool::ConstantPoolBuilder, FieldInfo, MethodInfo};
+
+    /// Return a class file `Test` with a field `x` of type `int` and the methods `<init>`,
+    /// `f()V` and `g()V`, adding entries to `constant_pool` as needed.
+    fn class_file(mut constant_pool: ConstantPoolBuilder) -> ClassFile {
+        let this_class = constant_pool.class("Test").unwrap();
+        let super_class = constant_pool.class("java/lang/Object").unwrap();
+        let code_index = constant_pool.utf8(CODE).unwrap();
+        let constant_value_index = constant_pool.utf8(CONSTANT_VALUE).unwrap();
+        let zero = constant_pool.integer(0).unwrap();
+
+        let code = || AttributeInfo::Code {
+            attribute_name_index: code_index,
+            attribute_length: 0,
+            max_stack: 0,
+            max_locals: 1,
+            code_length: 1,
+            code: vec![0xb1],
+            exception_table_length: 0,
+            exception_table: Vec::new(),
+            code_attributes_count: 0,
+            code_attributes: Vec::new(),
+        };
+        let mut method = |name: &str, access_flags: u16| MethodInfo {
+            access_flags,
+            name_index: constant_pool.utf8(name).unwrap(),
+            descriptor_index: constant_pool.utf8("()V").unwrap(),
+            attributes_count: 1,
+            attributes: vec![code()],
+        };
+        let methods = vec![
+            method("<init>", ACC_PUBLIC),
+            method("f", ACC_PUBLIC),
+            method("g", ACC_PRIVATE | ACC_STATIC),
+        ];
+        let field = FieldInfo {
+            access_flags: ACC_STATIC | ACC_FINAL,
+            name_index: constant_pool.utf8("x").unwrap(),
+            descriptor_index: constant_pool.utf8("I").unwrap(),
+            attributes_count: 1,
+            attributes: vec![AttributeInfo::ConstantValue {
+                attribute_name_index: constant_value_index,
+                attribute_length: 2,
+                constantvalue_index: zero,
+            }],
+        };
+
+        ClassFile {
+            major_version: 52,
+            access_flags: ACC_PUBLIC | ACC_SUPER,
+            this_class,
+            super_class,
+            constant_pool: constant_pool.build(),
+            fields: vec![field],
+            methods,
+            ..ClassFile::default()
+        }
+    }
+
+    fn kinds(class_file: &ClassFile) -> Vec<(ValidationErrorKind, Vec<PathSegment>)> {
+        validate(class_file)
+            .unwrap_err()
+            .into_iter()
+            .map(|err| (err.kind().clone(), err.path().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let mut constant_pool = ConstantPoolBuilder::new();
+        constant_pool.method_ref("Test", "f", "()V").unwrap();
+        constant_pool.field_ref("Test", "x", "I").unwrap();
+        constant_pool.string("[not a name]").unwrap();
+        constant_pool.class("[[Ljava/lang/String;").unwrap();
+        constant_pool
+            .method_handle(5, MemberRefKind::Method, "Test", "f", "()V")
+            .unwrap();
+
+        let class_file = class_file(constant_pool);
+        assert_eq!(validate(&class_file), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_reports_all_errors() {
+        use ValidationErrorKind::*;
+
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let dynamic = constant_pool.dynamic(0, "d", "I").unwrap();
+        let handle = constant_pool
+            .method_handle(5, MemberRefKind::Field, "Test", "x", "I")
+            .unwrap();
+        let field_ref = constant_pool.field_ref("Test", "y", "(I)V").unwrap();
+        let string = constant_pool.string("0").unwrap();
+        let duplicate_name = constant_pool.utf8("x").unwrap();
+        let descriptor = constant_pool.utf8("I").unwrap();
+
+        let mut class_file = class_file(constant_pool);
+        class_file.access_flags |= ACC_FINAL | ACC_ABSTRACT;
+        class_file.fields.push(FieldInfo {
+            access_flags: ACC_PUBLIC | ACC_PRIVATE,
+            name_index: duplicate_name,
+            descriptor_index: descriptor,
+            ..FieldInfo::default()
+        });
+        if let AttributeInfo::ConstantValue {
+            constantvalue_index,
+            ..
+        } = &mut class_file.fields[0].attributes[0]
+        {
+            *constantvalue_index = string;
+        }
+        class_file.methods[1].access_flags |= ACC_ABSTRACT;
+        class_file.methods[2].attributes.clear();
+
+        assert_eq!(
+            kinds(&class_file),
+            vec![
+                (
+                    UnsupportedConstant {
+                        kind: "Dynamic",
+                        major_version: 52
+                    },
+                    vec![PathSegment::ConstantPool(dynamic)]
+                ),
+                (
+                    InvalidMethodHandle(5),
+                    vec![PathSegment::ConstantPool(handle)]
+                ),
+                (
+                    InvalidDescriptor(DescriptorError::new(
+                        "(I)V",
+                        DescriptorErrorKind::UnexpectedCharacter {
+                            offset: 0,
+                            found: '('
+                        }
+                    )),
+                    vec![PathSegment::ConstantPool(field_ref)]
+                ),
+                (
+                    InvalidAccessFlags(ACC_PUBLIC | ACC_SUPER | ACC_FINAL | ACC_ABSTRACT),
+                    vec![]
+                ),
+                (
+                    InvalidConstantValue(string),
+                    vec![
+                        PathSegment::Field(0),
+                        PathSegment::Attribute(CONSTANT_VALUE.to_string())
+                    ]
+                ),
+                (
+                    InvalidAccessFlags(ACC_PUBLIC | ACC_PRIVATE),
+                    vec![PathSegment::Field(1)]
+                ),
+                (
+                    DuplicateMember {
+                        name: "x".to_string(),
+                        descriptor: "I".to_string()
+                    },
+                    vec![PathSegment::Field(1)]
+                ),
+                (
+                    MisplacedAttribute(CODE.to_string()),
+                    vec![PathSegment::Method(1)]
+                ),
+                (MissingCode, vec![PathSegment::Method(2)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_attribute_placement() {
+        let mut constant_pool = ConstantPoolBuilder::new();
+        let source_file = constant_pool.utf8(SOURCE_FILE).unwrap();
+        let custom = constant_pool.utf8("Custom").unwrap();
+
+        let mut class_file = class_file(constant_pool);
+        let attribute = |attribute_name_index| AttributeInfo::SourceFile {
+            attribute_name_index,
+            attribute_length: 2,
+            sourcefile_index: attribute_name_index,
+        };
+        class_file.attributes = vec![attribute(source_file), attribute(source_file)];
+        class_file.methods[0]
+            .attributes
+            .push(attribute(source_file));
+        class_file.fields[0].attributes.push(attribute(custom));
+
+        assert_eq!(
+            kinds(&class_file),
+            vec![
+                (
+                    ValidationErrorKind::MisplacedAttribute(SOURCE_FILE.to_string()),
+                    vec![PathSegment::Method(0)]
+                ),
+                (
+                    ValidationErrorKind::DuplicateAttribute(SOURCE_FILE.to_string()),
+                    vec![]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! An event-based API for reading and transforming class files.
+//!
+//! `Deserializer::accept` reads a class file in one pass, reporting its parts to a `ClassVisitor`
+//! as they are read instead of building a `ClassFile`. The events arrive in class file order:
+//!
+//!  1. `visit_header`,
+//!  2. `visit_constant` for each entry of the constant pool,
+//!  3. `visit_class`,
+//!  4. `visit_field` for each field, followed by `visit_attribute` for each of its attributes,
+//!  5. `visit_method` for each method, followed by `visit_attribute` for each of its attributes
+//!     and, before its `Code` attribute, `visit_instruction` for each instruction if the visitor
+//!     asks for them with `visits_instructions`,
+//!  6. `visit_attribute` for each attribute of the class,
+//!  7. `visit_end`.
+//!
+//! Visitors can be chained: the default implementation of every method forwards the event to
+//! the visitor returned by `next`, so a visitor in the middle of a chain only implements the
+//! events it changes or drops. `ClassNode` collects the events it receives into a `ClassFile`,
+//! which makes it the usual end of a chain whose output is to be serialized.
+
+use crate::{
+    bytecode::Instruction,
+    deserializer::MAGIC,
+    model::{
+        attributes::AttributeInfo, constant_pool::types::CpInfo, ClassFile, FieldInfo, MethodInfo,
+    },
+};
+
+/// The structure an attribute belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOwner {
+    Class,
+    /// The field at the given index in the class file.
+    Field(u16),
+    /// The method at the given index in the class file.
+    Method(u16),
+}
+
+/// Callbacks for the parts of a class file, in the order given in the module documentation.
+pub trait ClassVisitor {
+    /// The visitor that events are forwarded to by default. Defaults to `None`, which drops
+    /// them.
+    fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+        None
+    }
+
+    fn visit_header(&mut self, minor_version: u16, major_version: u16) {
+        if let Some(next) = self.next() {
+            next.visit_header(minor_version, major_version);
+        }
+    }
+
+    /// The entry at `index` of the constant pool. The second slot of `Long` and `Double` entries
+    /// is not visited.
+    fn visit_constant(&mut self, index: u16, cp_info: CpInfo) {
+        if let Some(next) = self.next() {
+            next.visit_constant(index, cp_info);
+        }
+    }
+
+    fn visit_class(
+        &mut self,
+        access_flags: u16,
+        this_class: u16,
+        super_class: u16,
+        interfaces: Vec<u16>,
+    ) {
+        if let Some(next) = self.next() {
+            next.visit_class(access_flags, this_class, super_class, interfaces);
+        }
+    }
+
+    fn visit_field(
+        &mut self,
+        field_idx: u16,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) {
+        if let Some(next) = self.next() {
+            next.visit_field(field_idx, access_flags, name_index, descriptor_index);
+        }
+    }
+
+    fn visit_method(
+        &mut self,
+        method_idx: u16,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) {
+        if let Some(next) = self.next() {
+            next.visit_method(method_idx, access_flags, name_index, descriptor_index);
+        }
+    }
+
+    fn visit_attribute(&mut self, owner: AttributeOwner, attribute: AttributeInfo) {
+        if let Some(next) = self.next() {
+            next.visit_attribute(owner, attribute);
+        }
+    }
+
+    /// Whether the code of methods should be decoded into instructions for
+    /// `visit_instruction`. Defaults to asking the next visitor, or `false` if there is none.
+    fn visits_instructions(&mut self) -> bool {
+        self.next().is_some_and(|next| next.visits_instructions())
+    }
+
+    /// The instruction at `offset` in the code of the method at `method_idx`.
+    fn visit_instruction(&mut self, method_idx: u16, offset: usize, instruction: &Instruction) {
+        if let Some(next) = self.next() {
+            next.visit_instruction(method_idx, offset, instruction);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(next) = self.next() {
+            next.visit_end();
+        }
+    }
+}
+
+/// A visitor that collects the events it receives into a `ClassFile`, with the counts set from
+/// the number of items visited. Attributes of fields and methods are added to the member visited
+/// last.
+#[derive(Debug, Default)]
+pub struct ClassNode {
+    class_file: ClassFile,
+    constant_pool: Vec<Option<CpInfo>>,
+}
+
+impl ClassNode {
+    pub fn new() -> Self {
+        ClassNode::default()
+    }
+
+    /// The class file built from the events received so far.
+    pub fn into_class_file(self) -> ClassFile {
+        let mut class_file = self.class_file;
+        class_file.constant_pool_count = self.constant_pool.len().max(1) as u16;
+        class_file.constant_pool = self.constant_pool.into();
+        class_file.interfaces_count = class_file.interfaces.len() as u16;
+        class_file.fields_count = class_file.fields.len() as u16;
+        for field in &mut class_file.fields {
+            field.attributes_count = field.attributes.len() as u16;
+        }
+        class_file.methods_count = class_file.methods.len() as u16;
+        for method in &mut class_file.methods {
+            method.attributes_count = method.attributes.len() as u16;
+        }
+        class_file.attributes_count = class_file.attributes.len() as u16;
+        class_file
+    }
+}
+
+impl ClassVisitor for ClassNode {
+    fn visit_header(&mut self, minor_version: u16, major_version: u16) {
+        self.class_file.magic = MAGIC;
+        self.class_file.minor_version = minor_version;
+        self.class_file.major_version = major_version;
+    }
+
+    fn visit_constant(&mut self, index: u16, cp_info: CpInfo) {
+        // Long and Double values take up two consecutive entries in the Constant Pool.
+        let width = match cp_info {
+            CpInfo::ConstantLongInfo { .. } | CpInfo::ConstantDoubleInfo { .. } => 2,
+            _ => 1,
+        };
+        let end = index as usize + width;
+        if self.constant_pool.len() < end {
+            self.constant_pool.resize(end, None);
+        }
+        self.constant_pool[index as usize] = Some(cp_info);
+    }
+
+    fn visit_class(
+        &mut self,
+        access_flags: u16,
+        this_class: u16,
+        super_class: u16,
+        interfaces: Vec<u16>,
+    ) {
+        self.class_file.access_flags = access_flags;
+        self.class_file.this_class = this_class;
+        self.class_file.super_class = super_class;
+        self.class_file.interfaces = interfaces;
+    }
+
+    fn visit_field(
+        &mut self,
+        _field_idx: u16,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) {
+        self.class_file.fields.push(FieldInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            ..FieldInfo::default()
+        });
+    }
+
+    fn visit_method(
+        &mut self,
+        _method_idx: u16,
+        access_flags: u16,
+        name_index: u16,
+        descriptor_index: u16,
+    ) {
+        self.class_file.methods.push(MethodInfo {
+            access_flags,
+            name_index,
+            descriptor_index,
+            ..MethodInfo::default()
+        });
+    }
+
+    fn visit_attribute(&mut self, owner: AttributeOwner, attribute: AttributeInfo) {
+        let attributes = match owner {
+            AttributeOwner::Class => Some(&mut self.class_file.attributes),
+            AttributeOwner::Field(_) => self
+                .class_file
+                .fields
+                .last_mut()
+                .map(|field| &mut field.attributes),
+            AttributeOwner::Method(_) => self
+                .class_file
+                .methods
+                .last_mut()
+                .map(|method| &mut method.attributes),
+        };
+
+        if let Some(attributes) = attributes {
+            attributes.push(attribute);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bytecode::BytecodeError,
+        deserializer::Deserializer,
+        error::{DeserializeErrorKind, PathSegment},
+        fixtures::{class_bytes, serialize},
+        rw::reader::Reader,
+    };
+    use std::io::Cursor;
+
+    /// Drops `SourceFile` attributes.
+    struct StripSourceFile<V> {
+        next: V,
+    }
+
+    impl<V: ClassVisitor> ClassVisitor for StripSourceFile<V> {
+        fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(&mut self.next)
+        }
+
+        fn visit_attribute(&mut self, owner: AttributeOwner, attribute: AttributeInfo) {
+            if !matches!(attribute, AttributeInfo::SourceFile { .. }) {
+                self.next.visit_attribute(owner, attribute);
+            }
+        }
+    }
+
+    /// Records the mnemonics of the instructions of each method.
+    struct InstructionRecorder<V> {
+        instructions: Vec<(u16, usize, &'static str)>,
+        next: V,
+    }
+
+    impl<V: ClassVisitor> ClassVisitor for InstructionRecorder<V> {
+        fn next(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(&mut self.next)
+        }
+
+        fn visits_instructions(&mut self) -> bool {
+            true
+        }
+
+        fn visit_instruction(&mut self, method_idx: u16, offset: usize, instruction: &Instruction) {
+            self.instructions
+                .push((method_idx, offset, instruction.mnemonic()));
+        }
+    }
+
+    #[test]
+    fn test_class_node() {
+        let bytes = class_bytes();
+        let mut node = ClassNode::new();
+        Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .accept(&mut node)
+            .unwrap();
+        let classfile = node.into_class_file();
+
+        let expected = Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .deserialize()
+            .unwrap();
+        assert_eq!(format!("{:?}", classfile), format!("{:?}", expected));
+        assert_eq!(serialize(&classfile), bytes);
+    }
+
+    #[test]
+    fn test_chained_visitors() {
+        let bytes = class_bytes();
+        let mut chain = InstructionRecorder {
+            instructions: Vec::new(),
+            next: StripSourceFile {
+                next: ClassNode::new(),
+            },
+        };
+        Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .accept(&mut chain)
+            .unwrap();
+
+        assert_eq!(
+            chain.instructions,
+            [
+                (0, 0, "iconst_1"),
+                (0, 1, "ireturn"),
+                (0, 2, "astore_0"),
+                (0, 3, "iconst_0"),
+                (0, 4, "ireturn")
+            ]
+        );
+        let classfile = chain.next.next.into_class_file();
+        assert_eq!(classfile.attributes_count, 0);
+        assert_eq!(classfile.fields[0].attributes_count, 1);
+        assert_eq!(classfile.methods[0].attributes_count, 2);
+        assert!(serialize(&classfile).len() < bytes.len());
+    }
+
+    #[test]
+    fn test_invalid_bytecode() {
+        let mut bytes = class_bytes();
+        let code_offset = bytes
+            .windows(2)
+            .position(|window| window == [0x04, 0xac])
+            .unwrap();
+        bytes[code_offset + 1] = 0xcb;
+
+        // the code is only decoded for visitors that ask for instructions
+        let mut node = ClassNode::new();
+        Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .accept(&mut node)
+            .unwrap();
+
+        let mut recorder = InstructionRecorder {
+            instructions: Vec::new(),
+            next: ClassNode::new(),
+        };
+        let err = Deserializer::new(Reader::new(Cursor::new(&bytes)))
+            .accept(&mut recorder)
+            .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &DeserializeErrorKind::InvalidBytecode(BytecodeError::InvalidOpcode {
+                offset: 1,
+                opcode: 0xcb
+            })
+        );
+        assert_eq!(err.offset(), Some(code_offset + 1));
+        assert_eq!(
+            err.path(),
+            [
+                PathSegment::Method(0),
+                PathSegment::Attribute("Code".to_string())
+            ]
+        );
+        assert_eq!(recorder.instructions, [(0, 0, "iconst_1")]);
+    }
+}
@@ -1,5 +1,16 @@
-use phoron_core::{deserializer::Deserializer, rw::reader::Reader};
-use std::{error::Error, io::Cursor};
+use phoron_core::{
+    bytecode::{self, Instruction},
+    codec::{AttributeCodec, AttributeRegistry},
+    deserializer::{DeserializeResult, Deserializer, SupportedVersions},
+    error::{DeserializeError, DeserializeErrorKind, PathSegment, SerializeError},
+    model::{
+        attributes::{AttributeInfo, CustomAttribute, TargetInfo},
+        constant_pool::{types::CpInfo, ConstantPool, ConstantPoolBuilder},
+    },
+    rw::{reader::Reader, writer::Writer},
+    serializer::{SerializeResult, Serializer},
+};
+use std::{error::Error, io::Cursor, sync::Arc};
 
 pub type DeserializerResult = Result<(), Box<dyn Error + Send + Sync + 'static>>;
 
@@ -2267,4 +2278,1054 @@ fn test_deserialize_implements_demo() -> DeserializerResult {
     let _classfile = deserializer.deserialize()?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Bytecode for the following class file:
+//class InstanceOf
+//  minor version: 0
+//  major version: 52
+//  flags: (0x0020) ACC_SUPER
+//  this_class: #9                          // InstanceOf
+//  super_class: #2                         // java/lang/Object
+//  interfaces: 0, fields: 0, methods: 2, attributes: 0
+//Constant pool:
+//   #1 = Methodref          #2.#3          // java/lang/Object."<init>":()V
+//   #2 = Class              #4             // java/lang/Object
+//   #3 = NameAndType        #5:#6          // "<init>":()V
+//   #4 = Utf8               java/lang/Object
+//   #5 = Utf8               <init>
+//   #6 = Utf8               ()V
+//   #7 = Class              #8             // java/lang/String
+//   #8 = Utf8               java/lang/String
+//   #9 = Class              #10            // InstanceOf
+//  #10 = Utf8               InstanceOf
+//  #11 = Utf8               Code
+//  #12 = Utf8               test
+//  #13 = Utf8               (Ljava/lang/Object;)Z
+//  #14 = Utf8               RuntimeVisibleTypeAnnotations
+//  #15 = Utf8               LT;
+//{
+//  InstanceOf();
+//    descriptor: ()V
+//    flags: (0x0000)
+//    Code:
+//      stack=1, locals=1, args_size=1
+//         0: aload_0
+//         1: invokespecial #1                  // Method java/lang/Object."<init>":()V
+//         4: return
+//
+//  boolean test(java.lang.Object);
+//    descriptor: (Ljava/lang/Object;)Z
+//    flags: (0x0000)
+//    Code:
+//      stack=1, locals=2, args_size=2
+//         0: aload_1
+//         1: instanceof    #7                  // class java/lang/String
+//         4: ireturn
+//      RuntimeVisibleTypeAnnotations:
+//        0: #15(): INSTANCEOF, offset=1
+//          T
+//}
+#[test]
+fn test_deserialize_instanceof_type_annotation() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x34, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x07, 0x00, 0x0a, 0x01, 0x00, 0x0a, 0x49, 0x6e, 0x73, 0x74, 0x61,
+        0x6e, 0x63, 0x65, 0x4f, 0x66, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65, 0x01, 0x00, 0x04,
+        0x74, 0x65, 0x73, 0x74, 0x01, 0x00, 0x15, 0x28, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c,
+        0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x3b, 0x29, 0x5a, 0x01, 0x00,
+        0x1d, 0x52, 0x75, 0x6e, 0x74, 0x69, 0x6d, 0x65, 0x56, 0x69, 0x73, 0x69, 0x62, 0x6c, 0x65,
+        0x54, 0x79, 0x70, 0x65, 0x41, 0x6e, 0x6e, 0x6f, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x73,
+        0x01, 0x00, 0x03, 0x4c, 0x54, 0x3b, 0x00, 0x20, 0x00, 0x09, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x0b, 0x00, 0x00,
+        0x00, 0x11, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x0d, 0x00, 0x01, 0x00, 0x0b, 0x00,
+        0x00, 0x00, 0x21, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x2b, 0xc1, 0x00, 0x07,
+        0xac, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0e, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x01, 0x43, 0x00,
+        0x01, 0x00, 0x00, 0x0f, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    let code_attributes = match &classfile.methods[1].attributes[0] {
+        AttributeInfo::Code {
+            code_attributes, ..
+        } => code_attributes,
+        attr => panic!("expected a Code attribute, found {:?}", attr),
+    };
+
+    match &code_attributes[0] {
+        AttributeInfo::RuntimeVisibleTypeAnnotations { annotations, .. } => {
+            assert_eq!(annotations[0].target_type, 0x43);
+            match annotations[0].target_info {
+                TargetInfo::OffsetTarget { offset } => assert_eq!(offset, 1),
+                ref target_info => panic!("expected an offset target, found {:?}", target_info),
+            }
+        }
+        attr => panic!("expected a type annotations attribute, found {:?}", attr),
+    }
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(bytes, &serialized_bytes[..]);
+
+    Ok(())
+}
+
+// Bytecode for the `Minimal` class file with an additional, non-standard class attribute:
+//  #15 = Utf8               ScalaSig
+//  ...
+//SourceFile: "Minimal.java"
+//  ScalaSig: length = 0x3 (unknown attribute)
+//   05 00 02
+#[test]
+fn test_deserialize_unknown_attribute() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    assert_eq!(classfile.attributes_count, 2);
+    match &classfile.attributes[1] {
+        AttributeInfo::Unknown {
+            attribute_name_index,
+            attribute_length,
+            info,
+        } => {
+            assert_eq!(*attribute_name_index, 15);
+            assert_eq!(*attribute_length, 3);
+            assert_eq!(info, &[0x05, 0x00, 0x02]);
+        }
+        attr => panic!("expected an unknown attribute, found {:?}", attr),
+    }
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(bytes, &serialized_bytes[..]);
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+struct ScalaSig {
+    major_version: u8,
+    minor_version: u8,
+    table_size: u8,
+}
+
+struct ScalaSigCodec;
+
+impl AttributeCodec for ScalaSigCodec {
+    fn name(&self) -> &str {
+        "ScalaSig"
+    }
+
+    fn decode(
+        &self,
+        info: &[u8],
+        _constant_pool: &ConstantPool,
+    ) -> DeserializeResult<Box<dyn CustomAttribute>> {
+        match info {
+            [major_version, minor_version, table_size] => Ok(Box::new(ScalaSig {
+                major_version: *major_version,
+                minor_version: *minor_version,
+                table_size: *table_size,
+            })),
+            _ => Err(DeserializeError::new(
+                "malformed ScalaSig attribute".to_string(),
+            )),
+        }
+    }
+
+    fn encode(
+        &self,
+        value: &dyn CustomAttribute,
+        _constant_pool: &ConstantPool,
+    ) -> SerializeResult<Vec<u8>> {
+        let sig = value
+            .downcast_ref::<ScalaSig>()
+            .ok_or_else(|| SerializeError::new("not a ScalaSig attribute".to_string()))?;
+        Ok(vec![sig.major_version, sig.minor_version, sig.table_size])
+    }
+}
+
+// Same class file as in `test_deserialize_unknown_attribute`, but with a codec registered for the
+// `ScalaSig` attribute.
+#[test]
+fn test_deserialize_custom_attribute() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+
+    let mut registry = AttributeRegistry::new();
+    registry.register(ScalaSigCodec);
+    let registry = Arc::new(registry);
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)))
+        .with_attribute_registry(registry.clone());
+    let classfile = deserializer.deserialize()?;
+
+    match &classfile.attributes[1] {
+        AttributeInfo::Custom { value, .. } => assert_eq!(
+            value.downcast_ref::<ScalaSig>(),
+            Some(&ScalaSig {
+                major_version: 5,
+                minor_version: 0,
+                table_size: 2,
+            })
+        ),
+        attr => panic!("expected a custom attribute, found {:?}", attr),
+    }
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer =
+        Serializer::new(Writer::new(&mut serialized_bytes)).with_attribute_registry(registry);
+    serializer.serialize(&classfile)?;
+    assert_eq!(bytes, &serialized_bytes[..]);
+
+    Ok(())
+}
+
+#[test]
+fn test_deserialize_truncated_class_file() {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(&bytes[..bytes.len() - 2])));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(err.kind(), &DeserializeErrorKind::UnexpectedEof);
+    assert_eq!(err.offset(), Some(277));
+    assert_eq!(
+        err.path(),
+        &[PathSegment::Attribute("ScalaSig".to_string())]
+    );
+    assert!(err.source().is_some());
+}
+
+#[test]
+fn test_deserialize_invalid_attribute_name_index() {
+    let mut bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+    // method #1 -> Code attribute_name_index
+    bytes[230] = 0x30;
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        &DeserializeErrorKind::InvalidAttributeNameIndex(0x30)
+    );
+    assert_eq!(err.offset(), Some(229));
+    assert_eq!(err.path(), &[PathSegment::Method(1)]);
+    assert_eq!(
+        err.to_string(),
+        "attribute name index 48 does not refer to a CONSTANT_Utf8 entry at byte offset 229 (in method #1)"
+    );
+}
+
+#[test]
+fn test_deserialize_invalid_constant_pool_count() {
+    let bytes = [0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x00];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        &DeserializeErrorKind::InvalidConstantPoolCount(0)
+    );
+    assert_eq!(err.offset(), Some(8));
+}
+
+#[test]
+fn test_deserialize_invalid_constant_pool_tag() {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x02, 0x02, 0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(err.kind(), &DeserializeErrorKind::InvalidConstantPoolTag(2));
+    assert_eq!(err.offset(), Some(10));
+    assert_eq!(err.path(), &[PathSegment::ConstantPool(1)]);
+}
+
+#[test]
+fn test_deserialize_invalid_code_length() {
+    let mut bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+    // method #0 -> Code -> code_length
+    bytes[199] = 0x00;
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(err.kind(), &DeserializeErrorKind::InvalidCodeLength(0));
+    assert_eq!(err.offset(), Some(196));
+    assert_eq!(
+        err.path(),
+        &[
+            PathSegment::Method(0),
+            PathSegment::Attribute("Code".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_deserialize_wide_constant_in_last_slot() {
+    // a Long entry in slot #1 of a constant pool with a count of 2
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x02, 0x05, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x01,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        &DeserializeErrorKind::InvalidConstantPoolCount(2)
+    );
+    assert_eq!(err.offset(), Some(10));
+    assert_eq!(err.path(), &[PathSegment::ConstantPool(1)]);
+}
+
+#[test]
+fn test_deserialize_invalid_attribute_length() {
+    let mut bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+
+    // SourceFile -> attribute_length
+    bytes[267] = 0x03;
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        &DeserializeErrorKind::InvalidAttributeLength {
+            attribute_length: 3,
+            actual: 2
+        }
+    );
+    assert_eq!(err.offset(), Some(262));
+    assert_eq!(
+        err.path(),
+        &[PathSegment::Attribute("SourceFile".to_string())]
+    );
+}
+
+#[test]
+fn test_deserialize_deeply_nested_element_values() {
+    let mut bytes = vec![
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x03, 0x01, 0x00, 0x19, 0x52, 0x75,
+        0x6e, 0x74, 0x69, 0x6d, 0x65, 0x56, 0x69, 0x73, 0x69, 0x62, 0x6c, 0x65, 0x41, 0x6e, 0x6e,
+        0x6f, 0x74, 0x61, 0x74, 0x69, 0x6f, 0x6e, 0x73, 0x01, 0x00, 0x03, 0x4c, 0x41, 0x3b, 0x00,
+        0x21, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x01, 0x00, 0x02,
+    ];
+    // an array of an array of an array ... of element values
+    for _ in 0..1000 {
+        bytes.extend_from_slice(&[b'[', 0x00, 0x01]);
+    }
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert!(matches!(
+        err.kind(),
+        DeserializeErrorKind::NestingTooDeep(_)
+    ));
+    assert_eq!(
+        err.path(),
+        &[PathSegment::Attribute(
+            "RuntimeVisibleAnnotations".to_string()
+        )]
+    );
+}
+
+#[test]
+fn test_deserialize_invalid_magic() {
+    let bytes = [0xca, 0xfe, 0xd0, 0x0d, 0x00, 0x00, 0x00, 0x41, 0x00, 0x01];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(err.kind(), &DeserializeErrorKind::InvalidMagic(0xcafed00d));
+    assert_eq!(err.offset(), Some(0));
+    assert_eq!(
+        err.to_string(),
+        "invalid magic number 0xcafed00d, expected 0xcafebabe at byte offset 0"
+    );
+}
+
+#[test]
+fn test_deserialize_unsupported_version() {
+    let bytes = [0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x01];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)))
+        .with_supported_versions(SupportedVersions {
+            min_major: 45,
+            max_major: 61,
+            allow_preview: false,
+        });
+    let err = deserializer.deserialize().unwrap_err();
+
+    assert_eq!(
+        err.kind(),
+        &DeserializeErrorKind::UnsupportedVersion {
+            major: 65,
+            minor: 0
+        }
+    );
+    assert_eq!(err.offset(), Some(4));
+}
+
+#[test]
+fn test_deserialize_preview_version() -> DeserializerResult {
+    let mut bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x41, 0x00, 0x10, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c, 0x01, 0x00, 0x04, 0x43, 0x6f,
+        0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72,
+        0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x04, 0x6d, 0x61, 0x69, 0x6e, 0x01, 0x00, 0x16,
+        0x28, 0x5b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74,
+        0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63,
+        0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0c, 0x4d, 0x69, 0x6e, 0x69, 0x6d, 0x61, 0x6c,
+        0x2e, 0x6a, 0x61, 0x76, 0x61, 0x01, 0x00, 0x08, 0x53, 0x63, 0x61, 0x6c, 0x61, 0x53, 0x69,
+        0x67, 0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01,
+        0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x0a, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x0b,
+        0x00, 0x0c, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0e,
+        0x00, 0x0f, 0x00, 0x00, 0x00, 0x03, 0x05, 0x00, 0x02,
+    ];
+    // minor_version = 0xFFFF
+    bytes[4] = 0xff;
+    bytes[5] = 0xff;
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let err = deserializer.deserialize().unwrap_err();
+    assert_eq!(
+        err.kind(),
+        &DeserializeErrorKind::UnsupportedVersion {
+            major: 65,
+            minor: 0xffff
+        }
+    );
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)))
+        .with_supported_versions(SupportedVersions {
+            allow_preview: true,
+            ..SupportedVersions::default()
+        });
+    let classfile = deserializer.deserialize()?;
+    assert_eq!(classfile.minor_version, 0xffff);
+    assert_eq!(classfile.major_version, 65);
+
+    Ok(())
+}
+
+//Classfile /tmp/w/j6/Lambda.class
+//  Last modified Oct 18, 2026; size 692 bytes
+//  SHA-256 checksum 73a8945169543fffc45759f9635ffedc0d8c78d202b67e458027f0abd668fa43
+//public class Lambda
+//  minor version: 0
+//  major version: 61
+//  flags: (0x0021) ACC_PUBLIC, ACC_SUPER
+//  this_class: #11                         // Lambda
+//  super_class: #2                         // java/lang/Object
+//  interfaces: 0, fields: 0, methods: 3, attributes: 2
+//Constant pool:
+//   #1 = Methodref          #2.#3          // java/lang/Object."<init>":()V
+//   #2 = Class              #4             // java/lang/Object
+//   #3 = NameAndType        #5:#6          // "<init>":()V
+//   #4 = Utf8               java/lang/Object
+//   #5 = Utf8               <init>
+//   #6 = Utf8               ()V
+//   #7 = InvokeDynamic      #0:#8          // #0:run:()Ljava/lang/Runnable;
+//   #8 = NameAndType        #9:#10         // run:()Ljava/lang/Runnable;
+//   #9 = Utf8               run
+//  #10 = Utf8               ()Ljava/lang/Runnable;
+//  #11 = Class              #12            // Lambda
+//  #12 = Utf8               Lambda
+//  #13 = Utf8               Code
+//  #14 = Utf8               make
+//  #15 = Utf8               lambda$make$0
+//  #16 = Utf8               BootstrapMethods
+//  #17 = MethodHandle       6:#18          // REF_invokeStatic java/lang/invoke/LambdaMetafactory.metafactory:(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;
+//  #18 = Methodref          #19.#20        // java/lang/invoke/LambdaMetafactory.metafactory:(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;
+//  #19 = Class              #21            // java/lang/invoke/LambdaMetafactory
+//  #20 = NameAndType        #22:#23        // metafactory:(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;
+//  #21 = Utf8               java/lang/invoke/LambdaMetafactory
+//  #22 = Utf8               metafactory
+//  #23 = Utf8               (Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;
+//  #24 = MethodType         #6             //  ()V
+//  #25 = MethodHandle       6:#26          // REF_invokeStatic Lambda.lambda$make$0:()V
+//  #26 = Methodref          #11.#27        // Lambda.lambda$make$0:()V
+//  #27 = NameAndType        #15:#6         // lambda$make$0:()V
+//  #28 = Utf8               InnerClasses
+//  #29 = Class              #30            // java/lang/invoke/MethodHandles$Lookup
+//  #30 = Utf8               java/lang/invoke/MethodHandles$Lookup
+//  #31 = Class              #32            // java/lang/invoke/MethodHandles
+//  #32 = Utf8               java/lang/invoke/MethodHandles
+//  #33 = Utf8               Lookup
+//{
+//  public Lambda();
+//    descriptor: ()V
+//    flags: (0x0001) ACC_PUBLIC
+//    Code:
+//      stack=1, locals=1, args_size=1
+//         0: aload_0
+//         1: invokespecial #1                  // Method java/lang/Object."<init>":()V
+//         4: return
+//
+//  static java.lang.Runnable make();
+//    descriptor: ()Ljava/lang/Runnable;
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=1, locals=0, args_size=0
+//         0: invokedynamic #7,  0              // InvokeDynamic #0:run:()Ljava/lang/Runnable;
+//         5: areturn
+//
+//  private static void lambda$make$0();
+//    descriptor: ()V
+//    flags: (0x100a) ACC_PRIVATE, ACC_STATIC, ACC_SYNTHETIC
+//    Code:
+//      stack=0, locals=0, args_size=0
+//         0: return
+//}
+//BootstrapMethods:
+//  0: #17 REF_invokeStatic java/lang/invoke/LambdaMetafactory.metafactory:(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite;
+//    Method arguments:
+//      #24 ()V
+//      #25 REF_invokeStatic Lambda.lambda$make$0:()V
+//      #24 ()V
+//InnerClasses:
+//  public static final #33= #29 of #31;    // Lookup=class java/lang/invoke/MethodHandles$Lookup of class java/lang/invoke/MethodHandles
+#[test]
+fn test_deserialize_lambda_constant_pool() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d, 0x00, 0x22, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x12, 0x00, 0x00,
+        0x00, 0x08, 0x0c, 0x00, 0x09, 0x00, 0x0a, 0x01, 0x00, 0x03, 0x72, 0x75, 0x6e, 0x01, 0x00,
+        0x16, 0x28, 0x29, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x52,
+        0x75, 0x6e, 0x6e, 0x61, 0x62, 0x6c, 0x65, 0x3b, 0x07, 0x00, 0x0c, 0x01, 0x00, 0x06, 0x4c,
+        0x61, 0x6d, 0x62, 0x64, 0x61, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65, 0x01, 0x00, 0x04,
+        0x6d, 0x61, 0x6b, 0x65, 0x01, 0x00, 0x0d, 0x6c, 0x61, 0x6d, 0x62, 0x64, 0x61, 0x24, 0x6d,
+        0x61, 0x6b, 0x65, 0x24, 0x30, 0x01, 0x00, 0x10, 0x42, 0x6f, 0x6f, 0x74, 0x73, 0x74, 0x72,
+        0x61, 0x70, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64, 0x73, 0x0f, 0x06, 0x00, 0x12, 0x0a, 0x00,
+        0x13, 0x00, 0x14, 0x07, 0x00, 0x15, 0x0c, 0x00, 0x16, 0x00, 0x17, 0x01, 0x00, 0x22, 0x6a,
+        0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x69, 0x6e, 0x76, 0x6f, 0x6b, 0x65,
+        0x2f, 0x4c, 0x61, 0x6d, 0x62, 0x64, 0x61, 0x4d, 0x65, 0x74, 0x61, 0x66, 0x61, 0x63, 0x74,
+        0x6f, 0x72, 0x79, 0x01, 0x00, 0x0b, 0x6d, 0x65, 0x74, 0x61, 0x66, 0x61, 0x63, 0x74, 0x6f,
+        0x72, 0x79, 0x01, 0x00, 0xcc, 0x28, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e,
+        0x67, 0x2f, 0x69, 0x6e, 0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64,
+        0x48, 0x61, 0x6e, 0x64, 0x6c, 0x65, 0x73, 0x24, 0x4c, 0x6f, 0x6f, 0x6b, 0x75, 0x70, 0x3b,
+        0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69,
+        0x6e, 0x67, 0x3b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x69,
+        0x6e, 0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64, 0x54, 0x79, 0x70,
+        0x65, 0x3b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x69, 0x6e,
+        0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64, 0x54, 0x79, 0x70, 0x65,
+        0x3b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x69, 0x6e, 0x76,
+        0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64, 0x48, 0x61, 0x6e, 0x64, 0x6c,
+        0x65, 0x3b, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x69, 0x6e,
+        0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64, 0x54, 0x79, 0x70, 0x65,
+        0x3b, 0x29, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x69, 0x6e,
+        0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x43, 0x61, 0x6c, 0x6c, 0x53, 0x69, 0x74, 0x65, 0x3b, 0x10,
+        0x00, 0x06, 0x0f, 0x06, 0x00, 0x1a, 0x0a, 0x00, 0x0b, 0x00, 0x1b, 0x0c, 0x00, 0x0f, 0x00,
+        0x06, 0x01, 0x00, 0x0c, 0x49, 0x6e, 0x6e, 0x65, 0x72, 0x43, 0x6c, 0x61, 0x73, 0x73, 0x65,
+        0x73, 0x07, 0x00, 0x1e, 0x01, 0x00, 0x25, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e,
+        0x67, 0x2f, 0x69, 0x6e, 0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64,
+        0x48, 0x61, 0x6e, 0x64, 0x6c, 0x65, 0x73, 0x24, 0x4c, 0x6f, 0x6f, 0x6b, 0x75, 0x70, 0x07,
+        0x00, 0x20, 0x01, 0x00, 0x1e, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f,
+        0x69, 0x6e, 0x76, 0x6f, 0x6b, 0x65, 0x2f, 0x4d, 0x65, 0x74, 0x68, 0x6f, 0x64, 0x48, 0x61,
+        0x6e, 0x64, 0x6c, 0x65, 0x73, 0x01, 0x00, 0x06, 0x4c, 0x6f, 0x6f, 0x6b, 0x75, 0x70, 0x00,
+        0x21, 0x00, 0x0b, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x01, 0x00, 0x05,
+        0x00, 0x06, 0x00, 0x01, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x11, 0x00, 0x01, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00,
+        0x0e, 0x00, 0x0a, 0x00, 0x01, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x12, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x06, 0xba, 0x00, 0x07, 0x00, 0x00, 0xb0, 0x00, 0x00, 0x00, 0x00, 0x10,
+        0x0a, 0x00, 0x0f, 0x00, 0x06, 0x00, 0x01, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x0d, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x10,
+        0x00, 0x00, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x11, 0x00, 0x03, 0x00, 0x18, 0x00, 0x19, 0x00,
+        0x18, 0x00, 0x1c, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x01, 0x00, 0x1d, 0x00, 0x1f, 0x00, 0x21,
+        0x00, 0x19,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    assert_eq!(classfile.constant_pool_count, 34);
+    assert_eq!(classfile.constant_pool.len(), 34);
+    assert_eq!(
+        classfile.constant_pool[7],
+        Some(CpInfo::ConstantInvokeDynamicInfo {
+            tag: 18,
+            bootstrap_method_attr_index: 0,
+            name_and_type_index: 8,
+        })
+    );
+    assert_eq!(
+        classfile.constant_pool[17],
+        Some(CpInfo::ConstantMethodHandleInfo {
+            tag: 15,
+            reference_kind: 6,
+            reference_index: 18,
+        })
+    );
+    assert_eq!(
+        classfile.constant_pool[24],
+        Some(CpInfo::ConstantMethodTypeInfo {
+            tag: 16,
+            descriptor_index: 6,
+        })
+    );
+    assert_eq!(
+        classfile.constant_pool[25],
+        Some(CpInfo::ConstantMethodHandleInfo {
+            tag: 15,
+            reference_kind: 6,
+            reference_index: 26,
+        })
+    );
+
+    let cp = &classfile.constant_pool;
+    let bootstrap = cp.get_method_handle(17)?;
+    assert_eq!(
+        bootstrap.reference.owner,
+        "java/lang/invoke/LambdaMetafactory"
+    );
+    assert_eq!(bootstrap.reference.name, "metafactory");
+    let call_site = cp.get_dynamic(7)?;
+    assert_eq!(call_site.name, "run");
+    assert_eq!(call_site.descriptor, "()Ljava/lang/Runnable;");
+    assert_eq!(cp.get_method_type(24)?, "()V");
+
+    match &classfile.methods[1].attributes[0] {
+        AttributeInfo::Code { code, .. } => assert_eq!(
+            bytecode::decode(code)?,
+            vec![
+                (0, Instruction::Invokedynamic(7)),
+                (5, Instruction::Areturn)
+            ]
+        ),
+        attr => panic!("expected a Code attribute, found {:?}", attr),
+    }
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(serialized_bytes, bytes);
+
+    Ok(())
+}
+
+//Classfile /tmp/w/j6/modout/module-info.class
+//  Last modified Oct 18, 2026; size 122 bytes
+//  SHA-256 checksum 69f3c9d7e610e4abde605692e628b4563cd8747fe847be052dda8c870cce860a
+//module demo
+//  minor version: 0
+//  major version: 61
+//  flags: (0x8000) ACC_MODULE
+//  this_class: #1                          // "module-info"
+//  super_class: #0
+//  interfaces: 0, fields: 0, methods: 0, attributes: 1
+//Constant pool:
+//   #1 = Class              #2             // "module-info"
+//   #2 = Utf8               module-info
+//   #3 = Utf8               Module
+//   #4 = Module             #5             // demo
+//   #5 = Utf8               demo
+//   #6 = Module             #7             // "java.base"
+//   #7 = Utf8               java.base
+//   #8 = Utf8               17.0.15
+//   #9 = Package            #5             // demo
+//{
+//}
+//Module:
+//  #4,0                                    // demo
+//  #0
+//  1                                       // requires
+//    #6,8000                                 // "java.base" ACC_MANDATED
+//    #8                                      // 17.0.15
+//  1                                       // exports
+//    #9,0                                    // demo
+//  0                                       // opens
+//  0                                       // uses
+//  0                                       // provides
+#[test]
+fn test_deserialize_module_info_constant_pool() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d, 0x00, 0x0a, 0x07, 0x00, 0x02, 0x01, 0x00,
+        0x0b, 0x6d, 0x6f, 0x64, 0x75, 0x6c, 0x65, 0x2d, 0x69, 0x6e, 0x66, 0x6f, 0x01, 0x00, 0x06,
+        0x4d, 0x6f, 0x64, 0x75, 0x6c, 0x65, 0x13, 0x00, 0x05, 0x01, 0x00, 0x04, 0x64, 0x65, 0x6d,
+        0x6f, 0x13, 0x00, 0x07, 0x01, 0x00, 0x09, 0x6a, 0x61, 0x76, 0x61, 0x2e, 0x62, 0x61, 0x73,
+        0x65, 0x01, 0x00, 0x07, 0x31, 0x37, 0x2e, 0x30, 0x2e, 0x31, 0x35, 0x14, 0x00, 0x05, 0x80,
+        0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03,
+        0x00, 0x00, 0x00, 0x1c, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x80,
+        0x00, 0x00, 0x08, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    assert_eq!(classfile.constant_pool_count, 10);
+    assert_eq!(classfile.constant_pool.len(), 10);
+    assert_eq!(
+        classfile.constant_pool[4],
+        Some(CpInfo::ConstantModuleInfo {
+            tag: 19,
+            name_index: 5
+        })
+    );
+    assert_eq!(
+        classfile.constant_pool[6],
+        Some(CpInfo::ConstantModuleInfo {
+            tag: 19,
+            name_index: 7
+        })
+    );
+    assert_eq!(
+        classfile.constant_pool[9],
+        Some(CpInfo::ConstantPackageInfo {
+            tag: 20,
+            name_index: 5
+        })
+    );
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(serialized_bytes, bytes);
+
+    Ok(())
+}
+
+// javac does not emit CONSTANT_Dynamic entries for ordinary Java code, so this constant pool is
+// built by hand:
+//   #1 = Dynamic            #0:#2          // #0:x:I
+//   #2 = NameAndType        #3:#4          // x:I
+//   #3 = Utf8               x
+//   #4 = Utf8               I
+//   #5 = Class              #6             // Condy
+//   #6 = Utf8               Condy
+#[test]
+fn test_deserialize_dynamic_constant_pool() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d, 0x00, 0x07, 0x11, 0x00, 0x00, 0x00, 0x02,
+        0x0c, 0x00, 0x03, 0x00, 0x04, 0x01, 0x00, 0x01, 0x78, 0x01, 0x00, 0x01, 0x49, 0x07, 0x00,
+        0x06, 0x01, 0x00, 0x05, 0x43, 0x6f, 0x6e, 0x64, 0x79, 0x00, 0x21, 0x00, 0x05, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    assert_eq!(classfile.constant_pool.len(), 7);
+    assert_eq!(
+        classfile.constant_pool[1],
+        Some(CpInfo::ConstantDynamicInfo {
+            tag: 17,
+            bootstrap_method_attr_index: 0,
+            name_and_type_index: 2,
+        })
+    );
+    assert_eq!(classfile.this_class, 5);
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(serialized_bytes, bytes);
+
+    Ok(())
+}
+
+//Classfile /tmp/w/j9/Strings.class
+//  Last modified Oct 18, 2026; size 227 bytes
+//  SHA-256 checksum cd1f09001641d336f905cabdc2b389bd04fe27969e04d3a8e684566d7940b72e
+//public class Strings
+//  minor version: 0
+//  major version: 61
+//  flags: (0x0021) ACC_PUBLIC, ACC_SUPER
+//  this_class: #7                          // Strings
+//  super_class: #2                         // java/lang/Object
+//  interfaces: 0, fields: 2, methods: 1, attributes: 0
+//Constant pool:
+//   #1 = Methodref          #2.#3          // java/lang/Object."<init>":()V
+//   #2 = Class              #4             // java/lang/Object
+//   #3 = NameAndType        #5:#6          // "<init>":()V
+//   #4 = Utf8               java/lang/Object
+//   #5 = Utf8               <init>
+//   #6 = Utf8               ()V
+//   #7 = Class              #8             // Strings
+//   #8 = Utf8               Strings
+//   #9 = Utf8               NUL
+//  #10 = Utf8               Ljava/lang/String;
+//  #11 = Utf8               ConstantValue
+//  #12 = String             #13            // a\u0000b
+//  #13 = Utf8               a\u0000b
+//  #14 = Utf8               EMOJI
+//  #15 = String             #16            // 😀
+//  #16 = Utf8               😀
+//  #17 = Utf8               Code
+//{
+//  static final java.lang.String NUL;
+//    descriptor: Ljava/lang/String;
+//    flags: (0x0018) ACC_STATIC, ACC_FINAL
+//    ConstantValue: String a\u0000b
+//
+//  static final java.lang.String EMOJI;
+//    descriptor: Ljava/lang/String;
+//    flags: (0x0018) ACC_STATIC, ACC_FINAL
+//    ConstantValue: String 😀
+//
+//  public Strings();
+//    descriptor: ()V
+//    flags: (0x0001) ACC_PUBLIC
+//    Code:
+//      stack=1, locals=1, args_size=1
+//         0: aload_0
+//         1: invokespecial #1                  // Method java/lang/Object."<init>":()V
+//         4: return
+//}
+#[test]
+fn test_deserialize_modified_utf8_strings() -> DeserializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d, 0x00, 0x12, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x07, 0x00, 0x08,
+        0x01, 0x00, 0x07, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x73, 0x01, 0x00, 0x03, 0x4e, 0x55,
+        0x4c, 0x01, 0x00, 0x12, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f,
+        0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3b, 0x01, 0x00, 0x0d, 0x43, 0x6f, 0x6e, 0x73, 0x74,
+        0x61, 0x6e, 0x74, 0x56, 0x61, 0x6c, 0x75, 0x65, 0x08, 0x00, 0x0d, 0x01, 0x00, 0x04, 0x61,
+        0xc0, 0x80, 0x62, 0x01, 0x00, 0x05, 0x45, 0x4d, 0x4f, 0x4a, 0x49, 0x08, 0x00, 0x10, 0x01,
+        0x00, 0x06, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65,
+        0x00, 0x21, 0x00, 0x07, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x18, 0x00, 0x09, 0x00,
+        0x0a, 0x00, 0x01, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x18, 0x00, 0x0e,
+        0x00, 0x0a, 0x00, 0x01, 0x00, 0x0b, 0x00, 0x00, 0x00, 0x02, 0x00, 0x0f, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x11, 0x00, 0x00, 0x00, 0x11, 0x00, 0x01,
+        0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00, 0x01, 0xb1, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    let cp = &classfile.constant_pool;
+    assert_eq!(cp.get_utf8_bytes(13)?, &[0x61, 0xc0, 0x80, 0x62]);
+    assert_eq!(cp.get_string(12)?, "a\0b");
+    assert_eq!(
+        cp.get_utf8_bytes(16)?,
+        &[0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]
+    );
+    assert_eq!(cp.get_string(15)?, "\u{1f600}");
+
+    let mut builder = ConstantPoolBuilder::from(classfile.constant_pool.clone());
+    assert_eq!(builder.string("a\0b")?, 12);
+    assert_eq!(builder.string("\u{1f600}")?, 15);
+
+    Ok(())
+}
+
+#[test]
+fn test_round_trip_catch_type_at_first_constant() -> DeserializerResult {
+    // `static void run()` with a handler catching the class at constant pool index #1
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x31, 0x00, 0x0a, 0x07, 0x00, 0x02, 0x01, 0x00,
+        0x13, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x45, 0x78, 0x63, 0x65,
+        0x70, 0x74, 0x69, 0x6f, 0x6e, 0x07, 0x00, 0x04, 0x01, 0x00, 0x07, 0x43, 0x61, 0x74, 0x63,
+        0x68, 0x65, 0x72, 0x07, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c,
+        0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x03, 0x72, 0x75,
+        0x6e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65, 0x00,
+        0x21, 0x00, 0x03, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x07,
+        0x00, 0x08, 0x00, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x16, 0x00, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x02, 0xb1, 0xbf, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    match &classfile.methods[0].attributes[0] {
+        AttributeInfo::Code {
+            exception_table, ..
+        } => assert_eq!(exception_table[0].catch_type, 1),
+        attr => panic!("expected a Code attribute, found {:?}", attr),
+    }
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(bytes, &serialized_bytes[..]);
+
+    Ok(())
+}
+
+#[test]
+fn test_round_trip_exception_at_first_constant() -> DeserializerResult {
+    // `static void run() throws Exception`, with `java/lang/Exception` at constant pool index #1
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x31, 0x00, 0x0b, 0x07, 0x00, 0x02, 0x01, 0x00,
+        0x13, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x45, 0x78, 0x63, 0x65,
+        0x70, 0x74, 0x69, 0x6f, 0x6e, 0x07, 0x00, 0x04, 0x01, 0x00, 0x07, 0x43, 0x61, 0x74, 0x63,
+        0x68, 0x65, 0x72, 0x07, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c,
+        0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x03, 0x72, 0x75,
+        0x6e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65, 0x01,
+        0x00, 0x0a, 0x45, 0x78, 0x63, 0x65, 0x70, 0x74, 0x69, 0x6f, 0x6e, 0x73, 0x00, 0x21, 0x00,
+        0x03, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x09, 0x00, 0x07, 0x00, 0x08,
+        0x00, 0x02, 0x00, 0x09, 0x00, 0x00, 0x00, 0x0d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x01, 0xb1, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x00, 0x04, 0x00, 0x01, 0x00,
+        0x01, 0x00, 0x00,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let classfile = deserializer.deserialize()?;
+
+    match &classfile.methods[0].attributes[1] {
+        AttributeInfo::Exceptions {
+            exception_index_table,
+            ..
+        } => assert_eq!(exception_index_table, &[1]),
+        attr => panic!("expected an Exceptions attribute, found {:?}", attr),
+    }
+
+    let mut serialized_bytes = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized_bytes));
+    serializer.serialize(&classfile)?;
+    assert_eq!(bytes, &serialized_bytes[..]);
+
+    Ok(())
+}
@@ -1,14 +1,16 @@
 use phoron_core::{
+    analysis::{self, Maxs, SimpleClassHierarchy, VerificationType},
+    deserializer::Deserializer,
     model::{
         attributes::{AttributeInfo::*, StackMapFrame::*, VerificationTypeInfo::*, *},
-        constant_pool::types::CpInfo::*,
+        constant_pool::{types::CpInfo::*, ConstantPoolBuilder},
         *,
     },
-    rw::writer::Writer,
+    rw::{reader::Reader, writer::Writer},
     serializer::Serializer,
 };
 
-use std::error::Error;
+use std::{error::Error, io::Cursor};
 
 pub type SerializerResult = Result<(), Box<dyn Error + Send + Sync + 'static>>;
 
@@ -274,7 +276,8 @@ fn test_serialize_hello_world() -> SerializerResult {
                     72, 101, 108, 108, 111, 87, 111, 114, 108, 100, 46, 106, 97, 118, 97,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 21,
         super_class: 2,
@@ -1002,7 +1005,8 @@ fn test_serialize_fields() -> SerializerResult {
                 length: 11,
                 bytes: vec![70, 105, 101, 108, 100, 115, 46, 106, 97, 118, 97],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 8,
         super_class: 2,
@@ -1927,7 +1931,8 @@ fn test_serialize_arith_evaluator() -> SerializerResult {
                     118, 97,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 22,
         super_class: 2,
@@ -1997,13 +2002,13 @@ fn test_serialize_arith_evaluator() -> SerializerResult {
                             start_pc: 11,
                             end_pc: 76,
                             handler_pc: 76,
-                            catch_type: 67,
+                            catch_type: 68,
                         },
                         ExceptionHandler {
                             start_pc: 77,
                             end_pc: 81,
                             handler_pc: 84,
-                            catch_type: 67,
+                            catch_type: 68,
                         },
                     ],
                     code_attributes_count: 2,
@@ -2363,7 +2368,8 @@ fn test_serialize_hello_world_no_line_number() -> SerializerResult {
                     101, 97, 109, 59,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 12,
         super_class: 5,
@@ -2651,7 +2657,8 @@ fn test_serialize_fields_demo() -> SerializerResult {
                     59,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 12,
         super_class: 5,
@@ -3028,7 +3035,8 @@ fn test_serialize_catcher() -> SerializerResult {
                     101, 97, 109, 59,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 27,
         super_class: 5,
@@ -3075,7 +3083,7 @@ fn test_serialize_catcher() -> SerializerResult {
                         start_pc: 0,
                         end_pc: 8,
                         handler_pc: 8,
-                        catch_type: 13,
+                        catch_type: 14,
                     }],
                     code_attributes_count: 0,
                     code_attributes: vec![],
@@ -3432,7 +3440,8 @@ fn test_serialize_lookupswitchdemo() -> SerializerResult {
                     101, 97, 109, 59,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 5,
         super_class: 10,
@@ -3873,7 +3882,8 @@ fn test_serialize_tableswitchdemo() -> SerializerResult {
                     101, 97, 109, 59,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 8,
         super_class: 11,
@@ -5004,7 +5014,8 @@ fn test_serialize_allinone() -> SerializerResult {
                 length: 13,
                 bytes: vec![67, 111, 110, 115, 116, 97, 110, 116, 86, 97, 108, 117, 101],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 50,
         super_class: 54,
@@ -5092,7 +5103,7 @@ fn test_serialize_allinone() -> SerializerResult {
                         start_pc: 0,
                         end_pc: 8,
                         handler_pc: 8,
-                        catch_type: 11,
+                        catch_type: 12,
                     }],
                     code_attributes_count: 0,
                     code_attributes: vec![],
@@ -5121,13 +5132,13 @@ fn test_serialize_allinone() -> SerializerResult {
                             start_pc: 0,
                             end_pc: 10,
                             handler_pc: 13,
-                            catch_type: 51,
+                            catch_type: 52,
                         },
                         ExceptionHandler {
                             start_pc: 0,
                             end_pc: 37,
                             handler_pc: 25,
-                            catch_type: 39,
+                            catch_type: 40,
                         },
                         ExceptionHandler {
                             start_pc: 0,
@@ -5332,7 +5343,7 @@ fn test_serialize_allinone() -> SerializerResult {
                         attribute_name_index: 34,
                         attribute_length: 4,
                         number_of_exceptions: 1,
-                        exception_index_table: vec![10],
+                        exception_index_table: vec![11],
                     },
                 ],
             },
@@ -5604,7 +5615,8 @@ fn test_serialize_vardemo() -> SerializerResult {
                 length: 3,
                 bytes: vec![66, 97, 122],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 20,
         super_class: 5,
@@ -5954,7 +5966,8 @@ fn test_serialize_throwsdemo() -> SerializerResult {
                 length: 10,
                 bytes: vec![84, 104, 114, 111, 119, 115, 68, 101, 109, 111],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 8,
         super_class: 5,
@@ -6004,7 +6017,7 @@ fn test_serialize_throwsdemo() -> SerializerResult {
                         attribute_name_index: 7,
                         attribute_length: 8,
                         number_of_exceptions: 3,
-                        exception_index_table: vec![16, 14, 22],
+                        exception_index_table: vec![17, 15, 23],
                     },
                 ],
             },
@@ -6358,7 +6371,8 @@ fn test_serialize_linenumberdemo() -> SerializerResult {
                     101, 97, 109, 59,
                 ],
             }),
-        ],
+        ]
+        .into(),
         access_flags: 33,
         this_class: 3,
         super_class: 6,
@@ -6614,7 +6628,8 @@ fn test_serialize_interface_demo() -> SerializerResult {
                 tag: 7,
                 name_index: 1,
             }),
-        ],
+        ]
+        .into(),
         access_flags: 545,
         this_class: 14,
         super_class: 15,
@@ -6633,7 +6648,7 @@ fn test_serialize_interface_demo() -> SerializerResult {
                     attribute_name_index: 2,
                     attribute_length: 4,
                     number_of_exceptions: 1,
-                    exception_index_table: vec![11],
+                    exception_index_table: vec![12],
                 }],
             },
             MethodInfo {
@@ -6842,7 +6857,8 @@ fn test_serialize_implements_demo() -> SerializerResult {
                 tag: 7,
                 name_index: 2,
             }),
-        ],
+        ]
+        .into(),
         access_flags: 32,
         this_class: 12,
         super_class: 17,
@@ -6904,3 +6920,687 @@ fn test_serialize_implements_demo() -> SerializerResult {
 
     Ok(())
 }
+
+//Classfile Maxs.class
+//  Last modified Oct 18, 2026; size 840 bytes
+//  SHA-256 checksum 3895cc9277e20935aa03845e6587f1e44532c0e4b2767c15b41a0d9bfdec40c8
+//  Compiled from "Maxs.java"
+//public class Maxs
+//  minor version: 0
+//  major version: 61
+//  flags: (0x0021) ACC_PUBLIC, ACC_SUPER
+//  this_class: #10                         // Maxs
+//  super_class: #2                         // java/lang/Object
+//  interfaces: 0, fields: 1, methods: 5, attributes: 1
+//Constant pool:
+//   #1 = Methodref          #2.#3          // java/lang/Object."<init>":()V
+//   #2 = Class              #4             // java/lang/Object
+//   #3 = NameAndType        #5:#6          // "<init>":()V
+//   #4 = Utf8               java/lang/Object
+//   #5 = Utf8               <init>
+//   #6 = Utf8               ()V
+//   #7 = Double             2.0d
+//   #9 = Fieldref           #10.#11        // Maxs.total:J
+//  #10 = Class              #12            // Maxs
+//  #11 = NameAndType        #13:#14        // total:J
+//  #12 = Utf8               Maxs
+//  #13 = Utf8               total
+//  #14 = Utf8               J
+//  #15 = Methodref          #16.#17        // java/lang/Integer.parseInt:(Ljava/lang/String;)I
+//  #16 = Class              #18            // java/lang/Integer
+//  #17 = NameAndType        #19:#20        // parseInt:(Ljava/lang/String;)I
+//  #18 = Utf8               java/lang/Integer
+//  #19 = Utf8               parseInt
+//  #20 = Utf8               (Ljava/lang/String;)I
+//  #21 = Class              #22            // java/lang/NumberFormatException
+//  #22 = Utf8               java/lang/NumberFormatException
+//  #23 = String             #24            // one
+//  #24 = Utf8               one
+//  #25 = String             #26            // two
+//  #26 = Utf8               two
+//  #27 = Methodref          #28.#29        // java/lang/String.valueOf:(I)Ljava/lang/String;
+//  #28 = Class              #30            // java/lang/String
+//  #29 = NameAndType        #31:#32        // valueOf:(I)Ljava/lang/String;
+//  #30 = Utf8               java/lang/String
+//  #31 = Utf8               valueOf
+//  #32 = Utf8               (I)Ljava/lang/String;
+//  #33 = Utf8               Code
+//  #34 = Utf8               LineNumberTable
+//  #35 = Utf8               mix
+//  #36 = Utf8               (IJD)D
+//  #37 = Utf8               sum
+//  #38 = Utf8               ([J)J
+//  #39 = Utf8               StackMapTable
+//  #40 = Class              #41            // "[J"
+//  #41 = Utf8               [J
+//  #42 = Utf8               parse
+//  #43 = Utf8               name
+//  #44 = Utf8               SourceFile
+//  #45 = Utf8               Maxs.java
+//{
+//  long total;
+//    descriptor: J
+//    flags: (0x0000)
+//
+//  public Maxs();
+//    descriptor: ()V
+//    flags: (0x0001) ACC_PUBLIC
+//    Code:
+//      stack=1, locals=1, args_size=1
+//         0: aload_0
+//         1: invokespecial #1                  // Method java/lang/Object."<init>":()V
+//         4: return
+//      LineNumberTable:
+//        line 1: 0
+//
+//  static double mix(int, long, double);
+//    descriptor: (IJD)D
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=6, locals=5, args_size=3
+//         0: iload_0
+//         1: i2l
+//         2: lload_1
+//         3: lmul
+//         4: l2d
+//         5: dload_3
+//         6: ldc2_w        #7                  // double 2.0d
+//         9: ddiv
+//        10: dadd
+//        11: dreturn
+//      LineNumberTable:
+//        line 5: 0
+//
+//  long sum(long[]);
+//    descriptor: ([J)J
+//    flags: (0x0000)
+//    Code:
+//      stack=5, locals=9, args_size=2
+//         0: lconst_0
+//         1: lstore_2
+//         2: aload_1
+//         3: astore        4
+//         5: aload         4
+//         7: arraylength
+//         8: istore        5
+//        10: iconst_0
+//        11: istore        6
+//        13: iload         6
+//        15: iload         5
+//        17: if_icmpge     38
+//        20: aload         4
+//        22: iload         6
+//        24: laload
+//        25: lstore        7
+//        27: lload_2
+//        28: lload         7
+//        30: ladd
+//        31: lstore_2
+//        32: iinc          6, 1
+//        35: goto          13
+//        38: aload_0
+//        39: dup
+//        40: getfield      #9                  // Field total:J
+//        43: lload_2
+//        44: ladd
+//        45: putfield      #9                  // Field total:J
+//        48: lload_2
+//        49: lreturn
+//      LineNumberTable:
+//        line 9: 0
+//        line 10: 2
+//        line 11: 27
+//        line 10: 32
+//        line 13: 38
+//        line 14: 48
+//      StackMapTable: number_of_entries = 2
+//        frame_type = 255 /* full_frame */
+//          offset_delta = 13
+//          locals = [ class Maxs, class "[J", long, class "[J", int, int ]
+//          stack = []
+//        frame_type = 248 /* chop */
+//          offset_delta = 24
+//
+//  static int parse(java.lang.String);
+//    descriptor: (Ljava/lang/String;)I
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=1, locals=2, args_size=1
+//         0: aload_0
+//         1: invokestatic  #15                 // Method java/lang/Integer.parseInt:(Ljava/lang/String;)I
+//         4: ireturn
+//         5: astore_1
+//         6: iconst_m1
+//         7: ireturn
+//      Exception table:
+//         from    to  target type
+//             0     4     5   Class java/lang/NumberFormatException
+//      LineNumberTable:
+//        line 19: 0
+//        line 20: 5
+//        line 21: 6
+//      StackMapTable: number_of_entries = 1
+//        frame_type = 69 /* same_locals_1_stack_item */
+//          stack = [ class java/lang/NumberFormatException ]
+//
+//  static java.lang.String name(int);
+//    descriptor: (I)Ljava/lang/String;
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=1, locals=1, args_size=1
+//         0: iload_0
+//         1: lookupswitch  { // 2
+//                       1: 28
+//                       2: 31
+//                 default: 34
+//            }
+//        28: ldc           #23                 // String one
+//        30: areturn
+//        31: ldc           #25                 // String two
+//        33: areturn
+//        34: iload_0
+//        35: invokestatic  #27                 // Method java/lang/String.valueOf:(I)Ljava/lang/String;
+//        38: areturn
+//      LineNumberTable:
+//        line 26: 0
+//        line 27: 28
+//        line 28: 31
+//        line 29: 34
+//      StackMapTable: number_of_entries = 3
+//        frame_type = 28 /* same */
+//        frame_type = 2 /* same */
+//        frame_type = 2 /* same */
+//}
+//SourceFile: "Maxs.java"
+#[test]
+fn test_serialize_computed_maxs() -> SerializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d, 0x00, 0x2e, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x06,
+        0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x06, 0x40, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x0a, 0x00, 0x0b, 0x07, 0x00, 0x0c, 0x0c,
+        0x00, 0x0d, 0x00, 0x0e, 0x01, 0x00, 0x04, 0x4d, 0x61, 0x78, 0x73, 0x01, 0x00, 0x05, 0x74,
+        0x6f, 0x74, 0x61, 0x6c, 0x01, 0x00, 0x01, 0x4a, 0x0a, 0x00, 0x10, 0x00, 0x11, 0x07, 0x00,
+        0x12, 0x0c, 0x00, 0x13, 0x00, 0x14, 0x01, 0x00, 0x11, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c,
+        0x61, 0x6e, 0x67, 0x2f, 0x49, 0x6e, 0x74, 0x65, 0x67, 0x65, 0x72, 0x01, 0x00, 0x08, 0x70,
+        0x61, 0x72, 0x73, 0x65, 0x49, 0x6e, 0x74, 0x01, 0x00, 0x15, 0x28, 0x4c, 0x6a, 0x61, 0x76,
+        0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29,
+        0x49, 0x07, 0x00, 0x16, 0x01, 0x00, 0x1f, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e,
+        0x67, 0x2f, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72, 0x46, 0x6f, 0x72, 0x6d, 0x61, 0x74, 0x45,
+        0x78, 0x63, 0x65, 0x70, 0x74, 0x69, 0x6f, 0x6e, 0x08, 0x00, 0x18, 0x01, 0x00, 0x03, 0x6f,
+        0x6e, 0x65, 0x08, 0x00, 0x1a, 0x01, 0x00, 0x03, 0x74, 0x77, 0x6f, 0x0a, 0x00, 0x1c, 0x00,
+        0x1d, 0x07, 0x00, 0x1e, 0x0c, 0x00, 0x1f, 0x00, 0x20, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76,
+        0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x01, 0x00,
+        0x07, 0x76, 0x61, 0x6c, 0x75, 0x65, 0x4f, 0x66, 0x01, 0x00, 0x15, 0x28, 0x49, 0x29, 0x4c,
+        0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e,
+        0x67, 0x3b, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65, 0x01, 0x00, 0x0f, 0x4c, 0x69, 0x6e,
+        0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72, 0x54, 0x61, 0x62, 0x6c, 0x65, 0x01, 0x00, 0x03,
+        0x6d, 0x69, 0x78, 0x01, 0x00, 0x06, 0x28, 0x49, 0x4a, 0x44, 0x29, 0x44, 0x01, 0x00, 0x03,
+        0x73, 0x75, 0x6d, 0x01, 0x00, 0x05, 0x28, 0x5b, 0x4a, 0x29, 0x4a, 0x01, 0x00, 0x0d, 0x53,
+        0x74, 0x61, 0x63, 0x6b, 0x4d, 0x61, 0x70, 0x54, 0x61, 0x62, 0x6c, 0x65, 0x07, 0x00, 0x29,
+        0x01, 0x00, 0x02, 0x5b, 0x4a, 0x01, 0x00, 0x05, 0x70, 0x61, 0x72, 0x73, 0x65, 0x01, 0x00,
+        0x04, 0x6e, 0x61, 0x6d, 0x65, 0x01, 0x00, 0x0a, 0x53, 0x6f, 0x75, 0x72, 0x63, 0x65, 0x46,
+        0x69, 0x6c, 0x65, 0x01, 0x00, 0x09, 0x4d, 0x61, 0x78, 0x73, 0x2e, 0x6a, 0x61, 0x76, 0x61,
+        0x00, 0x21, 0x00, 0x0a, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0d, 0x00,
+        0x0e, 0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x05, 0x00, 0x06, 0x00, 0x01, 0x00, 0x21,
+        0x00, 0x00, 0x00, 0x1d, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x2a, 0xb7, 0x00,
+        0x01, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x22, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x23, 0x00, 0x24, 0x00, 0x01, 0x00, 0x21, 0x00, 0x00,
+        0x00, 0x24, 0x00, 0x06, 0x00, 0x05, 0x00, 0x00, 0x00, 0x0c, 0x1a, 0x85, 0x1f, 0x69, 0x8a,
+        0x29, 0x14, 0x00, 0x07, 0x6f, 0x63, 0xaf, 0x00, 0x00, 0x00, 0x01, 0x00, 0x22, 0x00, 0x00,
+        0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x25, 0x00, 0x26, 0x00,
+        0x01, 0x00, 0x21, 0x00, 0x00, 0x00, 0x7c, 0x00, 0x05, 0x00, 0x09, 0x00, 0x00, 0x00, 0x32,
+        0x09, 0x41, 0x2b, 0x3a, 0x04, 0x19, 0x04, 0xbe, 0x36, 0x05, 0x03, 0x36, 0x06, 0x15, 0x06,
+        0x15, 0x05, 0xa2, 0x00, 0x15, 0x19, 0x04, 0x15, 0x06, 0x2f, 0x37, 0x07, 0x20, 0x16, 0x07,
+        0x61, 0x41, 0x84, 0x06, 0x01, 0xa7, 0xff, 0xea, 0x2a, 0x59, 0xb4, 0x00, 0x09, 0x20, 0x61,
+        0xb5, 0x00, 0x09, 0x20, 0xad, 0x00, 0x00, 0x00, 0x02, 0x00, 0x22, 0x00, 0x00, 0x00, 0x1a,
+        0x00, 0x06, 0x00, 0x00, 0x00, 0x09, 0x00, 0x02, 0x00, 0x0a, 0x00, 0x1b, 0x00, 0x0b, 0x00,
+        0x20, 0x00, 0x0a, 0x00, 0x26, 0x00, 0x0d, 0x00, 0x30, 0x00, 0x0e, 0x00, 0x27, 0x00, 0x00,
+        0x00, 0x18, 0x00, 0x02, 0xff, 0x00, 0x0d, 0x00, 0x06, 0x07, 0x00, 0x0a, 0x07, 0x00, 0x28,
+        0x04, 0x07, 0x00, 0x28, 0x01, 0x01, 0x00, 0x00, 0xf8, 0x00, 0x18, 0x00, 0x08, 0x00, 0x2a,
+        0x00, 0x14, 0x00, 0x01, 0x00, 0x21, 0x00, 0x00, 0x00, 0x3c, 0x00, 0x01, 0x00, 0x02, 0x00,
+        0x00, 0x00, 0x08, 0x2a, 0xb8, 0x00, 0x0f, 0xac, 0x4c, 0x02, 0xac, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x04, 0x00, 0x05, 0x00, 0x15, 0x00, 0x02, 0x00, 0x22, 0x00, 0x00, 0x00, 0x0e, 0x00,
+        0x03, 0x00, 0x00, 0x00, 0x13, 0x00, 0x05, 0x00, 0x14, 0x00, 0x06, 0x00, 0x15, 0x00, 0x27,
+        0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x45, 0x07, 0x00, 0x15, 0x00, 0x08, 0x00, 0x2b, 0x00,
+        0x20, 0x00, 0x01, 0x00, 0x21, 0x00, 0x00, 0x00, 0x56, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x27, 0x1a, 0xab, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00, 0x00, 0x00, 0x02, 0x00,
+        0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x1b, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x1e,
+        0x12, 0x17, 0xb0, 0x12, 0x19, 0xb0, 0x1a, 0xb8, 0x00, 0x1b, 0xb0, 0x00, 0x00, 0x00, 0x02,
+        0x00, 0x22, 0x00, 0x00, 0x00, 0x12, 0x00, 0x04, 0x00, 0x00, 0x00, 0x1a, 0x00, 0x1c, 0x00,
+        0x1b, 0x00, 0x1f, 0x00, 0x1c, 0x00, 0x22, 0x00, 0x1d, 0x00, 0x27, 0x00, 0x00, 0x00, 0x05,
+        0x00, 0x03, 0x1c, 0x02, 0x02, 0x00, 0x01, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x02, 0x00, 0x2d,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let mut classfile = deserializer.deserialize()?;
+
+    // the computed values match those of javac
+    for method in &classfile.methods {
+        let computed = analysis::compute_maxs(method, &classfile.constant_pool)?.unwrap();
+        match &method.attributes[0] {
+            Code {
+                max_stack,
+                max_locals,
+                ..
+            } => assert_eq!(
+                computed,
+                Maxs {
+                    max_stack: *max_stack,
+                    max_locals: *max_locals,
+                }
+            ),
+            attr => panic!("expected a Code attribute, found {:?}", attr),
+        }
+    }
+
+    for method in &mut classfile.methods {
+        if let Code {
+            max_stack,
+            max_locals,
+            ..
+        } = &mut method.attributes[0]
+        {
+            *max_stack = 0;
+            *max_locals = 0;
+        }
+    }
+
+    let mut serialized = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized)).with_computed_maxs(true);
+    serializer.serialize(&classfile)?;
+    assert_eq!(serialized, bytes);
+
+    Ok(())
+}
+
+//Classfile Frames.class
+//  Last modified Oct 18, 2026; size 1106 bytes
+//  SHA-256 checksum 31a0144af0f41bec56d3c58bb3126b325e9f995f8ae5a9aea8223be893e8f3a0
+//  Compiled from "Frames.java"
+//public class Frames
+//  minor version: 0
+//  major version: 61
+//  flags: (0x0021) ACC_PUBLIC, ACC_SUPER
+//  this_class: #8                          // Frames
+//  super_class: #14                        // java/lang/Object
+//  interfaces: 0, fields: 1, methods: 6, attributes: 1
+//Constant pool:
+//   #1 = Methodref          #2.#3          // java/lang/String.length:()I
+//   #2 = Class              #4             // java/lang/String
+//   #3 = NameAndType        #5:#6          // length:()I
+//   #4 = Utf8               java/lang/String
+//   #5 = Utf8               length
+//   #6 = Utf8               ()I
+//   #7 = Methodref          #8.#9          // Frames."<init>":(I)V
+//   #8 = Class              #10            // Frames
+//   #9 = NameAndType        #11:#12        // "<init>":(I)V
+//  #10 = Utf8               Frames
+//  #11 = Utf8               <init>
+//  #12 = Utf8               (I)V
+//  #13 = Methodref          #14.#15        // java/lang/Object."<init>":()V
+//  #14 = Class              #16            // java/lang/Object
+//  #15 = NameAndType        #11:#17        // "<init>":()V
+//  #16 = Utf8               java/lang/Object
+//  #17 = Utf8               ()V
+//  #18 = Fieldref           #8.#19         // Frames.size:I
+//  #19 = NameAndType        #20:#21        // size:I
+//  #20 = Utf8               size
+//  #21 = Utf8               I
+//  #22 = Class              #23            // java/io/IOException
+//  #23 = Utf8               java/io/IOException
+//  #24 = Methodref          #22.#15        // java/io/IOException."<init>":()V
+//  #25 = Class              #26            // java/lang/Exception
+//  #26 = Utf8               java/lang/Exception
+//  #27 = Methodref          #25.#15        // java/lang/Exception."<init>":()V
+//  #28 = Class              #29            // java/lang/StringBuilder
+//  #29 = Utf8               java/lang/StringBuilder
+//  #30 = String             #31            // x
+//  #31 = Utf8               x
+//  #32 = String             #33            // y
+//  #33 = Utf8               y
+//  #34 = Methodref          #28.#35        // java/lang/StringBuilder."<init>":(Ljava/lang/String;)V
+//  #35 = NameAndType        #11:#36        // "<init>":(Ljava/lang/String;)V
+//  #36 = Utf8               (Ljava/lang/String;)V
+//  #37 = Methodref          #38.#39        // java/lang/Integer.parseInt:(Ljava/lang/String;)I
+//  #38 = Class              #40            // java/lang/Integer
+//  #39 = NameAndType        #41:#42        // parseInt:(Ljava/lang/String;)I
+//  #40 = Utf8               java/lang/Integer
+//  #41 = Utf8               parseInt
+//  #42 = Utf8               (Ljava/lang/String;)I
+//  #43 = Class              #44            // java/lang/NumberFormatException
+//  #44 = Utf8               java/lang/NumberFormatException
+//  #45 = Utf8               Code
+//  #46 = Utf8               LineNumberTable
+//  #47 = Utf8               StackMapTable
+//  #48 = Utf8               failure
+//  #49 = Utf8               (I)Ljava/lang/Exception;
+//  #50 = Utf8               builder
+//  #51 = Utf8               (Z)Ljava/lang/StringBuilder;
+//  #52 = Utf8               sum
+//  #53 = Utf8               ([I)J
+//  #54 = Utf8               parse
+//  #55 = Utf8               SourceFile
+//  #56 = Utf8               Frames.java
+//{
+//  final int size;
+//    descriptor: I
+//    flags: (0x0010) ACC_FINAL
+//
+//  Frames(java.lang.String);
+//    descriptor: (Ljava/lang/String;)V
+//    flags: (0x0000)
+//    Code:
+//      stack=2, locals=2, args_size=2
+//         0: aload_0
+//         1: aload_1
+//         2: ifnonnull     9
+//         5: iconst_0
+//         6: goto          13
+//         9: aload_1
+//        10: invokevirtual #1                  // Method java/lang/String.length:()I
+//        13: invokespecial #7                  // Method "<init>":(I)V
+//        16: return
+//      LineNumberTable:
+//        line 7: 0
+//        line 8: 16
+//      StackMapTable: number_of_entries = 2
+//        frame_type = 73 /* same_locals_1_stack_item */
+//          stack = [ this ]
+//        frame_type = 255 /* full_frame */
+//          offset_delta = 3
+//          locals = [ this, class java/lang/String ]
+//          stack = [ this, int ]
+//
+//  Frames(int);
+//    descriptor: (I)V
+//    flags: (0x0000)
+//    Code:
+//      stack=2, locals=2, args_size=2
+//         0: aload_0
+//         1: invokespecial #13                 // Method java/lang/Object."<init>":()V
+//         4: aload_0
+//         5: iload_1
+//         6: putfield      #18                 // Field size:I
+//         9: return
+//      LineNumberTable:
+//        line 10: 0
+//        line 11: 4
+//        line 12: 9
+//
+//  static java.lang.Exception failure(int);
+//    descriptor: (I)Ljava/lang/Exception;
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=2, locals=2, args_size=1
+//         0: iload_0
+//         1: ifle          14
+//         4: new           #22                 // class java/io/IOException
+//         7: dup
+//         8: invokespecial #24                 // Method java/io/IOException."<init>":()V
+//        11: goto          21
+//        14: new           #25                 // class java/lang/Exception
+//        17: dup
+//        18: invokespecial #27                 // Method java/lang/Exception."<init>":()V
+//        21: astore_1
+//        22: aload_1
+//        23: areturn
+//      LineNumberTable:
+//        line 15: 0
+//        line 16: 22
+//      StackMapTable: number_of_entries = 2
+//        frame_type = 14 /* same */
+//        frame_type = 70 /* same_locals_1_stack_item */
+//          stack = [ class java/lang/Exception ]
+//
+//  static java.lang.StringBuilder builder(boolean);
+//    descriptor: (Z)Ljava/lang/StringBuilder;
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=3, locals=1, args_size=1
+//         0: new           #28                 // class java/lang/StringBuilder
+//         3: dup
+//         4: iload_0
+//         5: ifeq          13
+//         8: ldc           #30                 // String x
+//        10: goto          15
+//        13: ldc           #32                 // String y
+//        15: invokespecial #34                 // Method java/lang/StringBuilder."<init>":(Ljava/lang/String;)V
+//        18: areturn
+//      LineNumberTable:
+//        line 20: 0
+//      StackMapTable: number_of_entries = 2
+//        frame_type = 255 /* full_frame */
+//          offset_delta = 13
+//          locals = [ int ]
+//          stack = [ uninitialized 0, uninitialized 0 ]
+//        frame_type = 255 /* full_frame */
+//          offset_delta = 1
+//          locals = [ int ]
+//          stack = [ uninitialized 0, uninitialized 0, class java/lang/String ]
+//
+//  static long sum(int[]);
+//    descriptor: ([I)J
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=4, locals=4, args_size=1
+//         0: lconst_0
+//         1: lstore_1
+//         2: iconst_0
+//         3: istore_3
+//         4: iload_3
+//         5: aload_0
+//         6: arraylength
+//         7: if_icmpge     29
+//        10: aload_0
+//        11: iload_3
+//        12: iaload
+//        13: ifle          23
+//        16: lload_1
+//        17: aload_0
+//        18: iload_3
+//        19: iaload
+//        20: i2l
+//        21: ladd
+//        22: lstore_1
+//        23: iinc          3, 1
+//        26: goto          4
+//        29: lload_1
+//        30: lreturn
+//      LineNumberTable:
+//        line 24: 0
+//        line 25: 2
+//        line 26: 4
+//        line 27: 10
+//        line 28: 16
+//        line 30: 23
+//        line 32: 29
+//      StackMapTable: number_of_entries = 3
+//        frame_type = 253 /* append */
+//          offset_delta = 4
+//          locals = [ long, int ]
+//        frame_type = 18 /* same */
+//        frame_type = 5 /* same */
+//
+//  static int parse(java.lang.String);
+//    descriptor: (Ljava/lang/String;)I
+//    flags: (0x0008) ACC_STATIC
+//    Code:
+//      stack=1, locals=3, args_size=1
+//         0: aload_0
+//         1: invokestatic  #37                 // Method java/lang/Integer.parseInt:(Ljava/lang/String;)I
+//         4: istore_1
+//         5: goto          11
+//         8: astore_2
+//         9: iconst_m1
+//        10: istore_1
+//        11: iload_1
+//        12: ireturn
+//      Exception table:
+//         from    to  target type
+//             0     5     8   Class java/lang/NumberFormatException
+//      LineNumberTable:
+//        line 38: 0
+//        line 41: 5
+//        line 39: 8
+//        line 40: 9
+//        line 42: 11
+//      StackMapTable: number_of_entries = 2
+//        frame_type = 72 /* same_locals_1_stack_item */
+//          stack = [ class java/lang/NumberFormatException ]
+//        frame_type = 252 /* append */
+//          offset_delta = 2
+//          locals = [ int ]
+//}
+//SourceFile: "Frames.java"
+#[test]
+fn test_serialize_computed_stack_map_tables() -> SerializerResult {
+    let bytes = [
+        0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d, 0x00, 0x39, 0x0a, 0x00, 0x02, 0x00, 0x03,
+        0x07, 0x00, 0x04, 0x0c, 0x00, 0x05, 0x00, 0x06, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x01, 0x00, 0x06,
+        0x6c, 0x65, 0x6e, 0x67, 0x74, 0x68, 0x01, 0x00, 0x03, 0x28, 0x29, 0x49, 0x0a, 0x00, 0x08,
+        0x00, 0x09, 0x07, 0x00, 0x0a, 0x0c, 0x00, 0x0b, 0x00, 0x0c, 0x01, 0x00, 0x06, 0x46, 0x72,
+        0x61, 0x6d, 0x65, 0x73, 0x01, 0x00, 0x06, 0x3c, 0x69, 0x6e, 0x69, 0x74, 0x3e, 0x01, 0x00,
+        0x04, 0x28, 0x49, 0x29, 0x56, 0x0a, 0x00, 0x0e, 0x00, 0x0f, 0x07, 0x00, 0x10, 0x0c, 0x00,
+        0x0b, 0x00, 0x11, 0x01, 0x00, 0x10, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67,
+        0x2f, 0x4f, 0x62, 0x6a, 0x65, 0x63, 0x74, 0x01, 0x00, 0x03, 0x28, 0x29, 0x56, 0x09, 0x00,
+        0x08, 0x00, 0x13, 0x0c, 0x00, 0x14, 0x00, 0x15, 0x01, 0x00, 0x04, 0x73, 0x69, 0x7a, 0x65,
+        0x01, 0x00, 0x01, 0x49, 0x07, 0x00, 0x17, 0x01, 0x00, 0x13, 0x6a, 0x61, 0x76, 0x61, 0x2f,
+        0x69, 0x6f, 0x2f, 0x49, 0x4f, 0x45, 0x78, 0x63, 0x65, 0x70, 0x74, 0x69, 0x6f, 0x6e, 0x0a,
+        0x00, 0x16, 0x00, 0x0f, 0x07, 0x00, 0x1a, 0x01, 0x00, 0x13, 0x6a, 0x61, 0x76, 0x61, 0x2f,
+        0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x45, 0x78, 0x63, 0x65, 0x70, 0x74, 0x69, 0x6f, 0x6e, 0x0a,
+        0x00, 0x19, 0x00, 0x0f, 0x07, 0x00, 0x1d, 0x01, 0x00, 0x17, 0x6a, 0x61, 0x76, 0x61, 0x2f,
+        0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x42, 0x75, 0x69, 0x6c,
+        0x64, 0x65, 0x72, 0x08, 0x00, 0x1f, 0x01, 0x00, 0x01, 0x78, 0x08, 0x00, 0x21, 0x01, 0x00,
+        0x01, 0x79, 0x0a, 0x00, 0x1c, 0x00, 0x23, 0x0c, 0x00, 0x0b, 0x00, 0x24, 0x01, 0x00, 0x15,
+        0x28, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72,
+        0x69, 0x6e, 0x67, 0x3b, 0x29, 0x56, 0x0a, 0x00, 0x26, 0x00, 0x27, 0x07, 0x00, 0x28, 0x0c,
+        0x00, 0x29, 0x00, 0x2a, 0x01, 0x00, 0x11, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e,
+        0x67, 0x2f, 0x49, 0x6e, 0x74, 0x65, 0x67, 0x65, 0x72, 0x01, 0x00, 0x08, 0x70, 0x61, 0x72,
+        0x73, 0x65, 0x49, 0x6e, 0x74, 0x01, 0x00, 0x15, 0x28, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f,
+        0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x3b, 0x29, 0x49, 0x07,
+        0x00, 0x2c, 0x01, 0x00, 0x1f, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f,
+        0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72, 0x46, 0x6f, 0x72, 0x6d, 0x61, 0x74, 0x45, 0x78, 0x63,
+        0x65, 0x70, 0x74, 0x69, 0x6f, 0x6e, 0x01, 0x00, 0x04, 0x43, 0x6f, 0x64, 0x65, 0x01, 0x00,
+        0x0f, 0x4c, 0x69, 0x6e, 0x65, 0x4e, 0x75, 0x6d, 0x62, 0x65, 0x72, 0x54, 0x61, 0x62, 0x6c,
+        0x65, 0x01, 0x00, 0x0d, 0x53, 0x74, 0x61, 0x63, 0x6b, 0x4d, 0x61, 0x70, 0x54, 0x61, 0x62,
+        0x6c, 0x65, 0x01, 0x00, 0x07, 0x66, 0x61, 0x69, 0x6c, 0x75, 0x72, 0x65, 0x01, 0x00, 0x18,
+        0x28, 0x49, 0x29, 0x4c, 0x6a, 0x61, 0x76, 0x61, 0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x45,
+        0x78, 0x63, 0x65, 0x70, 0x74, 0x69, 0x6f, 0x6e, 0x3b, 0x01, 0x00, 0x07, 0x62, 0x75, 0x69,
+        0x6c, 0x64, 0x65, 0x72, 0x01, 0x00, 0x1c, 0x28, 0x5a, 0x29, 0x4c, 0x6a, 0x61, 0x76, 0x61,
+        0x2f, 0x6c, 0x61, 0x6e, 0x67, 0x2f, 0x53, 0x74, 0x72, 0x69, 0x6e, 0x67, 0x42, 0x75, 0x69,
+        0x6c, 0x64, 0x65, 0x72, 0x3b, 0x01, 0x00, 0x03, 0x73, 0x75, 0x6d, 0x01, 0x00, 0x05, 0x28,
+        0x5b, 0x49, 0x29, 0x4a, 0x01, 0x00, 0x05, 0x70, 0x61, 0x72, 0x73, 0x65, 0x01, 0x00, 0x0a,
+        0x53, 0x6f, 0x75, 0x72, 0x63, 0x65, 0x46, 0x69, 0x6c, 0x65, 0x01, 0x00, 0x0b, 0x46, 0x72,
+        0x61, 0x6d, 0x65, 0x73, 0x2e, 0x6a, 0x61, 0x76, 0x61, 0x00, 0x21, 0x00, 0x08, 0x00, 0x0e,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x10, 0x00, 0x14, 0x00, 0x15, 0x00, 0x00, 0x00, 0x06, 0x00,
+        0x00, 0x00, 0x0b, 0x00, 0x24, 0x00, 0x01, 0x00, 0x2d, 0x00, 0x00, 0x00, 0x44, 0x00, 0x02,
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x11, 0x2a, 0x2b, 0xc7, 0x00, 0x07, 0x03, 0xa7, 0x00, 0x07,
+        0x2b, 0xb6, 0x00, 0x01, 0xb7, 0x00, 0x07, 0xb1, 0x00, 0x00, 0x00, 0x02, 0x00, 0x2e, 0x00,
+        0x00, 0x00, 0x0a, 0x00, 0x02, 0x00, 0x00, 0x00, 0x07, 0x00, 0x10, 0x00, 0x08, 0x00, 0x2f,
+        0x00, 0x00, 0x00, 0x11, 0x00, 0x02, 0x49, 0x06, 0xff, 0x00, 0x03, 0x00, 0x02, 0x06, 0x07,
+        0x00, 0x02, 0x00, 0x02, 0x06, 0x01, 0x00, 0x00, 0x00, 0x0b, 0x00, 0x0c, 0x00, 0x01, 0x00,
+        0x2d, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0a, 0x2a, 0xb7,
+        0x00, 0x0d, 0x2a, 0x1b, 0xb5, 0x00, 0x12, 0xb1, 0x00, 0x00, 0x00, 0x01, 0x00, 0x2e, 0x00,
+        0x00, 0x00, 0x0e, 0x00, 0x03, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x04, 0x00, 0x0b, 0x00, 0x09,
+        0x00, 0x0c, 0x00, 0x08, 0x00, 0x30, 0x00, 0x31, 0x00, 0x01, 0x00, 0x2d, 0x00, 0x00, 0x00,
+        0x41, 0x00, 0x02, 0x00, 0x02, 0x00, 0x00, 0x00, 0x18, 0x1a, 0x9e, 0x00, 0x0d, 0xbb, 0x00,
+        0x16, 0x59, 0xb7, 0x00, 0x18, 0xa7, 0x00, 0x0a, 0xbb, 0x00, 0x19, 0x59, 0xb7, 0x00, 0x1b,
+        0x4c, 0x2b, 0xb0, 0x00, 0x00, 0x00, 0x02, 0x00, 0x2e, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x02,
+        0x00, 0x00, 0x00, 0x0f, 0x00, 0x16, 0x00, 0x10, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x07, 0x00,
+        0x02, 0x0e, 0x46, 0x07, 0x00, 0x19, 0x00, 0x08, 0x00, 0x32, 0x00, 0x33, 0x00, 0x01, 0x00,
+        0x2d, 0x00, 0x00, 0x00, 0x52, 0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x13, 0xbb, 0x00,
+        0x1c, 0x59, 0x1a, 0x99, 0x00, 0x08, 0x12, 0x1e, 0xa7, 0x00, 0x05, 0x12, 0x20, 0xb7, 0x00,
+        0x22, 0xb0, 0x00, 0x00, 0x00, 0x02, 0x00, 0x2e, 0x00, 0x00, 0x00, 0x06, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x14, 0x00, 0x2f, 0x00, 0x00, 0x00, 0x21, 0x00, 0x02, 0xff, 0x00, 0x0d, 0x00,
+        0x01, 0x01, 0x00, 0x02, 0x08, 0x00, 0x00, 0x08, 0x00, 0x00, 0xff, 0x00, 0x01, 0x00, 0x01,
+        0x01, 0x00, 0x03, 0x08, 0x00, 0x00, 0x08, 0x00, 0x00, 0x07, 0x00, 0x02, 0x00, 0x08, 0x00,
+        0x34, 0x00, 0x35, 0x00, 0x01, 0x00, 0x2d, 0x00, 0x00, 0x00, 0x5e, 0x00, 0x04, 0x00, 0x04,
+        0x00, 0x00, 0x00, 0x1f, 0x09, 0x40, 0x03, 0x3e, 0x1d, 0x2a, 0xbe, 0xa2, 0x00, 0x16, 0x2a,
+        0x1d, 0x2e, 0x9e, 0x00, 0x0a, 0x1f, 0x2a, 0x1d, 0x2e, 0x85, 0x61, 0x40, 0x84, 0x03, 0x01,
+        0xa7, 0xff, 0xea, 0x1f, 0xad, 0x00, 0x00, 0x00, 0x02, 0x00, 0x2e, 0x00, 0x00, 0x00, 0x1e,
+        0x00, 0x07, 0x00, 0x00, 0x00, 0x18, 0x00, 0x02, 0x00, 0x19, 0x00, 0x04, 0x00, 0x1a, 0x00,
+        0x0a, 0x00, 0x1b, 0x00, 0x10, 0x00, 0x1c, 0x00, 0x17, 0x00, 0x1e, 0x00, 0x1d, 0x00, 0x20,
+        0x00, 0x2f, 0x00, 0x00, 0x00, 0x09, 0x00, 0x03, 0xfd, 0x00, 0x04, 0x04, 0x01, 0x12, 0x05,
+        0x00, 0x08, 0x00, 0x36, 0x00, 0x2a, 0x00, 0x01, 0x00, 0x2d, 0x00, 0x00, 0x00, 0x4d, 0x00,
+        0x01, 0x00, 0x03, 0x00, 0x00, 0x00, 0x0d, 0x2a, 0xb8, 0x00, 0x25, 0x3c, 0xa7, 0x00, 0x06,
+        0x4d, 0x02, 0x3c, 0x1b, 0xac, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x00, 0x08, 0x00, 0x2b,
+        0x00, 0x02, 0x00, 0x2e, 0x00, 0x00, 0x00, 0x16, 0x00, 0x05, 0x00, 0x00, 0x00, 0x26, 0x00,
+        0x05, 0x00, 0x29, 0x00, 0x08, 0x00, 0x27, 0x00, 0x09, 0x00, 0x28, 0x00, 0x0b, 0x00, 0x2a,
+        0x00, 0x2f, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x02, 0x48, 0x07, 0x00, 0x2b, 0xfc, 0x00, 0x02,
+        0x01, 0x00, 0x01, 0x00, 0x37, 0x00, 0x00, 0x00, 0x02, 0x00, 0x38,
+    ];
+
+    let mut deserializer = Deserializer::new(Reader::new(Cursor::new(bytes)));
+    let mut classfile = deserializer.deserialize()?;
+
+    let mut hierarchy = SimpleClassHierarchy::new();
+    hierarchy
+        .add_class("java/io/IOException", "java/lang/Exception")
+        .add_class("java/lang/Exception", "java/lang/Throwable")
+        .add_class("java/lang/Throwable", "java/lang/Object");
+
+    let stack_maps = classfile
+        .methods
+        .iter()
+        .map(|method| analysis::compute_frames(&classfile, method, &hierarchy))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // `Frames(java.lang.String)` passes `this` to another constructor
+    let constructor = stack_maps[0].as_ref().unwrap();
+    assert_eq!(
+        constructor.initial.locals,
+        [
+            VerificationType::UninitializedThis,
+            VerificationType::object("java/lang/String")
+        ]
+    );
+    assert_eq!(constructor.frames[0].0, 9);
+    assert_eq!(
+        constructor.frames[0].1.stack,
+        [VerificationType::UninitializedThis]
+    );
+
+    // replace the `StackMapTable` attributes generated by javac with the computed ones
+    let mut constant_pool = ConstantPoolBuilder::from(std::mem::take(&mut classfile.constant_pool));
+    for (method, stack_map) in classfile.methods.iter_mut().zip(&stack_maps) {
+        if let Code {
+            code_attributes, ..
+        } = &mut method.attributes[0]
+        {
+            for attr in code_attributes.iter_mut() {
+                if let StackMapTable { .. } = attr {
+                    *attr = stack_map
+                        .as_ref()
+                        .unwrap()
+                        .to_attribute(&mut constant_pool)?;
+                }
+            }
+        }
+    }
+    assert_eq!(constant_pool.count(), classfile.constant_pool_count);
+    classfile.constant_pool = constant_pool.build();
+
+    // the computed frames pass verification
+    hierarchy
+        .add_class_file(&classfile)?
+        .add_class(
+            "java/lang/NumberFormatException",
+            "java/lang/IllegalArgumentException",
+        )
+        .add_class(
+            "java/lang/IllegalArgumentException",
+            "java/lang/RuntimeException",
+        )
+        .add_class("java/lang/RuntimeException", "java/lang/Exception");
+    assert_eq!(analysis::verify_class(&classfile, &hierarchy), Ok(()));
+
+    let mut serialized = Vec::new();
+    let mut serializer = Serializer::new(Writer::new(&mut serialized));
+    serializer.serialize(&classfile)?;
+    assert_eq!(serialized, bytes);
+
+    Ok(())
+}